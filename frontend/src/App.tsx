@@ -6,6 +6,7 @@ import { Dashboard } from './components/Dashboard';
 import { NewPost } from './components/NewPost';
 import { PostShow } from './components/PostShow';
 import { Home } from './components/Home';
+import { UserProfile } from './components/UserProfile';
 import './styles/markdown.css';
 
 const App = () => {
@@ -18,6 +19,7 @@ const App = () => {
           <Route path="/new-post" element={<NewPost />} />
           <Route path="/edit-post/:id" element={<NewPost />} />
           <Route path="/post/:id" element={<PostShow />} />
+          <Route path="/users/:handle" element={<UserProfile />} />
         </Routes>
       </Layout>
     </Router>
@@ -1,11 +1,16 @@
 import React, { useState, useEffect } from 'react';
+import ReactMarkdown from 'react-markdown';
+import { Reactions, ReactionSummary } from './Reactions';
 
 interface Comment {
   id: number;
   content: string;
+  rendered_content: string | null;
   user_id: string;
   post_id: number;
   parent_id: number | null;
+  status: 'pending' | 'approved' | 'rejected' | 'spam';
+  reactions: ReactionSummary;
   created_at: string;
   updated_at: string;
 }
@@ -16,21 +21,32 @@ interface CommentsProps {
 
 export const Comments: React.FC<CommentsProps> = ({ postId }) => {
   const [comments, setComments] = useState<Comment[]>([]);
+  const [nextCursor, setNextCursor] = useState<string | null>(null);
   const [newComment, setNewComment] = useState('');
   const [replyTo, setReplyTo] = useState<number | null>(null);
   const [loading, setLoading] = useState(true);
   const [error, setError] = useState<string | null>(null);
+  const [reported, setReported] = useState<number[]>([]);
 
   useEffect(() => {
     fetchComments();
   }, [postId]);
 
-  const fetchComments = async () => {
+  const fetchComments = async (cursor: string | null = null) => {
     try {
-      const response = await fetch(`/api/comments?post_id=${postId}`);
+      // Signed in users also get their own comments awaiting moderation
+      const token = localStorage.getItem('token');
+      const params = new URLSearchParams({ post_id: String(postId), page_size: '100' });
+      if (cursor) {
+        params.set('cursor', cursor);
+      }
+      const response = await fetch(`/api/comments?${params}`, {
+        headers: token ? { 'Authorization': `Bearer ${token}` } : {}
+      });
       if (response.ok) {
         const data = await response.json();
-        setComments(data);
+        setComments(prev => (cursor ? [...prev, ...data.items] : data.items));
+        setNextCursor(data.next_cursor ?? null);
       }
     } catch (error) {
       console.error('Error fetching comments:', error);
@@ -68,7 +84,9 @@ export const Comments: React.FC<CommentsProps> = ({ postId }) => {
         setReplyTo(null);
         await fetchComments(); // Refresh comments
       } else {
-        setError('Failed to post comment');
+        // The post may not allow the user to comment, say why
+        const data = await response.json().catch(() => null);
+        setError(data?.description ?? 'Failed to post comment');
       }
     } catch (error) {
       console.error('Error posting comment:', error);
@@ -76,6 +94,38 @@ export const Comments: React.FC<CommentsProps> = ({ postId }) => {
     }
   };
 
+  const handleReport = async (commentId: number) => {
+    const token = localStorage.getItem('token');
+    if (!token) {
+      setError('Please log in to report comments');
+      return;
+    }
+
+    try {
+      const response = await fetch('/api/reports', {
+        method: 'POST',
+        headers: {
+          'Content-Type': 'application/json',
+          'Authorization': `Bearer ${token}`
+        },
+        body: JSON.stringify({
+          target_type: 'comment',
+          target_id: commentId,
+          reason: 'spam'
+        })
+      });
+
+      if (response.ok) {
+        setReported(prev => [...prev, commentId]);
+      } else {
+        setError('Failed to report comment');
+      }
+    } catch (error) {
+      console.error('Error reporting comment:', error);
+      setError('Failed to report comment');
+    }
+  };
+
   const formatDate = (dateString: string) => {
     return new Date(dateString).toLocaleDateString('en-US', {
       year: 'numeric',
@@ -97,16 +147,32 @@ export const Comments: React.FC<CommentsProps> = ({ postId }) => {
         className={`mb-4 ${indentLevel > 0 ? 'ml-8 border-l-2 border-gray-200 pl-4' : ''}`}
       >
         <div className="bg-white rounded-lg shadow-sm p-4">
-          <div className="text-gray-700">{comment.content}</div>
+          <div className="text-gray-700">
+            <ReactMarkdown>{comment.rendered_content ?? comment.content}</ReactMarkdown>
+          </div>
           <div className="mt-2 text-sm text-gray-500">
             Posted on {formatDate(comment.created_at)}
+            {comment.status === 'pending' && (
+              <span className="ml-2 text-yellow-600">Awaiting moderation</span>
+            )}
           </div>
+          <Reactions path={`/api/comments/${comment.id}/reactions`} initial={comment.reactions} />
           <button
             onClick={() => setReplyTo(comment.id)}
             className="mt-2 text-blue-500 text-sm hover:text-blue-600"
           >
             Reply
           </button>
+          {reported.includes(comment.id) ? (
+            <span className="mt-2 ml-4 text-gray-500 text-sm">Reported</span>
+          ) : (
+            <button
+              onClick={() => handleReport(comment.id)}
+              className="mt-2 ml-4 text-gray-500 text-sm hover:text-red-600"
+            >
+              Report
+            </button>
+          )}
         </div>
 
         {replies.map(reply => renderComment(reply, level + 1))}
@@ -163,6 +229,15 @@ export const Comments: React.FC<CommentsProps> = ({ postId }) => {
           .filter(comment => !comment.parent_id) // Show only top-level comments
           .map(comment => renderComment(comment))}
       </div>
+
+      {nextCursor && (
+        <button
+          onClick={() => fetchComments(nextCursor)}
+          className="mt-6 text-blue-500 hover:text-blue-600"
+        >
+          Load more comments
+        </button>
+      )}
     </div>
   );
 };
@@ -18,7 +18,35 @@ interface PaginatedResponse {
   total_pages: number;
 }
 
+interface DashboardStats {
+  posts: { draft: number; scheduled: number; published: number };
+  comments_received: number;
+  views: number;
+  top_posts: { id: number; title: string | null; views: number; comments: number }[];
+  activity: {
+    kind: 'comment' | 'reaction' | 'follow';
+    at: string;
+    actor: { name: string; handle: string | null } | null;
+    post_id: number | null;
+    post_title: string | null;
+    emoji: string | null;
+  }[];
+}
+
+const describeActivity = (activity: DashboardStats['activity'][number]) => {
+  const actor = activity.actor?.name ?? 'Someone';
+  switch (activity.kind) {
+    case 'comment':
+      return `${actor} commented on ${activity.post_title ?? 'your post'}`;
+    case 'reaction':
+      return `${actor} reacted ${activity.emoji} to ${activity.post_title ?? 'your post'}`;
+    case 'follow':
+      return `${actor} started following you`;
+  }
+};
+
 export const Dashboard = () => {
+  const [stats, setStats] = useState<DashboardStats | null>(null);
   const [posts, setPosts] = useState<Post[]>([]);
   const [loading, setLoading] = useState(true);
   const [currentPage, setCurrentPage] = useState(1);
@@ -30,6 +58,22 @@ export const Dashboard = () => {
     fetchPosts();
   }, [currentPage, pageSize]);
 
+  useEffect(() => {
+    const fetchStats = async () => {
+      const token = localStorage.getItem('token');
+      if (!token) return;
+      const response = await fetch('/api/dashboard', {
+        headers: {
+          'Authorization': `Bearer ${token}`
+        }
+      });
+      if (response.ok) {
+        setStats(await response.json());
+      }
+    };
+    fetchStats();
+  }, []);
+
   const fetchPosts = async () => {
     try {
       const token = localStorage.getItem('token');
@@ -138,6 +182,37 @@ export const Dashboard = () => {
         </button>
       </div>
 
+      {stats && (
+        <div className="grid grid-cols-1 md:grid-cols-3 gap-6 mb-8">
+          <div className="bg-white rounded-lg shadow-md p-6">
+            <h2 className="text-lg font-semibold mb-2">Posts</h2>
+            <p>{stats.posts.published} published</p>
+            <p>{stats.posts.scheduled} scheduled</p>
+            <p>{stats.posts.draft} drafts</p>
+            <p className="mt-2 text-gray-600">
+              {stats.views} views, {stats.comments_received} comments received
+            </p>
+          </div>
+          <div className="bg-white rounded-lg shadow-md p-6">
+            <h2 className="text-lg font-semibold mb-2">Top posts</h2>
+            {stats.top_posts.map((post) => (
+              <p key={post.id} className="cursor-pointer" onClick={() => navigate(`/post/${post.id}`)}>
+                {post.title ?? 'Untitled'}: {post.views} views, {post.comments} comments
+              </p>
+            ))}
+          </div>
+          <div className="bg-white rounded-lg shadow-md p-6">
+            <h2 className="text-lg font-semibold mb-2">Recent activity</h2>
+            {stats.activity.slice(0, 5).map((activity, index) => (
+              <p key={index} className="text-sm">
+                {describeActivity(activity)}
+                <span className="text-gray-500"> · {new Date(activity.at).toLocaleDateString()}</span>
+              </p>
+            ))}
+          </div>
+        </div>
+      )}
+
       {loading ? (
         <div>Loading...</div>
       ) : (
@@ -5,10 +5,15 @@ interface Post {
   id: number;
   title: string;
   summary: string;
+  word_count: number;
+  reading_time: number;
+  cover_url: string | null;
   published: boolean;
   published_at: string | null;
   created_at: string;
   updated_at: string;
+  comment_count: number;
+  last_comment_at: string | null;
 }
 
 interface PaginatedResponse {
@@ -25,15 +30,16 @@ export const Home = () => {
   const [currentPage, setCurrentPage] = useState(1);
   const [pageSize] = useState(10);
   const [totalPages, setTotalPages] = useState(1);
+  const [sort, setSort] = useState<'newest' | 'most_discussed'>('newest');
   const navigate = useNavigate();
 
   useEffect(() => {
     fetchPosts();
-  }, [currentPage, pageSize]);
+  }, [currentPage, pageSize, sort]);
 
   const fetchPosts = async () => {
     try {
-      const response = await fetch(`/api/posts?page=${currentPage}&page_size=${pageSize}`);
+      const response = await fetch(`/api/posts?page=${currentPage}&page_size=${pageSize}&sort=${sort}`);
       if (response.ok) {
         const data: PaginatedResponse = await response.json();
         setPosts(data.items);
@@ -124,6 +130,19 @@ export const Home = () => {
   return (
     <div className="container mx-auto px-4 py-8">
       <h1 className="text-4xl font-bold mb-8 text-center">Blog Posts</h1>
+      <div className="max-w-3xl mx-auto mb-6 flex justify-end">
+        <select
+          value={sort}
+          onChange={(e) => {
+            setSort(e.target.value as 'newest' | 'most_discussed');
+            setCurrentPage(1);
+          }}
+          className="border rounded px-2 py-1"
+        >
+          <option value="newest">Newest</option>
+          <option value="most_discussed">Most discussed</option>
+        </select>
+      </div>
       {loading ? (
         <div className="text-center">Loading...</div>
       ) : (
@@ -135,17 +154,30 @@ export const Home = () => {
                 className="bg-white rounded-lg shadow-md p-6 hover:shadow-lg transition-shadow cursor-pointer"
                 onClick={() => navigate(`/post/${post.id}`)}
               >
+                {post.cover_url && (
+                  <img
+                    src={post.cover_url}
+                    alt=""
+                    className="w-full h-48 object-cover rounded-md mb-4"
+                  />
+                )}
                 <h2 className="text-2xl font-bold mb-2 hover:text-blue-600 transition-colors">
                   {post.title}
                 </h2>
                 {post.published_at && (
                   <div className="text-gray-600 mb-3">
                     Published on {formatDate(post.published_at)}
+                    {post.reading_time > 0 && ` · ${post.reading_time} min read`}
                   </div>
                 )}
                 <p className="text-gray-700 mb-4">{post.summary}</p>
-                <div className="text-blue-500 hover:text-blue-600">
-                  Read more →
+                <div className="flex justify-between">
+                  <div className="text-blue-500 hover:text-blue-600">
+                    Read more →
+                  </div>
+                  <div className="text-gray-500 text-sm">
+                    {post.comment_count} {post.comment_count === 1 ? 'comment' : 'comments'}
+                  </div>
                 </div>
               </article>
             ))}
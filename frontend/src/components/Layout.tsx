@@ -1,5 +1,6 @@
 import React from 'react';
 import { UserAuth } from './UserAuth';
+import { NotificationBadge } from './NotificationBadge';
 import { useNavigate } from 'react-router-dom';
 
 interface LayoutProps {
@@ -21,7 +22,10 @@ export const Layout: React.FC<LayoutProps> = ({ children }) => {
             <h1 className="text-2xl font-bold text-gray-900 cursor-pointer" onClick={() => navigate('/')}>
               My Blog
             </h1>
-            <UserAuth onAvatarClick={handleAvatarClick} />
+            <div className="flex items-center">
+              <NotificationBadge />
+              <UserAuth onAvatarClick={handleAvatarClick} />
+            </div>
           </div>
         </div>
       </header>
@@ -2,6 +2,21 @@ import React, { useState, useEffect } from 'react';
 import { useNavigate, useParams } from 'react-router-dom';
 import MDEditor from '@uiw/react-md-editor';
 
+interface MediaVariant {
+  name: string;
+  format: string;
+  url: string;
+  width: number;
+  height: number;
+}
+
+// WebP variants for `srcset`, browsers pick the smallest one that fits
+const srcSet = (variants: MediaVariant[]) =>
+  variants
+    .filter(variant => variant.format === 'webp')
+    .map(variant => `${variant.url} ${variant.width}w`)
+    .join(', ');
+
 interface Post {
   id: number;
   title: string;
@@ -9,6 +24,8 @@ interface Post {
   summary: string;
   published: boolean;
   user_id: string | null;
+  cover_media_id: number | null;
+  cover_url: string | null;
 }
 
 export const NewPost = () => {
@@ -22,7 +39,11 @@ export const NewPost = () => {
     summary: '',
     published: false,
     user_id: null as string | null,
+    cover_media_id: null as number | null,
   });
+  const [coverUrl, setCoverUrl] = useState<string | null>(null);
+  const [coverVariants, setCoverVariants] = useState<MediaVariant[]>([]);
+  const [uploading, setUploading] = useState(false);
   const [error, setError] = useState('');
   const [loading, setLoading] = useState(isEditing);
 
@@ -51,7 +72,9 @@ export const NewPost = () => {
             summary: post.summary,
             published: post.published,
             user_id: post.user_id,
+            cover_media_id: post.cover_media_id,
           });
+          setCoverUrl(post.cover_url);
         } else {
           setError('Failed to fetch post');
         }
@@ -87,6 +110,44 @@ export const NewPost = () => {
     }));
   };
 
+  const handleCoverChange = async (e: React.ChangeEvent<HTMLInputElement>) => {
+    const file = e.target.files?.[0];
+    if (!file) return;
+    setError('');
+    setUploading(true);
+
+    try {
+      const body = new FormData();
+      body.append('file', file);
+      const response = await fetch('/api/media', {
+        method: 'POST',
+        headers: {
+          'Authorization': `Bearer ${localStorage.getItem('token')}`
+        },
+        body
+      });
+      const data = await response.json();
+      if (response.ok) {
+        setFormData(prev => ({ ...prev, cover_media_id: data.id }));
+        setCoverUrl(data.url);
+        setCoverVariants(data.variants);
+      } else {
+        setError(data.description || data.message || 'Failed to upload image');
+      }
+    } catch (error) {
+      setError('Failed to upload image');
+    } finally {
+      setUploading(false);
+      e.target.value = '';
+    }
+  };
+
+  const removeCover = () => {
+    setFormData(prev => ({ ...prev, cover_media_id: null }));
+    setCoverUrl(null);
+    setCoverVariants([]);
+  };
+
   const handleSubmit = async (e: React.FormEvent) => {
     e.preventDefault();
     setError('');
@@ -151,6 +212,38 @@ export const NewPost = () => {
             />
           </div>
 
+          <div>
+            <label className="block text-sm font-medium text-gray-700 mb-2">
+              Cover image
+            </label>
+            {coverUrl && (
+              <div className="mb-2">
+                <img
+                  src={coverUrl}
+                  srcSet={srcSet(coverVariants) || undefined}
+                  sizes="(max-width: 768px) 100vw, 768px"
+                  alt="Cover"
+                  className="max-h-48 rounded-md"
+                />
+                <button
+                  type="button"
+                  onClick={removeCover}
+                  className="mt-1 text-sm text-red-600 hover:text-red-700"
+                >
+                  Remove cover
+                </button>
+              </div>
+            )}
+            <input
+              type="file"
+              accept="image/jpeg,image/png,image/gif,image/webp"
+              onChange={handleCoverChange}
+              disabled={uploading}
+              className="block text-sm text-gray-700"
+            />
+            {uploading && <div className="text-sm text-gray-500 mt-1">Uploading...</div>}
+          </div>
+
           <div>
             <label className="block text-sm font-medium text-gray-700 mb-2">
               Summary
@@ -0,0 +1,28 @@
+import React, { useState, useEffect } from 'react';
+
+export const NotificationBadge: React.FC = () => {
+  const [unreadCount, setUnreadCount] = useState(0);
+
+  useEffect(() => {
+    const token = localStorage.getItem('token');
+    if (!token) return;
+
+    const source = new EventSource(
+      `http://localhost:5150/api/notifications/stream?token=${encodeURIComponent(token)}`
+    );
+    source.addEventListener('unread', (event) => {
+      const data = JSON.parse((event as MessageEvent).data);
+      setUnreadCount(data.unread_count);
+    });
+
+    return () => source.close();
+  }, []);
+
+  if (unreadCount === 0) return null;
+
+  return (
+    <span className="inline-flex items-center justify-center px-2 py-1 mr-3 text-xs font-bold text-white bg-red-600 rounded-full">
+      {unreadCount}
+    </span>
+  );
+};
@@ -4,16 +4,20 @@ import ReactMarkdown from 'react-markdown';
 import { Prism as SyntaxHighlighter } from 'react-syntax-highlighter';
 import { tomorrow } from 'react-syntax-highlighter/dist/esm/styles/prism';
 import { Comments } from './Comments';
+import { Reactions, ReactionSummary } from './Reactions';
 
 interface Post {
   id: number;
   title: string;
   content: string;
+  rendered_content: string | null;
   summary: string;
   published: boolean;
   user_id: string;
   created_at: string;
   published_at: string | null;
+  reactions: ReactionSummary;
+  bookmarked?: boolean;
 }
 
 export const PostShow = () => {
@@ -21,6 +25,7 @@ export const PostShow = () => {
   const navigate = useNavigate();
   const [post, setPost] = useState<Post | null>(null);
   const [isAuthor, setIsAuthor] = useState(false);
+  const [views, setViews] = useState<{ views: number; unique_visitors: number } | null>(null);
   const [loading, setLoading] = useState(true);
 
   useEffect(() => {
@@ -53,6 +58,16 @@ export const PostShow = () => {
           if (userResponse.ok) {
             const userData = await userResponse.json();
             setIsAuthor(userData.pid === postData.user_id);
+            if (userData.pid === postData.user_id) {
+              const statsResponse = await fetch(`/api/posts/${id}/stats`, {
+                headers: {
+                  'Authorization': `Bearer ${token}`
+                }
+              });
+              if (statsResponse.ok) {
+                setViews(await statsResponse.json());
+              }
+            }
           }
         }
       } catch (error) {
@@ -69,6 +84,27 @@ export const PostShow = () => {
     navigate(`/edit-post/${id}`);
   };
 
+  const toggleBookmark = async () => {
+    if (!post) return;
+    const token = localStorage.getItem('token');
+    const response = post.bookmarked
+      ? await fetch(`/api/bookmarks/${post.id}`, {
+          method: 'DELETE',
+          headers: { 'Authorization': `Bearer ${token}` }
+        })
+      : await fetch('/api/bookmarks', {
+          method: 'POST',
+          headers: {
+            'Content-Type': 'application/json',
+            'Authorization': `Bearer ${token}`
+          },
+          body: JSON.stringify({ post_id: post.id })
+        });
+    if (response.ok) {
+      setPost({ ...post, bookmarked: !post.bookmarked });
+    }
+  };
+
   if (loading) {
     return <div className="container mx-auto px-4 py-8">Loading...</div>;
   }
@@ -82,6 +118,12 @@ export const PostShow = () => {
       <div className="max-w-3xl mx-auto">
         <div className="flex justify-between items-center mb-8">
           <h1 className="text-4xl font-bold">{post.title}</h1>
+          <button
+            onClick={toggleBookmark}
+            className="border border-gray-300 hover:bg-gray-100 px-4 py-2 rounded-md"
+          >
+            {post.bookmarked ? 'Bookmarked' : 'Bookmark'}
+          </button>
           {isAuthor && (
             <button
               onClick={handleEdit}
@@ -95,6 +137,9 @@ export const PostShow = () => {
         <div className="mb-6 text-gray-600">
           <p>Published: {post.published_at ? new Date(post.published_at).toLocaleDateString() : 'Draft'}</p>
           <p>Created: {new Date(post.created_at).toLocaleDateString()}</p>
+          {views && (
+            <p>Last 30 days: {views.views} views, {views.unique_visitors} visitors</p>
+          )}
         </div>
 
         <div className="prose max-w-none">
@@ -121,9 +166,11 @@ export const PostShow = () => {
                 }
               }}
             >
-              {post.content}
+              {post.rendered_content ?? post.content}
             </ReactMarkdown>
           </article>
+
+          <Reactions path={`/api/posts/${post.id}/reactions`} initial={post.reactions} />
           
           {/* Add Comments section */}
           <Comments postId={post.id} />
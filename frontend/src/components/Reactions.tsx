@@ -0,0 +1,60 @@
+import React, { useState } from 'react';
+
+export interface ReactionSummary {
+  counts: Record<string, number>;
+  mine: string[];
+}
+
+// The default emoji set of the server settings
+const EMOJIS = ['👍', '❤️', '😂', '🎉', '😮', '😢'];
+
+interface ReactionsProps {
+  // Endpoint toggling a reaction, e.g. `/api/posts/1/reactions`
+  path: string;
+  initial: ReactionSummary;
+}
+
+export const Reactions: React.FC<ReactionsProps> = ({ path, initial }) => {
+  const [summary, setSummary] = useState<ReactionSummary>(initial);
+
+  const toggle = async (emoji: string) => {
+    const token = localStorage.getItem('token');
+    if (!token) return;
+
+    try {
+      const response = await fetch(path, {
+        method: 'POST',
+        headers: {
+          'Content-Type': 'application/json',
+          'Authorization': `Bearer ${token}`
+        },
+        body: JSON.stringify({ emoji })
+      });
+      if (response.ok) {
+        setSummary(await response.json());
+      }
+    } catch (error) {
+      console.error('Error reacting:', error);
+    }
+  };
+
+  return (
+    <div className="flex flex-wrap gap-2 mt-2">
+      {EMOJIS.map(emoji => {
+        const count = summary.counts[emoji] ?? 0;
+        const mine = summary.mine.includes(emoji);
+        return (
+          <button
+            key={emoji}
+            onClick={() => toggle(emoji)}
+            className={`px-2 py-1 rounded-full text-sm border ${
+              mine ? 'bg-blue-100 border-blue-400' : 'border-gray-200 hover:bg-gray-100'
+            }`}
+          >
+            {emoji}{count > 0 && <span className="ml-1">{count}</span>}
+          </button>
+        );
+      })}
+    </div>
+  );
+};
@@ -0,0 +1,115 @@
+import React, { useState, useEffect } from 'react';
+import { useParams, Link } from 'react-router-dom';
+
+interface Profile {
+  name: string;
+  handle: string | null;
+  bio: string | null;
+  website: string | null;
+}
+
+interface Follows {
+  followers: number;
+  following: number;
+  followed?: boolean;
+}
+
+interface PostItem {
+  id: number;
+  title: string;
+  summary: string;
+}
+
+export const UserProfile = () => {
+  const { handle } = useParams();
+  const [profile, setProfile] = useState<Profile | null>(null);
+  const [posts, setPosts] = useState<PostItem[]>([]);
+  const [follows, setFollows] = useState<Follows | null>(null);
+  const [loading, setLoading] = useState(true);
+
+  useEffect(() => {
+    const fetchProfile = async () => {
+      try {
+        const token = localStorage.getItem('token');
+        const response = await fetch(`/api/users/${handle}`, {
+          headers: token ? { 'Authorization': `Bearer ${token}` } : {}
+        });
+        if (response.ok) {
+          const data = await response.json();
+          setProfile(data.profile);
+          setPosts(data.posts.items);
+          setFollows({
+            followers: data.followers,
+            following: data.following,
+            followed: data.followed
+          });
+        }
+      } catch (error) {
+        console.error('Error fetching profile:', error);
+      } finally {
+        setLoading(false);
+      }
+    };
+
+    fetchProfile();
+  }, [handle]);
+
+  const toggleFollow = async () => {
+    if (!follows) return;
+    const token = localStorage.getItem('token');
+    const response = await fetch(`/api/users/${handle}/follow`, {
+      method: follows.followed ? 'DELETE' : 'POST',
+      headers: { 'Authorization': `Bearer ${token}` }
+    });
+    if (response.ok) {
+      const counts = await response.json();
+      setFollows({ ...counts, followed: !follows.followed });
+    }
+  };
+
+  if (loading) {
+    return <div className="container mx-auto px-4 py-8">Loading...</div>;
+  }
+
+  if (!profile) {
+    return <div className="container mx-auto px-4 py-8">User not found</div>;
+  }
+
+  return (
+    <div className="container mx-auto px-4 py-8">
+      <div className="max-w-3xl mx-auto">
+        <h1 className="text-3xl font-bold">{profile.name}</h1>
+        <p className="text-gray-500">@{profile.handle}</p>
+        {follows && (
+          <div className="mt-2 flex items-center gap-4 text-gray-600">
+            <span>{follows.followers} followers</span>
+            <span>{follows.following} following</span>
+            {follows.followed !== undefined && (
+              <button
+                onClick={toggleFollow}
+                className="border border-gray-300 hover:bg-gray-100 px-3 py-1 rounded-md"
+              >
+                {follows.followed ? 'Unfollow' : 'Follow'}
+              </button>
+            )}
+          </div>
+        )}
+        {profile.bio && <p className="mt-4 text-gray-700">{profile.bio}</p>}
+        {profile.website && (
+          <a href={profile.website} className="text-blue-500 hover:text-blue-600">
+            {profile.website}
+          </a>
+        )}
+
+        <div className="mt-8 space-y-4">
+          {posts.map((post) => (
+            <Link key={post.id} to={`/post/${post.id}`} className="block bg-white rounded-lg shadow-sm p-4">
+              <h2 className="text-xl font-semibold">{post.title}</h2>
+              <p className="text-gray-600">{post.summary}</p>
+            </Link>
+          ))}
+        </div>
+      </div>
+    </div>
+  );
+};
@@ -8,6 +8,30 @@ mod m20241202_173429_posts;
 mod m20240104_000001_alter_posts_user_id_to_uuid;
 
 mod m20241204_155109_comments;
+mod m20241210_094512_add_profile_to_users;
+mod m20241211_142037_add_credential_changes_to_users;
+mod m20241212_101244_sessions;
+mod m20241213_090412_add_role_to_users;
+mod m20241213_091530_invites;
+mod m20241214_110233_audit_events;
+mod m20241215_083047_add_notification_preference_to_users;
+mod m20241216_141852_notifications;
+mod m20241217_102315_mentions;
+mod m20241218_093604_add_moderation_to_comments;
+mod m20241219_150721_spam_tokens;
+mod m20241220_112948_reports;
+mod m20241221_093012_follows;
+mod m20241221_101530_add_comment_policy_to_posts;
+mod m20241222_084511_add_reaction_count_to_comments;
+mod m20241223_091420_reactions;
+mod m20241224_103355_bookmarks;
+mod m20241225_094108_add_feed_index_to_posts;
+mod m20241226_101722_post_views;
+mod m20241227_093015_add_reading_stats_to_posts;
+mod m20241228_101204_media;
+mod m20241229_084417_media_variants;
+mod m20241230_091533_post_tags;
+mod m20241231_094215_exports;
 pub struct Migrator;
 
 #[async_trait::async_trait]
@@ -18,6 +42,30 @@ impl MigratorTrait for Migrator {
             Box::new(m20241202_173429_posts::Migration),
             Box::new(m20240104_000001_alter_posts_user_id_to_uuid::Migration),
             Box::new(m20241204_155109_comments::Migration),
+            Box::new(m20241210_094512_add_profile_to_users::Migration),
+            Box::new(m20241211_142037_add_credential_changes_to_users::Migration),
+            Box::new(m20241212_101244_sessions::Migration),
+            Box::new(m20241213_090412_add_role_to_users::Migration),
+            Box::new(m20241213_091530_invites::Migration),
+            Box::new(m20241214_110233_audit_events::Migration),
+            Box::new(m20241215_083047_add_notification_preference_to_users::Migration),
+            Box::new(m20241216_141852_notifications::Migration),
+            Box::new(m20241217_102315_mentions::Migration),
+            Box::new(m20241218_093604_add_moderation_to_comments::Migration),
+            Box::new(m20241219_150721_spam_tokens::Migration),
+            Box::new(m20241220_112948_reports::Migration),
+            Box::new(m20241221_093012_follows::Migration),
+            Box::new(m20241221_101530_add_comment_policy_to_posts::Migration),
+            Box::new(m20241222_084511_add_reaction_count_to_comments::Migration),
+            Box::new(m20241223_091420_reactions::Migration),
+            Box::new(m20241224_103355_bookmarks::Migration),
+            Box::new(m20241225_094108_add_feed_index_to_posts::Migration),
+            Box::new(m20241226_101722_post_views::Migration),
+            Box::new(m20241227_093015_add_reading_stats_to_posts::Migration),
+            Box::new(m20241228_101204_media::Migration),
+            Box::new(m20241229_084417_media_variants::Migration),
+            Box::new(m20241230_091533_post_tags::Migration),
+            Box::new(m20241231_094215_exports::Migration),
             // inject-above (do not remove this comment)
         ]
     }
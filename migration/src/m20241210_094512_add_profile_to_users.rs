@@ -0,0 +1,69 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One column per statement, SQLite can't add several in a single ALTER
+        for col in [
+            string_null(Users::Handle),
+            text_null(Users::Bio),
+            string_null(Users::Website),
+            string_null(Users::AvatarUrl),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Users::Table)
+                        .add_column(col)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-users-handle")
+                    .table(Users::Table)
+                    .col(Users::Handle)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-users-handle")
+                    .table(Users::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        for col in [Users::Handle, Users::Bio, Users::Website, Users::AvatarUrl] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Users::Table)
+                        .drop_column(col)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Handle,
+    Bio,
+    Website,
+    AvatarUrl,
+}
@@ -0,0 +1,54 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for col in [
+            timestamp_with_time_zone_null(Users::TokensValidAfter),
+            string_null(Users::PendingEmail),
+            string_null(Users::EmailChangeToken),
+            timestamp_with_time_zone_null(Users::EmailChangeSentAt),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Users::Table)
+                        .add_column(col)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for col in [
+            Users::TokensValidAfter,
+            Users::PendingEmail,
+            Users::EmailChangeToken,
+            Users::EmailChangeSentAt,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Users::Table)
+                        .drop_column(col)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    TokensValidAfter,
+    PendingEmail,
+    EmailChangeToken,
+    EmailChangeSentAt,
+}
@@ -0,0 +1,50 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(Sessions::Table)
+                    .col(pk_auto(Sessions::Id))
+                    .col(uuid(Sessions::UserId))
+                    .col(string_null(Sessions::UserAgent))
+                    .col(string_null(Sessions::Ip))
+                    .col(timestamp_with_time_zone(Sessions::LastSeenAt))
+                    .col(timestamp_with_time_zone_null(Sessions::RevokedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-sessions-user_id")
+                    .table(Sessions::Table)
+                    .col(Sessions::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Sessions::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Sessions {
+    Table,
+    Id,
+    UserId,
+    UserAgent,
+    Ip,
+    LastSeenAt,
+    RevokedAt,
+}
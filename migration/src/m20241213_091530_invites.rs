@@ -0,0 +1,44 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(Invites::Table)
+                    .col(pk_auto(Invites::Id))
+                    .col(string_uniq(Invites::Code))
+                    .col(string_null(Invites::Email))
+                    .col(integer(Invites::MaxUses))
+                    .col(integer(Invites::Uses).default(0))
+                    .col(timestamp_with_time_zone(Invites::ExpiresAt))
+                    .col(timestamp_with_time_zone_null(Invites::RevokedAt))
+                    .col(uuid(Invites::CreatedBy))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Invites::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Invites {
+    Table,
+    Id,
+    Code,
+    Email,
+    MaxUses,
+    Uses,
+    ExpiresAt,
+    RevokedAt,
+    CreatedBy,
+}
@@ -0,0 +1,63 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(AuditEvents::Table)
+                    .col(pk_auto(AuditEvents::Id))
+                    .col(uuid_null(AuditEvents::UserId))
+                    .col(string(AuditEvents::Event))
+                    .col(string(AuditEvents::Outcome))
+                    .col(string_null(AuditEvents::Ip))
+                    .col(string_null(AuditEvents::UserAgent))
+                    .col(string_null(AuditEvents::Detail))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-audit_events-user_id")
+                    .table(AuditEvents::Table)
+                    .col(AuditEvents::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-audit_events-created_at")
+                    .table(AuditEvents::Table)
+                    .col(AuditEvents::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuditEvents::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum AuditEvents {
+    Table,
+    Id,
+    UserId,
+    Event,
+    Outcome,
+    Ip,
+    UserAgent,
+    Detail,
+    CreatedAt,
+}
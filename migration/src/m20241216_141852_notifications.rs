@@ -0,0 +1,55 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(Notifications::Table)
+                    .col(pk_auto(Notifications::Id))
+                    .col(uuid(Notifications::UserId))
+                    .col(string(Notifications::Kind))
+                    .col(uuid_null(Notifications::ActorId))
+                    .col(integer_null(Notifications::PostId))
+                    .col(integer_null(Notifications::CommentId))
+                    .col(string_null(Notifications::Detail))
+                    .col(timestamp_with_time_zone_null(Notifications::ReadAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-notifications-user_id-read_at")
+                    .table(Notifications::Table)
+                    .col(Notifications::UserId)
+                    .col(Notifications::ReadAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Notifications::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Notifications {
+    Table,
+    Id,
+    UserId,
+    Kind,
+    ActorId,
+    PostId,
+    CommentId,
+    Detail,
+    ReadAt,
+}
@@ -0,0 +1,53 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(Mentions::Table)
+                    .col(pk_auto(Mentions::Id))
+                    .col(uuid(Mentions::UserId))
+                    .col(integer_null(Mentions::PostId))
+                    .col(integer_null(Mentions::CommentId))
+                    .to_owned(),
+            )
+            .await?;
+
+        for (name, col) in [
+            ("idx-mentions-user_id", Mentions::UserId),
+            ("idx-mentions-post_id", Mentions::PostId),
+            ("idx-mentions-comment_id", Mentions::CommentId),
+        ] {
+            manager
+                .create_index(
+                    Index::create()
+                        .name(name)
+                        .table(Mentions::Table)
+                        .col(col)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Mentions::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Mentions {
+    Table,
+    Id,
+    UserId,
+    PostId,
+    CommentId,
+}
@@ -0,0 +1,94 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Comments from before moderation stay visible
+        for col in [
+            string(Comments::Status).default("approved").to_owned(),
+            uuid_null(Comments::ModeratedBy),
+            timestamp_with_time_zone_null(Comments::ModeratedAt),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Comments::Table)
+                        .add_column(col)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-comments-status")
+                    .table(Comments::Table)
+                    .col(Comments::Status)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Posts::Table)
+                    .add_column(boolean(Posts::CommentModeration).default(false))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Posts::Table)
+                    .drop_column(Posts::CommentModeration)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-comments-status")
+                    .table(Comments::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        for col in [
+            Comments::Status,
+            Comments::ModeratedBy,
+            Comments::ModeratedAt,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Comments::Table)
+                        .drop_column(col)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Comments {
+    Table,
+    Status,
+    ModeratedBy,
+    ModeratedAt,
+}
+
+#[derive(DeriveIden)]
+enum Posts {
+    Table,
+    CommentModeration,
+}
@@ -0,0 +1,36 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(SpamTokens::Table)
+                    .col(pk_auto(SpamTokens::Id))
+                    .col(string_uniq(SpamTokens::Token))
+                    .col(integer(SpamTokens::Spam).default(0))
+                    .col(integer(SpamTokens::Ham).default(0))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SpamTokens::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SpamTokens {
+    Table,
+    Id,
+    Token,
+    Spam,
+    Ham,
+}
@@ -0,0 +1,121 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(Reports::Table)
+                    .col(pk_auto(Reports::Id))
+                    .col(uuid(Reports::ReporterId))
+                    .col(string(Reports::TargetType))
+                    .col(integer(Reports::TargetId))
+                    .col(string(Reports::Reason))
+                    .col(text_null(Reports::Details))
+                    .col(string(Reports::Status).default("open"))
+                    .col(uuid_null(Reports::ResolvedBy))
+                    .col(timestamp_with_time_zone_null(Reports::ResolvedAt))
+                    .col(string_null(Reports::ResolutionNote))
+                    .to_owned(),
+            )
+            .await?;
+
+        // One report per user and target, later ones are ignored
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-reports-reporter_id-target")
+                    .table(Reports::Table)
+                    .col(Reports::ReporterId)
+                    .col(Reports::TargetType)
+                    .col(Reports::TargetId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-reports-target-status")
+                    .table(Reports::Table)
+                    .col(Reports::TargetType)
+                    .col(Reports::TargetId)
+                    .col(Reports::Status)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Content hidden after too many reports, until a moderator decides
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Posts::Table)
+                    .add_column(timestamp_with_time_zone_null(Posts::HiddenAt))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Comments::Table)
+                    .add_column(timestamp_with_time_zone_null(Comments::HiddenAt))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Comments::Table)
+                    .drop_column(Comments::HiddenAt)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Posts::Table)
+                    .drop_column(Posts::HiddenAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Reports::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Reports {
+    Table,
+    Id,
+    ReporterId,
+    TargetType,
+    TargetId,
+    Reason,
+    Details,
+    Status,
+    ResolvedBy,
+    ResolvedAt,
+    ResolutionNote,
+}
+
+#[derive(DeriveIden)]
+enum Posts {
+    Table,
+    HiddenAt,
+}
+
+#[derive(DeriveIden)]
+enum Comments {
+    Table,
+    HiddenAt,
+}
@@ -0,0 +1,56 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(Follows::Table)
+                    .col(pk_auto(Follows::Id))
+                    .col(uuid(Follows::FollowerId))
+                    .col(uuid(Follows::FollowedId))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-follows-follower_id-followed_id")
+                    .table(Follows::Table)
+                    .col(Follows::FollowerId)
+                    .col(Follows::FollowedId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-follows-followed_id")
+                    .table(Follows::Table)
+                    .col(Follows::FollowedId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Follows::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Follows {
+    Table,
+    Id,
+    FollowerId,
+    FollowedId,
+}
@@ -0,0 +1,78 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Comments::Table)
+                    .add_column(integer(Comments::ReactionCount).default(0))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Listings page through the comments of a post in each sort order
+        for (name, col) in [
+            ("idx-comments-post_id-created_at", Comments::CreatedAt),
+            (
+                "idx-comments-post_id-reaction_count",
+                Comments::ReactionCount,
+            ),
+        ] {
+            manager
+                .create_index(
+                    Index::create()
+                        .name(name)
+                        .table(Comments::Table)
+                        .col(Comments::PostId)
+                        .col(col)
+                        .col(Comments::Id)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-comments-parent_id")
+                    .table(Comments::Table)
+                    .col(Comments::ParentId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for name in [
+            "idx-comments-parent_id",
+            "idx-comments-post_id-reaction_count",
+            "idx-comments-post_id-created_at",
+        ] {
+            manager
+                .drop_index(Index::drop().name(name).table(Comments::Table).to_owned())
+                .await?;
+        }
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Comments::Table)
+                    .drop_column(Comments::ReactionCount)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Comments {
+    Table,
+    Id,
+    PostId,
+    ParentId,
+    CreatedAt,
+    ReactionCount,
+}
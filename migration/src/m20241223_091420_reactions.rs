@@ -0,0 +1,64 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(Reactions::Table)
+                    .col(pk_auto(Reactions::Id))
+                    .col(uuid(Reactions::UserId))
+                    .col(string(Reactions::TargetType))
+                    .col(integer(Reactions::TargetId))
+                    .col(string(Reactions::Emoji))
+                    .to_owned(),
+            )
+            .await?;
+
+        // One reaction of each emoji per user and target
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-reactions-user_id-target-emoji")
+                    .table(Reactions::Table)
+                    .col(Reactions::UserId)
+                    .col(Reactions::TargetType)
+                    .col(Reactions::TargetId)
+                    .col(Reactions::Emoji)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-reactions-target")
+                    .table(Reactions::Table)
+                    .col(Reactions::TargetType)
+                    .col(Reactions::TargetId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Reactions::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Reactions {
+    Table,
+    Id,
+    UserId,
+    TargetType,
+    TargetId,
+    Emoji,
+}
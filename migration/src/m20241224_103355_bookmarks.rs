@@ -0,0 +1,89 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(BookmarkCollections::Table)
+                    .col(pk_auto(BookmarkCollections::Id))
+                    .col(uuid(BookmarkCollections::UserId))
+                    .col(string(BookmarkCollections::Name))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-bookmark_collections-user_id-name")
+                    .table(BookmarkCollections::Table)
+                    .col(BookmarkCollections::UserId)
+                    .col(BookmarkCollections::Name)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                table_auto_tz(Bookmarks::Table)
+                    .col(pk_auto(Bookmarks::Id))
+                    .col(uuid(Bookmarks::UserId))
+                    .col(integer(Bookmarks::PostId))
+                    .col(integer_null(Bookmarks::CollectionId))
+                    .to_owned(),
+            )
+            .await?;
+        // A post is bookmarked once, in at most one collection
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-bookmarks-user_id-post_id")
+                    .table(Bookmarks::Table)
+                    .col(Bookmarks::UserId)
+                    .col(Bookmarks::PostId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-bookmarks-collection_id")
+                    .table(Bookmarks::Table)
+                    .col(Bookmarks::CollectionId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Bookmarks::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(BookmarkCollections::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Bookmarks {
+    Table,
+    Id,
+    UserId,
+    PostId,
+    CollectionId,
+}
+
+#[derive(DeriveIden)]
+enum BookmarkCollections {
+    Table,
+    Id,
+    UserId,
+    Name,
+}
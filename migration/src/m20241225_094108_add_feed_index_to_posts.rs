@@ -0,0 +1,41 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The feed reads the latest posts of each followed author
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-posts-user_id-published_at")
+                    .table(Posts::Table)
+                    .col(Posts::UserId)
+                    .col(Posts::PublishedAt)
+                    .col(Posts::Id)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-posts-user_id-published_at")
+                    .table(Posts::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Posts {
+    Table,
+    Id,
+    UserId,
+    PublishedAt,
+}
@@ -0,0 +1,90 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Views of a post per day and visitor, the visitor being a keyed hash
+        // of their IP and user agent
+        manager
+            .create_table(
+                table_auto_tz(PostViews::Table)
+                    .col(pk_auto(PostViews::Id))
+                    .col(integer(PostViews::PostId))
+                    .col(date(PostViews::Day))
+                    .col(string(PostViews::Visitor))
+                    .col(integer(PostViews::Views).default(0))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-post_views-post_id-day-visitor")
+                    .table(PostViews::Table)
+                    .col(PostViews::PostId)
+                    .col(PostViews::Day)
+                    .col(PostViews::Visitor)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Views of a post per day coming from links on other sites
+        manager
+            .create_table(
+                table_auto_tz(PostReferrers::Table)
+                    .col(pk_auto(PostReferrers::Id))
+                    .col(integer(PostReferrers::PostId))
+                    .col(date(PostReferrers::Day))
+                    .col(string(PostReferrers::Referrer))
+                    .col(integer(PostReferrers::Views).default(0))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-post_referrers-post_id-day-referrer")
+                    .table(PostReferrers::Table)
+                    .col(PostReferrers::PostId)
+                    .col(PostReferrers::Day)
+                    .col(PostReferrers::Referrer)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PostReferrers::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(PostViews::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PostViews {
+    Table,
+    Id,
+    PostId,
+    Day,
+    Visitor,
+    Views,
+}
+
+#[derive(DeriveIden)]
+enum PostReferrers {
+    Table,
+    Id,
+    PostId,
+    Day,
+    Referrer,
+    Views,
+}
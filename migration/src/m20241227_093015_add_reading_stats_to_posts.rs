@@ -0,0 +1,42 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Computed from the content on save, reading time in minutes
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Posts::Table)
+                    .add_column(integer(Posts::WordCount).default(0))
+                    .add_column(integer(Posts::ReadingTime).default(0))
+                    .add_column(boolean(Posts::SummaryGenerated).default(false))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Posts::Table)
+                    .drop_column(Posts::SummaryGenerated)
+                    .drop_column(Posts::ReadingTime)
+                    .drop_column(Posts::WordCount)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Posts {
+    Table,
+    WordCount,
+    ReadingTime,
+    SummaryGenerated,
+}
@@ -0,0 +1,76 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Uploaded files, stored under `path` in the app storage
+        manager
+            .create_table(
+                table_auto_tz(Media::Table)
+                    .col(pk_auto(Media::Id))
+                    .col(uuid_uniq(Media::Pid))
+                    .col(uuid(Media::UserId))
+                    .col(string(Media::ContentType))
+                    .col(integer(Media::Size))
+                    .col(string(Media::Path))
+                    .col(string_null(Media::OriginalName))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-media-user_id")
+                    .table(Media::Table)
+                    .col(Media::UserId)
+                    .col(Media::Id)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Posts::Table)
+                    .add_column(integer_null(Posts::CoverMediaId))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Posts::Table)
+                    .drop_column(Posts::CoverMediaId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Media::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Media {
+    Table,
+    Id,
+    Pid,
+    UserId,
+    ContentType,
+    Size,
+    Path,
+    OriginalName,
+}
+
+#[derive(DeriveIden)]
+enum Posts {
+    Table,
+    CoverMediaId,
+}
@@ -0,0 +1,89 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Size of the original, and when its variants were built
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Media::Table)
+                    .add_column(integer_null(Media::Width))
+                    .add_column(integer_null(Media::Height))
+                    .add_column(timestamp_with_time_zone_null(Media::ProcessedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Resized copies of uploaded images, one per size and format
+        manager
+            .create_table(
+                table_auto_tz(MediaVariants::Table)
+                    .col(pk_auto(MediaVariants::Id))
+                    .col(integer(MediaVariants::MediaId))
+                    .col(string(MediaVariants::Name))
+                    .col(string(MediaVariants::Format))
+                    .col(string(MediaVariants::ContentType))
+                    .col(integer(MediaVariants::Width))
+                    .col(integer(MediaVariants::Height))
+                    .col(integer(MediaVariants::Size))
+                    .col(string(MediaVariants::Path))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-media_variants-media_id-name-format")
+                    .table(MediaVariants::Table)
+                    .col(MediaVariants::MediaId)
+                    .col(MediaVariants::Name)
+                    .col(MediaVariants::Format)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MediaVariants::Table).to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Media::Table)
+                    .drop_column(Media::ProcessedAt)
+                    .drop_column(Media::Height)
+                    .drop_column(Media::Width)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Media {
+    Table,
+    Width,
+    Height,
+    ProcessedAt,
+}
+
+#[derive(DeriveIden)]
+enum MediaVariants {
+    Table,
+    Id,
+    MediaId,
+    Name,
+    Format,
+    ContentType,
+    Width,
+    Height,
+    Size,
+    Path,
+}
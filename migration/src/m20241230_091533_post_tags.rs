@@ -0,0 +1,55 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(PostTags::Table)
+                    .col(pk_auto(PostTags::Id))
+                    .col(integer(PostTags::PostId))
+                    .col(string(PostTags::Tag))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-post_tags-post_id-tag")
+                    .table(PostTags::Table)
+                    .col(PostTags::PostId)
+                    .col(PostTags::Tag)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+        // Finding the posts with a tag
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-post_tags-tag")
+                    .table(PostTags::Table)
+                    .col(PostTags::Tag)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PostTags::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PostTags {
+    Table,
+    Id,
+    PostId,
+    Tag,
+}
@@ -0,0 +1,58 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Data export archives, stored under `path` in the app storage until
+        // they expire
+        manager
+            .create_table(
+                table_auto_tz(Exports::Table)
+                    .col(pk_auto(Exports::Id))
+                    .col(uuid_uniq(Exports::Token))
+                    .col(uuid(Exports::UserId))
+                    .col(string(Exports::Path))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-exports-user_id")
+                    .table(Exports::Table)
+                    .col(Exports::UserId)
+                    .to_owned(),
+            )
+            .await?;
+        // Finding the expired archives
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-exports-created_at")
+                    .table(Exports::Table)
+                    .col(Exports::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Exports::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Exports {
+    Table,
+    Id,
+    Token,
+    UserId,
+    Path,
+    CreatedAt,
+}
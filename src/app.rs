@@ -6,6 +6,7 @@ use loco_rs::{
     controller::AppRoutes,
     db::{self, truncate_table},
     environment::Environment,
+    storage::{self, Storage},
     task::Tasks,
     Result,
 };
@@ -14,7 +15,21 @@ use sea_orm::DatabaseConnection;
 use std::path::Path;
 
 use crate::{
-    controllers, initializers, models::_entities::users, tasks, workers::downloader::DownloadWorker,
+    common::{
+        analytics,
+        settings::{Settings, StorageDriver},
+    },
+    controllers, initializers,
+    models::_entities::{
+        audit_events, bookmark_collections, bookmarks, comments, exports, follows, invites, media,
+        media_variants, mentions, notifications, post_referrers, post_tags, post_views, posts,
+        reactions, reports, sessions, spam_tokens, users,
+    },
+    tasks,
+    workers::{
+        comment_digest::CommentDigestWorker, comment_notification::CommentNotificationWorker,
+        export::ExportWorker, media_variants::MediaVariantsWorker,
+    },
 };
 
 pub struct App;
@@ -38,10 +53,26 @@ impl Hooks for App {
         create_app::<Self, Migrator>(mode, environment).await
     }
 
+    async fn after_context(ctx: AppContext) -> Result<AppContext> {
+        let settings = Settings::from_context(&ctx)?;
+        let driver = match settings.storage.driver {
+            StorageDriver::Local => {
+                std::fs::create_dir_all(&settings.storage.path)?;
+                storage::drivers::local::new_with_prefix(&settings.storage.path)?
+            }
+            StorageDriver::Memory => storage::drivers::mem::new(),
+        };
+        Ok(AppContext {
+            storage: Storage::single(driver).into(),
+            ..ctx
+        })
+    }
+
     async fn initializers(_ctx: &AppContext) -> Result<Vec<Box<dyn Initializer>>> {
-        Ok(vec![Box::new(
-            initializers::view_engine::ViewEngineInitializer,
-        )])
+        Ok(vec![
+            Box::new(initializers::view_engine::ViewEngineInitializer),
+            Box::new(initializers::analytics::AnalyticsInitializer),
+        ])
     }
 
     fn routes(_ctx: &AppContext) -> AppRoutes {
@@ -49,16 +80,61 @@ impl Hooks for App {
             .add_route(controllers::comments::routes())
             .add_route(controllers::post::routes())
             .add_route(controllers::auth::routes())
+            .add_route(controllers::users::routes())
+            .add_route(controllers::invites::routes())
+            .add_route(controllers::audit::routes())
+            .add_route(controllers::notifications::routes())
+            .add_route(controllers::moderation::routes())
+            .add_route(controllers::reports::routes())
+            .add_route(controllers::bookmarks::routes())
+            .add_route(controllers::feed::routes())
+            .add_route(controllers::dashboard::routes())
+            .add_route(controllers::media::routes())
     }
     async fn connect_workers(ctx: &AppContext, queue: &Queue) -> Result<()> {
-        queue.register(DownloadWorker::build(ctx)).await?;
+        queue.register(ExportWorker::build(ctx)).await?;
+        queue
+            .register(CommentNotificationWorker::build(ctx))
+            .await?;
+        queue.register(CommentDigestWorker::build(ctx)).await?;
+        queue.register(MediaVariantsWorker::build(ctx)).await?;
         Ok(())
     }
     fn register_tasks(tasks: &mut Tasks) {
         tasks.register(tasks::seed::SeedData);
+        tasks.register(tasks::set_role::SetRole);
+        tasks.register(tasks::send_comment_digests::SendCommentDigests);
+        tasks.register(tasks::import_markdown::ImportMarkdown);
+        tasks.register(tasks::export_markdown::ExportMarkdown);
+        tasks.register(tasks::purge_exports::PurgeExports);
+        tasks.register(tasks::purge_sessions::PurgeSessions);
         // tasks-inject (do not remove)
     }
+    async fn on_shutdown(ctx: &AppContext) {
+        if let Err(err) = analytics::flush(&ctx.db).await {
+            tracing::error!(err = err.to_string(), "could not write post views");
+        }
+    }
     async fn truncate(db: &DatabaseConnection) -> Result<()> {
+        truncate_table(db, exports::Entity).await?;
+        truncate_table(db, post_tags::Entity).await?;
+        truncate_table(db, post_views::Entity).await?;
+        truncate_table(db, post_referrers::Entity).await?;
+        truncate_table(db, bookmarks::Entity).await?;
+        truncate_table(db, bookmark_collections::Entity).await?;
+        truncate_table(db, reactions::Entity).await?;
+        truncate_table(db, follows::Entity).await?;
+        truncate_table(db, reports::Entity).await?;
+        truncate_table(db, spam_tokens::Entity).await?;
+        truncate_table(db, mentions::Entity).await?;
+        truncate_table(db, notifications::Entity).await?;
+        truncate_table(db, comments::Entity).await?;
+        truncate_table(db, posts::Entity).await?;
+        truncate_table(db, media_variants::Entity).await?;
+        truncate_table(db, media::Entity).await?;
+        truncate_table(db, audit_events::Entity).await?;
+        truncate_table(db, invites::Entity).await?;
+        truncate_table(db, sessions::Entity).await?;
         truncate_table(db, users::Entity).await?;
         Ok(())
     }
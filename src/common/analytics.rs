@@ -0,0 +1,162 @@
+//! Page view counting of posts.
+//!
+//! Views are counted in memory and written in batches by [`flush`], every
+//! `flush_interval_seconds` and whenever `max_buffered` views are waiting, so
+//! reading a post doesn't cost a write. Stats add the [`buffered`] views to
+//! the stored ones. Visitors are told apart by a keyed
+//! hash of their IP and user agent that changes every day, so neither is
+//! stored and visitors can't be followed from one day to the next.
+//!
+//! Each app instance has its own buffer, so a visitor served by two
+//! instances within the dedupe window is counted twice.
+use std::{
+    collections::HashMap,
+    mem,
+    sync::{Mutex, MutexGuard, OnceLock, PoisonError},
+};
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use loco_rs::model::ModelResult;
+use sea_orm::DatabaseConnection;
+
+use crate::{
+    common::{settings::AnalyticsSettings, signing},
+    extractors::client::ClientInfo,
+    models::post_views,
+};
+
+/// Views of a post on a day, by visitor or referrer
+pub type Counts = HashMap<(i32, NaiveDate, String), i32>;
+
+#[derive(Default)]
+struct Buffer {
+    /// When each visitor was last counted on each post
+    seen: HashMap<(i32, String), DateTime<Utc>>,
+    /// How long a counted visitor is not counted again on the same post
+    window: Duration,
+    views: Counts,
+    referrers: Counts,
+    /// Views waiting to be written
+    pending: usize,
+}
+
+static BUFFER: OnceLock<Mutex<Buffer>> = OnceLock::new();
+
+fn buffer() -> MutexGuard<'static, Buffer> {
+    BUFFER
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Whether the user agent looks like a crawler, or is missing
+#[must_use]
+pub fn is_bot(settings: &AnalyticsSettings, user_agent: Option<&str>) -> bool {
+    let Some(user_agent) = user_agent.filter(|ua| !ua.trim().is_empty()) else {
+        return true;
+    };
+    let user_agent = user_agent.to_lowercase();
+    settings
+        .bot_user_agents
+        .iter()
+        .any(|bot| user_agent.contains(&bot.to_lowercase()))
+}
+
+/// The visitor of the day, a keyed hash of their IP and user agent
+#[must_use]
+pub fn visitor(secret: &str, day: NaiveDate, client: &ClientInfo) -> String {
+    signing::sign(
+        secret,
+        &format!(
+            "view:{day}:{}:{}",
+            client.ip.as_deref().unwrap_or_default(),
+            client.user_agent.as_deref().unwrap_or_default()
+        ),
+    )
+}
+
+/// Counts a view of the post by the client, unless it is a bot or the same
+/// visitor was counted within the dedupe window. Returns whether enough views
+/// are waiting that they should be flushed now.
+#[must_use]
+pub fn record(
+    settings: &AnalyticsSettings,
+    secret: &str,
+    post_id: i32,
+    client: &ClientInfo,
+) -> bool {
+    if is_bot(settings, client.user_agent.as_deref()) {
+        return false;
+    }
+    let now = Utc::now();
+    let day = now.date_naive();
+    let visitor = visitor(secret, day, client);
+    let window = Duration::minutes(settings.dedupe_window_minutes);
+
+    let mut buffer = buffer();
+    buffer.window = window;
+    let key = (post_id, visitor);
+    if buffer
+        .seen
+        .get(&key)
+        .is_some_and(|counted| now - *counted < window)
+    {
+        return false;
+    }
+    let (post_id, visitor) = key.clone();
+    buffer.seen.insert(key, now);
+    *buffer.views.entry((post_id, day, visitor)).or_default() += 1;
+    if let Some(referrer) = &client.referrer {
+        *buffer
+            .referrers
+            .entry((post_id, day, referrer.clone()))
+            .or_default() += 1;
+    }
+    buffer.pending += 1;
+    buffer.pending >= settings.max_buffered
+}
+
+/// Views counted but not written yet
+#[derive(Clone, Debug, Default)]
+pub struct Buffered {
+    pub views: Counts,
+    pub referrers: Counts,
+}
+
+/// The views of the posts matching that are still in the buffer. Reads add
+/// them to the stored views rather than flushing.
+#[must_use]
+pub fn buffered(matches: impl Fn(i32) -> bool) -> Buffered {
+    let buffer = buffer();
+    let of_posts = |counts: &Counts| {
+        counts
+            .iter()
+            .filter(|((post_id, _, _), _)| matches(*post_id))
+            .map(|(key, count)| (key.clone(), *count))
+            .collect()
+    };
+    Buffered {
+        views: of_posts(&buffer.views),
+        referrers: of_posts(&buffer.referrers),
+    }
+}
+
+/// Writes the buffered views. Views that fail to be written are dropped.
+///
+/// # Errors
+///
+/// When has DB query error
+pub async fn flush(db: &DatabaseConnection) -> ModelResult<()> {
+    let (views, referrers) = {
+        let mut buffer = buffer();
+        let now = Utc::now();
+        let window = buffer.window;
+        buffer.seen.retain(|_, counted| now - *counted < window);
+        buffer.pending = 0;
+        (
+            mem::take(&mut buffer.views),
+            mem::take(&mut buffer.referrers),
+        )
+    };
+    post_views::Model::add(db, views, referrers).await
+}
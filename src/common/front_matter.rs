@@ -0,0 +1,168 @@
+//! YAML front matter of Markdown files, as Hugo and Jekyll write it:
+//!
+//! ```markdown
+//! ---
+//! title: Hello
+//! date: 2024-05-01 10:00:00 +0200
+//! tags: [rust, web]
+//! slug: hello
+//! draft: false
+//! ---
+//!
+//! The content.
+//! ```
+//!
+//! TOML front matter (`+++`) is not supported.
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Line opening and closing the front matter
+const DELIMITER: &str = "---";
+
+/// The fields of the front matter, others are ignored
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct FrontMatter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Kept as written, see [`parse_date`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_tags",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slug: Option<String>,
+    #[serde(default)]
+    pub draft: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    /// Path of the cover image, relative to the file. Written by the export
+    /// only, covers are not imported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover: Option<String>,
+}
+
+/// A Markdown file split in its front matter and content
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Document<'a> {
+    pub front_matter: FrontMatter,
+    /// The content, without the blank lines around it
+    pub content: &'a str,
+}
+
+/// Tags as a list, or as one string separated by commas or spaces
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Tags {
+    List(Vec<String>),
+    Text(String),
+}
+
+fn deserialize_tags<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    Ok(match Option::<Tags>::deserialize(deserializer)? {
+        Some(Tags::List(tags)) => tags,
+        Some(Tags::Text(text)) => text
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|tag| !tag.is_empty())
+            .map(ToString::to_string)
+            .collect(),
+        None => Vec::new(),
+    })
+}
+
+/// Splits the front matter off the file. Files without front matter have
+/// the default one.
+///
+/// # Errors
+///
+/// When the front matter is not valid YAML or has fields of the wrong type
+pub fn parse(text: &str) -> Result<Document<'_>, serde_yaml::Error> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let Some((yaml, content)) = split(text) else {
+        return Ok(Document {
+            front_matter: FrontMatter::default(),
+            content: text.trim_matches(['\r', '\n']),
+        });
+    };
+    let front_matter = if yaml.trim().is_empty() {
+        FrontMatter::default()
+    } else {
+        serde_yaml::from_str(yaml)?
+    };
+    Ok(Document {
+        front_matter,
+        content: content.trim_matches(['\r', '\n']),
+    })
+}
+
+/// The YAML between the delimiters and the content after them
+fn split(text: &str) -> Option<(&str, &str)> {
+    let mut lines = text.split_inclusive('\n');
+    if lines.next()?.trim_end() != DELIMITER {
+        return None;
+    }
+    let start = text.find('\n')? + 1;
+    let mut pos = start;
+    for line in lines {
+        if line.trim_end() == DELIMITER {
+            return Some((&text[start..pos], &text[pos + line.len()..]));
+        }
+        pos += line.len();
+    }
+    None
+}
+
+/// Writes the front matter and content back as a Markdown file
+///
+/// # Errors
+///
+/// When the front matter can't be serialized
+pub fn render<T: Serialize>(front_matter: &T, content: &str) -> Result<String, serde_yaml::Error> {
+    let yaml = serde_yaml::to_string(front_matter)?;
+    Ok(format!(
+        "{DELIMITER}\n{yaml}{DELIMITER}\n\n{}\n",
+        content.trim_end()
+    ))
+}
+
+/// Reads a date as Hugo (RFC 3339) or Jekyll (`2024-05-01 10:00:00 +0200`)
+/// write it. Dates without a time zone are taken as UTC, and dates without a
+/// time as midnight.
+#[must_use]
+pub fn parse_date(date: &str) -> Option<DateTime<FixedOffset>> {
+    let date = date.trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(date) {
+        return Some(date);
+    }
+    for format in ["%Y-%m-%d %H:%M:%S %z", "%Y-%m-%d %H:%M %z"] {
+        if let Ok(date) = DateTime::parse_from_str(date, format) {
+            return Some(date);
+        }
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(date) = NaiveDateTime::parse_from_str(date, format) {
+            return Some(date.and_utc().fixed_offset());
+        }
+    }
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .ok()
+        .map(|date| {
+            date.and_time(chrono::NaiveTime::MIN)
+                .and_utc()
+                .fixed_offset()
+        })
+}
+
+/// Lowercase letters and digits, with single dashes in between, as used for
+/// slugs and file names
+#[must_use]
+pub fn slugify(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
@@ -0,0 +1,240 @@
+//! Image processing of uploads, in pure Rust.
+//!
+//! Originals are cleaned when uploaded: metadata that can carry the camera,
+//! the author or the place a photo was taken (EXIF with GPS, XMP, IPTC and
+//! text chunks) is cut out of the file without decoding it. Photos that rely
+//! on their EXIF orientation are turned upright and encoded again instead, as
+//! they would show rotated without it.
+//!
+//! Resized variants are built in the background by
+//! [`crate::workers::media_variants`]. WebP variants are lossless, the only
+//! WebP encoding available without native code.
+use std::io::Cursor;
+
+use image::{
+    codecs::{gif::GifEncoder, jpeg::JpegEncoder, png::PngEncoder, webp::WebPEncoder},
+    imageops::FilterType,
+    metadata::Orientation,
+    DynamicImage, ImageDecoder, ImageError, ImageReader, Limits,
+};
+
+use crate::models::media::ImageType;
+
+/// Quality of JPEG encoding, for variants and turned originals
+const JPEG_QUALITY: u8 = 85;
+
+/// Largest width or height of an upload, larger images are refused so that
+/// decoding them can't exhaust memory
+pub const MAX_DIMENSION: u32 = 12_000;
+
+/// A size images are scaled down to, to fit in a `max` × `max` box
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VariantSize {
+    pub name: &'static str,
+    pub max: u32,
+}
+
+/// The variants built for each upload, smallest first
+pub const VARIANT_SIZES: [VariantSize; 3] = [
+    VariantSize {
+        name: "thumbnail",
+        max: 320,
+    },
+    VariantSize {
+        name: "medium",
+        max: 960,
+    },
+    VariantSize {
+        name: "large",
+        max: 1920,
+    },
+];
+
+/// A cleaned upload with its size, as it displays
+#[derive(Debug)]
+pub struct Cleaned {
+    pub content: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+fn limits() -> Limits {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    limits
+}
+
+fn decoder(content: &[u8]) -> Result<impl ImageDecoder + '_, ImageError> {
+    let mut reader = ImageReader::new(Cursor::new(content)).with_guessed_format()?;
+    reader.limits(limits());
+    reader.into_decoder()
+}
+
+/// Decodes the image, turned upright according to its EXIF orientation
+///
+/// # Errors
+///
+/// When the content is not a valid image or is too large
+pub fn decode(content: &[u8]) -> Result<DynamicImage, ImageError> {
+    let mut decoder = decoder(content)?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+/// Removes the metadata of an uploaded image, see the module docs
+///
+/// # Errors
+///
+/// When the content is not a valid image or is too large
+pub fn clean(content: &[u8], image_type: ImageType) -> Result<Cleaned, ImageError> {
+    let mut decoder = decoder(content)?;
+    let (width, height) = decoder.dimensions();
+    let orientation = decoder.orientation()?;
+    if orientation == Orientation::NoTransforms {
+        return Ok(Cleaned {
+            content: strip_metadata(content, image_type),
+            width,
+            height,
+        });
+    }
+
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    Ok(Cleaned {
+        content: encode(&image, image_type)?,
+        width: image.width(),
+        height: image.height(),
+    })
+}
+
+/// Scales the image down to fit the size, smaller images are kept as is
+#[must_use]
+pub fn fit(image: &DynamicImage, size: VariantSize) -> DynamicImage {
+    if image.width() <= size.max && image.height() <= size.max {
+        image.clone()
+    } else {
+        image.resize(size.max, size.max, FilterType::Lanczos3)
+    }
+}
+
+/// Encodes the image in the format, without any metadata. Animated GIFs
+/// keep their first frame only.
+///
+/// # Errors
+///
+/// When the encoder fails
+pub fn encode(image: &DynamicImage, image_type: ImageType) -> Result<Vec<u8>, ImageError> {
+    let mut out = Vec::new();
+    match image_type {
+        ImageType::Jpeg => {
+            let encoder = JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY);
+            image.to_rgb8().write_with_encoder(encoder)?;
+        }
+        ImageType::Png => image.write_with_encoder(PngEncoder::new(&mut out))?,
+        ImageType::Gif => {
+            let mut encoder = GifEncoder::new(&mut out);
+            encoder.encode_frame(image::Frame::new(image.to_rgba8()))?;
+        }
+        ImageType::Webp => {
+            let encoder = WebPEncoder::new_lossless(&mut out);
+            image.to_rgba8().write_with_encoder(encoder)?;
+        }
+    }
+    Ok(out)
+}
+
+/// Cuts metadata out of the file, leaving the image data untouched. The
+/// content is expected to be of the given type.
+#[must_use]
+pub fn strip_metadata(content: &[u8], image_type: ImageType) -> Vec<u8> {
+    match image_type {
+        ImageType::Jpeg => strip_jpeg(content),
+        ImageType::Png => strip_png(content),
+        ImageType::Webp => strip_webp(content),
+        // GIF has no EXIF, comments and application data are harmless
+        ImageType::Gif => content.to_vec(),
+    }
+}
+
+/// Drops the APP1 (EXIF, XMP), APP13 (IPTC) and comment segments. Segments
+/// are read up to the start of the scan, the rest is copied as is.
+fn strip_jpeg(content: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(content.len());
+    out.extend_from_slice(&content[..2]);
+    let mut pos = 2;
+    while pos + 4 <= content.len() && content[pos] == 0xFF {
+        let marker = content[pos + 1];
+        let len = usize::from(u16::from_be_bytes([content[pos + 2], content[pos + 3]]));
+        let end = (pos + 2 + len).min(content.len());
+        if marker == 0xDA {
+            // start of scan, the image data follows
+            break;
+        }
+        if !matches!(marker, 0xE1 | 0xED | 0xFE) {
+            out.extend_from_slice(&content[pos..end]);
+        }
+        pos = end;
+    }
+    out.extend_from_slice(&content[pos.min(content.len())..]);
+    out
+}
+
+/// Drops the `eXIf`, `tEXt`, `zTXt`, `iTXt` and `tIME` chunks
+fn strip_png(content: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(content.len());
+    out.extend_from_slice(&content[..8]);
+    let mut pos = 8;
+    while pos + 12 <= content.len() {
+        let len = u32::from_be_bytes([
+            content[pos],
+            content[pos + 1],
+            content[pos + 2],
+            content[pos + 3],
+        ]);
+        let end = (pos + 12 + len as usize).min(content.len());
+        let kind = &content[pos + 4..pos + 8];
+        if !matches!(kind, b"eXIf" | b"tEXt" | b"zTXt" | b"iTXt" | b"tIME") {
+            out.extend_from_slice(&content[pos..end]);
+        }
+        pos = end;
+    }
+    out.extend_from_slice(&content[pos.min(content.len())..]);
+    out
+}
+
+/// Drops the `EXIF` and `XMP ` chunks and clears their flags in the `VP8X`
+/// header
+fn strip_webp(content: &[u8]) -> Vec<u8> {
+    /// Flags of the `VP8X` chunk telling EXIF and XMP chunks follow
+    const EXIF_XMP_FLAGS: u8 = 0b0000_1100;
+
+    let mut out = Vec::with_capacity(content.len());
+    out.extend_from_slice(&content[..12]);
+    let mut pos = 12;
+    while pos + 8 <= content.len() {
+        let len = u32::from_le_bytes([
+            content[pos + 4],
+            content[pos + 5],
+            content[pos + 6],
+            content[pos + 7],
+        ]) as usize;
+        // chunks are padded to an even size
+        let end = (pos + 8 + len + len % 2).min(content.len());
+        let kind = &content[pos..pos + 4];
+        if kind == b"VP8X" && end > pos + 8 {
+            let start = out.len();
+            out.extend_from_slice(&content[pos..end]);
+            out[start + 8] &= !EXIF_XMP_FLAGS;
+        } else if !matches!(kind, b"EXIF" | b"XMP ") {
+            out.extend_from_slice(&content[pos..end]);
+        }
+        pos = end;
+    }
+    out.extend_from_slice(&content[pos.min(content.len())..]);
+    let riff_size = u32::try_from(out.len() - 8).unwrap_or(u32::MAX);
+    out[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    out
+}
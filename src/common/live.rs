@@ -0,0 +1,28 @@
+//! In-process fan-out of "something changed for this user" signals, feeding
+//! the notification event streams. Each app instance has its own channel, so
+//! a user connected to another instance only catches up on the next change
+//! seen there or on reconnect.
+use std::sync::OnceLock;
+
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+/// Signals buffered per subscriber before the slowest ones start lagging
+const CAPACITY: usize = 256;
+
+static CHANNEL: OnceLock<broadcast::Sender<Uuid>> = OnceLock::new();
+
+fn channel() -> &'static broadcast::Sender<Uuid> {
+    CHANNEL.get_or_init(|| broadcast::channel(CAPACITY).0)
+}
+
+/// Tells the streams of the given user that their notifications changed
+pub fn publish(user_id: Uuid) {
+    // no receivers is fine, nobody is listening
+    let _ = channel().send(user_id);
+}
+
+#[must_use]
+pub fn subscribe() -> broadcast::Receiver<Uuid> {
+    channel().subscribe()
+}
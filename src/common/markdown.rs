@@ -0,0 +1,255 @@
+//! Plain text out of the Markdown of posts, for word counts and summaries.
+//!
+//! This is not a full Markdown parser: it drops the markup that shows up in
+//! posts (headings, emphasis, links, images, code, quotes, lists, tables and
+//! inline HTML) and keeps the words.
+
+/// Words read per minute when estimating reading time
+const WORDS_PER_MINUTE: u64 = 200;
+
+/// Longest generated summary, in characters
+pub const SUMMARY_LEN: usize = 200;
+
+/// A block of the content: a paragraph, heading, list, quote or code block
+#[derive(Debug, PartialEq, Eq)]
+enum Block {
+    /// Prose, with the markup removed
+    Text(String),
+    Heading(String),
+    Code(String),
+}
+
+/// The blocks of the content, split on blank lines and code fences
+fn blocks(markdown: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut code: Option<Vec<&str>> = None;
+
+    let flush = |paragraph: &mut Vec<&str>, blocks: &mut Vec<Block>| {
+        if !paragraph.is_empty() {
+            let text = strip_inline(&paragraph.join(" "));
+            if !text.is_empty() {
+                blocks.push(Block::Text(text));
+            }
+            paragraph.clear();
+        }
+    };
+
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            match code.take() {
+                Some(lines) => blocks.push(Block::Code(lines.join("\n"))),
+                None => {
+                    flush(&mut paragraph, &mut blocks);
+                    code = Some(Vec::new());
+                }
+            }
+            continue;
+        }
+        if let Some(lines) = code.as_mut() {
+            lines.push(line);
+            continue;
+        }
+        if trimmed.is_empty() || is_rule(trimmed) {
+            flush(&mut paragraph, &mut blocks);
+        } else if trimmed.starts_with('#') {
+            flush(&mut paragraph, &mut blocks);
+            let heading = strip_inline(trimmed.trim_start_matches('#'));
+            if !heading.is_empty() {
+                blocks.push(Block::Heading(heading));
+            }
+        } else if !is_table_divider(trimmed) {
+            paragraph.push(strip_line_prefix(trimmed));
+        }
+    }
+    if let Some(lines) = code {
+        // unclosed fence, the rest of the content is code
+        blocks.push(Block::Code(lines.join("\n")));
+    }
+    flush(&mut paragraph, &mut blocks);
+    blocks
+}
+
+/// `---`, `***` or `___` on their own line
+fn is_rule(line: &str) -> bool {
+    let marks: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3
+        && ["-", "*", "_"]
+            .iter()
+            .any(|mark| marks.chars().all(|c| c.to_string() == *mark))
+}
+
+/// The `|---|:---:|` line under a table header
+fn is_table_divider(line: &str) -> bool {
+    line.contains('-') && line.chars().all(|c| matches!(c, '|' | '-' | ':' | ' '))
+}
+
+/// Drops quote markers and list bullets or numbers from the line
+fn strip_line_prefix(line: &str) -> &str {
+    let mut line = line;
+    while let Some(rest) = line.strip_prefix('>') {
+        line = rest.trim_start();
+    }
+    for bullet in ["- [ ] ", "- [x] ", "- ", "* ", "+ "] {
+        if let Some(rest) = line.strip_prefix(bullet) {
+            return rest.trim_start();
+        }
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 {
+        if let Some(rest) = line[digits..]
+            .strip_prefix(". ")
+            .or_else(|| line[digits..].strip_prefix(") "))
+        {
+            return rest.trim_start();
+        }
+    }
+    line
+}
+
+/// Removes inline markup: images, link targets, emphasis, code ticks, HTML
+/// tags and table pipes
+fn strip_inline(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '!' if chars.get(i + 1) == Some(&'[') => {
+                // images say nothing in plain text
+                i = skip_link(&chars, i + 1).unwrap_or(i + 1);
+                continue;
+            }
+            '[' => {
+                if let Some(end) = skip_link(&chars, i) {
+                    let label: String = chars[i + 1..label_end(&chars, i)].iter().collect();
+                    out.push_str(&strip_inline(&label));
+                    i = end;
+                    continue;
+                }
+            }
+            '<' => {
+                if let Some(close) = chars[i..].iter().position(|c| *c == '>') {
+                    let tag: String = chars[i + 1..i + close].iter().collect();
+                    if tag.starts_with(|c: char| c.is_ascii_alphabetic() || c == '/') {
+                        out.push(' ');
+                        i += close + 1;
+                        continue;
+                    }
+                }
+            }
+            '*' | '_' | '`' | '~' | '|' => {
+                // an underscore inside a word is part of it
+                let inside_word = c == '_'
+                    && i > 0
+                    && chars[i - 1].is_alphanumeric()
+                    && chars.get(i + 1).is_some_and(|next| next.is_alphanumeric());
+                if !inside_word {
+                    if c == '|' {
+                        out.push(' ');
+                    }
+                    i += 1;
+                    continue;
+                }
+            }
+            '\\' if chars.get(i + 1).is_some_and(char::is_ascii_punctuation) => {
+                out.push(chars[i + 1]);
+                i += 2;
+                continue;
+            }
+            _ => {}
+        }
+        out.push(c);
+        i += 1;
+    }
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Index of the `]` closing the label opened at `start`
+fn label_end(chars: &[char], start: usize) -> usize {
+    let mut depth = 0;
+    for (offset, c) in chars[start..].iter().enumerate() {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return start + offset;
+                }
+            }
+            _ => {}
+        }
+    }
+    chars.len()
+}
+
+/// Index right after the `[label](target)` starting at `start`, if it is one
+fn skip_link(chars: &[char], start: usize) -> Option<usize> {
+    let end = label_end(chars, start);
+    if chars.get(end + 1) != Some(&'(') {
+        return None;
+    }
+    let close = chars[end + 1..].iter().position(|c| *c == ')')?;
+    Some(end + 1 + close + 1)
+}
+
+/// The words of the content, code included
+#[must_use]
+pub fn word_count(markdown: &str) -> u64 {
+    let words = blocks(markdown)
+        .iter()
+        .map(|block| match block {
+            Block::Text(text) | Block::Heading(text) | Block::Code(text) => {
+                text.split_whitespace().count()
+            }
+        })
+        .sum::<usize>();
+    u64::try_from(words).unwrap_or(u64::MAX)
+}
+
+/// Minutes it takes to read that many words, at least one for any content
+#[must_use]
+pub const fn reading_time(words: u64) -> u64 {
+    words.div_ceil(WORDS_PER_MINUTE)
+}
+
+/// The text of the first paragraphs, without markup, headings or code, cut
+/// at a word boundary to at most `max_len` characters. `None` when there is
+/// no prose.
+#[must_use]
+pub fn summary(markdown: &str, max_len: usize) -> Option<String> {
+    let mut summary = String::new();
+    for block in blocks(markdown) {
+        let Block::Text(text) = block else {
+            continue;
+        };
+        if !summary.is_empty() {
+            summary.push(' ');
+        }
+        summary.push_str(&text);
+        if summary.chars().count() >= max_len {
+            break;
+        }
+    }
+    if summary.is_empty() {
+        return None;
+    }
+    if summary.chars().count() <= max_len {
+        return Some(summary);
+    }
+
+    // leave room for the ellipsis
+    let limit = max_len.saturating_sub(1);
+    let cut: String = summary.chars().take(limit + 1).collect();
+    let at_boundary = cut.chars().last().is_some_and(char::is_whitespace);
+    let mut cut: String = cut.chars().take(limit).collect();
+    if !at_boundary {
+        if let Some(space) = cut.rfind(char::is_whitespace) {
+            cut.truncate(space);
+        }
+    }
+    let cut = cut.trim_end_matches(|c: char| c.is_whitespace() || ",;:-".contains(c));
+    Some(format!("{cut}…"))
+}
@@ -0,0 +1,8 @@
+pub mod settings;
+pub mod signing;
+pub mod live;
+pub mod spam;
+pub mod analytics;
+pub mod markdown;
+pub mod images;
+pub mod front_matter;
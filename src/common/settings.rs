@@ -0,0 +1,282 @@
+//! Application specific settings, read from the `settings:` section of the
+//! environment config file.
+//!
+//! ```yaml
+//! settings:
+//!   accounts:
+//!     deletion_mode: anonymize
+//!     invite_only: false
+//!     export_ttl_hours: 48
+//!     email_change_token_hours: 24
+//!   analytics:
+//!     dedupe_window_minutes: 30
+//!     bot_user_agents: [bot, crawler]
+//!   comments:
+//!     moderation: false
+//!     auto_approve_after: 3
+//!   media:
+//!     max_bytes: 5242880
+//!   reactions:
+//!     emojis: ["👍", "❤️", "🎉"]
+//!   reports:
+//!     hide_after: 3
+//!   spam:
+//!     enabled: true
+//!     blocked_words: [casino]
+//!   storage:
+//!     driver: local
+//!     path: storage
+//! ```
+use loco_rs::{app::AppContext, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::models::users::DeletionMode;
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Settings {
+    #[serde(default)]
+    pub accounts: AccountSettings,
+    #[serde(default)]
+    pub analytics: AnalyticsSettings,
+    #[serde(default)]
+    pub comments: CommentSettings,
+    #[serde(default)]
+    pub media: MediaSettings,
+    #[serde(default)]
+    pub reactions: ReactionSettings,
+    #[serde(default)]
+    pub reports: ReportSettings,
+    #[serde(default)]
+    pub spam: SpamSettings,
+    #[serde(default)]
+    pub storage: StorageSettings,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AccountSettings {
+    /// What happens to the posts and comments of a deleted account, unless
+    /// the user picks otherwise
+    pub deletion_mode: DeletionMode,
+    /// Registration requires an invite code issued by an admin
+    pub invite_only: bool,
+    /// Hours a data export can be downloaded. Older archives are deleted by
+    /// the `purge_exports` task.
+    pub export_ttl_hours: i64,
+    /// Hours the link confirming a new email address works
+    pub email_change_token_hours: i64,
+}
+
+impl Default for AccountSettings {
+    fn default() -> Self {
+        Self {
+            deletion_mode: DeletionMode::default(),
+            invite_only: false,
+            export_ttl_hours: 48,
+            email_change_token_hours: 24,
+        }
+    }
+}
+
+/// Page view counting of posts, see [`crate::common::analytics`]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AnalyticsSettings {
+    pub enabled: bool,
+    /// Views of the same visitor within this many minutes count once
+    pub dedupe_window_minutes: i64,
+    /// How often buffered views are written to the database
+    pub flush_interval_seconds: u64,
+    /// Buffered views that trigger a write before the interval is up
+    pub max_buffered: usize,
+    /// Views from user agents containing any of these, case insensitive, are
+    /// not counted. Views without a user agent are not counted either.
+    pub bot_user_agents: Vec<String>,
+}
+
+impl Default for AnalyticsSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dedupe_window_minutes: 30,
+            flush_interval_seconds: 10,
+            max_buffered: 1000,
+            bot_user_agents: [
+                "bot",
+                "crawl",
+                "spider",
+                "slurp",
+                "preview",
+                "headless",
+                "curl",
+                "wget",
+                "python-requests",
+                "facebookexternalhit",
+            ]
+            .into_iter()
+            .map(ToString::to_string)
+            .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CommentSettings {
+    /// Hold every new comment for moderation, not just those on posts that
+    /// ask for it
+    #[serde(default)]
+    pub moderation: bool,
+    /// Comments of users with at least this many approved comments and none
+    /// marked as spam skip the moderation queue
+    #[serde(default = "default_auto_approve_after")]
+    pub auto_approve_after: u64,
+}
+
+const fn default_auto_approve_after() -> u64 {
+    3
+}
+
+impl Default for CommentSettings {
+    fn default() -> Self {
+        Self {
+            moderation: false,
+            auto_approve_after: default_auto_approve_after(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MediaSettings {
+    /// Largest accepted upload, in bytes
+    pub max_bytes: usize,
+}
+
+impl Default for MediaSettings {
+    fn default() -> Self {
+        Self {
+            max_bytes: 5 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ReactionSettings {
+    /// The emojis users can react with to posts and comments
+    pub emojis: Vec<String>,
+}
+
+impl Default for ReactionSettings {
+    fn default() -> Self {
+        Self {
+            emojis: ["👍", "❤️", "😂", "🎉", "😮", "😢"]
+                .into_iter()
+                .map(ToString::to_string)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ReportSettings {
+    /// Posts and comments reported by this many distinct users are hidden
+    /// until a moderator looks at them
+    pub hide_after: u64,
+}
+
+impl Default for ReportSettings {
+    fn default() -> Self {
+        Self { hide_after: 3 }
+    }
+}
+
+/// Spam scoring of new comments, see [`crate::common::spam`]. Scores go from
+/// 0 to 1, the highest one of the scorers counts.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SpamSettings {
+    pub enabled: bool,
+    /// Comments scoring above this are held for moderation
+    pub moderate_above: f64,
+    /// Comments scoring above this are marked as spam right away
+    pub reject_above: f64,
+    /// Links allowed in a comment before it looks suspicious
+    pub max_links: usize,
+    /// Words that mark a comment as spam, case insensitive
+    pub blocked_words: Vec<String>,
+    /// Link domains that mark a comment as spam, subdomains included
+    pub blocked_domains: Vec<String>,
+    /// How far back to look for the same content posted before
+    pub duplicate_window_minutes: i64,
+    /// Comments a user may post within `velocity_window_seconds`
+    pub velocity_limit: u64,
+    pub velocity_window_seconds: i64,
+    /// Score with the classifier trained from moderator decisions
+    pub bayes: bool,
+}
+
+impl Default for SpamSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            moderate_above: 0.5,
+            reject_above: 0.9,
+            max_links: 2,
+            blocked_words: Vec::new(),
+            blocked_domains: Vec::new(),
+            duplicate_window_minutes: 60,
+            velocity_limit: 5,
+            velocity_window_seconds: 60,
+            bayes: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageDriver {
+    /// Files on the local disk under `path`
+    Local,
+    /// Files kept in memory, lost on restart. Meant for tests.
+    Memory,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StorageSettings {
+    pub driver: StorageDriver,
+    pub path: String,
+}
+
+impl Default for StorageSettings {
+    fn default() -> Self {
+        Self {
+            driver: StorageDriver::Local,
+            path: "storage".to_string(),
+        }
+    }
+}
+
+impl Settings {
+    /// Parses the settings from their config JSON value
+    ///
+    /// # Errors
+    ///
+    /// When the value does not match the settings shape
+    pub fn from_json(value: &serde_json::Value) -> Result<Self> {
+        Ok(serde_json::from_value(value.clone())?)
+    }
+
+    /// Settings of the running app, defaults when the config has none
+    ///
+    /// # Errors
+    ///
+    /// When the configured settings are invalid
+    pub fn from_context(ctx: &AppContext) -> Result<Self> {
+        ctx.config
+            .settings
+            .as_ref()
+            .map_or_else(|| Ok(Self::default()), Self::from_json)
+    }
+}
@@ -0,0 +1,27 @@
+//! HMAC-SHA256 signatures for values handed out in links, e.g. unsubscribe
+//! tokens, so they can be trusted when they come back without a login.
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn mac(secret: &str, message: &str) -> HmacSha256 {
+    // HMAC accepts keys of any length, this can't fail
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("any key length");
+    mac.update(message.as_bytes());
+    mac
+}
+
+/// Hex encoded signature of the message
+#[must_use]
+pub fn sign(secret: &str, message: &str) -> String {
+    hex::encode(mac(secret, message).finalize().into_bytes())
+}
+
+/// Whether the hex encoded signature matches the message, compared in
+/// constant time
+#[must_use]
+pub fn verify(secret: &str, message: &str, signature: &str) -> bool {
+    hex::decode(signature)
+        .is_ok_and(|signature| mac(secret, message).verify_slice(&signature).is_ok())
+}
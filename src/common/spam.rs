@@ -0,0 +1,296 @@
+//! Spam scoring of new comments.
+//!
+//! Each [`SpamScorer`] rates a comment from 0 (fine) to 1 (certainly spam),
+//! and the [`SpamFilter`] turns the highest rating into a [`Verdict`] using
+//! the thresholds of the settings. The built-in scorers are enabled from the
+//! `spam:` settings, others can be plugged in:
+//!
+//! ```rust,ignore
+//! let filter = SpamFilter::from_settings(&settings.spam).with(MyScorer);
+//! let verdict = filter.check(&ctx.db, &candidate).await?;
+//! ```
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use loco_rs::Result;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter};
+use uuid::Uuid;
+
+use crate::{
+    common::settings::SpamSettings,
+    models::{_entities::comments, spam_tokens},
+};
+
+/// A comment about to be saved
+#[derive(Clone, Debug)]
+pub struct Candidate<'a> {
+    pub content: &'a str,
+    pub user_id: Uuid,
+    pub post_id: Option<i32>,
+}
+
+#[async_trait]
+pub trait SpamScorer: Send + Sync {
+    /// Short name, logged along with the score
+    fn name(&self) -> &'static str;
+
+    /// How likely the candidate is spam, from 0 to 1
+    async fn score(&self, db: &DatabaseConnection, candidate: &Candidate<'_>) -> Result<f64>;
+}
+
+/// What to do with a scored comment
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Accept,
+    /// Hold it for a moderator
+    Moderate,
+    /// Mark it as spam
+    Reject,
+}
+
+/// Hosts of the `http` and `https` links in the content, lowercased
+fn link_hosts(content: &str) -> Vec<String> {
+    let content = content.to_lowercase();
+    content
+        .match_indices("http")
+        .filter_map(|(start, _)| {
+            let rest = &content[start + 4..];
+            let rest = rest
+                .strip_prefix("://")
+                .or_else(|| rest.strip_prefix("s://"))?;
+            let host: String = rest
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '.' || *c == '-')
+                .collect();
+            (!host.is_empty()).then_some(host)
+        })
+        .collect()
+}
+
+/// Comments with more links than allowed, the more the worse
+pub struct LinkCount {
+    pub max_links: usize,
+}
+
+#[async_trait]
+impl SpamScorer for LinkCount {
+    fn name(&self) -> &'static str {
+        "links"
+    }
+
+    async fn score(&self, _db: &DatabaseConnection, candidate: &Candidate<'_>) -> Result<f64> {
+        let extra = link_hosts(candidate.content)
+            .len()
+            .saturating_sub(self.max_links);
+        if extra == 0 {
+            return Ok(0.0);
+        }
+        #[allow(clippy::cast_precision_loss)]
+        Ok((0.5 + 0.1 * extra as f64).min(1.0))
+    }
+}
+
+/// Comments with a blocked word, or a link to a blocked domain
+pub struct Blocklist {
+    pub words: Vec<String>,
+    pub domains: Vec<String>,
+}
+
+#[async_trait]
+impl SpamScorer for Blocklist {
+    fn name(&self) -> &'static str {
+        "blocklist"
+    }
+
+    async fn score(&self, _db: &DatabaseConnection, candidate: &Candidate<'_>) -> Result<f64> {
+        let words = spam_tokens::tokens(candidate.content);
+        let blocked_word = self
+            .words
+            .iter()
+            .any(|blocked| words.contains(&blocked.to_lowercase()));
+        let blocked_domain = link_hosts(candidate.content).iter().any(|host| {
+            self.domains.iter().any(|domain| {
+                let domain = domain.to_lowercase();
+                *host == domain || host.ends_with(&format!(".{domain}"))
+            })
+        });
+        Ok(if blocked_word || blocked_domain {
+            1.0
+        } else {
+            0.0
+        })
+    }
+}
+
+/// The same content posted again recently: by the same user it is certainly
+/// spam, by someone else it is suspicious. Short comments like "Thanks!" are
+/// left alone.
+pub struct Duplicate {
+    pub window: Duration,
+}
+
+/// Shorter comments are expected to repeat
+const MIN_DUPLICATE_LEN: usize = 20;
+
+#[async_trait]
+impl SpamScorer for Duplicate {
+    fn name(&self) -> &'static str {
+        "duplicate"
+    }
+
+    async fn score(&self, db: &DatabaseConnection, candidate: &Candidate<'_>) -> Result<f64> {
+        if candidate.content.trim().chars().count() < MIN_DUPLICATE_LEN {
+            return Ok(0.0);
+        }
+        let copies = comments::Entity::find()
+            .filter(comments::Column::Content.eq(candidate.content))
+            .filter(comments::Column::CreatedAt.gt(Utc::now() - self.window))
+            .all(db)
+            .await?;
+        Ok(if copies.iter().any(|c| c.user_id == candidate.user_id) {
+            1.0
+        } else if copies.is_empty() {
+            0.0
+        } else {
+            0.7
+        })
+    }
+}
+
+/// Users commenting faster than the limit, flooding at twice the limit
+pub struct Velocity {
+    pub limit: u64,
+    pub window: Duration,
+}
+
+#[async_trait]
+impl SpamScorer for Velocity {
+    fn name(&self) -> &'static str {
+        "velocity"
+    }
+
+    async fn score(&self, db: &DatabaseConnection, candidate: &Candidate<'_>) -> Result<f64> {
+        let recent = comments::Entity::find()
+            .filter(comments::Column::UserId.eq(candidate.user_id))
+            .filter(comments::Column::CreatedAt.gt(Utc::now() - self.window))
+            .count(db)
+            .await?;
+        Ok(if recent >= self.limit.saturating_mul(2) {
+            1.0
+        } else if recent >= self.limit {
+            0.7
+        } else {
+            0.0
+        })
+    }
+}
+
+/// Naive Bayes classifier trained from moderator decisions, see
+/// [`spam_tokens::Model::train`]. Scores 0 until it has enough training.
+pub struct Bayes;
+
+#[async_trait]
+impl SpamScorer for Bayes {
+    fn name(&self) -> &'static str {
+        "bayes"
+    }
+
+    async fn score(&self, db: &DatabaseConnection, candidate: &Candidate<'_>) -> Result<f64> {
+        Ok(spam_tokens::Model::spam_probability(db, candidate.content)
+            .await?
+            .unwrap_or(0.0))
+    }
+}
+
+pub struct SpamFilter {
+    scorers: Vec<Box<dyn SpamScorer>>,
+    moderate_above: f64,
+    reject_above: f64,
+}
+
+impl SpamFilter {
+    /// A filter without scorers, accepting everything until some are added
+    #[must_use]
+    pub fn new(moderate_above: f64, reject_above: f64) -> Self {
+        Self {
+            scorers: Vec::new(),
+            moderate_above,
+            reject_above,
+        }
+    }
+
+    /// A filter with the built-in scorers, none when spam scoring is disabled
+    #[must_use]
+    pub fn from_settings(settings: &SpamSettings) -> Self {
+        let filter = Self::new(settings.moderate_above, settings.reject_above);
+        if !settings.enabled {
+            return filter;
+        }
+        let filter = filter
+            .with(LinkCount {
+                max_links: settings.max_links,
+            })
+            .with(Blocklist {
+                words: settings.blocked_words.clone(),
+                domains: settings.blocked_domains.clone(),
+            })
+            .with(Duplicate {
+                window: Duration::minutes(settings.duplicate_window_minutes),
+            })
+            .with(Velocity {
+                limit: settings.velocity_limit,
+                window: Duration::seconds(settings.velocity_window_seconds),
+            });
+        if settings.bayes {
+            filter.with(Bayes)
+        } else {
+            filter
+        }
+    }
+
+    #[must_use]
+    pub fn with(mut self, scorer: impl SpamScorer + 'static) -> Self {
+        self.scorers.push(Box::new(scorer));
+        self
+    }
+
+    /// Scores the candidate with every scorer and judges it by the highest
+    /// score
+    ///
+    /// # Errors
+    ///
+    /// When a scorer fails
+    pub async fn check(
+        &self,
+        db: &DatabaseConnection,
+        candidate: &Candidate<'_>,
+    ) -> Result<Verdict> {
+        let mut highest: Option<(&str, f64)> = None;
+        for scorer in &self.scorers {
+            let score = scorer.score(db, candidate).await?;
+            if highest.is_none_or(|(_, best)| score > best) {
+                highest = Some((scorer.name(), score));
+            }
+        }
+        let Some((scorer, score)) = highest else {
+            return Ok(Verdict::Accept);
+        };
+
+        let verdict = if score > self.reject_above {
+            Verdict::Reject
+        } else if score > self.moderate_above {
+            Verdict::Moderate
+        } else {
+            Verdict::Accept
+        };
+        if verdict != Verdict::Accept {
+            tracing::info!(
+                user_id = candidate.user_id.to_string(),
+                scorer,
+                score,
+                ?verdict,
+                "comment looks like spam"
+            );
+        }
+        Ok(verdict)
+    }
+}
@@ -0,0 +1,34 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unused_async)]
+use axum::debug_handler;
+use axum::extract::{Query, State};
+use loco_rs::prelude::*;
+
+use crate::{
+    controllers::post::{paginate_as, PaginatedResponse, PaginationParams},
+    extractors::auth,
+    models::{
+        audit_events::{self, SearchParams},
+        users::Role,
+    },
+    views::audit::AuditEventResponse,
+};
+
+/// Audit log of every user, filtered by actor, event, outcome, IP or time
+/// range
+#[debug_handler]
+pub async fn list(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Query(filters): Query<SearchParams>,
+    Query(params): Query<PaginationParams>,
+) -> Result<Response> {
+    auth.require_role(&ctx, Role::Admin).await?;
+    let query = audit_events::Model::search(&filters);
+    let page: PaginatedResponse<AuditEventResponse> = paginate_as(&ctx.db, query, &params).await?;
+    format::json(page)
+}
+
+pub fn routes() -> Routes {
+    Routes::new().prefix("api/admin/audit/").add("/", get(list))
+}
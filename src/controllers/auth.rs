@@ -1,14 +1,28 @@
-use axum::debug_handler;
-use loco_rs::prelude::*;
+use std::path::PathBuf;
+
+use axum::{
+    debug_handler,
+    http::{header, StatusCode},
+};
+use chrono::Duration;
+use loco_rs::{controller::bad_request, prelude::*};
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    common::settings::Settings,
+    extractors::{auth, client::ClientInfo},
     mailers::auth::AuthMailer,
     models::{
         _entities::users,
-        users::{LoginParams, RegisterParams},
+        audit_events::{self, EventKind, Outcome},
+        exports, invites, sessions,
+        users::{DeletionMode, LoginParams, ProfileParams, RegisterParams},
+    },
+    views::{
+        audit::AuditEventResponse,
+        auth::{ApiKeyResponse, CurrentResponse, LoginResponse, SessionResponse},
     },
-    views::auth::{CurrentResponse, LoginResponse},
+    workers::export::{ExportWorker, ExportWorkerArgs},
 };
 #[derive(Debug, Deserialize, Serialize)]
 pub struct VerifyParams {
@@ -26,26 +40,89 @@ pub struct ResetParams {
     pub password: String,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ChangePasswordParams {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ChangeEmailParams {
+    pub password: String,
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ConfirmEmailParams {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DeleteAccountParams {
+    pub password: String,
+    /// Defaults to the configured `accounts.deletion_mode`
+    pub mode: Option<DeletionMode>,
+}
+
 /// Register function creates a new user with the given parameters and sends a
-/// welcome email to the user
+/// welcome email to the user. In invite only mode the parameters must carry a
+/// usable invite code.
 #[debug_handler]
 async fn register(
     State(ctx): State<AppContext>,
+    client: ClientInfo,
     Json(params): Json<RegisterParams>,
 ) -> Result<Response> {
+    let failure = |reason: &str| {
+        client
+            .audit(EventKind::Register, Outcome::Failure)
+            .detail(format!("{}: {reason}", params.email))
+    };
+
+    // invite problems are reported, unlike the other failures below: they
+    // don't reveal whether the email is registered
+    let settings = Settings::from_context(&ctx)?;
+    match &params.invite_code {
+        Some(code) => {
+            let usable = invites::Model::find_by_code(&ctx.db, code)
+                .await
+                .is_ok_and(|invite| invite.is_usable_by(&params.email));
+            if !usable {
+                failure("invalid invite").record(&ctx.db).await?;
+                return bad_request("invite code is not valid");
+            }
+        }
+        None if settings.accounts.invite_only => {
+            failure("missing invite").record(&ctx.db).await?;
+            return bad_request("registration requires an invite code");
+        }
+        None => {}
+    }
+
     let res = users::Model::create_with_password(&ctx.db, &params).await;
 
     let user = match res {
         Ok(user) => user,
+        // the invite was used up by a concurrent registration
+        Err(ModelError::EntityNotFound) => {
+            failure("invalid invite").record(&ctx.db).await?;
+            return bad_request("invite code is not valid");
+        }
         Err(err) => {
             tracing::info!(
                 message = err.to_string(),
                 user_email = &params.email,
                 "could not register user",
             );
+            failure(&err.to_string()).record(&ctx.db).await?;
             return format::json(());
         }
     };
+    client
+        .audit(EventKind::Register, Outcome::Success)
+        .user(user.pid)
+        .record(&ctx.db)
+        .await?;
 
     let user = user
         .into_active_model()
@@ -62,9 +139,25 @@ async fn register(
 #[debug_handler]
 async fn verify(
     State(ctx): State<AppContext>,
+    client: ClientInfo,
     Json(params): Json<VerifyParams>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_verification_token(&ctx.db, &params.token).await?;
+    let user = match users::Model::find_by_verification_token(&ctx.db, &params.token).await {
+        Ok(user) => user,
+        Err(err) => {
+            client
+                .audit(EventKind::Verify, Outcome::Failure)
+                .detail("verification token not found")
+                .record(&ctx.db)
+                .await?;
+            return Err(err.into());
+        }
+    };
+    client
+        .audit(EventKind::Verify, Outcome::Success)
+        .user(user.pid)
+        .record(&ctx.db)
+        .await?;
 
     if user.email_verified_at.is_some() {
         tracing::info!(pid = user.pid.to_string(), "user already verified");
@@ -84,13 +177,24 @@ async fn verify(
 #[debug_handler]
 async fn forgot(
     State(ctx): State<AppContext>,
+    client: ClientInfo,
     Json(params): Json<ForgotParams>,
 ) -> Result<Response> {
     let Ok(user) = users::Model::find_by_email(&ctx.db, &params.email).await else {
+        client
+            .audit(EventKind::PasswordResetRequest, Outcome::Failure)
+            .detail(format!("{}: unknown email", params.email))
+            .record(&ctx.db)
+            .await?;
         // we don't want to expose our users email. if the email is invalid we still
         // returning success to the caller
         return format::json(());
     };
+    client
+        .audit(EventKind::PasswordResetRequest, Outcome::Success)
+        .user(user.pid)
+        .record(&ctx.db)
+        .await?;
 
     let user = user
         .into_active_model()
@@ -104,36 +208,85 @@ async fn forgot(
 
 /// reset user password by the given parameters
 #[debug_handler]
-async fn reset(State(ctx): State<AppContext>, Json(params): Json<ResetParams>) -> Result<Response> {
+async fn reset(
+    State(ctx): State<AppContext>,
+    client: ClientInfo,
+    Json(params): Json<ResetParams>,
+) -> Result<Response> {
     let Ok(user) = users::Model::find_by_reset_token(&ctx.db, &params.token).await else {
         // we don't want to expose our users email. if the email is invalid we still
         // returning success to the caller
         tracing::info!("reset token not found");
+        client
+            .audit(EventKind::PasswordReset, Outcome::Failure)
+            .detail("reset token not found")
+            .record(&ctx.db)
+            .await?;
 
         return format::json(());
     };
-    user.into_active_model()
+    let user = user
+        .into_active_model()
         .reset_password(&ctx.db, &params.password)
         .await?;
+    client
+        .audit(EventKind::PasswordReset, Outcome::Success)
+        .user(user.pid)
+        .record(&ctx.db)
+        .await?;
 
     format::json(())
 }
 
-/// Creates a user login and returns a token
+/// Creates a user login and returns a token. Each login is recorded as a
+/// session the user can list and revoke.
 #[debug_handler]
-async fn login(State(ctx): State<AppContext>, Json(params): Json<LoginParams>) -> Result<Response> {
-    let user = users::Model::find_by_email(&ctx.db, &params.email).await?;
+async fn login(
+    State(ctx): State<AppContext>,
+    client: ClientInfo,
+    Json(params): Json<LoginParams>,
+) -> Result<Response> {
+    let user = match users::Model::find_by_email(&ctx.db, &params.email).await {
+        Ok(user) => user,
+        Err(err) => {
+            client
+                .audit(EventKind::Login, Outcome::Failure)
+                .detail(format!("{}: unknown email", params.email))
+                .record(&ctx.db)
+                .await?;
+            return Err(err.into());
+        }
+    };
 
     let valid = user.verify_password(&params.password);
 
     if !valid {
+        client
+            .audit(EventKind::Login, Outcome::Failure)
+            .user(user.pid)
+            .detail("wrong password")
+            .record(&ctx.db)
+            .await?;
         return unauthorized("unauthorized!");
     }
 
     let jwt_secret = ctx.config.get_jwt_config()?;
 
+    let session = sessions::Model::create(
+        &ctx.db,
+        user.pid,
+        client.user_agent.clone(),
+        client.ip.clone(),
+    )
+    .await?;
+    client
+        .audit(EventKind::Login, Outcome::Success)
+        .user(user.pid)
+        .record(&ctx.db)
+        .await?;
+
     let token = user
-        .generate_jwt(&jwt_secret.secret, &jwt_secret.expiration)
+        .generate_jwt(&jwt_secret.secret, &jwt_secret.expiration, session.id)
         .or_else(|_| unauthorized("unauthorized!"))?;
 
     format::json(LoginResponse::new(&user, &token))
@@ -145,6 +298,286 @@ async fn current(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Respo
     format::json(CurrentResponse::new(&user))
 }
 
+/// Issues a fresh token for the session of the caller, pushing back its
+/// expiration
+#[debug_handler]
+async fn refresh(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    client: ClientInfo,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+
+    let jwt_secret = ctx.config.get_jwt_config()?;
+
+    let token = user
+        .generate_jwt(&jwt_secret.secret, &jwt_secret.expiration, auth.session_id)
+        .or_else(|_| unauthorized("unauthorized!"))?;
+    client
+        .audit(EventKind::TokenRefresh, Outcome::Success)
+        .user(user.pid)
+        .record(&ctx.db)
+        .await?;
+
+    format::json(LoginResponse::new(&user, &token))
+}
+
+/// Replaces the API key of the current user and returns the new one
+#[debug_handler]
+async fn rotate_api_key(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    client: ClientInfo,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = user.into_active_model().rotate_api_key(&ctx.db).await?;
+    client
+        .audit(EventKind::ApiKeyRotation, Outcome::Success)
+        .user(user.pid)
+        .record(&ctx.db)
+        .await?;
+
+    format::json(ApiKeyResponse::new(&user))
+}
+
+/// Recent security events of the current user
+#[debug_handler]
+async fn audit_log(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let events = audit_events::Model::find_recent_by_user(&ctx.db, user.pid).await?;
+    format::json(
+        events
+            .into_iter()
+            .map(AuditEventResponse::from)
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Updates the public profile fields of the current user
+#[debug_handler]
+async fn update_current(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Json(params): Json<ProfileParams>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = user
+        .into_active_model()
+        .update_profile(&ctx.db, &params)
+        .await?;
+    format::json(CurrentResponse::new(&user))
+}
+
+/// Changes the password of the current user. Every other session is logged
+/// out, the caller gets a fresh token to stay logged in.
+#[debug_handler]
+async fn change_password(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    client: ClientInfo,
+    Json(params): Json<ChangePasswordParams>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+
+    if !user.verify_password(&params.current_password) {
+        client
+            .audit(EventKind::PasswordChange, Outcome::Failure)
+            .user(user.pid)
+            .detail("wrong password")
+            .record(&ctx.db)
+            .await?;
+        return unauthorized("unauthorized!");
+    }
+
+    let user = user
+        .into_active_model()
+        .change_password(&ctx.db, &params.new_password)
+        .await?;
+    sessions::Model::revoke_all_for_user(&ctx.db, user.pid, Some(auth.session_id)).await?;
+    tracing::info!(pid = user.pid.to_string(), "user changed password");
+    client
+        .audit(EventKind::PasswordChange, Outcome::Success)
+        .user(user.pid)
+        .record(&ctx.db)
+        .await?;
+
+    let jwt_secret = ctx.config.get_jwt_config()?;
+
+    let token = user
+        .generate_jwt(&jwt_secret.secret, &jwt_secret.expiration, auth.session_id)
+        .or_else(|_| unauthorized("unauthorized!"))?;
+
+    format::json(LoginResponse::new(&user, &token))
+}
+
+/// Starts an email change for the current user. A confirmation link goes to
+/// the new address and a notice to the current one, the email is swapped only
+/// once the link is followed.
+#[debug_handler]
+async fn change_email(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Json(params): Json<ChangeEmailParams>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+
+    if !user.verify_password(&params.password) {
+        return unauthorized("unauthorized!");
+    }
+
+    let user = user
+        .into_active_model()
+        .set_email_change_sent(&ctx.db, &params.email)
+        .await?;
+
+    AuthMailer::send_email_change(&ctx, &user).await?;
+    AuthMailer::send_email_change_notice(&ctx, &user).await?;
+
+    format::json(())
+}
+
+/// Confirms a pending email change with the token sent to the new address
+#[debug_handler]
+async fn confirm_email(
+    State(ctx): State<AppContext>,
+    Json(params): Json<ConfirmEmailParams>,
+) -> Result<Response> {
+    let settings = Settings::from_context(&ctx)?;
+    let max_age = Duration::hours(settings.accounts.email_change_token_hours);
+    let Ok(user) = users::Model::find_by_email_change_token(&ctx.db, &params.token, max_age).await
+    else {
+        tracing::info!("email change token not found or expired");
+        return format::json(());
+    };
+
+    let user = user
+        .into_active_model()
+        .confirm_email_change(&ctx.db)
+        .await?;
+    tracing::info!(pid = user.pid.to_string(), "user changed email");
+
+    format::json(())
+}
+
+/// Deletes the account of the current user after checking their password
+#[debug_handler]
+async fn delete_current(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Json(params): Json<DeleteAccountParams>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+
+    if !user.verify_password(&params.password) {
+        return unauthorized("unauthorized!");
+    }
+
+    let mode = match params.mode {
+        Some(mode) => mode,
+        None => Settings::from_context(&ctx)?.accounts.deletion_mode,
+    };
+    let pid = user.pid.to_string();
+    user.delete_account(&ctx.db, &ctx.storage, mode).await?;
+    tracing::info!(pid, ?mode, "user deleted account");
+
+    format::empty()
+}
+
+/// Queues an export of the current user's data, a download link is emailed
+/// once it is ready
+#[debug_handler]
+async fn export(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+
+    ExportWorker::perform_later(
+        &ctx,
+        ExportWorkerArgs {
+            user_guid: user.pid.to_string(),
+        },
+    )
+    .await?;
+
+    format::json(())
+}
+
+/// Downloads a finished export archive of the current user
+#[debug_handler]
+async fn download_export(
+    auth: auth::JWT,
+    Path(token): Path<String>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let token = Uuid::parse_str(&token).map_err(|_| Error::NotFound)?;
+    let ttl = Duration::hours(Settings::from_context(&ctx)?.accounts.export_ttl_hours);
+    let export = exports::Model::find_current(&ctx.db, user.pid, token, ttl)
+        .await
+        .map_err(|_| Error::NotFound)?;
+
+    let archive: Vec<u8> = ctx
+        .storage
+        .download(&PathBuf::from(&export.path))
+        .await
+        .map_err(|err| {
+            tracing::info!(err = err.to_string(), "export archive not found");
+            Error::NotFound
+        })?;
+
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"export.zip\"".to_string(),
+            ),
+        ],
+        archive,
+    )
+        .into_response())
+}
+
+/// Lists the active login sessions of the current user
+#[debug_handler]
+async fn list_sessions(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let expiration = ctx.config.get_jwt_config()?.expiration;
+    let sessions = sessions::Model::find_active_by_user(&ctx.db, user.pid, expiration).await?;
+    format::json(
+        sessions
+            .iter()
+            .map(|session| SessionResponse::new(session, auth.session_id))
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Revokes one of the current user's sessions, logging that device out.
+/// Revoking the current session logs out the caller.
+#[debug_handler]
+async fn revoke_session(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let session = match sessions::Model::find_by_user_and_id(&ctx.db, user.pid, id).await {
+        Ok(session) => session,
+        Err(ModelError::EntityNotFound) => return Err(Error::NotFound),
+        Err(err) => return Err(err.into()),
+    };
+
+    if session.is_active() {
+        session.into_active_model().revoke(&ctx.db).await?;
+        tracing::info!(
+            pid = user.pid.to_string(),
+            session_id = id,
+            "session revoked"
+        );
+    }
+
+    format::empty()
+}
+
 pub fn routes() -> Routes {
     Routes::new()
         .prefix("/api/auth")
@@ -154,4 +587,16 @@ pub fn routes() -> Routes {
         .add("/forgot", post(forgot))
         .add("/reset", post(reset))
         .add("/current", get(current))
+        .add("/current", patch(update_current))
+        .add("/current", delete(delete_current))
+        .add("/export", post(export))
+        .add("/export/:token", get(download_export))
+        .add("/sessions", get(list_sessions))
+        .add("/sessions/:id", delete(revoke_session))
+        .add("/refresh", post(refresh))
+        .add("/api-key", post(rotate_api_key))
+        .add("/audit", get(audit_log))
+        .add("/change-password", post(change_password))
+        .add("/change-email", post(change_email))
+        .add("/confirm-email", post(confirm_email))
 }
@@ -0,0 +1,152 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unused_async)]
+use axum::debug_handler;
+use axum::extract::{Path, Query, State};
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    controllers::post::{self, PaginationParams},
+    extractors::auth,
+    models::{_entities::posts, bookmark_collections, bookmarks, posts::released},
+    views::bookmarks::{BookmarkResponse, CollectionResponse},
+};
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ListParams {
+    /// Only the bookmarks in this collection
+    pub collection_id: Option<i32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SaveParams {
+    pub post_id: i32,
+    /// Collection to file the bookmark in, none to keep it unfiled
+    pub collection_id: Option<i32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CollectionParams {
+    pub name: String,
+}
+
+fn current_user(auth: &auth::JWT) -> Result<Uuid> {
+    Uuid::parse_str(&auth.claims.pid).map_err(|_| Error::Unauthorized("invalid pid".to_string()))
+}
+
+/// A collection of the user, not found if it belongs to someone else
+async fn load_collection(
+    ctx: &AppContext,
+    user: Uuid,
+    id: i32,
+) -> Result<bookmark_collections::Model> {
+    bookmark_collections::Model::find_for_user(&ctx.db, user, id)
+        .await
+        .map_err(|_| Error::NotFound)
+}
+
+/// The posts the user bookmarked, most recent first. Posts that are no longer
+/// published are left out.
+#[debug_handler]
+pub async fn list(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Query(filters): Query<ListParams>,
+    Query(params): Query<PaginationParams>,
+) -> Result<Response> {
+    let user = current_user(&auth)?;
+    if let Some(collection_id) = filters.collection_id {
+        load_collection(&ctx, user, collection_id).await?;
+    }
+    let query = bookmarks::Model::posts_of(user, filters.collection_id);
+    format::json(post::paginate(&ctx.db, query, &params, Some(user)).await?)
+}
+
+/// Bookmarks a post, or moves the bookmark to another collection
+#[debug_handler]
+pub async fn add(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Json(params): Json<SaveParams>,
+) -> Result<Response> {
+    let user = current_user(&auth)?;
+    // drafts and scheduled posts can't be seen, so can't be bookmarked
+    let item = posts::Entity::find_by_id(params.post_id)
+        .filter(posts::Column::Published.eq(true))
+        .filter(released())
+        .one(&ctx.db)
+        .await?
+        .ok_or_else(|| Error::NotFound)?;
+    if item.hidden_at.is_some() && Some(user) != item.user_id {
+        return Err(Error::NotFound);
+    }
+    if let Some(collection_id) = params.collection_id {
+        load_collection(&ctx, user, collection_id).await?;
+    }
+    let bookmark =
+        bookmarks::Model::save(&ctx.db, user, params.post_id, params.collection_id).await?;
+    format::json(BookmarkResponse::from(bookmark))
+}
+
+#[debug_handler]
+pub async fn remove(
+    auth: auth::JWT,
+    Path(post_id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = current_user(&auth)?;
+    if !bookmarks::Model::remove(&ctx.db, user, post_id).await? {
+        return Err(Error::NotFound);
+    }
+    format::empty()
+}
+
+#[debug_handler]
+pub async fn list_collections(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = current_user(&auth)?;
+    let collections = bookmark_collections::Model::list_for(&ctx.db, user).await?;
+    format::json(
+        collections
+            .into_iter()
+            .map(CollectionResponse::from)
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Creates a collection, or returns the one with the same name
+#[debug_handler]
+pub async fn add_collection(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Json(params): Json<CollectionParams>,
+) -> Result<Response> {
+    let user = current_user(&auth)?;
+    let collection = bookmark_collections::Model::create(&ctx.db, user, &params.name).await?;
+    format::json(CollectionResponse::from(collection))
+}
+
+/// Deletes a collection, keeping its bookmarks unfiled
+#[debug_handler]
+pub async fn remove_collection(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = current_user(&auth)?;
+    load_collection(&ctx, user, id)
+        .await?
+        .remove(&ctx.db)
+        .await?;
+    format::empty()
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("api/bookmarks/")
+        .add("/", get(list))
+        .add("/", post(add))
+        .add(":post_id", delete(remove))
+        .add("collections", get(list_collections))
+        .add("collections", post(add_collection))
+        .add("collections/:id", delete(remove_collection))
+}
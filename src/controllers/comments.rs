@@ -1,35 +1,75 @@
 #![allow(clippy::missing_errors_doc)]
 #![allow(clippy::unnecessary_struct_initialization)]
 #![allow(clippy::unused_async)]
-use loco_rs::prelude::*;
+use loco_rs::{controller::ErrorDetail, prelude::*};
+use sea_orm::{PaginatorTrait, QuerySelect};
 use serde::{Deserialize, Serialize};
-use axum::{debug_handler, extract::Query};
+use axum::{debug_handler, extract::Query, http::StatusCode};
 use uuid::Uuid;
 
-use crate::models::_entities::comments::{ActiveModel, Column, Entity, Model};
+use crate::{
+    common::{
+        settings::Settings,
+        spam::{Candidate, SpamFilter, Verdict},
+    },
+    controllers::post::{PaginatedResponse, ReactParams},
+    extractors::auth,
+    models::{
+        _entities::{
+            comments::{ActiveModel, Column, Entity, Model},
+            posts,
+        },
+        comments::{self, CommentSort, CommentStatus, Cursor},
+        mentions::{self, Source},
+        reactions::{self, ReactionTarget},
+        users::{self, Role},
+    },
+    views::comments::CommentResponse,
+    workers::comment_notification::{CommentNotificationWorker, CommentNotificationWorkerArgs},
+};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Params {
     pub content: Option<String>,
     pub post_id: Option<i32>,
-    #[serde(skip_deserializing)]
-    pub user_id: Option<Uuid>,
     pub parent_id: Option<i32>,
 }
 
+/// Largest page of comments a client can ask for
+const MAX_PAGE_SIZE: u64 = 100;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct QueryPostParams {
     pub post_id: u64,
+    #[serde(default)]
+    pub sort: CommentSort,
+    /// The `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    #[serde(default = "default_page_size")]
+    pub page_size: u64,
+    /// Only the comments starting a thread, each with its first replies
+    #[serde(default)]
+    pub top_level: bool,
+    /// Replies previewed per thread when listing top level comments
+    #[serde(default = "default_replies")]
+    pub replies: u64,
+    /// Only the replies to this comment
+    pub parent_id: Option<i32>,
+}
+
+fn default_page_size() -> u64 {
+    20
+}
+
+fn default_replies() -> u64 {
+    3
 }
 
 impl Params {
+    /// Only the content can be edited, a comment stays on its post and in
+    /// its thread
     fn update(&self, item: &mut ActiveModel) {
         item.content = Set(self.content.clone());
-        item.post_id = Set(self.post_id);
-        if let Some(user_id) = self.user_id {
-            item.user_id = Set(user_id);
-        }
-        item.parent_id = Set(self.parent_id);
     }
 }
 
@@ -38,30 +78,233 @@ async fn load_item(ctx: &AppContext, id: i32) -> Result<Model> {
     item.ok_or_else(|| Error::NotFound)
 }
 
+/// Comments ready for display, with their mentions linked and their
+/// reactions
+async fn render(
+    ctx: &AppContext,
+    items: Vec<Model>,
+    viewer: Option<Uuid>,
+) -> Result<Vec<CommentResponse>> {
+    let known = mentions::Model::known_handles(
+        &ctx.db,
+        items.iter().filter_map(|item| item.content.as_deref()),
+    )
+    .await?;
+    let ids: Vec<i32> = items.iter().map(|item| item.id).collect();
+    let mut reactions =
+        reactions::Model::summaries(&ctx.db, ReactionTarget::Comment, &ids, viewer).await?;
+    Ok(items
+        .into_iter()
+        .map(|item| {
+            let item_reactions = reactions.remove(&item.id).unwrap_or_default();
+            CommentResponse::new(item, &known).with_reactions(item_reactions)
+        })
+        .collect())
+}
+
+async fn sync_mentions(ctx: &AppContext, item: &Model) -> Result<()> {
+    let source = Source::Comment {
+        id: item.id,
+        post_id: item.post_id,
+    };
+    mentions::Model::sync(&ctx.db, source, item.content.as_deref(), Some(item.user_id)).await?;
+    Ok(())
+}
+
+/// Tells the mentioned users, the post author and the parent comment author
+/// about a comment once it is approved
+pub async fn announce(ctx: &AppContext, item: &Model) -> Result<()> {
+    sync_mentions(ctx, item).await?;
+    CommentNotificationWorker::perform_later(
+        ctx,
+        CommentNotificationWorkerArgs {
+            comment_id: item.id,
+        },
+    )
+    .await
+}
+
+/// The status a new or edited comment starts with: held when the post is
+/// moderated, and screened by the spam filter unless a moderator wrote it
+async fn screen(
+    ctx: &AppContext,
+    post: Option<&posts::Model>,
+    user: &users::Model,
+    content: Option<&str>,
+) -> Result<CommentStatus> {
+    let settings = Settings::from_context(ctx)?;
+    let mut status = Model::initial_status(&ctx.db, &settings.comments, post, user).await?;
+
+    // Moderators are trusted, everyone else goes through the spam filter
+    if !user.has_role(Role::Moderator) {
+        let candidate = Candidate {
+            content: content.unwrap_or_default(),
+            user_id: user.pid,
+            post_id: post.map(|post| post.id),
+        };
+        match SpamFilter::from_settings(&settings.spam)
+            .check(&ctx.db, &candidate)
+            .await?
+        {
+            Verdict::Accept => {}
+            Verdict::Moderate => status = CommentStatus::Pending,
+            Verdict::Reject => status = CommentStatus::Spam,
+        }
+    }
+    Ok(status)
+}
+
+fn viewer(auth: Option<&auth::JWT>) -> Option<Uuid> {
+    auth.and_then(|auth| Uuid::parse_str(&auth.claims.pid).ok())
+}
+
+/// Approved comments of the post, and the pending ones of the current user,
+/// a page at a time. Each page gives the cursor of the next one.
 #[debug_handler]
 pub async fn list(
+    auth: Option<auth::JWT>,
     Query(params): Query<QueryPostParams>,
     State(ctx): State<AppContext>
 ) -> Result<Response> {
-    let post_id = params.post_id;
-    format::json(Entity::find().filter(Column::PostId.eq(post_id)).all(&ctx.db).await?)
+    let viewer = viewer(auth.as_ref());
+    let page_size = params.page_size.clamp(1, MAX_PAGE_SIZE);
+    let threads = params.top_level && params.parent_id.is_none();
+
+    let mut query = Entity::find()
+        .filter(Column::PostId.eq(params.post_id))
+        .filter(comments::visible_to(viewer));
+    if let Some(parent_id) = params.parent_id {
+        query = query.filter(Column::ParentId.eq(parent_id));
+    } else if threads {
+        query = query.filter(Column::ParentId.is_null());
+    }
+    let total = query.clone().count(&ctx.db).await?;
+
+    let mut page = 1;
+    if let Some(cursor) = &params.cursor {
+        query = query.filter(params.sort.after(&Cursor::decode(cursor)?)?);
+        let remaining = query.clone().count(&ctx.db).await?;
+        page += total.saturating_sub(remaining) / page_size;
+    }
+    let mut items = params
+        .sort
+        .order(query)
+        .limit(page_size + 1)
+        .all(&ctx.db)
+        .await?;
+    let next_cursor = if u64::try_from(items.len()).unwrap_or(u64::MAX) > page_size {
+        items.pop();
+        items
+            .last()
+            .map(|last| Cursor::after(last, params.sort).encode())
+    } else {
+        None
+    };
+
+    let ids: Vec<i32> = items.iter().map(|item| item.id).collect();
+    let (counts, mut previews) = if threads {
+        (
+            Model::reply_counts(&ctx.db, &ids, viewer).await?,
+            Model::reply_previews(&ctx.db, &ids, viewer, params.replies).await?,
+        )
+    } else {
+        Default::default()
+    };
+    let known = mentions::Model::known_handles(
+        &ctx.db,
+        items
+            .iter()
+            .chain(previews.values().flatten())
+            .filter_map(|item| item.content.as_deref()),
+    )
+    .await?;
+    let shown: Vec<i32> = items
+        .iter()
+        .chain(previews.values().flatten())
+        .map(|item| item.id)
+        .collect();
+    let mut reactions =
+        reactions::Model::summaries(&ctx.db, ReactionTarget::Comment, &shown, viewer).await?;
+    let items = items
+        .into_iter()
+        .map(|item| {
+            let id = item.id;
+            let response = CommentResponse::new(item, &known)
+                .with_reactions(reactions.remove(&id).unwrap_or_default());
+            if !threads {
+                return response;
+            }
+            let replies = previews
+                .remove(&id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|reply| {
+                    let reply_reactions = reactions.remove(&reply.id).unwrap_or_default();
+                    CommentResponse::new(reply, &known).with_reactions(reply_reactions)
+                })
+                .collect();
+            response.with_replies(counts.get(&id).copied().unwrap_or_default(), replies)
+        })
+        .collect();
+
+    format::json(PaginatedResponse {
+        items,
+        total,
+        page,
+        page_size,
+        total_pages: total.div_ceil(page_size),
+        next_cursor,
+    })
 }
 
 #[debug_handler]
 pub async fn add(
     auth: auth::JWT,
     State(ctx): State<AppContext>, 
-    Json(mut params): Json<Params>
+    Json(params): Json<Params>
 ) -> Result<Response> {
-    // Set the user_id from the auth token
-    params.user_id = Some(Uuid::parse_str(&auth.claims.pid).unwrap());
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let post_id = params.post_id.ok_or_else(|| Error::NotFound)?;
+    let post = posts::Entity::find_by_id(post_id)
+        .one(&ctx.db)
+        .await?
+        .ok_or_else(|| Error::NotFound)?;
+    // Posts hidden after being reported are only shown to their author
+    if post.hidden_at.is_some() && post.user_id != Some(user.pid) {
+        return Err(Error::NotFound);
+    }
+    // Replies stay in a thread of the same post, on a comment the user can see
+    if let Some(parent_id) = params.parent_id {
+        Entity::find_by_id(parent_id)
+            .filter(Column::PostId.eq(post.id))
+            .filter(comments::visible_to(Some(user.pid)))
+            .one(&ctx.db)
+            .await?
+            .ok_or_else(|| Error::NotFound)?;
+    }
+    if let Some(refusal) = post.comment_refusal(&ctx.db, &user).await? {
+        return Err(Error::CustomError(
+            StatusCode::FORBIDDEN,
+            ErrorDetail::new("comments_not_allowed", refusal.message()),
+        ));
+    }
+    let status = screen(&ctx, Some(&post), &user, params.content.as_deref()).await?;
 
     let mut item = ActiveModel {
+        post_id: Set(Some(post.id)),
+        parent_id: Set(params.parent_id),
+        user_id: Set(user.pid),
         ..Default::default()
     };
     params.update(&mut item);
+    item.status = Set(status.as_str().to_string());
     let item = item.insert(&ctx.db).await?;
-    format::json(item)
+
+    if status == CommentStatus::Approved {
+        announce(&ctx, &item).await?;
+    }
+
+    format::json(render(&ctx, vec![item], Some(user.pid)).await?.pop())
 }
 
 #[debug_handler]
@@ -78,10 +321,29 @@ pub async fn update(
         return Err(Error::Unauthorized("..".to_owned()));
     }
 
-    let mut active_item = item.into_active_model();
+    let before = item.status();
+    let mut active_item = item.clone().into_active_model();
     params.update(&mut active_item);
+    // Edited content is screened again, comments a moderator rejected stay so
+    let edited = params.content != item.content;
+    if edited && matches!(before, CommentStatus::Approved | CommentStatus::Pending) {
+        let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+        let post = match item.post_id {
+            Some(post_id) => posts::Entity::find_by_id(post_id).one(&ctx.db).await?,
+            None => None,
+        };
+        let status = screen(&ctx, post.as_ref(), &user, params.content.as_deref()).await?;
+        active_item.status = Set(status.as_str().to_string());
+    }
     let item = active_item.update(&ctx.db).await?;
-    format::json(item)
+    if item.status() == CommentStatus::Approved {
+        if before == CommentStatus::Approved {
+            sync_mentions(&ctx, &item).await?;
+        } else {
+            announce(&ctx, &item).await?;
+        }
+    }
+    format::json(render(&ctx, vec![item], viewer(Some(&auth))).await?.pop())
 }
 
 #[debug_handler]
@@ -97,13 +359,57 @@ pub async fn remove(
         return Err(Error::Unauthorized("..".to_owned()));
     }
 
+    let source = Source::Comment {
+        id: item.id,
+        post_id: item.post_id,
+    };
+    mentions::Model::remove_for(&ctx.db, source).await?;
+    reactions::Model::remove_for(&ctx.db, ReactionTarget::Comment, &[item.id]).await?;
     item.delete(&ctx.db).await?;
     format::empty()
 }
 
+/// Adds the reaction of the current user to the comment, or takes it back
+#[debug_handler]
+pub async fn react(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    Json(params): Json<ReactParams>,
+) -> Result<Response> {
+    let item = load_item(&ctx, id).await?;
+    let user = viewer(Some(&auth));
+    if !item.is_visible_to(user) {
+        return Err(Error::NotFound);
+    }
+    let user = user.ok_or_else(|| Error::Unauthorized("invalid pid".to_string()))?;
+    let settings = Settings::from_context(&ctx)?;
+    reactions::Model::toggle(
+        &ctx.db,
+        &settings.reactions,
+        user,
+        ReactionTarget::Comment,
+        item.id,
+        &params.emoji,
+    )
+    .await?;
+    let summary =
+        reactions::Model::summary(&ctx.db, ReactionTarget::Comment, item.id, Some(user)).await?;
+    format::json(summary)
+}
+
 #[debug_handler]
-pub async fn get_one(Path(id): Path<i32>, State(ctx): State<AppContext>) -> Result<Response> {
-    format::json(load_item(&ctx, id).await?)
+pub async fn get_one(
+    auth: Option<auth::JWT>,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let item = load_item(&ctx, id).await?;
+    let viewer = viewer(auth.as_ref());
+    if !item.is_visible_to(viewer) {
+        return Err(Error::NotFound);
+    }
+    format::json(render(&ctx, vec![item], viewer).await?.pop())
 }
 
 pub fn routes() -> Routes {
@@ -115,4 +421,5 @@ pub fn routes() -> Routes {
         .add(":id", delete(remove))
         .add(":id", put(update))
         .add(":id", patch(update))
+        .add(":id/reactions", post(react))
 }
@@ -0,0 +1,261 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unused_async)]
+use std::collections::{HashMap, HashSet};
+
+use axum::debug_handler;
+use axum::extract::{Query, State};
+use loco_rs::prelude::*;
+use sea_orm::{QueryOrder, QuerySelect};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::analytics,
+    extractors::auth,
+    models::{
+        _entities::{
+            comments, follows,
+            posts::{Column, Entity, Model},
+            reactions, users,
+        },
+        comments::visible_to,
+        post_views::{self, view_count},
+        posts::{self, comment_count},
+        reactions::ReactionTarget,
+    },
+    views::dashboard::{Activity, ActivityKind, Actor, DashboardResponse, TopPost},
+};
+
+/// Posts listed as top posts
+const TOP_POSTS: u64 = 5;
+
+/// Entries of the activity timeline
+const ACTIVITY: u64 = 20;
+
+/// What the top posts are ranked by
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TopBy {
+    #[default]
+    Views,
+    Comments,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DashboardParams {
+    #[serde(default)]
+    pub top: TopBy,
+}
+
+fn to_u64(value: i64) -> u64 {
+    u64::try_from(value).unwrap_or_default()
+}
+
+/// The author's posts ranked by views or comments. `buffered` has the views
+/// of each post still waiting to be written.
+async fn top_posts(
+    db: &DatabaseConnection,
+    author: Uuid,
+    by: TopBy,
+    buffered: &HashMap<i32, u64>,
+) -> Result<Vec<TopPost>> {
+    let rank = match by {
+        TopBy::Views => view_count(),
+        TopBy::Comments => comment_count(),
+    };
+    let query = Entity::find()
+        .select_only()
+        .column(Column::Id)
+        .column(Column::Title)
+        .column_as(view_count(), "views")
+        .column_as(comment_count(), "comments")
+        .filter(Column::UserId.eq(author))
+        .filter(Column::Published.eq(true));
+    let mut rows: Vec<(i32, Option<String>, i64, i64)> = query
+        .clone()
+        .order_by_desc(rank)
+        .order_by_desc(Column::Id)
+        .limit(TOP_POSTS)
+        .into_tuple()
+        .all(db)
+        .await?;
+    // posts with buffered views may climb into the top ones
+    if by == TopBy::Views && !buffered.is_empty() {
+        let shown: HashSet<i32> = rows.iter().map(|(id, ..)| *id).collect();
+        let others: Vec<i32> = buffered
+            .keys()
+            .filter(|id| !shown.contains(id))
+            .copied()
+            .collect();
+        rows.extend(
+            query
+                .filter(Column::Id.is_in(others))
+                .into_tuple::<(i32, Option<String>, i64, i64)>()
+                .all(db)
+                .await?,
+        );
+    }
+    let mut top: Vec<TopPost> = rows
+        .into_iter()
+        .map(|(id, title, views, comments)| TopPost {
+            id,
+            title,
+            views: to_u64(views) + buffered.get(&id).copied().unwrap_or_default(),
+            comments: to_u64(comments),
+        })
+        .collect();
+    if by == TopBy::Views {
+        top.sort_by(|a, b| b.views.cmp(&a.views).then_with(|| b.id.cmp(&a.id)));
+        top.truncate(usize::try_from(TOP_POSTS).unwrap_or(usize::MAX));
+    }
+    Ok(top)
+}
+
+/// The latest comments and reactions on the author's posts and follows of
+/// the author, merged into one timeline
+async fn activity(db: &DatabaseConnection, author: Uuid) -> Result<Vec<Activity>> {
+    let comments = comments::Entity::find()
+        .filter(comments::Column::PostId.in_subquery(posts::ids_of(author)))
+        .filter(comments::Column::UserId.ne(author))
+        .filter(visible_to(None))
+        .order_by_desc(comments::Column::CreatedAt)
+        .limit(ACTIVITY)
+        .all(db)
+        .await?;
+    let reactions = reactions::Entity::find()
+        .filter(reactions::Column::TargetType.eq(ReactionTarget::Post.as_str()))
+        .filter(reactions::Column::TargetId.in_subquery(posts::ids_of(author)))
+        .filter(reactions::Column::UserId.ne(author))
+        .order_by_desc(reactions::Column::CreatedAt)
+        .limit(ACTIVITY)
+        .all(db)
+        .await?;
+    let follows = follows::Entity::find()
+        .filter(follows::Column::FollowedId.eq(author))
+        .order_by_desc(follows::Column::CreatedAt)
+        .limit(ACTIVITY)
+        .all(db)
+        .await?;
+
+    let mut timeline: Vec<(Activity, Uuid)> = comments
+        .into_iter()
+        .map(|comment| {
+            let activity = Activity {
+                kind: ActivityKind::Comment,
+                at: comment.created_at,
+                actor: None,
+                post_id: comment.post_id,
+                post_title: None,
+                comment_id: Some(comment.id),
+                emoji: None,
+            };
+            (activity, comment.user_id)
+        })
+        .chain(reactions.into_iter().map(|reaction| {
+            let activity = Activity {
+                kind: ActivityKind::Reaction,
+                at: reaction.created_at,
+                actor: None,
+                post_id: Some(reaction.target_id),
+                post_title: None,
+                comment_id: None,
+                emoji: Some(reaction.emoji),
+            };
+            (activity, reaction.user_id)
+        }))
+        .chain(follows.into_iter().map(|follow| {
+            let activity = Activity {
+                kind: ActivityKind::Follow,
+                at: follow.created_at,
+                actor: None,
+                post_id: None,
+                post_title: None,
+                comment_id: None,
+                emoji: None,
+            };
+            (activity, follow.follower_id)
+        }))
+        .collect();
+    timeline.sort_by_key(|(activity, _)| std::cmp::Reverse(activity.at));
+    timeline.truncate(usize::try_from(ACTIVITY).unwrap_or(usize::MAX));
+
+    let actor_ids: HashSet<Uuid> = timeline.iter().map(|(_, actor)| *actor).collect();
+    let actors: HashMap<Uuid, users::Model> = users::Entity::find()
+        .filter(users::Column::Pid.is_in(actor_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|user| (user.pid, user))
+        .collect();
+    let post_ids: HashSet<i32> = timeline
+        .iter()
+        .filter_map(|(activity, _)| activity.post_id)
+        .collect();
+    let titles: HashMap<i32, Option<String>> = Entity::find()
+        .select_only()
+        .column(Column::Id)
+        .column(Column::Title)
+        .filter(Column::Id.is_in(post_ids))
+        .into_tuple::<(i32, Option<String>)>()
+        .all(db)
+        .await?
+        .into_iter()
+        .collect();
+
+    Ok(timeline
+        .into_iter()
+        .map(|(mut activity, actor)| {
+            activity.actor = actors.get(&actor).map(|user| Actor {
+                pid: user.pid.to_string(),
+                name: user.name.clone(),
+                handle: user.handle.clone(),
+            });
+            activity.post_title = activity
+                .post_id
+                .and_then(|id| titles.get(&id).cloned().flatten());
+            activity
+        })
+        .collect())
+}
+
+/// Numbers for the author's dashboard: posts by state, comments received,
+/// views, top posts and recent activity
+#[debug_handler]
+pub async fn show(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Query(params): Query<DashboardParams>,
+) -> Result<Response> {
+    let author = Uuid::parse_str(&auth.claims.pid)
+        .map_err(|_| Error::Unauthorized("invalid pid".to_string()))?;
+    // views still waiting in the buffer are added, not flushed
+    let own: HashSet<i32> = Entity::find()
+        .select_only()
+        .column(Column::Id)
+        .filter(Column::UserId.eq(author))
+        .into_tuple::<i32>()
+        .all(&ctx.db)
+        .await?
+        .into_iter()
+        .collect();
+    let buffered = analytics::buffered(|post_id| own.contains(&post_id));
+    let mut buffered_by_post: HashMap<i32, u64> = HashMap::new();
+    for ((post_id, _, _), views) in &buffered.views {
+        *buffered_by_post.entry(*post_id).or_default() += u64::try_from(*views).unwrap_or_default();
+    }
+
+    let posts = Model::state_counts(&ctx.db, author).await?;
+    let comments_received = Model::comments_received(&ctx.db, author).await?;
+    let views = post_views::Model::total_of_author(&ctx.db, author, &buffered).await?;
+
+    format::json(DashboardResponse {
+        posts,
+        comments_received,
+        views,
+        top_posts: top_posts(&ctx.db, author, params.top, &buffered_by_post).await?,
+        activity: activity(&ctx.db, author).await?,
+    })
+}
+
+pub fn routes() -> Routes {
+    Routes::new().prefix("api/dashboard/").add("/", get(show))
+}
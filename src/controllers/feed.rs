@@ -0,0 +1,74 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unused_async)]
+use axum::debug_handler;
+use axum::extract::{Query, State};
+use loco_rs::prelude::*;
+use sea_orm::{PaginatorTrait, QuerySelect};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    controllers::post::{self, PaginatedResponse, PostListItem},
+    extractors::auth,
+    models::{comments::Cursor, follows},
+};
+
+/// Largest page of the feed a client can ask for
+const MAX_PAGE_SIZE: u64 = 100;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FeedParams {
+    /// The `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    #[serde(default = "default_page_size")]
+    pub page_size: u64,
+}
+
+fn default_page_size() -> u64 {
+    10
+}
+
+/// The published posts of the authors the current user follows, latest
+/// first, paged by cursor
+#[debug_handler]
+pub async fn list(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Query(params): Query<FeedParams>,
+) -> Result<Response> {
+    let user = Uuid::parse_str(&auth.claims.pid)
+        .map_err(|_| Error::Unauthorized("invalid pid".to_string()))?;
+    let page_size = params.page_size.clamp(1, MAX_PAGE_SIZE);
+
+    let mut query = follows::Model::feed(user);
+    let total = query.clone().count(&ctx.db).await?;
+    let mut page = 1;
+    if let Some(cursor) = &params.cursor {
+        query = query.filter(follows::Model::feed_after(&Cursor::decode(cursor)?)?);
+        let remaining = query.clone().count(&ctx.db).await?;
+        page += total.saturating_sub(remaining) / page_size;
+    }
+    let mut posts = query.limit(page_size + 1).all(&ctx.db).await?;
+    let next_cursor = if u64::try_from(posts.len()).unwrap_or(u64::MAX) > page_size {
+        posts.pop();
+        posts
+            .last()
+            .map(|last| follows::Model::feed_cursor(last).encode())
+    } else {
+        None
+    };
+
+    let mut items: Vec<PostListItem> = posts.into_iter().map(PostListItem::from).collect();
+    post::decorate(&ctx.db, &mut items, Some(user)).await?;
+    format::json(PaginatedResponse {
+        items,
+        total,
+        page,
+        page_size,
+        total_pages: total.div_ceil(page_size),
+        next_cursor,
+    })
+}
+
+pub fn routes() -> Routes {
+    Routes::new().prefix("api/feed/").add("/", get(list))
+}
@@ -0,0 +1,67 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unused_async)]
+use axum::debug_handler;
+use axum::extract::{Path, State};
+use loco_rs::prelude::*;
+
+use crate::{
+    extractors::auth,
+    mailers::auth::AuthMailer,
+    models::{
+        invites::{self, CreateParams},
+        users::Role,
+    },
+    views::invites::InviteResponse,
+};
+
+async fn load_item(ctx: &AppContext, id: i32) -> Result<invites::Model> {
+    let item = invites::Entity::find_by_id(id).one(&ctx.db).await?;
+    item.ok_or_else(|| Error::NotFound)
+}
+
+/// Issues an invite. When it has a preassigned email, the invitation is
+/// mailed to it.
+#[debug_handler]
+pub async fn add(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Json(params): Json<CreateParams>,
+) -> Result<Response> {
+    let admin = auth.require_role(&ctx, Role::Admin).await?;
+    let invite = invites::Model::create(&ctx.db, admin.pid, &params).await?;
+
+    AuthMailer::send_invite(&ctx, &invite, &admin).await?;
+
+    format::json(InviteResponse::new(&invite))
+}
+
+#[debug_handler]
+pub async fn list(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    auth.require_role(&ctx, Role::Admin).await?;
+    let invites = invites::Model::list(&ctx.db).await?;
+    format::json(invites.iter().map(InviteResponse::new).collect::<Vec<_>>())
+}
+
+#[debug_handler]
+pub async fn revoke(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    auth.require_role(&ctx, Role::Admin).await?;
+    let invite = load_item(&ctx, id).await?;
+    let invite = if invite.revoked_at.is_none() {
+        invite.into_active_model().revoke(&ctx.db).await?
+    } else {
+        invite
+    };
+    format::json(InviteResponse::new(&invite))
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("api/admin/invites/")
+        .add("/", get(list))
+        .add("/", post(add))
+        .add(":id", delete(revoke))
+}
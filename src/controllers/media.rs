@@ -0,0 +1,223 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unused_async)]
+use axum::debug_handler;
+use axum::{
+    extract::{DefaultBodyLimit, Multipart, Path, State},
+    http::{header, HeaderMap, StatusCode},
+};
+use bytes::BytesMut;
+use loco_rs::{controller::ErrorDetail, prelude::*};
+
+use crate::{
+    common::settings::Settings,
+    extractors::auth,
+    models::{
+        media::{self, ImageType},
+        media_variants,
+    },
+    views::media::MediaResponse,
+    workers::media_variants::{MediaVariantsWorker, MediaVariantsWorkerArgs},
+};
+
+/// Files never change once uploaded, a new upload gets a new pid
+const CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+fn current_user(auth: &auth::JWT) -> Result<Uuid> {
+    Uuid::parse_str(&auth.claims.pid).map_err(|_| Error::Unauthorized("invalid pid".to_string()))
+}
+
+fn too_large(max_bytes: usize) -> Error {
+    Error::CustomError(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        ErrorDetail::new(
+            "payload_too_large",
+            &format!("files may be at most {max_bytes} bytes"),
+        ),
+    )
+}
+
+/// The media with its variants
+async fn render(ctx: &AppContext, media: media::Model) -> Result<MediaResponse> {
+    let variants = media_variants::Model::for_media(&ctx.db, &[media.id])
+        .await?
+        .remove(&media.id)
+        .unwrap_or_default();
+    Ok(MediaResponse::new(media, variants))
+}
+
+/// Uploads an image as the `file` field of a multipart form. The file is
+/// read up to the configured size limit and must be a JPEG, PNG, GIF or WebP
+/// image, whatever its name or declared content type. Its metadata is
+/// removed and its variants are built in the background.
+#[debug_handler]
+async fn upload(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    mut multipart: Multipart,
+) -> Result<Response> {
+    let user = current_user(&auth)?;
+    let max_bytes = Settings::from_context(&ctx)?.media.max_bytes;
+
+    while let Some(mut field) = multipart
+        .next_field()
+        .await
+        .map_err(|err| Error::BadRequest(err.body_text()))?
+    {
+        if field.name() != Some("file") {
+            continue;
+        }
+        let original_name = field.file_name().map(ToString::to_string);
+        let mut content = BytesMut::new();
+        while let Some(chunk) = field
+            .chunk()
+            .await
+            .map_err(|err| Error::BadRequest(err.body_text()))?
+        {
+            if content.len() + chunk.len() > max_bytes {
+                return Err(too_large(max_bytes));
+            }
+            content.extend_from_slice(&chunk);
+        }
+
+        let media =
+            media::Model::create(&ctx.db, &ctx.storage, user, content.freeze(), original_name)
+                .await?;
+        tracing::info!(
+            pid = media.pid.to_string(),
+            size = media.size,
+            "media uploaded"
+        );
+        MediaVariantsWorker::perform_later(
+            &ctx,
+            MediaVariantsWorkerArgs {
+                media_pid: media.pid.to_string(),
+            },
+        )
+        .await?;
+        // the variants are already built when workers run in the foreground
+        let media = media::Model::find_by_pid(&ctx.db, media.pid).await?;
+        return format::json(render(&ctx, media).await?);
+    }
+    Err(Error::BadRequest("missing `file` field".to_string()))
+}
+
+/// The uploads of the current user, newest first
+#[debug_handler]
+async fn list(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = current_user(&auth)?;
+    let media = media::Model::list_for(&ctx.db, user).await?;
+    let ids: Vec<i32> = media.iter().map(|media| media.id).collect();
+    let mut variants = media_variants::Model::for_media(&ctx.db, &ids).await?;
+    format::json(
+        media
+            .into_iter()
+            .map(|media| {
+                let variants = variants.remove(&media.id).unwrap_or_default();
+                MediaResponse::new(media, variants)
+            })
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Responds with a stored file. The path of a file never changes, so its
+/// ETag is the path.
+async fn serve_file(
+    ctx: &AppContext,
+    path: &str,
+    content_type: String,
+    headers: &HeaderMap,
+) -> Result<Response> {
+    let etag = format!("\"{path}\"");
+    if headers
+        .get(header::IF_NONE_MATCH)
+        .is_some_and(|value| value.as_bytes() == etag.as_bytes())
+    {
+        return Ok((
+            StatusCode::NOT_MODIFIED,
+            [
+                (header::ETAG, etag),
+                (header::CACHE_CONTROL, CACHE_CONTROL.to_string()),
+            ],
+        )
+            .into_response());
+    }
+
+    let content: Vec<u8> = ctx
+        .storage
+        .download(std::path::Path::new(path))
+        .await
+        .map_err(|err| {
+            tracing::warn!(err = err.to_string(), path, "media file not found");
+            Error::NotFound
+        })?;
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::CACHE_CONTROL, CACHE_CONTROL.to_string()),
+            (header::ETAG, etag),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+        ],
+        content,
+    )
+        .into_response())
+}
+
+/// Serves an uploaded file. Anyone may fetch it, as covers show on public
+/// posts, and clients may cache it for good.
+#[debug_handler]
+async fn serve(
+    Path(pid): Path<Uuid>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    let media = media::Model::find_by_pid(&ctx.db, pid)
+        .await
+        .map_err(|_| Error::NotFound)?;
+    serve_file(&ctx, &media.path, media.content_type, &headers).await
+}
+
+/// Serves a variant of an upload, named like `medium.webp`
+#[debug_handler]
+async fn serve_variant(
+    Path((pid, file)): Path<(Uuid, String)>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    let (name, extension) = file.rsplit_once('.').ok_or(Error::NotFound)?;
+    let format = ImageType::from_extension(extension).ok_or(Error::NotFound)?;
+    let media = media::Model::find_by_pid(&ctx.db, pid)
+        .await
+        .map_err(|_| Error::NotFound)?;
+    let variant = media_variants::Model::find_file(&ctx.db, media.id, name, format)
+        .await
+        .map_err(|_| Error::NotFound)?;
+    serve_file(&ctx, &variant.path, variant.content_type, &headers).await
+}
+
+/// Deletes an upload of the current user. Posts using it as their cover are
+/// left without one.
+#[debug_handler]
+async fn remove(
+    auth: auth::JWT,
+    Path(pid): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = current_user(&auth)?;
+    let media = media::Model::find_for_user(&ctx.db, user, pid)
+        .await
+        .map_err(|_| Error::NotFound)?;
+    media.remove(&ctx.db, &ctx.storage).await?;
+    format::empty()
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("api/media/")
+        .add("/", get(list))
+        // the size limit is checked while reading, against the settings
+        .add("/", post(upload).layer(DefaultBodyLimit::disable()))
+        .add(":pid", get(serve))
+        .add(":pid/:file", get(serve_variant))
+        .add(":pid", delete(remove))
+}
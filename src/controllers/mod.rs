@@ -1,4 +1,14 @@
 pub mod auth;
 
 pub mod post;
-pub mod comments;
\ No newline at end of file
+pub mod comments;
+pub mod users;
+pub mod invites;
+pub mod audit;
+pub mod notifications;
+pub mod moderation;
+pub mod reports;
+pub mod bookmarks;
+pub mod feed;
+pub mod dashboard;
+pub mod media;
@@ -0,0 +1,165 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unused_async)]
+use axum::debug_handler;
+use axum::extract::{Path, Query, State};
+use loco_rs::prelude::*;
+use sea_orm::QueryOrder;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    controllers::{
+        comments::announce,
+        post::{paginate_as, PaginationParams},
+    },
+    extractors::auth,
+    models::{
+        _entities::comments,
+        comments::CommentStatus,
+        notifications::{NewNotification, NotificationKind},
+        reports::{self, ReportStatus},
+        spam_tokens,
+        users::Role,
+    },
+    views::{
+        moderation::ModerationResponse,
+        reports::{ReportResponse, ResolveResponse},
+    },
+};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct QueueParams {
+    /// Which comments to list, the pending ones by default
+    pub status: Option<CommentStatus>,
+}
+
+/// A moderator decision
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Approve,
+    Reject,
+    Spam,
+}
+
+impl Action {
+    const fn status(self) -> CommentStatus {
+        match self {
+            Self::Approve => CommentStatus::Approved,
+            Self::Reject => CommentStatus::Rejected,
+            Self::Spam => CommentStatus::Spam,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ModerateParams {
+    pub ids: Vec<i32>,
+    pub action: Action,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReportQueueParams {
+    /// Which reports to list, the open ones by default
+    pub status: Option<ReportStatus>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ResolveParams {
+    /// `resolved` keeps the content hidden, `dismissed` shows it again
+    pub status: ReportStatus,
+    pub note: Option<String>,
+}
+
+/// The moderation queue, oldest first so comments are handled in order
+#[debug_handler]
+pub async fn queue(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Query(filters): Query<QueueParams>,
+    Query(params): Query<PaginationParams>,
+) -> Result<Response> {
+    auth.require_role(&ctx, Role::Moderator).await?;
+    let status = filters.status.unwrap_or(CommentStatus::Pending);
+    let query = comments::Entity::find()
+        .filter(comments::Column::Status.eq(status.as_str()))
+        .order_by_asc(comments::Column::CreatedAt)
+        .order_by_asc(comments::Column::Id);
+    format::json(paginate_as::<_, comments::Model>(&ctx.db, query, &params).await?)
+}
+
+/// Approves, rejects or marks as spam a batch of comments. Comments already
+/// in the requested state are left alone. Approvals and spam reports train
+/// the spam classifier.
+#[debug_handler]
+pub async fn moderate(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Json(params): Json<ModerateParams>,
+) -> Result<Response> {
+    let moderator = auth.require_role(&ctx, Role::Moderator).await?;
+    let status = params.action.status();
+    let changed = comments::Model::moderate(&ctx.db, &params.ids, status, moderator.pid).await?;
+
+    for (comment, _) in &changed {
+        if status == CommentStatus::Approved {
+            announce(&ctx, comment).await?;
+        }
+        if let (Some(content), CommentStatus::Approved | CommentStatus::Spam) =
+            (&comment.content, status)
+        {
+            spam_tokens::Model::train(&ctx.db, content, status == CommentStatus::Spam).await?;
+        }
+        NewNotification::new(comment.user_id, NotificationKind::Moderation)
+            .actor(moderator.pid)
+            .post(comment.post_id)
+            .comment(comment.id)
+            .detail(status.as_str())
+            .send(&ctx.db)
+            .await?;
+    }
+
+    format::json(ModerationResponse {
+        updated: changed.into_iter().map(|(comment, _)| comment.id).collect(),
+    })
+}
+
+/// The reports on posts and comments, oldest first
+#[debug_handler]
+pub async fn report_queue(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Query(filters): Query<ReportQueueParams>,
+    Query(params): Query<PaginationParams>,
+) -> Result<Response> {
+    auth.require_role(&ctx, Role::Moderator).await?;
+    let query = reports::Model::queue(filters.status.unwrap_or(ReportStatus::Open));
+    format::json(paginate_as::<_, ReportResponse>(&ctx.db, query, &params).await?)
+}
+
+/// Settles the report along with every other open report on the same content
+#[debug_handler]
+pub async fn resolve(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    Json(params): Json<ResolveParams>,
+) -> Result<Response> {
+    let moderator = auth.require_role(&ctx, Role::Moderator).await?;
+    let report = reports::Entity::find_by_id(id)
+        .one(&ctx.db)
+        .await?
+        .ok_or_else(|| Error::NotFound)?;
+    let settled = report
+        .resolve(&ctx.db, params.status, moderator.pid, params.note)
+        .await?;
+    format::json(ResolveResponse { settled })
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("api/moderation/")
+        .add("comments", get(queue))
+        .add("comments", post(moderate))
+        .add("reports", get(report_queue))
+        .add("reports/:id/resolve", post(resolve))
+}
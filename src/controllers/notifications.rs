@@ -0,0 +1,259 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unused_async)]
+use std::{convert::Infallible, time::Duration};
+
+use axum::debug_handler;
+use axum::extract::{Path, Query, State};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures_util::stream::{self, Stream};
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    time::{timeout_at, Instant},
+};
+
+use crate::{
+    common::live,
+    controllers::post::{paginate_as, PaginationParams},
+    extractors::auth,
+    models::{
+        notifications,
+        users::{self, NotificationPreference},
+    },
+    views::notifications::{
+        InboxResponse, NotificationResponse, PreferencesResponse, UnreadCountResponse,
+    },
+};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PreferencesParams {
+    pub comment_emails: NotificationPreference,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct InboxParams {
+    #[serde(default)]
+    pub unread_only: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StreamParams {
+    /// The JWT, `EventSource` can't send an `Authorization` header
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UnsubscribeParams {
+    pub token: String,
+}
+
+fn user_id(auth: &auth::JWT) -> Result<Uuid> {
+    Uuid::parse_str(&auth.claims.pid).map_err(|_| Error::Unauthorized("invalid pid".to_string()))
+}
+
+/// The inbox of the current user with its unread count
+#[debug_handler]
+pub async fn list(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Query(filters): Query<InboxParams>,
+    Query(params): Query<PaginationParams>,
+) -> Result<Response> {
+    let user_id = user_id(&auth)?;
+    let query = notifications::Model::inbox(user_id, filters.unread_only);
+    format::json(InboxResponse {
+        unread_count: notifications::Model::unread_count(&ctx.db, user_id).await?,
+        notifications: paginate_as(&ctx.db, query, &params).await?,
+    })
+}
+
+#[debug_handler]
+pub async fn unread_count(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let unread_count = notifications::Model::unread_count(&ctx.db, user_id(&auth)?).await?;
+    format::json(UnreadCountResponse { unread_count })
+}
+
+#[debug_handler]
+pub async fn mark_read(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let notification =
+        match notifications::Model::find_by_user_and_id(&ctx.db, user_id(&auth)?, id).await {
+            Ok(notification) => notification,
+            Err(ModelError::EntityNotFound) => return Err(Error::NotFound),
+            Err(err) => return Err(err.into()),
+        };
+    let notification = if notification.read_at.is_none() {
+        notification.into_active_model().mark_read(&ctx.db).await?
+    } else {
+        notification
+    };
+    format::json(NotificationResponse::from(notification))
+}
+
+#[debug_handler]
+pub async fn mark_all_read(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let user_id = user_id(&auth)?;
+    notifications::Model::mark_all_read(&ctx.db, user_id).await?;
+    format::json(UnreadCountResponse { unread_count: 0 })
+}
+
+async fn unread_event(db: &DatabaseConnection, user_id: Uuid) -> Event {
+    let event = Event::default().event("unread");
+    match notifications::Model::unread_count(db, user_id).await {
+        Ok(unread_count) => event
+            .data(serde_json::to_string(&UnreadCountResponse { unread_count }).unwrap_or_default()),
+        Err(err) => {
+            tracing::warn!(
+                err = err.to_string(),
+                "could not count unread notifications"
+            );
+            event.comment("unavailable")
+        }
+    }
+}
+
+/// How often a quiet stream checks that its token is still accepted
+const TOKEN_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Server-sent events with the unread count of the user: once on connect,
+/// then every time their notifications change. The stream ends once its token
+/// is no longer accepted, e.g. when the session is revoked or the password
+/// changed.
+fn unread_stream(
+    ctx: AppContext,
+    token: String,
+    user_id: Uuid,
+    changes: broadcast::Receiver<Uuid>,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    stream::unfold(
+        (ctx, token, changes, true),
+        move |(ctx, token, mut changes, first)| async move {
+            if !first {
+                let mut check_at = Instant::now() + TOKEN_CHECK_INTERVAL;
+                loop {
+                    let changed = match timeout_at(check_at, changes.recv()).await {
+                        Ok(Ok(changed)) if changed == user_id => true,
+                        Ok(Ok(_)) => continue,
+                        // missed signals, one of them may have been ours
+                        Ok(Err(RecvError::Lagged(_))) => true,
+                        Ok(Err(RecvError::Closed)) => return None,
+                        Err(_) => false,
+                    };
+                    if auth::JWT::from_token(&ctx, &token).await.is_err() {
+                        return None;
+                    }
+                    if changed {
+                        break;
+                    }
+                    check_at = Instant::now() + TOKEN_CHECK_INTERVAL;
+                }
+            }
+            let event = unread_event(&ctx.db, user_id).await;
+            Some((Ok(event), (ctx, token, changes, false)))
+        },
+    )
+}
+
+#[debug_handler]
+pub async fn stream(
+    State(ctx): State<AppContext>,
+    Query(params): Query<StreamParams>,
+) -> Result<Response> {
+    let auth = auth::JWT::from_token(&ctx, &params.token).await?;
+    let user_id = user_id(&auth)?;
+    let stream = unread_stream(ctx, params.token, user_id, live::subscribe());
+    Ok(Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
+
+#[debug_handler]
+pub async fn preferences(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    format::json(PreferencesResponse::new(&user))
+}
+
+#[debug_handler]
+pub async fn update_preferences(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Json(params): Json<PreferencesParams>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = user
+        .into_active_model()
+        .set_notification_preference(&ctx.db, params.comment_emails)
+        .await?;
+    format::json(PreferencesResponse::new(&user))
+}
+
+/// Page of the unsubscribe link in every notification. Opening the link only
+/// asks for confirmation, so that mail scanners following links don't
+/// unsubscribe anyone. No login needed, the token is signed.
+#[debug_handler]
+pub async fn confirm_unsubscribe(
+    State(ctx): State<AppContext>,
+    Query(params): Query<UnsubscribeParams>,
+) -> Result<Response> {
+    let jwt_secret = ctx.config.get_jwt_config()?;
+    let Ok(user) =
+        users::Model::find_by_unsubscribe_token(&ctx.db, &jwt_secret.secret, &params.token).await
+    else {
+        return Err(Error::NotFound);
+    };
+
+    let token = user.unsubscribe_token(&jwt_secret.secret);
+    format::html(&format!(
+        "<!DOCTYPE html>\n<html><body>\n\
+         <p>Stop receiving comment notification emails?</p>\n\
+         <form method=\"post\" action=\"/api/notifications/unsubscribe?token={token}\">\
+         <button type=\"submit\">Unsubscribe</button></form>\n\
+         </body></html>\n"
+    ))
+}
+
+/// Unsubscribes from comment emails, from the confirmation page or in one
+/// click from mail clients (RFC 8058)
+#[debug_handler]
+pub async fn unsubscribe(
+    State(ctx): State<AppContext>,
+    Query(params): Query<UnsubscribeParams>,
+) -> Result<Response> {
+    let jwt_secret = ctx.config.get_jwt_config()?;
+    let Ok(user) =
+        users::Model::find_by_unsubscribe_token(&ctx.db, &jwt_secret.secret, &params.token).await
+    else {
+        return Err(Error::NotFound);
+    };
+
+    if user.notification_preference() != NotificationPreference::Off {
+        let user = user
+            .into_active_model()
+            .set_notification_preference(&ctx.db, NotificationPreference::Off)
+            .await?;
+        tracing::info!(
+            pid = user.pid.to_string(),
+            "user unsubscribed from comment emails"
+        );
+    }
+
+    format::text("You will no longer receive comment notification emails.")
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("api/notifications/")
+        .add("/", get(list))
+        .add("unread-count", get(unread_count))
+        .add("read-all", post(mark_all_read))
+        .add("stream", get(stream))
+        .add(":id/read", post(mark_read))
+        .add("preferences", get(preferences))
+        .add("preferences", put(update_preferences))
+        .add("unsubscribe", get(confirm_unsubscribe))
+        .add("unsubscribe", post(unsubscribe))
+}
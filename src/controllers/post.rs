@@ -4,11 +4,24 @@
 use axum::debug_handler;
 use axum::extract::{Path, Query, State};
 use loco_rs::prelude::*;
-use sea_orm::{PaginatorTrait, QueryOrder};
-use serde::{Deserialize, Serialize};
+use sea_orm::{sea_query::Order, PaginatorTrait, QueryOrder, Select, TransactionTrait};
+use serde::{Deserialize, Deserializer, Serialize};
 use uuid::Uuid;
 
-use crate::models::_entities::posts::{ActiveModel, Column, Entity, Model};
+use crate::{
+    common::{analytics, settings::Settings},
+    extractors::{auth, client::ClientInfo},
+    models::{
+        _entities::posts::{ActiveModel, Column, Entity, Model},
+        bookmarks, media,
+        mentions::{self, Source},
+        post_tags, post_views,
+        posts::{self, CommentPolicy},
+        reactions::{self, ReactionSummary, ReactionTarget},
+        users::Role,
+    },
+    views::posts::PostResponse,
+};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Params {
@@ -17,32 +30,70 @@ pub struct Params {
     pub summary: Option<String>,
     pub published: Option<bool>,
     pub slug: Option<String>,
-    pub user_id: Option<Uuid>,
     pub published_at: Option<DateTimeWithTimeZone>,
+    /// Hold new comments for moderation, unchanged when not given
+    pub comment_moderation: Option<bool>,
+    /// Who may comment, unchanged when not given
+    pub comment_policy: Option<CommentPolicy>,
+    /// Close comments this many days after publishing, unchanged when not
+    /// given and never closing when `null`
+    #[serde(default, deserialize_with = "nullable")]
+    pub comments_close_after_days: Option<Option<u16>>,
+    /// An upload of the author shown as the cover image, unchanged when not
+    /// given and removed when `null`
+    #[serde(default, deserialize_with = "nullable")]
+    pub cover_media_id: Option<Option<i32>>,
 }
 
 impl Params {
     fn update(&self, item: &mut ActiveModel) {
+        let was_published = item.published.try_as_ref().copied().flatten() == Some(true);
         item.title = Set(self.title.clone());
         item.content = Set(self.content.clone());
         item.summary = Set(self.summary.clone());
-        item.published = Set(self.published.clone());
+        item.published = Set(self.published);
         item.slug = Set(self.slug.clone());
-        item.user_id = Set(self.user_id);
-        
-        // Set published_at based on published value
-        if let Some(published) = self.published {
-            if published {
+        if let Some(comment_moderation) = self.comment_moderation {
+            item.comment_moderation = Set(comment_moderation);
+        }
+        if let Some(comment_policy) = self.comment_policy {
+            item.comment_policy = Set(comment_policy.as_str().to_string());
+        }
+        if let Some(close_after_days) = self.comments_close_after_days {
+            item.comments_close_after_days = Set(close_after_days.map(i32::from));
+        }
+        if let Some(cover_media_id) = self.cover_media_id {
+            item.cover_media_id = Set(cover_media_id);
+        }
+
+        // Set published_at based on published value, keeping it while the
+        // post stays published so edits don't reopen closed comments
+        match self.published {
+            Some(true) if self.published_at.is_some() => item.published_at = Set(self.published_at),
+            Some(true) if !was_published => {
                 item.published_at = Set(Some(chrono::Utc::now().into()));
-            } else {
-                item.published_at = Set(None);
             }
-        } else {
-            item.published_at = Set(self.published_at.clone());
+            Some(true) => {}
+            Some(false) => item.published_at = Set(None),
+            None => item.published_at = Set(self.published_at),
         }
     }
 }
 
+/// Tells a field sent as `null`, which clears the value, from a missing one
+fn nullable<'de, D, T>(deserializer: D) -> std::result::Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReactParams {
+    pub emoji: String,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PublishParams {
     pub published: bool,
@@ -64,6 +115,37 @@ fn default_page_size() -> u64 {
     10
 }
 
+/// Order of the post list
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PostSort {
+    /// Latest published first
+    #[default]
+    Newest,
+    /// Most public comments first
+    MostDiscussed,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ListParams {
+    #[serde(default)]
+    pub sort: PostSort,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StatsParams {
+    /// Days to report on, today included
+    #[serde(default = "default_stats_days")]
+    pub days: u64,
+}
+
+fn default_stats_days() -> u64 {
+    30
+}
+
+/// Longest period the stats can cover
+const MAX_STATS_DAYS: u64 = 365;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PaginatedResponse<T> {
     pub items: Vec<T>,
@@ -71,6 +153,9 @@ pub struct PaginatedResponse<T> {
     pub page: u64,
     pub page_size: u64,
     pub total_pages: u64,
+    /// Where the next page starts, for listings paged by cursor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -78,26 +163,59 @@ pub struct PostListItem {
     pub id: i32,
     pub title: Option<String>,
     pub summary: Option<String>,
+    pub word_count: i32,
+    /// Estimated minutes to read the post
+    pub reading_time: i32,
+    pub cover_media_id: Option<i32>,
+    pub cover_url: Option<String>,
+    pub tags: Vec<String>,
     pub published: Option<bool>,
     pub slug: Option<String>,
     pub user_id: Option<Uuid>,
     pub published_at: Option<DateTimeWithTimeZone>,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
+    pub comment_policy: CommentPolicy,
+    /// When comments close on their own
+    pub comments_close_at: Option<DateTimeWithTimeZone>,
+    /// Whether anyone may still comment, clients hide the comment box if not
+    pub comments_open: bool,
+    /// Public comments on the post
+    pub comment_count: u64,
+    pub last_comment_at: Option<DateTimeWithTimeZone>,
+    pub reactions: ReactionSummary,
+    /// Whether the current user bookmarked the post, only for signed in users
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bookmarked: Option<bool>,
 }
 
 impl From<Model> for PostListItem {
     fn from(model: Model) -> Self {
+        let comment_policy = model.comment_policy();
+        let comments_close_at = model.comments_close_at();
+        let comments_open = !model.comments_closed();
         Self {
             id: model.id,
             title: model.title,
             summary: model.summary,
+            word_count: model.word_count,
+            reading_time: model.reading_time,
+            cover_media_id: model.cover_media_id,
+            cover_url: None,
+            tags: Vec::new(),
             published: model.published,
             slug: model.slug,
             user_id: model.user_id,
             published_at: model.published_at,
             created_at: model.created_at,
             updated_at: model.updated_at,
+            comment_policy,
+            comments_close_at,
+            comments_open,
+            comment_count: 0,
+            last_comment_at: None,
+            reactions: ReactionSummary::default(),
+            bookmarked: None,
         }
     }
 }
@@ -107,36 +225,190 @@ async fn load_item(ctx: &AppContext, id: i32) -> Result<Model> {
     item.ok_or_else(|| Error::NotFound)
 }
 
-#[debug_handler]
-pub async fn list(
-    State(ctx): State<AppContext>,
-    Query(params): Query<PaginationParams>,
-) -> Result<Response> {
+/// Loads a post the current user may change: their own, or any post for
+/// moderators
+async fn load_own_item(ctx: &AppContext, auth: &auth::JWT, id: i32) -> Result<Model> {
+    let item = load_item(ctx, id).await?;
+    let user = viewer(Some(auth));
+    if user.is_none() || user != item.user_id {
+        auth.require_role(ctx, Role::Moderator).await?;
+    }
+    Ok(item)
+}
+
+/// The current user, if signed in
+fn viewer(auth: Option<&auth::JWT>) -> Option<Uuid> {
+    auth.and_then(|auth| Uuid::parse_str(&auth.claims.pid).ok())
+}
+
+/// Counts a view of the post, unless it is unpublished or the author's own.
+/// Views are buffered and written in batches, see [`analytics`].
+fn record_view(
+    ctx: &AppContext,
+    item: &Model,
+    client: &ClientInfo,
+    viewer: Option<Uuid>,
+) -> Result<()> {
+    let settings = Settings::from_context(ctx)?;
+    let own = viewer.is_some() && viewer == item.user_id;
+    if !settings.analytics.enabled || item.published != Some(true) || own {
+        return Ok(());
+    }
+    let secret = ctx.config.get_jwt_config()?.secret.clone();
+    if analytics::record(&settings.analytics, &secret, item.id, client) {
+        let db = ctx.db.clone();
+        tokio::spawn(async move {
+            if let Err(err) = analytics::flush(&db).await {
+                tracing::error!(err = err.to_string(), "could not write post views");
+            }
+        });
+    }
+    Ok(())
+}
+
+/// A post ready for display, with its mentions linked, its reactions and
+/// whether the viewer bookmarked it
+async fn render(ctx: &AppContext, item: Model, viewer: Option<Uuid>) -> Result<PostResponse> {
+    let item_id = item.id;
+    let known = mentions::Model::known_handles(&ctx.db, item.content.as_deref()).await?;
+    let reactions =
+        reactions::Model::summary(&ctx.db, ReactionTarget::Post, item.id, viewer).await?;
+    let bookmarked = match viewer {
+        Some(viewer) => Some(
+            bookmarks::Model::bookmarked_among(&ctx.db, viewer, &[item.id])
+                .await?
+                .contains(&item.id),
+        ),
+        None => None,
+    };
+    let cover_url = match item.cover_media_id {
+        Some(id) => media::Model::pids_of(&ctx.db, &[id])
+            .await?
+            .get(&id)
+            .map(|pid| media::url(*pid)),
+        None => None,
+    };
+    Ok(PostResponse::new(item, &known)
+        .with_reactions(reactions)
+        .with_bookmarked(bookmarked)
+        .with_cover_url(cover_url)
+        .with_tags(post_tags::Model::of(&ctx.db, item_id).await?))
+}
+
+/// Fetches one page of the given posts query as list items, see [`decorate`]
+///
+/// # Errors
+///
+/// When has DB query error
+pub async fn paginate(
+    db: &DatabaseConnection,
+    query: Select<Entity>,
+    params: &PaginationParams,
+    viewer: Option<Uuid>,
+) -> Result<PaginatedResponse<PostListItem>> {
+    let mut page: PaginatedResponse<PostListItem> = paginate_as(db, query, params).await?;
+    decorate(db, &mut page.items, viewer).await?;
+    Ok(page)
+}
+
+/// Fills in the comment counts and reactions of the list items, the viewer's
+/// own included, and whether the viewer bookmarked them
+///
+/// # Errors
+///
+/// When has DB query error
+pub async fn decorate(
+    db: &DatabaseConnection,
+    items: &mut [PostListItem],
+    viewer: Option<Uuid>,
+) -> Result<()> {
+    let ids: Vec<i32> = items.iter().map(|item| item.id).collect();
+    let stats = Model::comment_stats(db, &ids).await?;
+    let mut reactions = reactions::Model::summaries(db, ReactionTarget::Post, &ids, viewer).await?;
+    let bookmarked = match viewer {
+        Some(viewer) => Some(bookmarks::Model::bookmarked_among(db, viewer, &ids).await?),
+        None => None,
+    };
+    let cover_ids: Vec<i32> = items
+        .iter()
+        .filter_map(|item| item.cover_media_id)
+        .collect();
+    let covers = media::Model::pids_of(db, &cover_ids).await?;
+    let mut tags = post_tags::Model::for_posts(db, &ids).await?;
+    for item in items {
+        let stats = stats.get(&item.id).copied().unwrap_or_default();
+        item.comment_count = stats.count;
+        item.last_comment_at = stats.last_comment_at;
+        item.reactions = reactions.remove(&item.id).unwrap_or_default();
+        item.bookmarked = bookmarked.as_ref().map(|ids| ids.contains(&item.id));
+        item.cover_url = item
+            .cover_media_id
+            .and_then(|id| covers.get(&id))
+            .map(|pid| media::url(*pid));
+        item.tags = tags.remove(&item.id).unwrap_or_default();
+    }
+    Ok(())
+}
+
+/// Fetches one page of any query, converting each row to the item type
+///
+/// # Errors
+///
+/// When has DB query error
+pub async fn paginate_as<E, T>(
+    db: &DatabaseConnection,
+    query: Select<E>,
+    params: &PaginationParams,
+) -> Result<PaginatedResponse<T>>
+where
+    E: EntityTrait,
+    E::Model: Send + Sync,
+    T: From<E::Model>,
+{
     let page = params.page.max(1) - 1;
     let page_size = params.page_size.max(1);
 
-    let paginator = Entity::find()
-        .order_by_desc(Column::PublishedAt)
-        .paginate(&ctx.db, page_size);
+    let paginator = query.paginate(db, page_size);
 
     let total = paginator.num_items().await?;
-    let total_pages = (total + page_size - 1) / page_size;
-    let items: Vec<PostListItem> = paginator
+    let total_pages = total.div_ceil(page_size);
+    let items: Vec<T> = paginator
         .fetch_page(page)
         .await?
         .into_iter()
-        .map(PostListItem::from)
+        .map(T::from)
         .collect();
 
-    format::json(PaginatedResponse {
+    Ok(PaginatedResponse {
         items,
         total,
         page: page + 1,
         page_size,
         total_pages,
+        next_cursor: None,
     })
 }
 
+#[debug_handler]
+pub async fn list(
+    auth: Option<auth::JWT>,
+    State(ctx): State<AppContext>,
+    Query(filters): Query<ListParams>,
+    Query(params): Query<PaginationParams>,
+) -> Result<Response> {
+    let mut query = Entity::find()
+        .filter(Column::Published.eq(true))
+        .filter(posts::released())
+        .filter(Column::HiddenAt.is_null());
+    if filters.sort == PostSort::MostDiscussed {
+        query = query.order_by(posts::comment_count(), Order::Desc);
+    }
+    let query = query
+        .order_by_desc(Column::PublishedAt)
+        .order_by_desc(Column::Id);
+    format::json(paginate(&ctx.db, query, &params, viewer(auth.as_ref())).await?)
+}
+
 #[debug_handler]
 pub async fn add(
     auth: auth::JWT,
@@ -146,95 +418,190 @@ pub async fn add(
     let mut item = ActiveModel {
         ..Default::default()
     };
+    let author = Uuid::parse_str(&auth.claims.pid).unwrap();
+    if let Some(Some(cover)) = params.cover_media_id {
+        media::Model::check_cover(&ctx.db, author, cover).await?;
+    }
     params.update(&mut item);
-    item.user_id = Set(Some(Uuid::parse_str(&auth.claims.pid).unwrap()));
+    item.user_id = Set(Some(author));
 
     let item = item.insert(&ctx.db).await?;
-    format::json(item)
+    mentions::Model::sync(
+        &ctx.db,
+        Source::Post(item.id),
+        item.content.as_deref(),
+        item.user_id,
+    )
+    .await?;
+    format::json(render(&ctx, item, viewer(Some(&auth))).await?)
 }
 
 #[debug_handler]
 pub async fn update(
+    auth: auth::JWT,
     Path(id): Path<i32>,
     State(ctx): State<AppContext>,
     Json(params): Json<Params>,
 ) -> Result<Response> {
-    let item = load_item(&ctx, id).await?;
+    let item = load_own_item(&ctx, &auth, id).await?;
     let mut active_item = item.clone().into_active_model();
-    
-    // Create new params with the existing user_id if not provided
-    let mut updated_params = params;
-    if updated_params.user_id.is_none() {
-        updated_params.user_id = item.user_id;
+
+    if let Some(Some(cover)) = params.cover_media_id {
+        if item.cover_media_id != Some(cover) {
+            let user = Uuid::parse_str(&auth.claims.pid)
+                .map_err(|_| Error::Unauthorized("invalid pid".to_string()))?;
+            media::Model::check_cover(&ctx.db, user, cover).await?;
+        }
     }
-    
-    updated_params.update(&mut active_item);
+
+    params.update(&mut active_item);
     let item = active_item.update(&ctx.db).await?;
-    format::json(item)
+    mentions::Model::sync(
+        &ctx.db,
+        Source::Post(item.id),
+        item.content.as_deref(),
+        item.user_id,
+    )
+    .await?;
+    format::json(render(&ctx, item, viewer(Some(&auth))).await?)
 }
 
 #[debug_handler]
 pub async fn publish(
+    auth: auth::JWT,
     Path(id): Path<i32>,
     State(ctx): State<AppContext>,
     Json(params): Json<PublishParams>,
 ) -> Result<Response> {
-    let item = load_item(&ctx, id).await?;
+    let item = load_own_item(&ctx, &auth, id).await?;
+    let was_published = item.published == Some(true);
     let mut active_item = item.into_active_model();
-    
+
     active_item.published = Set(Some(params.published));
     // Update published_at timestamp when publishing
-    if params.published {
-        active_item.published_at = Set(Some(chrono::Utc::now().into()));
-    } else {
+    if !params.published {
         active_item.published_at = Set(None);
+    } else if !was_published {
+        active_item.published_at = Set(Some(chrono::Utc::now().into()));
     }
-    
+
     let item = active_item.update(&ctx.db).await?;
     format::json(item)
 }
 
 #[debug_handler]
-pub async fn remove(Path(id): Path<i32>, State(ctx): State<AppContext>) -> Result<Response> {
-    load_item(&ctx, id).await?.delete(&ctx.db).await?;
+pub async fn remove(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let item = load_own_item(&ctx, &auth, id).await?;
+    let txn = ctx.db.begin().await?;
+    posts::remove_all(&txn, &[item.id], &[]).await?;
+    txn.commit().await?;
     format::empty()
 }
 
+/// Shows the post and counts the view
+async fn show(
+    ctx: &AppContext,
+    item: Model,
+    auth: Option<&auth::JWT>,
+    client: &ClientInfo,
+) -> Result<Response> {
+    let viewer = viewer(auth);
+    // Posts hidden after being reported are only shown to their author
+    if item.hidden_at.is_some() && viewer != item.user_id {
+        return Err(Error::NotFound);
+    }
+    record_view(ctx, &item, client, viewer)?;
+    format::json(render(ctx, item, viewer).await?)
+}
+
 #[debug_handler]
-pub async fn get_one(Path(id): Path<i32>, State(ctx): State<AppContext>) -> Result<Response> {
-    format::json(load_item(&ctx, id).await?)
+pub async fn get_one(
+    auth: Option<auth::JWT>,
+    client: ClientInfo,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let item = load_item(&ctx, id).await?;
+    show(&ctx, item, auth.as_ref(), &client).await
 }
 
 #[debug_handler]
-pub async fn my_posts(
-    auth: auth::JWT,
+pub async fn get_by_slug(
+    auth: Option<auth::JWT>,
+    client: ClientInfo,
+    Path(slug): Path<String>,
     State(ctx): State<AppContext>,
-    Query(params): Query<PaginationParams>,
 ) -> Result<Response> {
-    let page = params.page.max(1) - 1;
-    let page_size = params.page_size.max(1);
+    let item = Entity::find()
+        .filter(Column::Slug.eq(slug))
+        .order_by_desc(Column::Published)
+        .order_by_asc(Column::Id)
+        .one(&ctx.db)
+        .await?
+        .ok_or_else(|| Error::NotFound)?;
+    show(&ctx, item, auth.as_ref(), &client).await
+}
 
-    let paginator = Entity::find()
-        .filter(Column::UserId.eq(Uuid::parse_str(&auth.claims.pid).unwrap()))
-        .order_by_desc(Column::PublishedAt)
-        .paginate(&ctx.db, page_size);
+/// Daily views, unique visitors and referrers of the post, for its author
+/// and moderators
+#[debug_handler]
+pub async fn stats(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    Query(params): Query<StatsParams>,
+) -> Result<Response> {
+    let item = load_own_item(&ctx, &auth, id).await?;
+    let to = chrono::Utc::now().date_naive();
+    let from = post_views::Model::period_start(to, params.days.clamp(1, MAX_STATS_DAYS));
+    let buffered = analytics::buffered(|post_id| post_id == item.id);
+    format::json(post_views::Model::stats(&ctx.db, item.id, from, to, &buffered).await?)
+}
 
-    let total = paginator.num_items().await?;
-    let total_pages = (total + page_size - 1) / page_size;
-    let items: Vec<PostListItem> = paginator
-        .fetch_page(page)
-        .await?
-        .into_iter()
-        .map(PostListItem::from)
-        .collect();
+/// Adds the reaction of the current user to the post, or takes it back
+#[debug_handler]
+pub async fn react(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    Json(params): Json<ReactParams>,
+) -> Result<Response> {
+    let item = load_item(&ctx, id).await?;
+    let user = Uuid::parse_str(&auth.claims.pid)
+        .map_err(|_| Error::Unauthorized("invalid pid".to_string()))?;
+    if item.hidden_at.is_some() && Some(user) != item.user_id {
+        return Err(Error::NotFound);
+    }
+    let settings = Settings::from_context(&ctx)?;
+    reactions::Model::toggle(
+        &ctx.db,
+        &settings.reactions,
+        user,
+        ReactionTarget::Post,
+        item.id,
+        &params.emoji,
+    )
+    .await?;
+    let summary =
+        reactions::Model::summary(&ctx.db, ReactionTarget::Post, item.id, Some(user)).await?;
+    format::json(summary)
+}
 
-    format::json(PaginatedResponse {
-        items,
-        total,
-        page: page + 1,
-        page_size,
-        total_pages,
-    })
+#[debug_handler]
+pub async fn my_posts(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Query(params): Query<PaginationParams>,
+) -> Result<Response> {
+    let user = Uuid::parse_str(&auth.claims.pid).unwrap();
+    let query = Entity::find()
+        .filter(Column::UserId.eq(user))
+        .order_by_desc(Column::PublishedAt);
+    format::json(paginate(&ctx.db, query, &params, Some(user)).await?)
 }
 
 pub fn routes() -> Routes {
@@ -247,5 +614,8 @@ pub fn routes() -> Routes {
         .add(":id", put(update))
         .add(":id", patch(update))
         .add(":id/publish", patch(publish))
+        .add(":id/reactions", post(react))
+        .add(":id/stats", get(stats))
+        .add("slug/:slug", get(get_by_slug))
         .add("my", get(my_posts))
 }
@@ -0,0 +1,35 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unused_async)]
+use axum::debug_handler;
+use axum::extract::State;
+use loco_rs::prelude::*;
+
+use crate::{
+    common::settings::Settings,
+    extractors::auth,
+    models::reports::{self, CreateParams},
+    views::reports::ReportResponse,
+};
+
+/// Reports a post or comment. Reporting the same content twice returns the
+/// first report.
+#[debug_handler]
+pub async fn add(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Json(params): Json<CreateParams>,
+) -> Result<Response> {
+    let reporter = Uuid::parse_str(&auth.claims.pid)
+        .map_err(|_| Error::Unauthorized("invalid pid".to_string()))?;
+    if !params.target_type.exists(&ctx.db, params.target_id).await? {
+        return Err(Error::NotFound);
+    }
+    let settings = Settings::from_context(&ctx)?;
+    let report =
+        reports::Model::file(&ctx.db, reporter, &params, settings.reports.hide_after).await?;
+    format::json(ReportResponse::from(report))
+}
+
+pub fn routes() -> Routes {
+    Routes::new().prefix("api/reports/").add("/", post(add))
+}
@@ -0,0 +1,88 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unused_async)]
+use axum::debug_handler;
+use axum::extract::{Path, Query, State};
+use loco_rs::prelude::*;
+use sea_orm::QueryOrder;
+
+use crate::{
+    controllers::post::{self, PaginationParams},
+    extractors::auth,
+    models::{
+        _entities::posts::{Column, Entity},
+        follows,
+        posts::released,
+        users,
+    },
+    views::users::UserPageResponse,
+};
+
+async fn load_user(ctx: &AppContext, handle: &str) -> Result<users::Model> {
+    match users::Model::find_by_handle(&ctx.db, handle).await {
+        Ok(user) => Ok(user),
+        Err(ModelError::EntityNotFound) => Err(Error::NotFound),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Public author page: the profile and a page of the author's published posts
+#[debug_handler]
+pub async fn show(
+    auth: Option<auth::JWT>,
+    Path(handle): Path<String>,
+    State(ctx): State<AppContext>,
+    Query(params): Query<PaginationParams>,
+) -> Result<Response> {
+    let user = load_user(&ctx, &handle).await?;
+
+    let query = Entity::find()
+        .filter(Column::UserId.eq(user.pid))
+        .filter(Column::Published.eq(true))
+        .filter(released())
+        .filter(Column::HiddenAt.is_null())
+        .order_by_desc(Column::PublishedAt);
+    let viewer = auth.and_then(|auth| Uuid::parse_str(&auth.claims.pid).ok());
+    let posts = post::paginate(&ctx.db, query, &params, viewer).await?;
+    let counts = follows::Model::counts(&ctx.db, user.pid).await?;
+    let followed = match viewer {
+        Some(viewer) => Some(follows::Model::is_following(&ctx.db, viewer, user.pid).await?),
+        None => None,
+    };
+
+    format::json(UserPageResponse::new(&user, posts).with_follows(counts, followed))
+}
+
+/// Follows the author, who is notified of the new follower
+#[debug_handler]
+pub async fn follow(
+    auth: auth::JWT,
+    Path(handle): Path<String>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let follower = Uuid::parse_str(&auth.claims.pid)
+        .map_err(|_| Error::Unauthorized("invalid pid".to_string()))?;
+    let user = load_user(&ctx, &handle).await?;
+    follows::Model::follow(&ctx.db, follower, user.pid).await?;
+    format::json(follows::Model::counts(&ctx.db, user.pid).await?)
+}
+
+#[debug_handler]
+pub async fn unfollow(
+    auth: auth::JWT,
+    Path(handle): Path<String>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let follower = Uuid::parse_str(&auth.claims.pid)
+        .map_err(|_| Error::Unauthorized("invalid pid".to_string()))?;
+    let user = load_user(&ctx, &handle).await?;
+    follows::Model::unfollow(&ctx.db, follower, user.pid).await?;
+    format::json(follows::Model::counts(&ctx.db, user.pid).await?)
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("api/users/")
+        .add(":handle", get(show))
+        .add(":handle/follow", post(follow))
+        .add(":handle/follow", delete(unfollow))
+}
@@ -0,0 +1,124 @@
+//! JWT extractor used by the app controllers in place of loco's
+//! `auth::JWT`.
+//!
+//! On top of validating the token signature and expiration, it rejects tokens
+//! the user has since invalidated, e.g. by changing their password, and tokens
+//! of revoked login sessions. Handlers keep the same shape:
+//!
+//! ```rust,ignore
+//! use crate::extractors::auth;
+//!
+//! async fn current(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+//!     format::json(auth.claims.pid)
+//! }
+//! ```
+use async_trait::async_trait;
+use axum::{
+    extract::{FromRef, FromRequestParts},
+    http::{request::Parts, StatusCode},
+};
+use loco_rs::{
+    app::AppContext,
+    auth::jwt::{self, UserClaims},
+    controller::ErrorDetail,
+    prelude::auth as loco_auth,
+    Error, Result,
+};
+use serde_json::Value;
+
+use crate::models::{
+    sessions,
+    users::{self, Role, ISSUED_AT_CLAIM, SESSION_CLAIM},
+};
+
+#[derive(Debug)]
+pub struct JWT {
+    pub claims: UserClaims,
+    /// Login session the token belongs to
+    pub session_id: i32,
+}
+
+impl JWT {
+    /// Authenticates a token passed outside the `Authorization` header, e.g.
+    /// in the query string of an event stream which can't set headers
+    ///
+    /// # Errors
+    ///
+    /// When the token is not valid or was revoked
+    pub async fn from_token(ctx: &AppContext, token: &str) -> Result<Self> {
+        let jwt_secret = ctx.config.get_jwt_config()?;
+        let claims = jwt::JWT::new(&jwt_secret.secret)
+            .validate(token)
+            .map_err(|_| Error::Unauthorized("token is not valid".to_string()))?
+            .claims;
+        Self::from_claims(ctx, claims).await
+    }
+
+    /// Checks the claims of a token with a valid signature against the user
+    /// and its login session
+    async fn from_claims(ctx: &AppContext, claims: UserClaims) -> Result<Self> {
+        let user = users::Model::find_by_pid(&ctx.db, &claims.pid)
+            .await
+            .map_err(|_| Error::Unauthorized("token is not valid".to_string()))?;
+
+        let claim = |name: &str| {
+            claims
+                .claims
+                .as_ref()
+                .and_then(|c| c.get(name))
+                .and_then(Value::as_i64)
+        };
+
+        let issued_at_ms = claim(ISSUED_AT_CLAIM).unwrap_or_default();
+        if !user.accepts_token_issued_at(issued_at_ms) {
+            return Err(Error::Unauthorized("token was revoked".to_string()));
+        }
+
+        // every token is tied to a login session, tokens without one can't be
+        // listed nor revoked by the user so they are refused
+        let session_id = claim(SESSION_CLAIM)
+            .and_then(|id| i32::try_from(id).ok())
+            .ok_or_else(|| Error::Unauthorized("token has no session".to_string()))?;
+        let session = sessions::Model::find_by_user_and_id(&ctx.db, user.pid, session_id)
+            .await
+            .map_err(|_| Error::Unauthorized("session not found".to_string()))?;
+        if !session.is_active() {
+            return Err(Error::Unauthorized("session was revoked".to_string()));
+        }
+        session.touch(&ctx.db).await?;
+
+        Ok(Self { claims, session_id })
+    }
+
+    /// Loads the authenticated user, refusing with `403 Forbidden` unless it
+    /// has at least the given role
+    ///
+    /// # Errors
+    ///
+    /// When the user lacks the role or could not be loaded
+    pub async fn require_role(&self, ctx: &AppContext, role: Role) -> Result<users::Model> {
+        let user = users::Model::find_by_pid(&ctx.db, &self.claims.pid).await?;
+        if !user.has_role(role) {
+            return Err(Error::CustomError(
+                StatusCode::FORBIDDEN,
+                ErrorDetail::new("forbidden", &format!("requires the {} role", role.as_str())),
+            ));
+        }
+        Ok(user)
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for JWT
+where
+    AppContext: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Error> {
+        let loco_auth::JWT { claims } = loco_auth::JWT::from_request_parts(parts, state).await?;
+        let ctx = AppContext::from_ref(state);
+        Self::from_claims(&ctx, claims).await
+    }
+}
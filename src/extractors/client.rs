@@ -0,0 +1,93 @@
+//! Details about the client making the request, as recorded alongside
+//! security relevant events.
+use std::convert::Infallible;
+
+use async_trait::async_trait;
+use axum::{
+    extract::FromRequestParts,
+    http::{header, request::Parts},
+};
+use loco_rs::prelude::RemoteIP;
+
+use crate::models::audit_events::{EventKind, NewEvent, Outcome};
+
+/// Longest user agent kept, anything past it is cut
+const MAX_USER_AGENT_LEN: usize = 255;
+
+#[derive(Clone, Debug, Default)]
+pub struct ClientInfo {
+    /// Client IP as resolved by the `remote_ip` middleware, `None` when the
+    /// middleware is disabled
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    /// Host of the page that linked here, when it is on another site
+    pub referrer: Option<String>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Infallible> {
+        let ip = match parts.extensions.get::<RemoteIP>() {
+            Some(RemoteIP::Forwarded(ip) | RemoteIP::Socket(ip)) => Some(ip.to_string()),
+            Some(RemoteIP::None) | None => None,
+        };
+        let user_agent = parts
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|ua| ua.chars().take(MAX_USER_AGENT_LEN).collect());
+        let host = parts
+            .headers
+            .get(header::HOST)
+            .and_then(|value| value.to_str().ok())
+            .map(|host| strip_port(host).to_lowercase());
+        let referrer = parts
+            .headers
+            .get(header::REFERER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(referrer_host)
+            .filter(|referrer| host.as_ref() != Some(referrer));
+
+        Ok(Self {
+            ip,
+            user_agent,
+            referrer,
+        })
+    }
+}
+
+fn strip_port(host: &str) -> &str {
+    host.rsplit_once(':')
+        .filter(|(_, port)| port.chars().all(|c| c.is_ascii_digit()))
+        .map_or(host, |(host, _)| host)
+}
+
+/// Lowercased host of an `http` or `https` URL, without the port
+fn referrer_host(url: &str) -> Option<String> {
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let authority = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = strip_port(authority).to_lowercase();
+    (!host.is_empty()).then_some(host)
+}
+
+impl ClientInfo {
+    /// Starts an audit log event made by this client
+    #[must_use]
+    pub fn audit(&self, kind: EventKind, outcome: Outcome) -> NewEvent {
+        NewEvent {
+            ip: self.ip.clone(),
+            user_agent: self.user_agent.clone(),
+            ..NewEvent::new(kind, outcome)
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use axum::async_trait;
+use loco_rs::{
+    app::{AppContext, Initializer},
+    Result,
+};
+
+use crate::common::{analytics, settings::Settings};
+
+/// Writes the buffered post views every `flush_interval_seconds` while the
+/// server runs
+#[allow(clippy::module_name_repetitions)]
+pub struct AnalyticsInitializer;
+
+#[async_trait]
+impl Initializer for AnalyticsInitializer {
+    fn name(&self) -> String {
+        "analytics".to_string()
+    }
+
+    async fn before_run(&self, ctx: &AppContext) -> Result<()> {
+        let settings = Settings::from_context(ctx)?.analytics;
+        if !settings.enabled {
+            return Ok(());
+        }
+        let db = ctx.db.clone();
+        let every = std::time::Duration::from_secs(settings.flush_interval_seconds.max(1));
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            loop {
+                interval.tick().await;
+                if let Err(err) = analytics::flush(&db).await {
+                    tracing::error!(err = err.to_string(), "could not write post views");
+                }
+            }
+        });
+        Ok(())
+    }
+}
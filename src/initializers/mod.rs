@@ -1 +1,2 @@
 pub mod view_engine;
+pub mod analytics;
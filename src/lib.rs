@@ -1,5 +1,7 @@
 pub mod app;
+pub mod common;
 pub mod controllers;
+pub mod extractors;
 pub mod initializers;
 pub mod mailers;
 pub mod models;
@@ -4,10 +4,14 @@
 use loco_rs::prelude::*;
 use serde_json::json;
 
-use crate::models::users;
+use crate::models::{invites, users};
 
 static welcome: Dir<'_> = include_dir!("src/mailers/auth/welcome");
 static forgot: Dir<'_> = include_dir!("src/mailers/auth/forgot");
+static change_email: Dir<'_> = include_dir!("src/mailers/auth/change_email");
+static email_change_notice: Dir<'_> = include_dir!("src/mailers/auth/email_change_notice");
+static export_ready: Dir<'_> = include_dir!("src/mailers/auth/export_ready");
+static invite: Dir<'_> = include_dir!("src/mailers/auth/invite");
 // #[derive(Mailer)] // -- disabled for faster build speed. it works. but lets
 // move on for now.
 
@@ -62,4 +66,117 @@ impl AuthMailer {
 
         Ok(())
     }
+
+    /// Sending the confirmation link of a pending email change to the new
+    /// address
+    ///
+    /// # Errors
+    ///
+    /// When email sending is failed
+    pub async fn send_email_change(ctx: &AppContext, user: &users::Model) -> Result<()> {
+        let Some(new_email) = &user.pending_email else {
+            return Ok(());
+        };
+        Self::mail_template(
+            ctx,
+            &change_email,
+            mailer::Args {
+                to: new_email.to_string(),
+                locals: json!({
+                  "name": user.name,
+                  "emailChangeToken": user.email_change_token,
+                  "domain": ctx.config.server.full_url()
+                }),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Letting the current address know that an email change was requested
+    ///
+    /// # Errors
+    ///
+    /// When email sending is failed
+    pub async fn send_email_change_notice(ctx: &AppContext, user: &users::Model) -> Result<()> {
+        Self::mail_template(
+            ctx,
+            &email_change_notice,
+            mailer::Args {
+                to: user.email.to_string(),
+                locals: json!({
+                  "name": user.name,
+                  "newEmail": user.pending_email,
+                  "domain": ctx.config.server.full_url()
+                }),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Sending the download link of a finished data export
+    ///
+    /// # Errors
+    ///
+    /// When email sending is failed
+    pub async fn send_export_ready(
+        ctx: &AppContext,
+        user: &users::Model,
+        token: &str,
+    ) -> Result<()> {
+        Self::mail_template(
+            ctx,
+            &export_ready,
+            mailer::Args {
+                to: user.email.to_string(),
+                locals: json!({
+                  "name": user.name,
+                  "exportToken": token,
+                  "domain": ctx.config.server.full_url()
+                }),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Sending an invite to register to its preassigned email. Invites
+    /// without one are shared by the admin some other way.
+    ///
+    /// # Errors
+    ///
+    /// When email sending is failed
+    pub async fn send_invite(
+        ctx: &AppContext,
+        invitation: &invites::Model,
+        inviter: &users::Model,
+    ) -> Result<()> {
+        let Some(email) = &invitation.email else {
+            return Ok(());
+        };
+        Self::mail_template(
+            ctx,
+            &invite,
+            mailer::Args {
+                to: email.to_string(),
+                locals: json!({
+                  "inviterName": inviter.name,
+                  "inviteCode": invitation.code,
+                  "expiresAt": invitation.expires_at.format("%Y-%m-%d %H:%M %Z").to_string(),
+                  "domain": ctx.config.server.full_url()
+                }),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
 }
@@ -0,0 +1,11 @@
+;<html>
+
+<body>
+  Hey {{name}},
+  You asked to use this address for your account. Please confirm the change by clicking the link below:
+  <a href="{{domain}}/confirm-email#{{emailChangeToken}}">Confirm Your Email</a>
+  If you didn't request this change, please ignore this email.
+  <p>Best regards,<br>The Loco Team</p>
+</body>
+
+</html>
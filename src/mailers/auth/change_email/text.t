@@ -0,0 +1,3 @@
+Confirm your new email address with this link:
+
+{{domain}}/confirm-email#{{emailChangeToken}}
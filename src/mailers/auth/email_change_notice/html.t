@@ -0,0 +1,11 @@
+;<html>
+
+<body>
+  Hey {{name}},
+  Someone asked to change the email address of your account to {{newEmail}}.
+  The change only happens once it is confirmed from the new address.
+  If this wasn't you, please change your password right away.
+  <p>Best regards,<br>The Loco Team</p>
+</body>
+
+</html>
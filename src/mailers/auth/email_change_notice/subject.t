@@ -0,0 +1 @@
+Your email address is about to change
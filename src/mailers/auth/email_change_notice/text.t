@@ -0,0 +1,2 @@
+Someone asked to change the email address of your account to {{newEmail}}.
+If this wasn't you, please change your password right away.
@@ -0,0 +1,11 @@
+;<html>
+
+<body>
+  Hey {{name}},
+  The export of your profile, posts and comments you asked for is ready.
+  <a href="{{domain}}/export#{{exportToken}}">Download Your Data</a>
+  You need to be logged in to download it.
+  <p>Best regards,<br>The Loco Team</p>
+</body>
+
+</html>
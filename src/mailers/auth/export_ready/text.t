@@ -0,0 +1,3 @@
+Your data export is ready. Download it while logged in with this link:
+
+{{domain}}/export#{{exportToken}}
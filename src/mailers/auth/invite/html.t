@@ -0,0 +1,11 @@
+;<html>
+
+<body>
+  Hey,
+  {{inviterName}} invited you to join.
+  <a href="{{domain}}/register#{{inviteCode}}">Create Your Account</a>
+  The invite expires on {{expiresAt}}.
+  <p>Best regards,<br>The Loco Team</p>
+</body>
+
+</html>
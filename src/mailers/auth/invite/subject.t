@@ -0,0 +1 @@
+You're invited to join
@@ -0,0 +1,3 @@
+{{inviterName}} invited you to join. Create your account with this link before {{expiresAt}}:
+
+{{domain}}/register#{{inviteCode}}
@@ -0,0 +1,136 @@
+// notification mailer
+#![allow(non_upper_case_globals)]
+
+use loco_rs::prelude::*;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::models::{
+    _entities::{comments, posts},
+    users,
+};
+
+static new_comment: Dir<'_> = include_dir!("src/mailers/notification/new_comment");
+static reply: Dir<'_> = include_dir!("src/mailers/notification/reply");
+static digest: Dir<'_> = include_dir!("src/mailers/notification/digest");
+
+/// Why a user is told about a comment
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommentNotice {
+    /// Someone commented on one of the user's posts
+    NewComment,
+    /// Someone replied to one of the user's comments
+    Reply,
+}
+
+/// A comment listed in a digest email
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DigestEntry {
+    pub notice: CommentNotice,
+    pub commenter_name: String,
+    pub post_id: Option<i32>,
+    pub post_title: String,
+    pub comment_id: i32,
+    pub excerpt: String,
+}
+
+/// Longest comment excerpt shown in an email
+const EXCERPT_LEN: usize = 280;
+
+/// Cuts the comment content to a short plain excerpt
+#[must_use]
+pub fn excerpt(comment: &comments::Model) -> String {
+    let content = comment.content.as_deref().unwrap_or_default().trim();
+    if content.chars().count() <= EXCERPT_LEN {
+        return content.to_string();
+    }
+    let mut excerpt: String = content.chars().take(EXCERPT_LEN).collect();
+    excerpt.push('…');
+    excerpt
+}
+
+fn unsubscribe_url(ctx: &AppContext, user: &users::Model) -> Result<String> {
+    let jwt_secret = ctx.config.get_jwt_config()?;
+    Ok(format!(
+        "{}/api/notifications/unsubscribe?token={}",
+        ctx.config.server.full_url(),
+        user.unsubscribe_token(&jwt_secret.secret)
+    ))
+}
+
+#[allow(clippy::module_name_repetitions)]
+pub struct NotificationMailer {}
+impl Mailer for NotificationMailer {}
+impl NotificationMailer {
+    /// Sending a single comment notification to the given user
+    ///
+    /// # Errors
+    ///
+    /// When email sending is failed
+    pub async fn send_comment(
+        ctx: &AppContext,
+        user: &users::Model,
+        notice: CommentNotice,
+        comment: &comments::Model,
+        post: &posts::Model,
+        commenter_name: &str,
+    ) -> Result<()> {
+        let template = match notice {
+            CommentNotice::NewComment => &new_comment,
+            CommentNotice::Reply => &reply,
+        };
+        Self::mail_template(
+            ctx,
+            template,
+            mailer::Args {
+                to: user.email.to_string(),
+                locals: json!({
+                  "name": user.name,
+                  "commenterName": commenter_name,
+                  "postId": post.id,
+                  "postTitle": post.title.as_deref().unwrap_or("Untitled"),
+                  "commentId": comment.id,
+                  "excerpt": excerpt(comment),
+                  "unsubscribeUrl": unsubscribe_url(ctx, user)?,
+                  "domain": ctx.config.server.full_url()
+                }),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Sending the daily digest of new comments to the given user
+    ///
+    /// # Errors
+    ///
+    /// When email sending is failed
+    pub async fn send_digest(
+        ctx: &AppContext,
+        user: &users::Model,
+        entries: &[DigestEntry],
+    ) -> Result<()> {
+        Self::mail_template(
+            ctx,
+            &digest,
+            mailer::Args {
+                to: user.email.to_string(),
+                locals: json!({
+                  "name": user.name,
+                  "count": entries.len(),
+                  "entries": entries,
+                  "unsubscribeUrl": unsubscribe_url(ctx, user)?,
+                  "domain": ctx.config.server.full_url()
+                }),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,17 @@
+;<html>
+
+<body>
+  Hey {{name | escape}},
+  <p>Here is what happened since the last digest:</p>
+  {% for entry in entries %}
+  <p>
+    {% if entry.notice == "reply" %}{{entry.commenterName | escape}} replied to your comment on "{{entry.postTitle | escape}}"{% else %}{{entry.commenterName | escape}} commented on "{{entry.postTitle | escape}}"{% endif %}:
+  </p>
+  <blockquote>{{entry.excerpt | escape}}</blockquote>
+  <a href="{{domain}}/post/{{entry.postId}}#comment-{{entry.commentId}}">Read it</a>
+  {% endfor %}
+  <p>Best regards,<br>The Loco Team</p>
+  <p><small><a href="{{unsubscribeUrl}}">Unsubscribe from comment notifications</a></small></p>
+</body>
+
+</html>
@@ -0,0 +1 @@
+{{count}} new comment{% if count != 1 %}s{% endif %} for you
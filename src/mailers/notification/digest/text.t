@@ -0,0 +1,7 @@
+Here is what happened since the last digest:
+{% for entry in entries %}
+{% if entry.notice == "reply" %}{{entry.commenterName}} replied to your comment on "{{entry.postTitle}}"{% else %}{{entry.commenterName}} commented on "{{entry.postTitle}}"{% endif %}:
+{{entry.excerpt}}
+{{domain}}/post/{{entry.postId}}#comment-{{entry.commentId}}
+{% endfor %}
+Stop these emails: {{unsubscribeUrl}}
@@ -0,0 +1,12 @@
+;<html>
+
+<body>
+  Hey {{name | escape}},
+  <p>{{commenterName | escape}} commented on your post "{{postTitle | escape}}":</p>
+  <blockquote>{{excerpt | escape}}</blockquote>
+  <a href="{{domain}}/post/{{postId}}#comment-{{commentId}}">Read the comment</a>
+  <p>Best regards,<br>The Loco Team</p>
+  <p><small><a href="{{unsubscribeUrl}}">Unsubscribe from comment notifications</a></small></p>
+</body>
+
+</html>
@@ -0,0 +1 @@
+New comment on "{{postTitle}}"
@@ -0,0 +1,7 @@
+{{commenterName}} commented on your post "{{postTitle}}":
+
+{{excerpt}}
+
+Read it: {{domain}}/post/{{postId}}#comment-{{commentId}}
+
+Stop these emails: {{unsubscribeUrl}}
@@ -0,0 +1,12 @@
+;<html>
+
+<body>
+  Hey {{name | escape}},
+  <p>{{commenterName | escape}} replied to your comment on "{{postTitle | escape}}":</p>
+  <blockquote>{{excerpt | escape}}</blockquote>
+  <a href="{{domain}}/post/{{postId}}#comment-{{commentId}}">Read the reply</a>
+  <p>Best regards,<br>The Loco Team</p>
+  <p><small><a href="{{unsubscribeUrl}}">Unsubscribe from comment notifications</a></small></p>
+</body>
+
+</html>
@@ -0,0 +1 @@
+{{commenterName}} replied to your comment
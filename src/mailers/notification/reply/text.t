@@ -0,0 +1,7 @@
+{{commenterName}} replied to your comment on "{{postTitle}}":
+
+{{excerpt}}
+
+Read it: {{domain}}/post/{{postId}}#comment-{{commentId}}
+
+Stop these emails: {{unsubscribeUrl}}
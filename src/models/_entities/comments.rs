@@ -16,6 +16,11 @@ pub struct Model {
     #[sea_orm(unique)]
     pub user_id: Uuid,
     pub parent_id: Option<i32>,
+    pub status: String,
+    pub moderated_by: Option<Uuid>,
+    pub moderated_at: Option<DateTimeWithTimeZone>,
+    pub hidden_at: Option<DateTimeWithTimeZone>,
+    pub reaction_count: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
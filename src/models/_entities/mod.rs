@@ -2,6 +2,23 @@
 
 pub mod prelude;
 
+pub mod audit_events;
+pub mod bookmark_collections;
+pub mod bookmarks;
 pub mod comments;
+pub mod exports;
+pub mod follows;
+pub mod invites;
+pub mod media;
+pub mod media_variants;
+pub mod mentions;
+pub mod notifications;
+pub mod post_referrers;
+pub mod post_tags;
+pub mod post_views;
 pub mod posts;
+pub mod reactions;
+pub mod reports;
+pub mod sessions;
+pub mod spam_tokens;
 pub mod users;
@@ -18,6 +18,14 @@ pub struct Model {
     pub slug: Option<String>,
     pub published_at: Option<DateTimeWithTimeZone>,
     pub user_id: Option<Uuid>,
+    pub comment_moderation: bool,
+    pub hidden_at: Option<DateTimeWithTimeZone>,
+    pub comment_policy: String,
+    pub comments_close_after_days: Option<i32>,
+    pub word_count: i32,
+    pub reading_time: i32,
+    pub summary_generated: bool,
+    pub cover_media_id: Option<i32>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
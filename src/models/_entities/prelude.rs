@@ -1,5 +1,22 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.2
 
+pub use super::audit_events::Entity as AuditEvents;
+pub use super::bookmark_collections::Entity as BookmarkCollections;
+pub use super::bookmarks::Entity as Bookmarks;
 pub use super::comments::Entity as Comments;
+pub use super::exports::Entity as Exports;
+pub use super::follows::Entity as Follows;
+pub use super::invites::Entity as Invites;
+pub use super::media::Entity as Media;
+pub use super::media_variants::Entity as MediaVariants;
+pub use super::mentions::Entity as Mentions;
+pub use super::notifications::Entity as Notifications;
+pub use super::post_referrers::Entity as PostReferrers;
+pub use super::post_tags::Entity as PostTags;
+pub use super::post_views::Entity as PostViews;
 pub use super::posts::Entity as Posts;
+pub use super::reactions::Entity as Reactions;
+pub use super::reports::Entity as Reports;
+pub use super::sessions::Entity as Sessions;
+pub use super::spam_tokens::Entity as SpamTokens;
 pub use super::users::Entity as Users;
@@ -22,6 +22,19 @@ pub struct Model {
     pub email_verification_token: Option<String>,
     pub email_verification_sent_at: Option<DateTimeWithTimeZone>,
     pub email_verified_at: Option<DateTimeWithTimeZone>,
+    #[sea_orm(unique)]
+    pub handle: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub bio: Option<String>,
+    pub website: Option<String>,
+    pub avatar_url: Option<String>,
+    pub tokens_valid_after: Option<DateTimeWithTimeZone>,
+    pub pending_email: Option<String>,
+    pub email_change_token: Option<String>,
+    pub email_change_sent_at: Option<DateTimeWithTimeZone>,
+    pub role: String,
+    pub notification_preference: String,
+    pub digest_sent_at: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,201 @@
+use chrono::{offset::Local, Duration};
+use loco_rs::prelude::*;
+use sea_orm::{QueryOrder, QuerySelect, Select};
+use serde::{Deserialize, Serialize};
+
+pub use super::_entities::audit_events::{self, ActiveModel, Entity, Model};
+pub type AuditEvents = Entity;
+
+/// How many events, and how far back, a user sees in their own history
+pub const RECENT_EVENTS_LIMIT: u64 = 50;
+pub const RECENT_EVENTS_WINDOW: Duration = Duration::days(90);
+
+/// Longest free text detail kept on an event
+const MAX_DETAIL_LEN: usize = 255;
+
+/// Security relevant actions recorded in the audit log
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Register,
+    Verify,
+    Login,
+    PasswordResetRequest,
+    PasswordReset,
+    PasswordChange,
+    TokenRefresh,
+    ApiKeyRotation,
+}
+
+impl EventKind {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Register => "register",
+            Self::Verify => "verify",
+            Self::Login => "login",
+            Self::PasswordResetRequest => "password_reset_request",
+            Self::PasswordReset => "password_reset",
+            Self::PasswordChange => "password_change",
+            Self::TokenRefresh => "token_refresh",
+            Self::ApiKeyRotation => "api_key_rotation",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Success,
+    Failure,
+}
+
+impl Outcome {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Failure => "failure",
+        }
+    }
+}
+
+/// An event about to be recorded, see `ClientInfo::audit` to start one with
+/// the request client details filled in
+#[derive(Clone, Debug)]
+pub struct NewEvent {
+    pub kind: EventKind,
+    pub outcome: Outcome,
+    /// The acting user, when known
+    pub user_id: Option<Uuid>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    /// Why it failed, or what was attempted, e.g. the email of a failed login
+    pub detail: Option<String>,
+}
+
+impl NewEvent {
+    #[must_use]
+    pub const fn new(kind: EventKind, outcome: Outcome) -> Self {
+        Self {
+            kind,
+            outcome,
+            user_id: None,
+            ip: None,
+            user_agent: None,
+            detail: None,
+        }
+    }
+
+    #[must_use]
+    pub const fn user(mut self, user_id: Uuid) -> Self {
+        self.user_id = Some(user_id);
+        self
+    }
+
+    #[must_use]
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into().chars().take(MAX_DETAIL_LEN).collect());
+        self
+    }
+
+    /// Saves the event
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn record(self, db: &DatabaseConnection) -> ModelResult<Model> {
+        Ok(ActiveModel {
+            user_id: ActiveValue::set(self.user_id),
+            event: ActiveValue::set(self.kind.as_str().to_string()),
+            outcome: ActiveValue::set(self.outcome.as_str().to_string()),
+            ip: ActiveValue::set(self.ip),
+            user_agent: ActiveValue::set(self.user_agent),
+            detail: ActiveValue::set(self.detail),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?)
+    }
+}
+
+/// Filters of the admin audit log query, all optional
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct SearchParams {
+    /// pid of the acting user
+    pub user_id: Option<Uuid>,
+    pub event: Option<EventKind>,
+    pub outcome: Option<Outcome>,
+    pub ip: Option<String>,
+    /// Events at or after this time
+    pub since: Option<DateTimeWithTimeZone>,
+    /// Events before this time
+    pub until: Option<DateTimeWithTimeZone>,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Model {
+    /// Query of the events matching the filters, most recent first
+    #[must_use]
+    pub fn search(params: &SearchParams) -> Select<Entity> {
+        let mut condition = model::query::condition();
+        if let Some(user_id) = params.user_id {
+            condition = condition.eq(audit_events::Column::UserId, user_id);
+        }
+        if let Some(event) = params.event {
+            condition = condition.eq(audit_events::Column::Event, event.as_str());
+        }
+        if let Some(outcome) = params.outcome {
+            condition = condition.eq(audit_events::Column::Outcome, outcome.as_str());
+        }
+        if let Some(ip) = &params.ip {
+            condition = condition.eq(audit_events::Column::Ip, ip);
+        }
+        if let Some(since) = params.since {
+            condition = condition.gte(audit_events::Column::CreatedAt, since);
+        }
+        if let Some(until) = params.until {
+            condition = condition.lt(audit_events::Column::CreatedAt, until);
+        }
+        Entity::find()
+            .filter(condition.build())
+            .order_by_desc(audit_events::Column::CreatedAt)
+            .order_by_desc(audit_events::Column::Id)
+    }
+
+    /// The latest events of the given user
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn find_recent_by_user(
+        db: &DatabaseConnection,
+        user_id: Uuid,
+    ) -> ModelResult<Vec<Self>> {
+        Ok(Self::search(&SearchParams {
+            user_id: Some(user_id),
+            since: Some((Local::now() - RECENT_EVENTS_WINDOW).into()),
+            ..Default::default()
+        })
+        .limit(RECENT_EVENTS_LIMIT)
+        .all(db)
+        .await?)
+    }
+}
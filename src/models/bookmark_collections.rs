@@ -0,0 +1,113 @@
+use loco_rs::prelude::*;
+use sea_orm::{
+    sea_query::{Expr, OnConflict},
+    QueryOrder, TransactionTrait,
+};
+
+pub use super::_entities::bookmark_collections::{self, ActiveModel, Entity, Model};
+use super::_entities::bookmarks;
+pub type BookmarkCollections = Entity;
+
+/// Longest name a collection can have
+const MAX_NAME_LEN: usize = 50;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Model {
+    /// Creates a collection of the user. Creating one with the name of an
+    /// existing collection returns that collection.
+    ///
+    /// # Errors
+    ///
+    /// When the name is empty or too long, or has DB query error
+    pub async fn create(db: &DatabaseConnection, user: Uuid, name: &str) -> ModelResult<Self> {
+        let name = name.trim();
+        if name.is_empty() || name.chars().count() > MAX_NAME_LEN {
+            return Err(ModelError::Any(
+                format!("name must be 1 to {MAX_NAME_LEN} characters").into(),
+            ));
+        }
+        Entity::insert(ActiveModel {
+            user_id: ActiveValue::set(user),
+            name: ActiveValue::set(name.to_string()),
+            ..Default::default()
+        })
+        .on_conflict(
+            OnConflict::columns([
+                bookmark_collections::Column::UserId,
+                bookmark_collections::Column::Name,
+            ])
+            .do_nothing()
+            .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+
+        Entity::find()
+            .filter(bookmark_collections::Column::UserId.eq(user))
+            .filter(bookmark_collections::Column::Name.eq(name))
+            .one(db)
+            .await?
+            .ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// A collection of the user
+    ///
+    /// # Errors
+    ///
+    /// When the user has no such collection or has DB query error
+    pub async fn find_for_user(db: &DatabaseConnection, user: Uuid, id: i32) -> ModelResult<Self> {
+        Entity::find_by_id(id)
+            .filter(bookmark_collections::Column::UserId.eq(user))
+            .one(db)
+            .await?
+            .ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// The collections of the user, by name
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_for(db: &DatabaseConnection, user: Uuid) -> ModelResult<Vec<Self>> {
+        Ok(Entity::find()
+            .filter(bookmark_collections::Column::UserId.eq(user))
+            .order_by_asc(bookmark_collections::Column::Name)
+            .all(db)
+            .await?)
+    }
+
+    /// Deletes the collection. Its bookmarks are kept, outside of any
+    /// collection.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn remove(self, db: &DatabaseConnection) -> ModelResult<()> {
+        let txn = db.begin().await?;
+        bookmarks::Entity::update_many()
+            .col_expr(bookmarks::Column::CollectionId, Expr::value(None::<i32>))
+            .filter(bookmarks::Column::CollectionId.eq(self.id))
+            .exec(&txn)
+            .await?;
+        self.delete(&txn).await?;
+        txn.commit().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::HashSet;
+
+use loco_rs::prelude::*;
+use sea_orm::{
+    sea_query::{Expr, OnConflict},
+    ConnectionTrait, JoinType, QueryOrder, QuerySelect, Select,
+};
+
+pub use super::_entities::bookmarks::{self, ActiveModel, Entity, Model};
+use super::_entities::{bookmark_collections, posts};
+use super::posts::released;
+pub type Bookmarks = Entity;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Model {
+    /// Bookmarks the post for the user, in the given collection or none.
+    /// Bookmarking a post again moves it to the given collection.
+    ///
+    /// The collection is expected to belong to the user.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn save(
+        db: &DatabaseConnection,
+        user: Uuid,
+        post_id: i32,
+        collection_id: Option<i32>,
+    ) -> ModelResult<Self> {
+        Entity::insert(ActiveModel {
+            user_id: ActiveValue::set(user),
+            post_id: ActiveValue::set(post_id),
+            collection_id: ActiveValue::set(collection_id),
+            ..Default::default()
+        })
+        .on_conflict(
+            OnConflict::columns([bookmarks::Column::UserId, bookmarks::Column::PostId])
+                .update_column(bookmarks::Column::CollectionId)
+                .value(bookmarks::Column::UpdatedAt, Expr::current_timestamp())
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+
+        Entity::find()
+            .filter(bookmarks::Column::UserId.eq(user))
+            .filter(bookmarks::Column::PostId.eq(post_id))
+            .one(db)
+            .await?
+            .ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Removes the bookmark of the user on the post. Returns whether there
+    /// was one.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn remove(db: &DatabaseConnection, user: Uuid, post_id: i32) -> ModelResult<bool> {
+        let res = Entity::delete_many()
+            .filter(bookmarks::Column::UserId.eq(user))
+            .filter(bookmarks::Column::PostId.eq(post_id))
+            .exec(db)
+            .await?;
+        Ok(res.rows_affected > 0)
+    }
+
+    /// Query of the posts the user bookmarked, in the collection when given,
+    /// most recently bookmarked first. Posts that were unpublished, hidden or
+    /// deleted since are left out, and scheduled ones until their time comes.
+    #[must_use]
+    pub fn posts_of(user: Uuid, collection_id: Option<i32>) -> Select<posts::Entity> {
+        let mut query = posts::Entity::find()
+            .join_rev(
+                JoinType::InnerJoin,
+                Entity::belongs_to(posts::Entity)
+                    .from(bookmarks::Column::PostId)
+                    .to(posts::Column::Id)
+                    .into(),
+            )
+            .filter(bookmarks::Column::UserId.eq(user))
+            .filter(posts::Column::Published.eq(true))
+            .filter(released())
+            .filter(posts::Column::HiddenAt.is_null());
+        if let Some(collection_id) = collection_id {
+            query = query.filter(bookmarks::Column::CollectionId.eq(collection_id));
+        }
+        query
+            .order_by_desc(bookmarks::Column::CreatedAt)
+            .order_by_desc(bookmarks::Column::Id)
+    }
+
+    /// Which of the posts the user bookmarked
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn bookmarked_among(
+        db: &DatabaseConnection,
+        user: Uuid,
+        post_ids: &[i32],
+    ) -> ModelResult<HashSet<i32>> {
+        if post_ids.is_empty() {
+            return Ok(HashSet::new());
+        }
+        let bookmarked: Vec<i32> = Entity::find()
+            .select_only()
+            .column(bookmarks::Column::PostId)
+            .filter(bookmarks::Column::UserId.eq(user))
+            .filter(bookmarks::Column::PostId.is_in(post_ids.iter().copied()))
+            .into_tuple()
+            .all(db)
+            .await?;
+        Ok(bookmarked.into_iter().collect())
+    }
+
+    /// Removes every bookmark on the posts, when they are deleted
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn remove_for<C: ConnectionTrait>(db: &C, post_ids: &[i32]) -> ModelResult<()> {
+        Entity::delete_many()
+            .filter(bookmarks::Column::PostId.is_in(post_ids.iter().copied()))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Removes the bookmarks and collections of a user
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn remove_by<C: ConnectionTrait>(db: &C, user: Uuid) -> ModelResult<()> {
+        Entity::delete_many()
+            .filter(bookmarks::Column::UserId.eq(user))
+            .exec(db)
+            .await?;
+        bookmark_collections::Entity::delete_many()
+            .filter(bookmark_collections::Column::UserId.eq(user))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+}
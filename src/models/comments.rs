@@ -1,7 +1,212 @@
-use sea_orm::entity::prelude::*;
-use super::_entities::comments::{ActiveModel, Entity};
+use std::collections::HashMap;
+
+use chrono::DateTime;
+use loco_rs::model::{ModelError, ModelResult};
+use sea_orm::{
+    entity::prelude::*,
+    sea_query::{Alias, Asterisk, Expr, Func, Order, Query, WindowStatement},
+    ActiveValue, Condition, IntoActiveModel, QueryOrder, QuerySelect, QueryTrait, Select,
+    TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use super::_entities::{
+    comments::{self, ActiveModel, Entity, Model},
+    posts,
+};
+use super::users::{self, Role};
+use crate::common::settings::CommentSettings;
 pub type Comments = Entity;
 
+/// Where a comment stands in moderation. Only approved comments are public.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CommentStatus {
+    /// Waiting in the moderation queue, only visible to its author
+    Pending,
+    Approved,
+    Rejected,
+    Spam,
+}
+
+impl CommentStatus {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Approved => "approved",
+            Self::Rejected => "rejected",
+            Self::Spam => "spam",
+        }
+    }
+}
+
+impl std::str::FromStr for CommentStatus {
+    type Err = ModelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(Self::Pending),
+            "approved" => Ok(Self::Approved),
+            "rejected" => Ok(Self::Rejected),
+            "spam" => Ok(Self::Spam),
+            _ => Err(ModelError::Any(
+                format!("unknown comment status `{s}`").into(),
+            )),
+        }
+    }
+}
+
+/// Comments the viewer may see: the approved ones not hidden after being
+/// reported, and their own pending or approved ones
+#[must_use]
+pub fn visible_to(viewer: Option<Uuid>) -> Condition {
+    let public = Condition::all()
+        .add(comments::Column::Status.eq(CommentStatus::Approved.as_str()))
+        .add(comments::Column::HiddenAt.is_null());
+    match viewer {
+        Some(viewer) => Condition::any().add(public).add(
+            Condition::all()
+                .add(comments::Column::UserId.eq(viewer))
+                .add(comments::Column::Status.is_in([
+                    CommentStatus::Pending.as_str(),
+                    CommentStatus::Approved.as_str(),
+                ])),
+        ),
+        None => public,
+    }
+}
+
+/// Order of a comment listing, ties broken by id
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CommentSort {
+    #[default]
+    Oldest,
+    Newest,
+    /// Most reactions first
+    MostLiked,
+}
+
+impl CommentSort {
+    /// The sort key of the comment, see [`Cursor`]
+    fn key(self, comment: &Model) -> i64 {
+        match self {
+            Self::Oldest | Self::Newest => comment.created_at.timestamp_micros(),
+            Self::MostLiked => i64::from(comment.reaction_count),
+        }
+    }
+
+    /// Orders the query
+    #[must_use]
+    pub fn order(self, query: Select<Entity>) -> Select<Entity> {
+        match self {
+            Self::Oldest => query
+                .order_by_asc(comments::Column::CreatedAt)
+                .order_by_asc(comments::Column::Id),
+            Self::Newest => query
+                .order_by_desc(comments::Column::CreatedAt)
+                .order_by_desc(comments::Column::Id),
+            Self::MostLiked => query
+                .order_by_desc(comments::Column::ReactionCount)
+                .order_by_desc(comments::Column::Id),
+        }
+    }
+
+    /// The comments coming after the cursor in this order
+    ///
+    /// # Errors
+    ///
+    /// When the cursor is out of range
+    pub fn after(self, cursor: &Cursor) -> ModelResult<Condition> {
+        let invalid = || ModelError::Any("invalid cursor".into());
+        let (key, tie) = match self {
+            Self::Oldest | Self::Newest => {
+                let created_at: DateTimeWithTimeZone = DateTime::from_timestamp_micros(cursor.key)
+                    .ok_or_else(invalid)?
+                    .into();
+                let key = if self == Self::Oldest {
+                    comments::Column::CreatedAt.gt(created_at)
+                } else {
+                    comments::Column::CreatedAt.lt(created_at)
+                };
+                (key, comments::Column::CreatedAt.eq(created_at))
+            }
+            Self::MostLiked => {
+                let count = i32::try_from(cursor.key).map_err(|_| invalid())?;
+                (
+                    comments::Column::ReactionCount.lt(count),
+                    comments::Column::ReactionCount.eq(count),
+                )
+            }
+        };
+        let id = if self == Self::Oldest {
+            comments::Column::Id.gt(cursor.id)
+        } else {
+            comments::Column::Id.lt(cursor.id)
+        };
+        Ok(Condition::any()
+            .add(key)
+            .add(Condition::all().add(tie).add(id)))
+    }
+}
+
+/// Where a page of comments or posts ends, to fetch the next one from.
+/// Clients get it as an opaque string.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cursor {
+    key: i64,
+    id: i32,
+}
+
+impl Cursor {
+    #[must_use]
+    pub const fn new(key: i64, id: i32) -> Self {
+        Self { key, id }
+    }
+
+    /// The sort key of the last item of the page
+    #[must_use]
+    pub const fn key(&self) -> i64 {
+        self.key
+    }
+
+    /// The id of the last item of the page
+    #[must_use]
+    pub const fn id(&self) -> i32 {
+        self.id
+    }
+
+    /// The cursor right after the comment in the given order
+    #[must_use]
+    pub fn after(comment: &Model, sort: CommentSort) -> Self {
+        Self {
+            key: sort.key(comment),
+            id: comment.id,
+        }
+    }
+
+    #[must_use]
+    pub fn encode(&self) -> String {
+        hex::encode(format!("{}.{}", self.key, self.id))
+    }
+
+    /// Reads a cursor given by a client
+    ///
+    /// # Errors
+    ///
+    /// When the cursor is malformed
+    pub fn decode(cursor: &str) -> ModelResult<Self> {
+        let invalid = || ModelError::Any("invalid cursor".into());
+        let decoded = String::from_utf8(hex::decode(cursor).map_err(|_| invalid())?)
+            .map_err(|_| invalid())?;
+        let (key, id) = decoded.split_once('.').ok_or_else(invalid)?;
+        Ok(Self {
+            key: key.parse().map_err(|_| invalid())?,
+            id: id.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
 #[async_trait::async_trait]
 impl ActiveModelBehavior for ActiveModel {
     // extend activemodel below (keep comment for generators)
@@ -19,3 +224,224 @@ impl ActiveModelBehavior for ActiveModel {
         }
     }
 }
+
+impl Model {
+    /// Moderation status, pending when unknown
+    #[must_use]
+    pub fn status(&self) -> CommentStatus {
+        self.status.parse().unwrap_or(CommentStatus::Pending)
+    }
+
+    /// Whether the viewer may see the comment, see [`visible_to`]
+    #[must_use]
+    pub fn is_visible_to(&self, viewer: Option<Uuid>) -> bool {
+        match self.status() {
+            CommentStatus::Approved => self.hidden_at.is_none() || viewer == Some(self.user_id),
+            CommentStatus::Pending => viewer == Some(self.user_id),
+            CommentStatus::Rejected | CommentStatus::Spam => false,
+        }
+    }
+
+    /// The status a new comment by the user on the post starts with. Comments
+    /// are held for moderation when the post or the settings ask for it,
+    /// unless the user is trusted: a moderator, the post author, or someone
+    /// with enough approved comments and none marked as spam.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn initial_status(
+        db: &DatabaseConnection,
+        settings: &CommentSettings,
+        post: Option<&posts::Model>,
+        user: &users::Model,
+    ) -> Result<CommentStatus, DbErr> {
+        let moderated = settings.moderation || post.is_some_and(|post| post.comment_moderation);
+        if !moderated
+            || user.has_role(Role::Moderator)
+            || post.is_some_and(|post| post.user_id == Some(user.pid))
+        {
+            return Ok(CommentStatus::Approved);
+        }
+
+        let count = |status: CommentStatus| {
+            Entity::find()
+                .filter(comments::Column::UserId.eq(user.pid))
+                .filter(comments::Column::Status.eq(status.as_str()))
+                .count(db)
+        };
+        if count(CommentStatus::Approved).await? >= settings.auto_approve_after
+            && count(CommentStatus::Spam).await? == 0
+        {
+            Ok(CommentStatus::Approved)
+        } else {
+            Ok(CommentStatus::Pending)
+        }
+    }
+
+    /// Sets the status of the given comments on behalf of a moderator.
+    /// Returns the comments that changed along with their previous status.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn moderate(
+        db: &DatabaseConnection,
+        ids: &[i32],
+        status: CommentStatus,
+        moderator: Uuid,
+    ) -> ModelResult<Vec<(Self, CommentStatus)>> {
+        let txn = db.begin().await?;
+        let items = Entity::find()
+            .filter(comments::Column::Id.is_in(ids.iter().copied()))
+            .order_by_asc(comments::Column::Id)
+            .all(&txn)
+            .await?;
+
+        let mut changed = Vec::new();
+        for item in items {
+            let previous = item.status();
+            if previous == status {
+                continue;
+            }
+            let mut item = item.into_active_model();
+            item.status = ActiveValue::set(status.as_str().to_string());
+            item.moderated_by = ActiveValue::set(Some(moderator));
+            item.moderated_at = ActiveValue::set(Some(chrono::Utc::now().into()));
+            changed.push((item.update(&txn).await?, previous));
+        }
+        txn.commit().await?;
+        Ok(changed)
+    }
+
+    /// The post the comment is on
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn post(&self, db: &DatabaseConnection) -> Result<Option<posts::Model>, DbErr> {
+        match self.post_id {
+            Some(post_id) => posts::Entity::find_by_id(post_id).one(db).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Comments left by others on the user's posts, or in reply to the
+    /// user's comments, that were approved since the given time, oldest first.
+    /// Held comments count from their approval, not from when they were left.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn find_for_user_since(
+        db: &DatabaseConnection,
+        user_id: Uuid,
+        since: DateTimeWithTimeZone,
+    ) -> Result<Vec<Self>, DbErr> {
+        let own_posts = Query::select()
+            .column(posts::Column::Id)
+            .from(posts::Entity)
+            .and_where(posts::Column::UserId.eq(user_id))
+            .to_owned();
+        let own_comments = Query::select()
+            .column(comments::Column::Id)
+            .from(Entity)
+            .and_where(comments::Column::UserId.eq(user_id))
+            .to_owned();
+
+        let approved_at = Expr::expr(Func::coalesce([
+            Expr::col(comments::Column::ModeratedAt).into(),
+            Expr::col(comments::Column::CreatedAt).into(),
+        ]));
+
+        Entity::find()
+            .filter(comments::Column::UserId.ne(user_id))
+            .filter(comments::Column::Status.eq(CommentStatus::Approved.as_str()))
+            .filter(approved_at.clone().gt(since))
+            .filter(
+                Condition::any()
+                    .add(comments::Column::PostId.in_subquery(own_posts))
+                    .add(comments::Column::ParentId.in_subquery(own_comments)),
+            )
+            .order_by_asc(approved_at)
+            .all(db)
+            .await
+    }
+
+    /// How many replies to each of the comments the viewer may see
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn reply_counts(
+        db: &DatabaseConnection,
+        parent_ids: &[i32],
+        viewer: Option<Uuid>,
+    ) -> Result<HashMap<i32, u64>, DbErr> {
+        if parent_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let counts: Vec<(Option<i32>, i64)> = Entity::find()
+            .select_only()
+            .column(comments::Column::ParentId)
+            .column_as(comments::Column::Id.count(), "count")
+            .filter(comments::Column::ParentId.is_in(parent_ids.iter().copied()))
+            .filter(visible_to(viewer))
+            .group_by(comments::Column::ParentId)
+            .into_tuple()
+            .all(db)
+            .await?;
+        Ok(counts
+            .into_iter()
+            .filter_map(|(parent_id, count)| Some((parent_id?, u64::try_from(count).ok()?)))
+            .collect())
+    }
+
+    /// The first replies to each of the comments the viewer may see, at most
+    /// `limit` per comment, oldest first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn reply_previews(
+        db: &DatabaseConnection,
+        parent_ids: &[i32],
+        viewer: Option<Uuid>,
+        limit: u64,
+    ) -> Result<HashMap<i32, Vec<Self>>, DbErr> {
+        if parent_ids.is_empty() || limit == 0 {
+            return Ok(HashMap::new());
+        }
+        let rank = Alias::new("thread_rank");
+        let mut replies = Entity::find()
+            .filter(comments::Column::ParentId.is_in(parent_ids.iter().copied()))
+            .filter(visible_to(viewer))
+            .into_query();
+        replies.expr_window_as(
+            Expr::cust("ROW_NUMBER()"),
+            WindowStatement::partition_by(comments::Column::ParentId)
+                .order_by(comments::Column::CreatedAt, Order::Asc)
+                .order_by(comments::Column::Id, Order::Asc)
+                .to_owned(),
+            rank.clone(),
+        );
+        let query = Query::select()
+            .column(Asterisk)
+            .from_subquery(replies, Alias::new("replies"))
+            .and_where(Expr::col(rank.clone()).lte(limit))
+            .order_by(rank, Order::Asc)
+            .to_owned();
+
+        let mut previews: HashMap<i32, Vec<Self>> = HashMap::new();
+        for reply in Entity::find()
+            .from_raw_sql(db.get_database_backend().build(&query))
+            .all(db)
+            .await?
+        {
+            if let Some(parent_id) = reply.parent_id {
+                previews.entry(parent_id).or_default().push(reply);
+            }
+        }
+        Ok(previews)
+    }
+}
@@ -0,0 +1,140 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{Duration, Utc};
+use loco_rs::{prelude::*, storage::Storage};
+use sea_orm::ConnectionTrait;
+
+pub use super::_entities::exports::{self, ActiveModel, Entity, Model};
+pub type Exports = Entity;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Model {
+    /// Records an archive uploaded for the user
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn create(
+        db: &DatabaseConnection,
+        user: Uuid,
+        token: Uuid,
+        path: &Path,
+    ) -> ModelResult<Self> {
+        Ok(ActiveModel {
+            token: Set(token),
+            user_id: Set(user),
+            path: Set(path.to_string_lossy().to_string()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?)
+    }
+
+    /// The archive of the user with the token, unless it is older than the
+    /// time to live
+    ///
+    /// # Errors
+    ///
+    /// When there is no such archive, or it expired
+    pub async fn find_current(
+        db: &DatabaseConnection,
+        user: Uuid,
+        token: Uuid,
+        ttl: Duration,
+    ) -> ModelResult<Self> {
+        Entity::find()
+            .filter(exports::Column::Token.eq(token))
+            .filter(exports::Column::UserId.eq(user))
+            .filter(exports::Column::CreatedAt.gt(Utc::now() - ttl))
+            .one(db)
+            .await?
+            .ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Deletes the archives with their files
+    async fn remove_all(
+        db: &DatabaseConnection,
+        storage: &Storage,
+        items: Vec<Self>,
+    ) -> ModelResult<usize> {
+        let count = items.len();
+        for item in items {
+            if let Err(err) = storage.delete(&PathBuf::from(&item.path)).await {
+                tracing::warn!(
+                    err = err.to_string(),
+                    path = item.path,
+                    "could not delete export archive"
+                );
+            }
+            Entity::delete_by_id(item.id).exec(db).await?;
+        }
+        Ok(count)
+    }
+
+    /// Deletes all the archives of the user, when the account is deleted,
+    /// returning the paths of their files to delete once it is committed
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn remove_by<C: ConnectionTrait>(db: &C, user: Uuid) -> ModelResult<Vec<String>> {
+        let items = Entity::find()
+            .filter(exports::Column::UserId.eq(user))
+            .all(db)
+            .await?;
+        Entity::delete_many()
+            .filter(exports::Column::UserId.eq(user))
+            .exec(db)
+            .await?;
+        Ok(items.into_iter().map(|item| item.path).collect())
+    }
+
+    /// Deletes the files of removed archives, failures are only logged as
+    /// their rows are already gone
+    pub async fn delete_files(storage: &Storage, paths: Vec<String>) {
+        for path in paths {
+            if let Err(err) = storage.delete(&PathBuf::from(&path)).await {
+                tracing::warn!(
+                    err = err.to_string(),
+                    path,
+                    "could not delete export archive"
+                );
+            }
+        }
+    }
+
+    /// Deletes the archives older than the time to live. Returns how many
+    /// were deleted.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn remove_expired(
+        db: &DatabaseConnection,
+        storage: &Storage,
+        ttl: Duration,
+    ) -> ModelResult<usize> {
+        let items = Entity::find()
+            .filter(exports::Column::CreatedAt.lte(Utc::now() - ttl))
+            .all(db)
+            .await?;
+        Self::remove_all(db, storage, items).await
+    }
+}
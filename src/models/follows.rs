@@ -0,0 +1,180 @@
+use chrono::DateTime;
+use loco_rs::prelude::*;
+use sea_orm::{
+    sea_query::{OnConflict, Query},
+    Condition, PaginatorTrait, QueryOrder, Select,
+};
+use serde::{Deserialize, Serialize};
+
+pub use super::_entities::follows::{self, ActiveModel, Entity, Model};
+use super::{
+    _entities::posts,
+    comments::Cursor,
+    notifications::{NewNotification, NotificationKind},
+    posts::released,
+};
+pub type Follows = Entity;
+
+/// How many users follow a user, and how many they follow
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct FollowCounts {
+    pub followers: u64,
+    pub following: u64,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Model {
+    /// Whether the follower follows the other user
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn is_following<C: ConnectionTrait>(
+        db: &C,
+        follower: Uuid,
+        followed: Uuid,
+    ) -> ModelResult<bool> {
+        let count = Entity::find()
+            .filter(follows::Column::FollowerId.eq(follower))
+            .filter(follows::Column::FollowedId.eq(followed))
+            .count(db)
+            .await?;
+        Ok(count > 0)
+    }
+
+    /// Makes the follower follow the other user, who is notified the first
+    /// time. Returns whether the follow is new.
+    ///
+    /// # Errors
+    ///
+    /// When users try to follow themselves or has DB query error
+    pub async fn follow(
+        db: &DatabaseConnection,
+        follower: Uuid,
+        followed: Uuid,
+    ) -> ModelResult<bool> {
+        if follower == followed {
+            return Err(ModelError::Any("you can't follow yourself".into()));
+        }
+        let inserted = Entity::insert(ActiveModel {
+            follower_id: ActiveValue::set(follower),
+            followed_id: ActiveValue::set(followed),
+            ..Default::default()
+        })
+        .on_conflict(
+            OnConflict::columns([follows::Column::FollowerId, follows::Column::FollowedId])
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+        if inserted > 0 {
+            NewNotification::new(followed, NotificationKind::Follow)
+                .actor(follower)
+                .send(db)
+                .await?;
+        }
+        Ok(inserted > 0)
+    }
+
+    /// Stops the follower following the other user. Returns whether they did.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn unfollow(
+        db: &DatabaseConnection,
+        follower: Uuid,
+        followed: Uuid,
+    ) -> ModelResult<bool> {
+        let res = Entity::delete_many()
+            .filter(follows::Column::FollowerId.eq(follower))
+            .filter(follows::Column::FollowedId.eq(followed))
+            .exec(db)
+            .await?;
+        Ok(res.rows_affected > 0)
+    }
+
+    /// Follower and following counts of the user
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn counts(db: &DatabaseConnection, user: Uuid) -> ModelResult<FollowCounts> {
+        Ok(FollowCounts {
+            followers: Entity::find()
+                .filter(follows::Column::FollowedId.eq(user))
+                .count(db)
+                .await?,
+            following: Entity::find()
+                .filter(follows::Column::FollowerId.eq(user))
+                .count(db)
+                .await?,
+        })
+    }
+
+    /// Query of the published posts of the authors the user follows, latest
+    /// first. Scheduled posts join once their time comes.
+    ///
+    /// The authors are matched with a subquery on the follows of the user, so
+    /// the posts are read from the `user_id, published_at` index of each
+    /// author however many are followed.
+    #[must_use]
+    pub fn feed(user: Uuid) -> Select<posts::Entity> {
+        let followed = Query::select()
+            .column(follows::Column::FollowedId)
+            .from(Entity)
+            .and_where(follows::Column::FollowerId.eq(user))
+            .to_owned();
+        posts::Entity::find()
+            .filter(posts::Column::UserId.in_subquery(followed))
+            .filter(posts::Column::Published.eq(true))
+            .filter(posts::Column::PublishedAt.is_not_null())
+            .filter(released())
+            .filter(posts::Column::HiddenAt.is_null())
+            .order_by_desc(posts::Column::PublishedAt)
+            .order_by_desc(posts::Column::Id)
+    }
+
+    /// The cursor right after the post in the feed
+    #[must_use]
+    pub fn feed_cursor(post: &posts::Model) -> Cursor {
+        let published_at = post.published_at.unwrap_or(post.created_at);
+        Cursor::new(published_at.timestamp_micros(), post.id)
+    }
+
+    /// The feed posts coming after the cursor
+    ///
+    /// # Errors
+    ///
+    /// When the cursor is out of range
+    pub fn feed_after(cursor: &Cursor) -> ModelResult<Condition> {
+        let published_at: DateTimeWithTimeZone = DateTime::from_timestamp_micros(cursor.key())
+            .ok_or_else(|| ModelError::Any("invalid cursor".into()))?
+            .into();
+        Ok(Condition::any()
+            .add(posts::Column::PublishedAt.lt(published_at))
+            .add(
+                Condition::all()
+                    .add(posts::Column::PublishedAt.eq(published_at))
+                    .add(posts::Column::Id.lt(cursor.id())),
+            ))
+    }
+}
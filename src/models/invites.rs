@@ -0,0 +1,186 @@
+use chrono::{offset::Local, Duration};
+use loco_rs::prelude::*;
+use sea_orm::{sea_query::Expr, QueryOrder};
+use serde::{Deserialize, Serialize};
+
+pub use super::_entities::invites::{self, ActiveModel, Entity, Model};
+pub type Invites = Entity;
+
+/// How long an invite stays usable when issued without an explicit expiry
+pub const DEFAULT_TTL: Duration = Duration::days(7);
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct CreateParams {
+    /// When set, only this address can register with the invite
+    pub email: Option<String>,
+    /// How many accounts can be registered with the invite, defaults to one
+    pub max_uses: Option<i32>,
+    /// Defaults to [`DEFAULT_TTL`] from now
+    pub expires_at: Option<DateTimeWithTimeZone>,
+}
+
+#[derive(Debug, Validate, Deserialize)]
+pub struct Validator {
+    #[validate(email(message = "invalid email"))]
+    pub email: Option<String>,
+    #[validate(range(min = 1, message = "must be at least 1"))]
+    pub max_uses: i32,
+}
+
+impl Validatable for ActiveModel {
+    fn validator(&self) -> Box<dyn Validate> {
+        Box::new(Validator {
+            email: self.email.clone().take().flatten(),
+            max_uses: self.max_uses.clone().take().unwrap_or_default(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        self.validate()?;
+        if insert {
+            let mut this = self;
+            this.code = ActiveValue::Set(format!("inv-{}", Uuid::new_v4()));
+            Ok(this)
+        } else if self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Model {
+    /// Issues a new invite on behalf of the given admin
+    ///
+    /// # Errors
+    ///
+    /// When the params are invalid or has DB query error
+    pub async fn create(
+        db: &DatabaseConnection,
+        created_by: Uuid,
+        params: &CreateParams,
+    ) -> ModelResult<Self> {
+        let email = params
+            .email
+            .as_deref()
+            .map(str::trim)
+            .filter(|email| !email.is_empty())
+            .map(str::to_lowercase);
+        Ok(ActiveModel {
+            email: ActiveValue::set(email),
+            max_uses: ActiveValue::set(params.max_uses.unwrap_or(1)),
+            expires_at: ActiveValue::set(
+                params
+                    .expires_at
+                    .unwrap_or_else(|| (Local::now() + DEFAULT_TTL).into()),
+            ),
+            created_by: ActiveValue::set(created_by),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?)
+    }
+
+    /// All the invites, most recent first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list(db: &DatabaseConnection) -> ModelResult<Vec<Self>> {
+        Ok(Entity::find()
+            .order_by_desc(invites::Column::CreatedAt)
+            .order_by_desc(invites::Column::Id)
+            .all(db)
+            .await?)
+    }
+
+    /// finds an invite by its code
+    ///
+    /// # Errors
+    ///
+    /// When could not find the invite or DB query error
+    pub async fn find_by_code(db: &DatabaseConnection, code: &str) -> ModelResult<Self> {
+        let invite = Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(invites::Column::Code, code)
+                    .build(),
+            )
+            .one(db)
+            .await?;
+        invite.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Whether someone registering with the given email can still use the
+    /// invite
+    #[must_use]
+    pub fn is_usable_by(&self, email: &str) -> bool {
+        self.revoked_at.is_none()
+            && self.uses < self.max_uses
+            && self.expires_at > Local::now()
+            && self
+                .email
+                .as_ref()
+                .is_none_or(|invited| invited.eq_ignore_ascii_case(email.trim()))
+    }
+
+    /// Counts one use of the invite with the given code. The check and the
+    /// increment are a single statement, so concurrent registrations can't
+    /// exceed the usage limit.
+    ///
+    /// # Errors
+    ///
+    /// `ModelError::EntityNotFound` when there is no such invite or it is not
+    /// usable by the email, or DB query error
+    pub async fn redeem<C>(db: &C, code: &str, email: &str) -> ModelResult<()>
+    where
+        C: ConnectionTrait,
+    {
+        let res = Entity::update_many()
+            .col_expr(
+                invites::Column::Uses,
+                Expr::col(invites::Column::Uses).add(1),
+            )
+            .filter(
+                model::query::condition()
+                    .eq(invites::Column::Code, code)
+                    .is_null(invites::Column::RevokedAt)
+                    .gt(invites::Column::ExpiresAt, Local::now())
+                    .build(),
+            )
+            .filter(Expr::col(invites::Column::Uses).lt(Expr::col(invites::Column::MaxUses)))
+            .filter(
+                invites::Column::Email
+                    .is_null()
+                    .or(invites::Column::Email.eq(email.trim().to_lowercase())),
+            )
+            .exec(db)
+            .await?;
+        if res.rows_affected == 0 {
+            return Err(ModelError::EntityNotFound);
+        }
+        Ok(())
+    }
+}
+
+impl ActiveModel {
+    /// Revokes the invite, it can no longer be used to register
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn revoke(mut self, db: &DatabaseConnection) -> ModelResult<Model> {
+        self.revoked_at = ActiveValue::set(Some(Local::now().into()));
+        Ok(self.update(db).await?)
+    }
+}
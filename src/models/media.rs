@@ -0,0 +1,329 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use bytes::Bytes;
+use loco_rs::{prelude::*, storage::Storage};
+use sea_orm::{sea_query::Expr, ConnectionTrait, QueryOrder, TransactionTrait};
+use serde::{Deserialize, Serialize};
+
+pub use super::_entities::media::{self, ActiveModel, Entity, Model};
+use super::_entities::{media_variants, posts};
+use crate::common::images;
+pub type Media = Entity;
+
+/// The image formats that may be uploaded
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageType {
+    Jpeg,
+    Png,
+    Gif,
+    Webp,
+}
+
+impl ImageType {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpeg",
+            Self::Png => "png",
+            Self::Gif => "gif",
+            Self::Webp => "webp",
+        }
+    }
+
+    /// Tells the format from the first bytes of the file, whatever name or
+    /// content type the client gave it
+    #[must_use]
+    pub fn sniff(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(Self::Jpeg)
+        } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(Self::Png)
+        } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            Some(Self::Gif)
+        } else if bytes.len() >= 12 && bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP" {
+            Some(Self::Webp)
+        } else {
+            None
+        }
+    }
+
+    #[must_use]
+    pub const fn content_type(self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
+            Self::Gif => "image/gif",
+            Self::Webp => "image/webp",
+        }
+    }
+
+    /// The type of files with the extension, as used in URLs
+    #[must_use]
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "jpg" => Some(Self::Jpeg),
+            "png" => Some(Self::Png),
+            "gif" => Some(Self::Gif),
+            "webp" => Some(Self::Webp),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpg",
+            Self::Png => "png",
+            Self::Gif => "gif",
+            Self::Webp => "webp",
+        }
+    }
+}
+
+impl std::str::FromStr for ImageType {
+    type Err = ModelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jpeg" => Ok(Self::Jpeg),
+            "png" => Ok(Self::Png),
+            "gif" => Ok(Self::Gif),
+            "webp" => Ok(Self::Webp),
+            _ => Err(ModelError::Any(format!("unknown image type: {s}").into())),
+        }
+    }
+}
+
+/// Where media files are served from
+#[must_use]
+pub fn url(pid: Uuid) -> String {
+    format!("/api/media/{pid}")
+}
+
+fn storage_error(err: impl std::fmt::Display) -> ModelError {
+    ModelError::Any(format!("storage error: {err}").into())
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Model {
+    /// Stores an uploaded image of the user, without its metadata. The format
+    /// is told from the content, files that are not a supported image are
+    /// refused.
+    ///
+    /// # Errors
+    ///
+    /// When the file is not a supported image, the storage or the DB fails
+    pub async fn create(
+        db: &DatabaseConnection,
+        storage: &Storage,
+        user: Uuid,
+        content: Bytes,
+        original_name: Option<String>,
+    ) -> ModelResult<Self> {
+        let Some(image_type) = ImageType::sniff(&content) else {
+            return Err(ModelError::Any(
+                "unsupported file type, upload a JPEG, PNG, GIF or WebP image".into(),
+            ));
+        };
+        let cleaned = images::clean(&content, image_type).map_err(|err| {
+            tracing::info!(err = err.to_string(), "unreadable image uploaded");
+            ModelError::Any(format!("could not read the image: {err}").into())
+        })?;
+        let content = Bytes::from(cleaned.content);
+        let size = i32::try_from(content.len())
+            .map_err(|_| ModelError::Any("file is too large".into()))?;
+        let pid = Uuid::new_v4();
+        let path = PathBuf::from("media").join(format!("{pid}.{}", image_type.extension()));
+        storage
+            .upload(&path, &content)
+            .await
+            .map_err(storage_error)?;
+
+        let original_name = original_name
+            .map(|name| name.chars().take(255).collect::<String>())
+            .filter(|name| !name.trim().is_empty());
+        let media = ActiveModel {
+            pid: ActiveValue::set(pid),
+            user_id: ActiveValue::set(user),
+            content_type: ActiveValue::set(image_type.content_type().to_string()),
+            size: ActiveValue::set(size),
+            path: ActiveValue::set(path.to_string_lossy().to_string()),
+            original_name: ActiveValue::set(original_name),
+            width: ActiveValue::set(i32::try_from(cleaned.width).ok()),
+            height: ActiveValue::set(i32::try_from(cleaned.height).ok()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await;
+        match media {
+            Ok(media) => Ok(media),
+            Err(err) => {
+                // don't leave a file no row points to
+                let _ = storage.delete(&path).await;
+                Err(err.into())
+            }
+        }
+    }
+
+    /// finds a media by its pid
+    ///
+    /// # Errors
+    ///
+    /// When could not find the media or DB query error
+    pub async fn find_by_pid(db: &DatabaseConnection, pid: Uuid) -> ModelResult<Self> {
+        Entity::find()
+            .filter(media::Column::Pid.eq(pid))
+            .one(db)
+            .await?
+            .ok_or(ModelError::EntityNotFound)
+    }
+
+    /// Finds a media of the user by its pid
+    ///
+    /// # Errors
+    ///
+    /// When could not find the media or DB query error
+    pub async fn find_for_user(
+        db: &DatabaseConnection,
+        user: Uuid,
+        pid: Uuid,
+    ) -> ModelResult<Self> {
+        Entity::find()
+            .filter(media::Column::Pid.eq(pid))
+            .filter(media::Column::UserId.eq(user))
+            .one(db)
+            .await?
+            .ok_or(ModelError::EntityNotFound)
+    }
+
+    /// Checks that the media exists and belongs to the user, before using it
+    /// as the cover of one of their posts
+    ///
+    /// # Errors
+    ///
+    /// When the media is not one of the user's or DB query error
+    pub async fn check_cover(db: &DatabaseConnection, user: Uuid, id: i32) -> ModelResult<()> {
+        let owned = Entity::find_by_id(id)
+            .filter(media::Column::UserId.eq(user))
+            .one(db)
+            .await?;
+        if owned.is_none() {
+            return Err(ModelError::Any(
+                "cover media not found among your uploads".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The uploads of the user, newest first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_for(db: &DatabaseConnection, user: Uuid) -> ModelResult<Vec<Self>> {
+        Ok(Entity::find()
+            .filter(media::Column::UserId.eq(user))
+            .order_by_desc(media::Column::Id)
+            .all(db)
+            .await?)
+    }
+
+    /// The pids of the given media, to link them
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn pids_of(db: &DatabaseConnection, ids: &[i32]) -> ModelResult<HashMap<i32, Uuid>> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        Ok(Entity::find()
+            .filter(media::Column::Id.is_in(ids.to_vec()))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|media| (media.id, media.pid))
+            .collect())
+    }
+
+    /// Deletes the media with its variants and their files. Posts using it as
+    /// their cover are left without one.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn remove(self, db: &DatabaseConnection, storage: &Storage) -> ModelResult<()> {
+        let txn = db.begin().await?;
+        let paths = self.remove_rows(&txn).await?;
+        txn.commit().await?;
+
+        Self::delete_files(storage, paths).await;
+        Ok(())
+    }
+
+    /// Deletes all the uploads of the user with their variants, returning the
+    /// paths of their files to delete once the deletion is committed
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn remove_by<C: ConnectionTrait>(db: &C, user: Uuid) -> ModelResult<Vec<String>> {
+        let items = Entity::find()
+            .filter(media::Column::UserId.eq(user))
+            .all(db)
+            .await?;
+        let mut paths = Vec::new();
+        for media in items {
+            paths.extend(media.remove_rows(db).await?);
+        }
+        Ok(paths)
+    }
+
+    /// Deletes the files of removed media, failures are only logged as their
+    /// rows are already gone
+    pub async fn delete_files(storage: &Storage, paths: Vec<String>) {
+        for path in paths {
+            if let Err(err) = storage.delete(&PathBuf::from(&path)).await {
+                tracing::warn!(err = err.to_string(), path, "could not delete media file");
+            }
+        }
+    }
+
+    /// Deletes the rows of the media and its variants, returning the paths of
+    /// their files
+    async fn remove_rows<C: ConnectionTrait>(self, db: &C) -> ModelResult<Vec<String>> {
+        posts::Entity::update_many()
+            .col_expr(
+                posts::Column::CoverMediaId,
+                Expr::value(Option::<i32>::None),
+            )
+            .filter(posts::Column::CoverMediaId.eq(self.id))
+            .exec(db)
+            .await?;
+        let variants = media_variants::Model::remove_for(db, self.id).await?;
+        Entity::delete_by_id(self.id).exec(db).await?;
+        Ok(variants
+            .into_iter()
+            .map(|variant| variant.path)
+            .chain([self.path])
+            .collect())
+    }
+}
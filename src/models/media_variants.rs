@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use loco_rs::prelude::*;
+use sea_orm::{sea_query::Expr, ConnectionTrait, QueryOrder, TransactionTrait};
+
+pub use super::_entities::media_variants::{self, ActiveModel, Entity, Model};
+use super::{_entities::media, media::ImageType};
+pub type MediaVariants = Entity;
+
+/// A variant built from an upload, before it is recorded
+#[derive(Clone, Debug)]
+pub struct NewVariant {
+    pub name: &'static str,
+    pub format: ImageType,
+    pub width: u32,
+    pub height: u32,
+    pub size: usize,
+    pub path: String,
+}
+
+/// Where a variant is served from
+#[must_use]
+pub fn url(pid: Uuid, name: &str, format: ImageType) -> String {
+    format!("/api/media/{pid}/{name}.{}", format.extension())
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Model {
+    #[must_use]
+    pub fn format(&self) -> Option<ImageType> {
+        self.format.parse().ok()
+    }
+
+    /// The variants of each of the media, smallest first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn for_media(
+        db: &DatabaseConnection,
+        ids: &[i32],
+    ) -> ModelResult<HashMap<i32, Vec<Self>>> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let variants = Entity::find()
+            .filter(media_variants::Column::MediaId.is_in(ids.to_vec()))
+            .order_by_asc(media_variants::Column::Width)
+            .order_by_asc(media_variants::Column::Format)
+            .all(db)
+            .await?;
+        let mut by_media: HashMap<i32, Vec<Self>> = HashMap::new();
+        for variant in variants {
+            by_media.entry(variant.media_id).or_default().push(variant);
+        }
+        Ok(by_media)
+    }
+
+    /// A variant of the media by its name and format
+    ///
+    /// # Errors
+    ///
+    /// When could not find the variant or DB query error
+    pub async fn find_file(
+        db: &DatabaseConnection,
+        media_id: i32,
+        name: &str,
+        format: ImageType,
+    ) -> ModelResult<Self> {
+        Entity::find()
+            .filter(media_variants::Column::MediaId.eq(media_id))
+            .filter(media_variants::Column::Name.eq(name))
+            .filter(media_variants::Column::Format.eq(format.as_str()))
+            .one(db)
+            .await?
+            .ok_or(ModelError::EntityNotFound)
+    }
+
+    /// Records the variants built for the media in place of earlier ones and
+    /// marks it processed. Returns the earlier variants that were not built
+    /// again, whose files are left to delete.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn replace(
+        db: &DatabaseConnection,
+        media_id: i32,
+        variants: Vec<NewVariant>,
+    ) -> ModelResult<Vec<Self>> {
+        let txn = db.begin().await?;
+        let previous = Self::remove_for(&txn, media_id).await?;
+        let paths: Vec<String> = variants.iter().map(|v| v.path.clone()).collect();
+        if !variants.is_empty() {
+            Entity::insert_many(variants.into_iter().map(|variant| ActiveModel {
+                media_id: ActiveValue::set(media_id),
+                name: ActiveValue::set(variant.name.to_string()),
+                format: ActiveValue::set(variant.format.as_str().to_string()),
+                content_type: ActiveValue::set(variant.format.content_type().to_string()),
+                width: ActiveValue::set(i32::try_from(variant.width).unwrap_or(i32::MAX)),
+                height: ActiveValue::set(i32::try_from(variant.height).unwrap_or(i32::MAX)),
+                size: ActiveValue::set(i32::try_from(variant.size).unwrap_or(i32::MAX)),
+                path: ActiveValue::set(variant.path),
+                ..Default::default()
+            }))
+            .exec_without_returning(&txn)
+            .await?;
+        }
+        media::Entity::update_many()
+            .col_expr(media::Column::ProcessedAt, Expr::current_timestamp().into())
+            .filter(media::Column::Id.eq(media_id))
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+
+        Ok(previous
+            .into_iter()
+            .filter(|variant| !paths.contains(&variant.path))
+            .collect())
+    }
+
+    /// Deletes the variants of the media, returning them
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn remove_for<C>(db: &C, media_id: i32) -> ModelResult<Vec<Self>>
+    where
+        C: ConnectionTrait,
+    {
+        let variants = Entity::find()
+            .filter(media_variants::Column::MediaId.eq(media_id))
+            .all(db)
+            .await?;
+        Entity::delete_many()
+            .filter(media_variants::Column::MediaId.eq(media_id))
+            .exec(db)
+            .await?;
+        Ok(variants)
+    }
+}
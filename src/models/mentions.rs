@@ -0,0 +1,241 @@
+use std::collections::HashSet;
+use std::ops::Range;
+
+use loco_rs::prelude::*;
+use sea_orm::{Condition, QuerySelect};
+
+pub use super::_entities::mentions::{self, ActiveModel, Entity, Model};
+use super::{
+    _entities::users,
+    notifications::{NewNotification, NotificationKind},
+};
+pub type Mentions = Entity;
+
+/// Longest handle a mention can refer to, see the handle validation of users
+const MAX_HANDLE_LEN: usize = 30;
+const MIN_HANDLE_LEN: usize = 3;
+
+const fn is_handle_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Every `@handle` in the content with its byte range, `@` included.
+///
+/// The `@` must not follow a word character, so email addresses are not
+/// mentions, and the handle must end the word.
+fn scan(content: &str) -> Vec<(Range<usize>, &str)> {
+    let mut found = Vec::new();
+    let mut prev: Option<char> = None;
+    for (start, c) in content.char_indices() {
+        let after_word = prev.is_some_and(|p| is_handle_char(p) || p == '@');
+        prev = Some(c);
+        if c != '@' || after_word {
+            continue;
+        }
+        let rest = &content[start + 1..];
+        let len = rest
+            .find(|c: char| !is_handle_char(c))
+            .unwrap_or(rest.len());
+        if (MIN_HANDLE_LEN..=MAX_HANDLE_LEN).contains(&len) {
+            found.push((start..start + 1 + len, &rest[..len]));
+        }
+    }
+    found
+}
+
+/// The handles mentioned in the content, lowercased, without duplicates, in
+/// order of first mention
+#[must_use]
+pub fn handles(content: &str) -> Vec<String> {
+    let mut handles: Vec<String> = Vec::new();
+    for (_, handle) in scan(content) {
+        let handle = handle.to_lowercase();
+        if !handles.contains(&handle) {
+            handles.push(handle);
+        }
+    }
+    handles
+}
+
+/// Turns the mentions of known handles into markdown links to their profile.
+/// Mentions of other handles are left as they are.
+#[must_use]
+pub fn link(content: &str, known: &HashSet<String>) -> String {
+    let mut linked = String::with_capacity(content.len());
+    let mut last = 0;
+    for (range, handle) in scan(content) {
+        let handle_lower = handle.to_lowercase();
+        if !known.contains(&handle_lower) {
+            continue;
+        }
+        linked.push_str(&content[last..range.start]);
+        linked.push_str(&format!("[@{handle}](/users/{handle_lower})"));
+        last = range.end;
+    }
+    linked.push_str(&content[last..]);
+    linked
+}
+
+/// What the mentions were made in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    Post(i32),
+    Comment { id: i32, post_id: Option<i32> },
+}
+
+impl Source {
+    const fn post_id(self) -> Option<i32> {
+        match self {
+            Self::Post(id) => Some(id),
+            Self::Comment { post_id, .. } => post_id,
+        }
+    }
+
+    const fn comment_id(self) -> Option<i32> {
+        match self {
+            Self::Post(_) => None,
+            Self::Comment { id, .. } => Some(id),
+        }
+    }
+
+    fn condition(self) -> Condition {
+        match self {
+            Self::Post(id) => Condition::all()
+                .add(mentions::Column::PostId.eq(id))
+                .add(mentions::Column::CommentId.is_null()),
+            Self::Comment { id, .. } => Condition::all().add(mentions::Column::CommentId.eq(id)),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Model {
+    /// The handles mentioned in any of the contents that belong to a user
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn known_handles<'a>(
+        db: &DatabaseConnection,
+        contents: impl IntoIterator<Item = &'a str> + Send,
+    ) -> ModelResult<HashSet<String>> {
+        let mut mentioned: Vec<String> = Vec::new();
+        for content in contents {
+            mentioned.extend(handles(content));
+        }
+        if mentioned.is_empty() {
+            return Ok(HashSet::new());
+        }
+        let known: Vec<Option<String>> = users::Entity::find()
+            .select_only()
+            .column(users::Column::Handle)
+            .filter(users::Column::Handle.is_in(mentioned))
+            .into_tuple()
+            .all(db)
+            .await?;
+        Ok(known.into_iter().flatten().collect())
+    }
+
+    /// Brings the stored mentions of the source in line with its content and
+    /// notifies the newly mentioned users, unless an earlier version of the
+    /// content already did. Returns the newly mentioned users.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn sync(
+        db: &DatabaseConnection,
+        source: Source,
+        content: Option<&str>,
+        actor: Option<Uuid>,
+    ) -> ModelResult<Vec<Uuid>> {
+        let handles = content.map(handles).unwrap_or_default();
+        let mentioned: Vec<Uuid> = if handles.is_empty() {
+            Vec::new()
+        } else {
+            users::Entity::find()
+                .select_only()
+                .column(users::Column::Pid)
+                .filter(users::Column::Handle.is_in(handles))
+                .into_tuple()
+                .all(db)
+                .await?
+        };
+        let existing: Vec<Uuid> = Entity::find()
+            .select_only()
+            .column(mentions::Column::UserId)
+            .filter(source.condition())
+            .into_tuple()
+            .all(db)
+            .await?;
+
+        let removed: Vec<Uuid> = existing
+            .iter()
+            .filter(|user_id| !mentioned.contains(user_id))
+            .copied()
+            .collect();
+        if !removed.is_empty() {
+            Entity::delete_many()
+                .filter(source.condition())
+                .filter(mentions::Column::UserId.is_in(removed))
+                .exec(db)
+                .await?;
+        }
+
+        let added: Vec<Uuid> = mentioned
+            .into_iter()
+            .filter(|user_id| !existing.contains(user_id))
+            .collect();
+        for user_id in &added {
+            ActiveModel {
+                user_id: ActiveValue::set(*user_id),
+                post_id: ActiveValue::set(source.post_id()),
+                comment_id: ActiveValue::set(source.comment_id()),
+                ..Default::default()
+            }
+            .insert(db)
+            .await?;
+
+            let mut notification =
+                NewNotification::new(*user_id, NotificationKind::Mention).post(source.post_id());
+            if let Some(actor) = actor {
+                notification = notification.actor(actor);
+            }
+            if let Some(comment_id) = source.comment_id() {
+                notification = notification.comment(comment_id);
+            }
+            notification.send_once(db).await?;
+        }
+        Ok(added)
+    }
+
+    /// Removes the mentions made in the source, when it is deleted
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn remove_for(db: &DatabaseConnection, source: Source) -> ModelResult<()> {
+        Entity::delete_many()
+            .filter(source.condition())
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+}
@@ -2,3 +2,20 @@ pub mod _entities;
 pub mod users;
 pub mod posts;
 pub mod comments;
+pub mod sessions;
+pub mod invites;
+pub mod audit_events;
+pub mod notifications;
+pub mod mentions;
+pub mod spam_tokens;
+pub mod reports;
+pub mod follows;
+pub mod reactions;
+pub mod bookmark_collections;
+pub mod bookmarks;
+pub mod post_views;
+pub mod post_referrers;
+pub mod media;
+pub mod media_variants;
+pub mod post_tags;
+pub mod exports;
@@ -0,0 +1,240 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use sea_orm::{sea_query::Expr, PaginatorTrait, QueryOrder, Select};
+use serde::{Deserialize, Serialize};
+
+pub use super::_entities::notifications::{self, ActiveModel, Entity, Model};
+use crate::common::live;
+pub type Notifications = Entity;
+
+/// What a notification is about
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    /// Someone commented on one of the user's posts
+    Comment,
+    /// Someone replied to one of the user's comments
+    Reply,
+    /// Someone mentioned the user
+    Mention,
+    /// A moderator decided on one of the user's comments
+    Moderation,
+    /// Someone started following the user
+    Follow,
+}
+
+impl NotificationKind {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Comment => "comment",
+            Self::Reply => "reply",
+            Self::Mention => "mention",
+            Self::Moderation => "moderation",
+            Self::Follow => "follow",
+        }
+    }
+}
+
+/// A notification about to be created
+#[derive(Clone, Debug)]
+pub struct NewNotification {
+    /// The notified user
+    pub user_id: Uuid,
+    pub kind: NotificationKind,
+    /// Who caused it, if anyone
+    pub actor_id: Option<Uuid>,
+    pub post_id: Option<i32>,
+    pub comment_id: Option<i32>,
+    /// Kind specific detail, e.g. the moderation decision
+    pub detail: Option<String>,
+}
+
+impl NewNotification {
+    #[must_use]
+    pub const fn new(user_id: Uuid, kind: NotificationKind) -> Self {
+        Self {
+            user_id,
+            kind,
+            actor_id: None,
+            post_id: None,
+            comment_id: None,
+            detail: None,
+        }
+    }
+
+    #[must_use]
+    pub const fn actor(mut self, actor_id: Uuid) -> Self {
+        self.actor_id = Some(actor_id);
+        self
+    }
+
+    #[must_use]
+    pub const fn post(mut self, post_id: Option<i32>) -> Self {
+        self.post_id = post_id;
+        self
+    }
+
+    #[must_use]
+    pub const fn comment(mut self, comment_id: i32) -> Self {
+        self.comment_id = Some(comment_id);
+        self
+    }
+
+    #[must_use]
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// Saves the notification and signals the live streams of the user.
+    /// Users are never notified of their own actions.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn send(self, db: &DatabaseConnection) -> ModelResult<Option<Model>> {
+        if self.actor_id == Some(self.user_id) {
+            return Ok(None);
+        }
+        let notification = ActiveModel {
+            user_id: ActiveValue::set(self.user_id),
+            kind: ActiveValue::set(self.kind.as_str().to_string()),
+            actor_id: ActiveValue::set(self.actor_id),
+            post_id: ActiveValue::set(self.post_id),
+            comment_id: ActiveValue::set(self.comment_id),
+            detail: ActiveValue::set(self.detail),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        live::publish(notification.user_id);
+        Ok(Some(notification))
+    }
+
+    /// Like [`Self::send`], unless the user was already notified of the same
+    /// kind of thing about the same post and comment
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn send_once(self, db: &DatabaseConnection) -> ModelResult<Option<Model>> {
+        let mut condition = model::query::condition()
+            .eq(notifications::Column::UserId, self.user_id)
+            .eq(notifications::Column::Kind, self.kind.as_str());
+        condition = match self.post_id {
+            Some(post_id) => condition.eq(notifications::Column::PostId, post_id),
+            None => condition.is_null(notifications::Column::PostId),
+        };
+        condition = match self.comment_id {
+            Some(comment_id) => condition.eq(notifications::Column::CommentId, comment_id),
+            None => condition.is_null(notifications::Column::CommentId),
+        };
+        let sent = Entity::find().filter(condition.build()).count(db).await?;
+        if sent > 0 {
+            return Ok(None);
+        }
+        self.send(db).await
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Model {
+    /// Query of the user's notifications, most recent first
+    #[must_use]
+    pub fn inbox(user_id: Uuid, unread_only: bool) -> Select<Entity> {
+        let mut condition = model::query::condition().eq(notifications::Column::UserId, user_id);
+        if unread_only {
+            condition = condition.is_null(notifications::Column::ReadAt);
+        }
+        Entity::find()
+            .filter(condition.build())
+            .order_by_desc(notifications::Column::CreatedAt)
+            .order_by_desc(notifications::Column::Id)
+    }
+
+    /// How many notifications the user has not read yet
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn unread_count(db: &DatabaseConnection, user_id: Uuid) -> ModelResult<u64> {
+        Ok(Self::inbox(user_id, true).count(db).await?)
+    }
+
+    /// finds a notification of the given user by its id
+    ///
+    /// # Errors
+    ///
+    /// When could not find the notification or DB query error
+    pub async fn find_by_user_and_id(
+        db: &DatabaseConnection,
+        user_id: Uuid,
+        id: i32,
+    ) -> ModelResult<Self> {
+        let notification = Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(notifications::Column::Id, id)
+                    .eq(notifications::Column::UserId, user_id)
+                    .build(),
+            )
+            .one(db)
+            .await?;
+        notification.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Marks every unread notification of the user as read
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn mark_all_read(db: &DatabaseConnection, user_id: Uuid) -> ModelResult<u64> {
+        let res = Entity::update_many()
+            .col_expr(
+                notifications::Column::ReadAt,
+                Expr::value(Some(DateTimeWithTimeZone::from(Local::now()))),
+            )
+            .filter(
+                model::query::condition()
+                    .eq(notifications::Column::UserId, user_id)
+                    .is_null(notifications::Column::ReadAt)
+                    .build(),
+            )
+            .exec(db)
+            .await?;
+        live::publish(user_id);
+        Ok(res.rows_affected)
+    }
+}
+
+impl ActiveModel {
+    /// Marks the notification as read
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn mark_read(mut self, db: &DatabaseConnection) -> ModelResult<Model> {
+        self.read_at = ActiveValue::set(Some(Local::now().into()));
+        let notification = self.update(db).await?;
+        live::publish(notification.user_id);
+        Ok(notification)
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::{BTreeSet, HashMap};
+
+use loco_rs::prelude::*;
+use sea_orm::{ConnectionTrait, QueryOrder};
+
+pub use super::_entities::post_tags::{self, ActiveModel, Entity, Model};
+pub type PostTags = Entity;
+
+/// Longest tag, in characters
+const MAX_TAG_LEN: usize = 50;
+
+/// Tags as they are stored: trimmed, lowercase, with dashes for spaces,
+/// without duplicates and sorted
+#[must_use]
+pub fn normalize<S: AsRef<str>>(tags: &[S]) -> Vec<String> {
+    tags.iter()
+        .map(|tag| {
+            tag.as_ref()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join("-")
+                .to_lowercase()
+                .chars()
+                .take(MAX_TAG_LEN)
+                .collect::<String>()
+        })
+        .filter(|tag| !tag.is_empty())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Model {
+    /// The tags of the post, sorted
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn of<C: ConnectionTrait>(db: &C, post_id: i32) -> ModelResult<Vec<String>> {
+        Ok(Self::for_posts(db, &[post_id])
+            .await?
+            .remove(&post_id)
+            .unwrap_or_default())
+    }
+
+    /// The tags of each of the posts, sorted. Posts without tags are left
+    /// out.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn for_posts<C: ConnectionTrait>(
+        db: &C,
+        post_ids: &[i32],
+    ) -> ModelResult<HashMap<i32, Vec<String>>> {
+        if post_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let tags = Entity::find()
+            .filter(post_tags::Column::PostId.is_in(post_ids.iter().copied()))
+            .order_by_asc(post_tags::Column::Tag)
+            .all(db)
+            .await?;
+        let mut by_post: HashMap<i32, Vec<String>> = HashMap::new();
+        for tag in tags {
+            by_post.entry(tag.post_id).or_default().push(tag.tag);
+        }
+        Ok(by_post)
+    }
+
+    /// Replaces the tags of the post, see [`normalize`]
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn set_for<C, S>(db: &C, post_id: i32, tags: &[S]) -> ModelResult<()>
+    where
+        C: ConnectionTrait,
+        S: AsRef<str> + Sync,
+    {
+        Self::remove_for(db, &[post_id]).await?;
+        let tags = normalize(tags);
+        if tags.is_empty() {
+            return Ok(());
+        }
+        Entity::insert_many(tags.into_iter().map(|tag| ActiveModel {
+            post_id: ActiveValue::set(post_id),
+            tag: ActiveValue::set(tag),
+            ..Default::default()
+        }))
+        .exec_without_returning(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Removes the tags of the posts, when they are deleted
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn remove_for<C: ConnectionTrait>(db: &C, post_ids: &[i32]) -> ModelResult<()> {
+        Entity::delete_many()
+            .filter(post_tags::Column::PostId.is_in(post_ids.iter().copied()))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+}
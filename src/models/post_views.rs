@@ -0,0 +1,383 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Days, NaiveDate};
+use loco_rs::prelude::*;
+use sea_orm::{
+    sea_query::{Alias, Expr, OnConflict, Query, SimpleExpr},
+    ConnectionTrait, QueryOrder, QuerySelect, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+
+pub use super::_entities::post_views::{self, ActiveModel, Entity, Model};
+use super::{
+    _entities::{post_referrers, posts},
+    posts::ids_of,
+};
+use crate::common::analytics::{Buffered, Counts};
+pub type PostViews = Entity;
+
+/// Rows written per statement
+const BATCH_SIZE: usize = 1000;
+
+/// Referrers listed in the stats of a post
+const TOP_REFERRERS: u64 = 20;
+
+/// Views of a post on one day
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DailyViews {
+    pub day: NaiveDate,
+    pub views: u64,
+    pub unique_visitors: u64,
+}
+
+/// Views of a post coming from links on another site
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ReferrerViews {
+    pub referrer: String,
+    pub views: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PostStats {
+    pub views: u64,
+    /// Visitors are counted once per day, a visitor coming back on another
+    /// day counts again
+    pub unique_visitors: u64,
+    /// Every day of the period, oldest first, days without views included
+    pub daily: Vec<DailyViews>,
+    /// The sites linking to the post the most
+    pub referrers: Vec<ReferrerViews>,
+}
+
+/// Number of views of each post of the query, to sort by
+#[must_use]
+pub fn view_count() -> SimpleExpr {
+    SimpleExpr::SubQuery(
+        None,
+        Box::new(
+            Query::select()
+                .expr(Expr::expr(post_views::Column::Views.sum()).if_null(0))
+                .from(Entity)
+                .and_where(
+                    Expr::col((Entity, post_views::Column::PostId))
+                        .equals((posts::Entity, posts::Column::Id)),
+                )
+                .to_owned()
+                .into_sub_query_statement(),
+        ),
+    )
+    .cast_as(Alias::new("bigint"))
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+fn to_u64(value: Option<i64>) -> u64 {
+    value
+        .and_then(|value| u64::try_from(value).ok())
+        .unwrap_or_default()
+}
+
+impl Model {
+    /// Adds the counted views to the stored ones, in a single transaction
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn add(db: &DatabaseConnection, views: Counts, referrers: Counts) -> ModelResult<()> {
+        if views.is_empty() && referrers.is_empty() {
+            return Ok(());
+        }
+        // the views of the row that was about to be inserted
+        let excluded = || Expr::col((Alias::new("excluded"), Alias::new("views")));
+        let txn = db.begin().await?;
+
+        let views: Vec<_> = views.into_iter().collect();
+        for batch in views.chunks(BATCH_SIZE) {
+            let rows = batch
+                .iter()
+                .map(|((post_id, day, visitor), count)| ActiveModel {
+                    post_id: ActiveValue::set(*post_id),
+                    day: ActiveValue::set(*day),
+                    visitor: ActiveValue::set(visitor.clone()),
+                    views: ActiveValue::set(*count),
+                    ..Default::default()
+                });
+            Entity::insert_many(rows)
+                .on_conflict(
+                    OnConflict::columns([
+                        post_views::Column::PostId,
+                        post_views::Column::Day,
+                        post_views::Column::Visitor,
+                    ])
+                    .value(
+                        post_views::Column::Views,
+                        Expr::col((Entity, post_views::Column::Views)).add(excluded()),
+                    )
+                    .value(post_views::Column::UpdatedAt, Expr::current_timestamp())
+                    .to_owned(),
+                )
+                .exec_without_returning(&txn)
+                .await?;
+        }
+
+        let referrers: Vec<_> = referrers.into_iter().collect();
+        for batch in referrers.chunks(BATCH_SIZE) {
+            let rows =
+                batch.iter().map(
+                    |((post_id, day, referrer), count)| post_referrers::ActiveModel {
+                        post_id: ActiveValue::set(*post_id),
+                        day: ActiveValue::set(*day),
+                        referrer: ActiveValue::set(referrer.clone()),
+                        views: ActiveValue::set(*count),
+                        ..Default::default()
+                    },
+                );
+            post_referrers::Entity::insert_many(rows)
+                .on_conflict(
+                    OnConflict::columns([
+                        post_referrers::Column::PostId,
+                        post_referrers::Column::Day,
+                        post_referrers::Column::Referrer,
+                    ])
+                    .value(
+                        post_referrers::Column::Views,
+                        Expr::col((post_referrers::Entity, post_referrers::Column::Views))
+                            .add(excluded()),
+                    )
+                    .value(post_referrers::Column::UpdatedAt, Expr::current_timestamp())
+                    .to_owned(),
+                )
+                .exec_without_returning(&txn)
+                .await?;
+        }
+
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Views of the post from the given day up to the other, both included,
+    /// with the ones still in the buffer
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn stats(
+        db: &DatabaseConnection,
+        post_id: i32,
+        from: NaiveDate,
+        to: NaiveDate,
+        buffered: &Buffered,
+    ) -> ModelResult<PostStats> {
+        let in_period =
+            |(id, day, _): &(i32, NaiveDate, String)| *id == post_id && (from..=to).contains(day);
+
+        let daily: Vec<(NaiveDate, Option<i64>, i64)> = Entity::find()
+            .select_only()
+            .column(post_views::Column::Day)
+            .column_as(
+                post_views::Column::Views
+                    .sum()
+                    .cast_as(Alias::new("bigint")),
+                "views",
+            )
+            .column_as(post_views::Column::Id.count(), "unique_visitors")
+            .filter(post_views::Column::PostId.eq(post_id))
+            .filter(post_views::Column::Day.between(from, to))
+            .group_by(post_views::Column::Day)
+            .into_tuple()
+            .all(db)
+            .await?;
+        let mut by_day: HashMap<NaiveDate, DailyViews> = daily
+            .into_iter()
+            .map(|(day, views, visitors)| {
+                let views = DailyViews {
+                    day,
+                    views: to_u64(views),
+                    unique_visitors: to_u64(Some(visitors)),
+                };
+                (day, views)
+            })
+            .collect();
+
+        // a buffered visitor already stored that day is not a new one
+        let waiting: Vec<_> = buffered
+            .views
+            .iter()
+            .filter(|(key, _)| in_period(key))
+            .collect();
+        let stored: HashSet<(NaiveDate, String)> = if waiting.is_empty() {
+            HashSet::new()
+        } else {
+            Entity::find()
+                .select_only()
+                .column(post_views::Column::Day)
+                .column(post_views::Column::Visitor)
+                .filter(post_views::Column::PostId.eq(post_id))
+                .filter(post_views::Column::Day.between(from, to))
+                .filter(
+                    post_views::Column::Visitor
+                        .is_in(waiting.iter().map(|((_, _, visitor), _)| visitor.clone())),
+                )
+                .into_tuple()
+                .all(db)
+                .await?
+                .into_iter()
+                .collect()
+        };
+        for ((_, day, visitor), count) in waiting {
+            let entry = by_day.entry(*day).or_insert(DailyViews {
+                day: *day,
+                views: 0,
+                unique_visitors: 0,
+            });
+            entry.views += u64::try_from(*count).unwrap_or_default();
+            if !stored.contains(&(*day, visitor.clone())) {
+                entry.unique_visitors += 1;
+            }
+        }
+
+        let daily: Vec<DailyViews> = from
+            .iter_days()
+            .take_while(|day| *day <= to)
+            .map(|day| {
+                by_day.remove(&day).unwrap_or(DailyViews {
+                    day,
+                    views: 0,
+                    unique_visitors: 0,
+                })
+            })
+            .collect();
+
+        let mut waiting: HashMap<String, u64> = HashMap::new();
+        for (key, count) in &buffered.referrers {
+            if in_period(key) {
+                *waiting.entry(key.2.clone()).or_default() +=
+                    u64::try_from(*count).unwrap_or_default();
+            }
+        }
+        let total = post_referrers::Column::Views
+            .sum()
+            .cast_as(Alias::new("bigint"));
+        let query = post_referrers::Entity::find()
+            .select_only()
+            .column(post_referrers::Column::Referrer)
+            .column_as(total.clone(), "views")
+            .filter(post_referrers::Column::PostId.eq(post_id))
+            .filter(post_referrers::Column::Day.between(from, to))
+            .group_by(post_referrers::Column::Referrer);
+        let mut referrers: HashMap<String, u64> = query
+            .clone()
+            .order_by_desc(total)
+            .order_by_asc(post_referrers::Column::Referrer)
+            .limit(TOP_REFERRERS)
+            .into_tuple::<(String, Option<i64>)>()
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|(referrer, views)| (referrer, to_u64(views)))
+            .collect();
+        // the buffered referrers may climb into the top ones
+        if !waiting.is_empty() {
+            let stored: Vec<(String, Option<i64>)> = query
+                .filter(post_referrers::Column::Referrer.is_in(waiting.keys().cloned()))
+                .into_tuple()
+                .all(db)
+                .await?;
+            for (referrer, views) in stored {
+                referrers.insert(referrer, to_u64(views));
+            }
+            for (referrer, views) in waiting {
+                *referrers.entry(referrer).or_default() += views;
+            }
+        }
+        let mut referrers: Vec<ReferrerViews> = referrers
+            .into_iter()
+            .map(|(referrer, views)| ReferrerViews { referrer, views })
+            .collect();
+        referrers.sort_by(|a, b| {
+            b.views
+                .cmp(&a.views)
+                .then_with(|| a.referrer.cmp(&b.referrer))
+        });
+        referrers.truncate(usize::try_from(TOP_REFERRERS).unwrap_or(usize::MAX));
+
+        Ok(PostStats {
+            views: daily.iter().map(|day| day.views).sum(),
+            unique_visitors: daily.iter().map(|day| day.unique_visitors).sum(),
+            daily,
+            referrers,
+        })
+    }
+
+    /// How many views all the posts of the author got, with the buffered
+    /// ones, which must be of the author's posts
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn total_of_author(
+        db: &DatabaseConnection,
+        author: Uuid,
+        buffered: &Buffered,
+    ) -> ModelResult<u64> {
+        let views: Option<Option<i64>> = Entity::find()
+            .select_only()
+            .column_as(
+                post_views::Column::Views
+                    .sum()
+                    .cast_as(Alias::new("bigint")),
+                "views",
+            )
+            .filter(post_views::Column::PostId.in_subquery(ids_of(author)))
+            .into_tuple()
+            .one(db)
+            .await?;
+        let waiting: u64 = buffered
+            .views
+            .values()
+            .map(|count| u64::try_from(*count).unwrap_or_default())
+            .sum();
+        Ok(to_u64(views.flatten()) + waiting)
+    }
+
+    /// The first day of a period of the given number of days ending today
+    #[must_use]
+    pub fn period_start(today: NaiveDate, days: u64) -> NaiveDate {
+        today
+            .checked_sub_days(Days::new(days.saturating_sub(1)))
+            .unwrap_or(today)
+    }
+
+    /// Removes the views of the posts, when they are deleted
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn remove_for<C: ConnectionTrait>(db: &C, post_ids: &[i32]) -> ModelResult<()> {
+        Entity::delete_many()
+            .filter(post_views::Column::PostId.is_in(post_ids.iter().copied()))
+            .exec(db)
+            .await?;
+        post_referrers::Entity::delete_many()
+            .filter(post_referrers::Column::PostId.is_in(post_ids.iter().copied()))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+}
@@ -1,21 +1,479 @@
-use sea_orm::entity::prelude::*;
-use super::_entities::posts::{ActiveModel, Entity};
+use std::collections::HashMap;
+
+use chrono::{Duration, Utc};
+use loco_rs::model::{ModelError, ModelResult};
+use sea_orm::{
+    entity::prelude::*,
+    sea_query::{Alias, CaseStatement, Expr, Query, SelectStatement, SimpleExpr},
+    Condition, PaginatorTrait, QuerySelect, Set,
+};
+use serde::{Deserialize, Serialize};
+
+use super::_entities::{
+    comments, mentions, notifications,
+    posts::{self, ActiveModel, Entity, Model},
+    reports,
+};
+use super::{
+    bookmarks,
+    comments::visible_to,
+    follows, post_tags, post_views,
+    reactions::{self, ReactionTarget},
+    reports::ReportTarget,
+    users::{self, Role},
+};
+use crate::common::markdown;
 pub type Posts = Entity;
 
+/// Who may comment on a post. The post author and moderators may always
+/// comment, unless comments are closed.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CommentPolicy {
+    /// Anyone signed in
+    Open,
+    /// Users who verified their email
+    Registered,
+    /// Users following the author
+    Followers,
+    /// Nobody
+    Closed,
+}
+
+impl CommentPolicy {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Registered => "registered",
+            Self::Followers => "followers",
+            Self::Closed => "closed",
+        }
+    }
+}
+
+impl std::str::FromStr for CommentPolicy {
+    type Err = ModelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(Self::Open),
+            "registered" => Ok(Self::Registered),
+            "followers" => Ok(Self::Followers),
+            "closed" => Ok(Self::Closed),
+            _ => Err(ModelError::Any(
+                format!("unknown comment policy `{s}`").into(),
+            )),
+        }
+    }
+}
+
+/// Why a user may not comment on a post
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommentRefusal {
+    /// Hidden after being reported
+    Hidden,
+    Closed,
+    Expired,
+    Unverified,
+    NotFollower,
+}
+
+impl CommentRefusal {
+    #[must_use]
+    pub const fn message(self) -> &'static str {
+        match self {
+            Self::Hidden => "comments are closed while this post is under review",
+            Self::Closed => "comments are closed on this post",
+            Self::Expired => "comments closed automatically on this post",
+            Self::Unverified => "verify your email to comment on this post",
+            Self::NotFollower => "only followers of the author can comment on this post",
+        }
+    }
+}
+
+/// Where a post stands in its lifecycle
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PostState {
+    /// Not published
+    Draft,
+    /// Published with a publication time still to come
+    Scheduled,
+    Published,
+}
+
+impl PostState {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Draft => "draft",
+            Self::Scheduled => "scheduled",
+            Self::Published => "published",
+        }
+    }
+
+    /// The state of each post of the query, as a column
+    #[must_use]
+    pub fn expr() -> SimpleExpr {
+        CaseStatement::new()
+            .case(
+                Condition::any()
+                    .add(posts::Column::Published.is_null())
+                    .add(posts::Column::Published.eq(false)),
+                Self::Draft.as_str(),
+            )
+            .case(
+                Expr::col(posts::Column::PublishedAt).gt(Expr::current_timestamp()),
+                Self::Scheduled.as_str(),
+            )
+            .finally(Self::Published.as_str())
+            .into()
+    }
+}
+
+impl std::str::FromStr for PostState {
+    type Err = ModelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "draft" => Ok(Self::Draft),
+            "scheduled" => Ok(Self::Scheduled),
+            "published" => Ok(Self::Published),
+            _ => Err(ModelError::Any(format!("unknown post state `{s}`").into())),
+        }
+    }
+}
+
+/// How many posts of an author are in each state
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PostStateCounts {
+    pub draft: u64,
+    pub scheduled: u64,
+    pub published: u64,
+}
+
+/// The public discussion of a post
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CommentStats {
+    pub count: u64,
+    pub last_comment_at: Option<DateTimeWithTimeZone>,
+}
+
+/// Ids of the author's posts, as a subquery
+#[must_use]
+pub fn ids_of(author: Uuid) -> SelectStatement {
+    Query::select()
+        .column(posts::Column::Id)
+        .from(Entity)
+        .and_where(posts::Column::UserId.eq(author))
+        .to_owned()
+}
+
+/// Posts whose publication time has come, leaving out the scheduled ones
+#[must_use]
+pub fn released() -> Condition {
+    Condition::any()
+        .add(posts::Column::PublishedAt.is_null())
+        .add(Expr::col((posts::Entity, posts::Column::PublishedAt)).lte(Expr::current_timestamp()))
+}
+
+/// Number of public comments on each post of the query, to sort by
+#[must_use]
+pub fn comment_count() -> SimpleExpr {
+    SimpleExpr::SubQuery(
+        None,
+        Box::new(
+            Query::select()
+                .expr(comments::Column::Id.count())
+                .from(comments::Entity)
+                .and_where(
+                    Expr::col((comments::Entity, comments::Column::PostId))
+                        .equals((posts::Entity, posts::Column::Id)),
+                )
+                .cond_where(visible_to(None))
+                .to_owned()
+                .into_sub_query_statement(),
+        ),
+    )
+}
+
+/// Deletes the posts and comments, the comments on the posts, and all that
+/// points at them: mentions, reactions, bookmarks, views, tags, notifications
+/// and reports. Replies to the deleted comments stay, as top level comments.
+///
+/// # Errors
+///
+/// When has DB query error
+pub async fn remove_all<C: ConnectionTrait>(
+    db: &C,
+    post_ids: &[i32],
+    comment_ids: &[i32],
+) -> ModelResult<()> {
+    let comment_ids: Vec<i32> = comments::Entity::find()
+        .select_only()
+        .column(comments::Column::Id)
+        .filter(
+            Condition::any()
+                .add(comments::Column::Id.is_in(comment_ids.iter().copied()))
+                .add(comments::Column::PostId.is_in(post_ids.iter().copied())),
+        )
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    mentions::Entity::delete_many()
+        .filter(
+            Condition::any()
+                .add(mentions::Column::PostId.is_in(post_ids.iter().copied()))
+                .add(mentions::Column::CommentId.is_in(comment_ids.clone())),
+        )
+        .exec(db)
+        .await?;
+    reactions::Model::remove_for(db, ReactionTarget::Post, post_ids).await?;
+    reactions::Model::remove_for(db, ReactionTarget::Comment, &comment_ids).await?;
+    bookmarks::Model::remove_for(db, post_ids).await?;
+    post_views::Model::remove_for(db, post_ids).await?;
+    post_tags::Model::remove_for(db, post_ids).await?;
+    notifications::Entity::delete_many()
+        .filter(
+            Condition::any()
+                .add(notifications::Column::PostId.is_in(post_ids.iter().copied()))
+                .add(notifications::Column::CommentId.is_in(comment_ids.clone())),
+        )
+        .exec(db)
+        .await?;
+    reports::Entity::delete_many()
+        .filter(
+            Condition::any()
+                .add(
+                    Condition::all()
+                        .add(reports::Column::TargetType.eq(ReportTarget::Post.as_str()))
+                        .add(reports::Column::TargetId.is_in(post_ids.iter().copied())),
+                )
+                .add(
+                    Condition::all()
+                        .add(reports::Column::TargetType.eq(ReportTarget::Comment.as_str()))
+                        .add(reports::Column::TargetId.is_in(comment_ids.clone())),
+                ),
+        )
+        .exec(db)
+        .await?;
+    comments::Entity::update_many()
+        .col_expr(comments::Column::ParentId, Expr::value(Option::<i32>::None))
+        .filter(comments::Column::ParentId.is_in(comment_ids.clone()))
+        .exec(db)
+        .await?;
+    comments::Entity::delete_many()
+        .filter(comments::Column::Id.is_in(comment_ids))
+        .exec(db)
+        .await?;
+    Entity::delete_many()
+        .filter(posts::Column::Id.is_in(post_ids.iter().copied()))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
 #[async_trait::async_trait]
 impl ActiveModelBehavior for ActiveModel {
     // extend activemodel below (keep comment for generators)
 
-    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    async fn before_save<C>(self, db: &C, insert: bool) -> std::result::Result<Self, DbErr>
     where
         C: ConnectionTrait,
     {
-        if !insert && self.updated_at.is_unchanged() {
-            let mut this = self;
+        let mut this = self;
+        if !insert && this.updated_at.is_unchanged() {
             this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
-            Ok(this)
-        } else {
-            Ok(self)
         }
+        if this.content.is_set() || this.summary.is_set() {
+            this.reading_stats(db, insert).await?;
+        }
+        Ok(this)
+    }
+}
+
+impl ActiveModel {
+    /// Counts the words of the content and generates the summary when none
+    /// was given. A generated summary sent back unchanged stays generated, so
+    /// it follows later edits of the content.
+    async fn reading_stats<C>(&mut self, db: &C, insert: bool) -> std::result::Result<(), DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let stored = match self.id.try_as_ref() {
+            Some(id) if !insert => Entity::find_by_id(*id).one(db).await?,
+            _ => None,
+        };
+        let content = match self.content.try_as_ref() {
+            Some(content) => content.clone(),
+            None => stored.as_ref().and_then(|post| post.content.clone()),
+        };
+        let content = content.as_deref().unwrap_or_default();
+        let summary = match self.summary.try_as_ref() {
+            Some(summary) => summary.clone(),
+            None => stored.as_ref().and_then(|post| post.summary.clone()),
+        };
+
+        let words = markdown::word_count(content);
+        self.word_count = Set(i32::try_from(words).unwrap_or(i32::MAX));
+        let minutes = markdown::reading_time(words);
+        self.reading_time = Set(i32::try_from(minutes).unwrap_or(i32::MAX));
+
+        let generated = summary
+            .as_deref()
+            .is_none_or(|summary| summary.trim().is_empty())
+            || stored.is_some_and(|post| post.summary_generated && post.summary == summary);
+        if generated {
+            self.summary = Set(markdown::summary(content, markdown::SUMMARY_LEN));
+        }
+        self.summary_generated = Set(generated);
+        Ok(())
+    }
+}
+
+impl Model {
+    #[must_use]
+    pub fn comment_policy(&self) -> CommentPolicy {
+        self.comment_policy.parse().unwrap_or(CommentPolicy::Open)
+    }
+
+    /// When comments close on their own, counted from publication
+    #[must_use]
+    pub fn comments_close_at(&self) -> Option<DateTimeWithTimeZone> {
+        let days = self.comments_close_after_days?;
+        let since = self.published_at.unwrap_or(self.created_at);
+        Some(since + Duration::days(i64::from(days)))
+    }
+
+    /// Whether nobody can comment anymore, whatever their standing
+    #[must_use]
+    pub fn comments_closed(&self) -> bool {
+        self.comment_policy() == CommentPolicy::Closed
+            || self
+                .comments_close_at()
+                .is_some_and(|close_at| close_at <= Utc::now())
+    }
+
+    /// Why the user may not comment on the post, if they may not
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn comment_refusal(
+        &self,
+        db: &DatabaseConnection,
+        user: &users::Model,
+    ) -> ModelResult<Option<CommentRefusal>> {
+        if self.hidden_at.is_some() {
+            return Ok(Some(CommentRefusal::Hidden));
+        }
+        let policy = self.comment_policy();
+        if policy == CommentPolicy::Closed {
+            return Ok(Some(CommentRefusal::Closed));
+        }
+        if self.comments_closed() {
+            return Ok(Some(CommentRefusal::Expired));
+        }
+        if user.has_role(Role::Moderator) || self.user_id == Some(user.pid) {
+            return Ok(None);
+        }
+        Ok(match policy {
+            CommentPolicy::Registered if user.email_verified_at.is_none() => {
+                Some(CommentRefusal::Unverified)
+            }
+            CommentPolicy::Followers => {
+                let following = match self.user_id {
+                    Some(author) => follows::Model::is_following(db, user.pid, author).await?,
+                    None => false,
+                };
+                (!following).then_some(CommentRefusal::NotFollower)
+            }
+            _ => None,
+        })
+    }
+
+    /// How many posts of the author are in each state, in one query
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn state_counts(
+        db: &DatabaseConnection,
+        author: Uuid,
+    ) -> ModelResult<PostStateCounts> {
+        let rows: Vec<(String, i64)> = Entity::find()
+            .select_only()
+            .column_as(PostState::expr(), "state")
+            .column_as(posts::Column::Id.count(), "count")
+            .filter(posts::Column::UserId.eq(author))
+            // by the alias, the expression has bound values and wouldn't match
+            .group_by(Expr::col(Alias::new("state")))
+            .into_tuple()
+            .all(db)
+            .await?;
+        let mut counts = PostStateCounts::default();
+        for (state, count) in rows {
+            let count = u64::try_from(count).unwrap_or_default();
+            match state.parse() {
+                Ok(PostState::Draft) => counts.draft = count,
+                Ok(PostState::Scheduled) => counts.scheduled = count,
+                Ok(PostState::Published) => counts.published = count,
+                Err(_) => {}
+            }
+        }
+        Ok(counts)
+    }
+
+    /// How many public comments others left on the author's posts
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn comments_received(db: &DatabaseConnection, author: Uuid) -> ModelResult<u64> {
+        Ok(comments::Entity::find()
+            .filter(comments::Column::PostId.in_subquery(ids_of(author)))
+            .filter(comments::Column::UserId.ne(author))
+            .filter(visible_to(None))
+            .count(db)
+            .await?)
+    }
+
+    /// The public comment count and latest comment of each of the posts
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn comment_stats(
+        db: &DatabaseConnection,
+        ids: &[i32],
+    ) -> ModelResult<HashMap<i32, CommentStats>> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let rows: Vec<(Option<i32>, i64, Option<DateTimeWithTimeZone>)> = comments::Entity::find()
+            .select_only()
+            .column(comments::Column::PostId)
+            .column_as(comments::Column::Id.count(), "count")
+            .column_as(comments::Column::CreatedAt.max(), "last_comment_at")
+            .filter(comments::Column::PostId.is_in(ids.iter().copied()))
+            .filter(visible_to(None))
+            .group_by(comments::Column::PostId)
+            .into_tuple()
+            .all(db)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(post_id, count, last_comment_at)| {
+                Some((
+                    post_id?,
+                    CommentStats {
+                        count: u64::try_from(count).ok()?,
+                        last_comment_at,
+                    },
+                ))
+            })
+            .collect())
     }
 }
@@ -0,0 +1,267 @@
+use std::collections::{BTreeMap, HashMap};
+
+use loco_rs::prelude::*;
+use sea_orm::{
+    sea_query::{Expr, OnConflict, Query, SimpleExpr},
+    ConnectionTrait, QuerySelect, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+
+use super::_entities::comments;
+pub use super::_entities::reactions::{self, ActiveModel, Entity, Model};
+use crate::common::settings::ReactionSettings;
+pub type Reactions = Entity;
+
+/// What is reacted to
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReactionTarget {
+    Post,
+    Comment,
+}
+
+impl ReactionTarget {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Post => "post",
+            Self::Comment => "comment",
+        }
+    }
+}
+
+/// The reactions to a post or comment
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ReactionSummary {
+    /// How many users reacted with each emoji
+    pub counts: BTreeMap<String, u64>,
+    /// The emojis the current user reacted with
+    pub mine: Vec<String>,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Model {
+    /// Adds the reaction of the user, or takes it back when they already
+    /// reacted with the emoji. Returns whether the user now has the reaction.
+    ///
+    /// The reaction count of comments moves along in the same transaction,
+    /// by increments so concurrent toggles can't lose an update.
+    ///
+    /// # Errors
+    ///
+    /// When the emoji isn't one of the settings or has DB query error
+    pub async fn toggle(
+        db: &DatabaseConnection,
+        settings: &ReactionSettings,
+        user: Uuid,
+        target: ReactionTarget,
+        target_id: i32,
+        emoji: &str,
+    ) -> ModelResult<bool> {
+        if !settings.emojis.iter().any(|allowed| allowed == emoji) {
+            return Err(ModelError::Any(
+                format!("unknown reaction `{emoji}`").into(),
+            ));
+        }
+
+        let txn = db.begin().await?;
+        let inserted = Entity::insert(ActiveModel {
+            user_id: ActiveValue::set(user),
+            target_type: ActiveValue::set(target.as_str().to_string()),
+            target_id: ActiveValue::set(target_id),
+            emoji: ActiveValue::set(emoji.to_string()),
+            ..Default::default()
+        })
+        .on_conflict(
+            OnConflict::columns([
+                reactions::Column::UserId,
+                reactions::Column::TargetType,
+                reactions::Column::TargetId,
+                reactions::Column::Emoji,
+            ])
+            .do_nothing()
+            .to_owned(),
+        )
+        .exec_without_returning(&txn)
+        .await?;
+
+        let reacted = inserted > 0;
+        let changed = if reacted {
+            inserted
+        } else {
+            Entity::delete_many()
+                .filter(reactions::Column::UserId.eq(user))
+                .filter(reactions::Column::TargetType.eq(target.as_str()))
+                .filter(reactions::Column::TargetId.eq(target_id))
+                .filter(reactions::Column::Emoji.eq(emoji))
+                .exec(&txn)
+                .await?
+                .rows_affected
+        };
+        if changed > 0 && target == ReactionTarget::Comment {
+            let count = Expr::col(comments::Column::ReactionCount);
+            comments::Entity::update_many()
+                .col_expr(
+                    comments::Column::ReactionCount,
+                    if reacted { count.add(1) } else { count.sub(1) },
+                )
+                .filter(comments::Column::Id.eq(target_id))
+                .exec(&txn)
+                .await?;
+        }
+        txn.commit().await?;
+        Ok(reacted)
+    }
+
+    /// The reactions to each of the posts or comments, with the ones of the
+    /// viewer
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn summaries(
+        db: &DatabaseConnection,
+        target: ReactionTarget,
+        ids: &[i32],
+        viewer: Option<Uuid>,
+    ) -> ModelResult<HashMap<i32, ReactionSummary>> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let on_targets = || {
+            Entity::find()
+                .filter(reactions::Column::TargetType.eq(target.as_str()))
+                .filter(reactions::Column::TargetId.is_in(ids.iter().copied()))
+        };
+
+        let counts: Vec<(i32, String, i64)> = on_targets()
+            .select_only()
+            .column(reactions::Column::TargetId)
+            .column(reactions::Column::Emoji)
+            .column_as(reactions::Column::Id.count(), "count")
+            .group_by(reactions::Column::TargetId)
+            .group_by(reactions::Column::Emoji)
+            .into_tuple()
+            .all(db)
+            .await?;
+        let mut summaries: HashMap<i32, ReactionSummary> = HashMap::new();
+        for (target_id, emoji, count) in counts {
+            summaries
+                .entry(target_id)
+                .or_default()
+                .counts
+                .insert(emoji, u64::try_from(count).unwrap_or_default());
+        }
+
+        if let Some(viewer) = viewer {
+            let mine: Vec<(i32, String)> = on_targets()
+                .select_only()
+                .column(reactions::Column::TargetId)
+                .column(reactions::Column::Emoji)
+                .filter(reactions::Column::UserId.eq(viewer))
+                .into_tuple()
+                .all(db)
+                .await?;
+            for (target_id, emoji) in mine {
+                summaries.entry(target_id).or_default().mine.push(emoji);
+            }
+            for summary in summaries.values_mut() {
+                summary.mine.sort();
+            }
+        }
+        Ok(summaries)
+    }
+
+    /// The reactions to a single post or comment
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn summary(
+        db: &DatabaseConnection,
+        target: ReactionTarget,
+        id: i32,
+        viewer: Option<Uuid>,
+    ) -> ModelResult<ReactionSummary> {
+        Ok(Self::summaries(db, target, &[id], viewer)
+            .await?
+            .remove(&id)
+            .unwrap_or_default())
+    }
+
+    /// Removes the reactions to a post or comment, when it is deleted
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn remove_for<C: ConnectionTrait>(
+        db: &C,
+        target: ReactionTarget,
+        ids: &[i32],
+    ) -> ModelResult<()> {
+        Entity::delete_many()
+            .filter(reactions::Column::TargetType.eq(target.as_str()))
+            .filter(reactions::Column::TargetId.is_in(ids.iter().copied()))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Removes the reactions of a user, taking them off the comment counts
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn remove_by<C: ConnectionTrait>(db: &C, user: Uuid) -> ModelResult<()> {
+        let own = Query::select()
+            .expr(reactions::Column::Id.count())
+            .from(Entity)
+            .and_where(reactions::Column::UserId.eq(user))
+            .and_where(reactions::Column::TargetType.eq(ReactionTarget::Comment.as_str()))
+            .and_where(
+                Expr::col((Entity, reactions::Column::TargetId))
+                    .equals((comments::Entity, comments::Column::Id)),
+            )
+            .to_owned();
+        let reacted = Query::select()
+            .column(reactions::Column::TargetId)
+            .from(Entity)
+            .and_where(reactions::Column::UserId.eq(user))
+            .and_where(reactions::Column::TargetType.eq(ReactionTarget::Comment.as_str()))
+            .to_owned();
+        comments::Entity::update_many()
+            .col_expr(
+                comments::Column::ReactionCount,
+                Expr::col(comments::Column::ReactionCount).sub(SimpleExpr::SubQuery(
+                    None,
+                    Box::new(own.into_sub_query_statement()),
+                )),
+            )
+            .filter(comments::Column::Id.in_subquery(reacted))
+            .exec(db)
+            .await?;
+
+        Entity::delete_many()
+            .filter(reactions::Column::UserId.eq(user))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+}
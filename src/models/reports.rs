@@ -0,0 +1,323 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use sea_orm::{
+    sea_query::{Expr, OnConflict},
+    PaginatorTrait, QueryOrder, Select,
+};
+use serde::{Deserialize, Serialize};
+
+pub use super::_entities::reports::{self, ActiveModel, Entity, Model};
+use super::_entities::{comments, posts};
+pub type Reports = Entity;
+
+/// Longest free text a report can carry
+const MAX_DETAILS_LEN: usize = 2000;
+
+/// What is reported
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportTarget {
+    Post,
+    Comment,
+}
+
+impl ReportTarget {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Post => "post",
+            Self::Comment => "comment",
+        }
+    }
+
+    /// Whether the reported post or comment exists
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn exists(self, db: &DatabaseConnection, id: i32) -> ModelResult<bool> {
+        let count = match self {
+            Self::Post => posts::Entity::find_by_id(id).count(db).await?,
+            Self::Comment => comments::Entity::find_by_id(id).count(db).await?,
+        };
+        Ok(count > 0)
+    }
+
+    /// Hides the post or comment from everyone, or shows it again
+    async fn set_hidden(self, db: &DatabaseConnection, id: i32, hidden: bool) -> ModelResult<()> {
+        let hidden_at = hidden.then(|| DateTimeWithTimeZone::from(Local::now()));
+        match self {
+            Self::Post => {
+                posts::Entity::update_many()
+                    .col_expr(posts::Column::HiddenAt, Expr::value(hidden_at))
+                    .filter(posts::Column::Id.eq(id))
+                    .filter(if hidden {
+                        posts::Column::HiddenAt.is_null()
+                    } else {
+                        posts::Column::HiddenAt.is_not_null()
+                    })
+                    .exec(db)
+                    .await?;
+            }
+            Self::Comment => {
+                comments::Entity::update_many()
+                    .col_expr(comments::Column::HiddenAt, Expr::value(hidden_at))
+                    .filter(comments::Column::Id.eq(id))
+                    .filter(if hidden {
+                        comments::Column::HiddenAt.is_null()
+                    } else {
+                        comments::Column::HiddenAt.is_not_null()
+                    })
+                    .exec(db)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for ReportTarget {
+    type Err = ModelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "post" => Ok(Self::Post),
+            "comment" => Ok(Self::Comment),
+            _ => Err(ModelError::Any(
+                format!("unknown report target `{s}`").into(),
+            )),
+        }
+    }
+}
+
+/// Why the content is reported
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportReason {
+    Spam,
+    Harassment,
+    HateSpeech,
+    Violence,
+    SexualContent,
+    Misinformation,
+    Other,
+}
+
+impl ReportReason {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Spam => "spam",
+            Self::Harassment => "harassment",
+            Self::HateSpeech => "hate_speech",
+            Self::Violence => "violence",
+            Self::SexualContent => "sexual_content",
+            Self::Misinformation => "misinformation",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// Where a report stands
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportStatus {
+    /// Waiting for a moderator
+    Open,
+    /// Upheld, the content stays hidden
+    Resolved,
+    /// Unfounded, the content is shown again
+    Dismissed,
+}
+
+impl ReportStatus {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Resolved => "resolved",
+            Self::Dismissed => "dismissed",
+        }
+    }
+}
+
+impl std::str::FromStr for ReportStatus {
+    type Err = ModelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(Self::Open),
+            "resolved" => Ok(Self::Resolved),
+            "dismissed" => Ok(Self::Dismissed),
+            _ => Err(ModelError::Any(
+                format!("unknown report status `{s}`").into(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CreateParams {
+    pub target_type: ReportTarget,
+    pub target_id: i32,
+    pub reason: ReportReason,
+    pub details: Option<String>,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Model {
+    /// Reported target
+    #[must_use]
+    pub fn target(&self) -> Option<ReportTarget> {
+        self.target_type.parse().ok()
+    }
+
+    /// Files a report by the user. A user reporting the same content again
+    /// gets their first report back. Once `hide_after` distinct users have
+    /// open reports on the content, it is hidden.
+    ///
+    /// # Errors
+    ///
+    /// When the details are too long or has DB query error
+    pub async fn file(
+        db: &DatabaseConnection,
+        reporter: Uuid,
+        params: &CreateParams,
+        hide_after: u64,
+    ) -> ModelResult<Self> {
+        let details = params
+            .details
+            .as_deref()
+            .map(str::trim)
+            .filter(|details| !details.is_empty());
+        if details.is_some_and(|details| details.chars().count() > MAX_DETAILS_LEN) {
+            return Err(ModelError::Any(
+                format!("details can't be longer than {MAX_DETAILS_LEN} characters").into(),
+            ));
+        }
+
+        Entity::insert(ActiveModel {
+            reporter_id: ActiveValue::set(reporter),
+            target_type: ActiveValue::set(params.target_type.as_str().to_string()),
+            target_id: ActiveValue::set(params.target_id),
+            reason: ActiveValue::set(params.reason.as_str().to_string()),
+            details: ActiveValue::set(details.map(ToString::to_string)),
+            ..Default::default()
+        })
+        .on_conflict(
+            OnConflict::columns([
+                reports::Column::ReporterId,
+                reports::Column::TargetType,
+                reports::Column::TargetId,
+            ])
+            .do_nothing()
+            .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+
+        let target = || {
+            model::query::condition()
+                .eq(reports::Column::TargetType, params.target_type.as_str())
+                .eq(reports::Column::TargetId, params.target_id)
+        };
+        let report = Entity::find()
+            .filter(target().eq(reports::Column::ReporterId, reporter).build())
+            .one(db)
+            .await?
+            .ok_or_else(|| ModelError::EntityNotFound)?;
+
+        let reporters = Entity::find()
+            .filter(
+                target()
+                    .eq(reports::Column::Status, ReportStatus::Open.as_str())
+                    .build(),
+            )
+            .count(db)
+            .await?;
+        if reporters >= hide_after {
+            params
+                .target_type
+                .set_hidden(db, params.target_id, true)
+                .await?;
+        }
+        Ok(report)
+    }
+
+    /// Query of the reports in the given state, oldest first
+    #[must_use]
+    pub fn queue(status: ReportStatus) -> Select<Entity> {
+        Entity::find()
+            .filter(reports::Column::Status.eq(status.as_str()))
+            .order_by_asc(reports::Column::CreatedAt)
+            .order_by_asc(reports::Column::Id)
+    }
+
+    /// Settles every open report on the same content as this one. Resolved
+    /// content is hidden, dismissed content is shown again. Returns how many
+    /// reports were settled.
+    ///
+    /// # Errors
+    ///
+    /// When the status is open or has DB query error
+    pub async fn resolve(
+        &self,
+        db: &DatabaseConnection,
+        status: ReportStatus,
+        moderator: Uuid,
+        note: Option<String>,
+    ) -> ModelResult<u64> {
+        if status == ReportStatus::Open {
+            return Err(ModelError::Any(
+                "reports can only be resolved or dismissed".into(),
+            ));
+        }
+        let target = self
+            .target()
+            .ok_or_else(|| ModelError::Any("unknown report target".into()))?;
+
+        let res = Entity::update_many()
+            .col_expr(reports::Column::Status, Expr::value(status.as_str()))
+            .col_expr(reports::Column::ResolvedBy, Expr::value(Some(moderator)))
+            .col_expr(
+                reports::Column::ResolvedAt,
+                Expr::value(Some(DateTimeWithTimeZone::from(Local::now()))),
+            )
+            .col_expr(reports::Column::ResolutionNote, Expr::value(note))
+            .col_expr(
+                reports::Column::UpdatedAt,
+                Expr::value(DateTimeWithTimeZone::from(Local::now())),
+            )
+            .filter(
+                model::query::condition()
+                    .eq(reports::Column::TargetType, self.target_type.clone())
+                    .eq(reports::Column::TargetId, self.target_id)
+                    .eq(reports::Column::Status, ReportStatus::Open.as_str())
+                    .build(),
+            )
+            .exec(db)
+            .await?;
+
+        target
+            .set_hidden(db, self.target_id, status == ReportStatus::Resolved)
+            .await?;
+        Ok(res.rows_affected)
+    }
+}
@@ -0,0 +1,186 @@
+use chrono::{offset::Local, Duration};
+use loco_rs::prelude::*;
+use sea_orm::{sea_query::Expr, QueryOrder};
+
+pub use super::_entities::sessions::{self, ActiveModel, Entity, Model};
+pub type Sessions = Entity;
+
+/// How often `last_seen_at` is written for an active session, so that
+/// authenticated requests don't all end up with a DB write
+const LAST_SEEN_RESOLUTION: Duration = Duration::minutes(1);
+
+/// The last time a session may have been seen and still hold a token that did
+/// not expire, given the token expiration in seconds. Tokens are only issued on
+/// login and refresh, which both see the session. None when the expiration is
+/// too far out to tell.
+fn expiry_cutoff(expiration: u64) -> Option<DateTimeWithTimeZone> {
+    let lifetime = i64::try_from(expiration)
+        .ok()
+        .and_then(Duration::try_seconds)?;
+    let cutoff = Local::now().checked_sub_signed(lifetime.checked_add(&LAST_SEEN_RESOLUTION)?)?;
+    Some(cutoff.into())
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Model {
+    /// Records a new session for the given user on login
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn create(
+        db: &DatabaseConnection,
+        user_id: Uuid,
+        user_agent: Option<String>,
+        ip: Option<String>,
+    ) -> ModelResult<Self> {
+        Ok(ActiveModel {
+            user_id: ActiveValue::set(user_id),
+            user_agent: ActiveValue::set(user_agent),
+            ip: ActiveValue::set(ip),
+            last_seen_at: ActiveValue::set(Local::now().into()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?)
+    }
+
+    /// Sessions of the user that were not revoked nor left to expire with
+    /// their tokens, most recently seen first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn find_active_by_user(
+        db: &DatabaseConnection,
+        user_id: Uuid,
+        expiration: u64,
+    ) -> ModelResult<Vec<Self>> {
+        let mut condition = model::query::condition()
+            .eq(sessions::Column::UserId, user_id)
+            .is_null(sessions::Column::RevokedAt);
+        if let Some(cutoff) = expiry_cutoff(expiration) {
+            condition = condition.gt(sessions::Column::LastSeenAt, cutoff);
+        }
+        Ok(Entity::find()
+            .filter(condition.build())
+            .order_by_desc(sessions::Column::LastSeenAt)
+            .all(db)
+            .await?)
+    }
+
+    /// finds a session of the given user by its id
+    ///
+    /// # Errors
+    ///
+    /// When could not find the session or DB query error
+    pub async fn find_by_user_and_id(
+        db: &DatabaseConnection,
+        user_id: Uuid,
+        id: i32,
+    ) -> ModelResult<Self> {
+        let session = Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(sessions::Column::Id, id)
+                    .eq(sessions::Column::UserId, user_id)
+                    .build(),
+            )
+            .one(db)
+            .await?;
+        session.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Revokes every active session of the user, but the one to keep
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn revoke_all_for_user(
+        db: &DatabaseConnection,
+        user_id: Uuid,
+        keep: Option<i32>,
+    ) -> ModelResult<()> {
+        let mut condition = model::query::condition()
+            .eq(sessions::Column::UserId, user_id)
+            .is_null(sessions::Column::RevokedAt);
+        if let Some(keep) = keep {
+            condition = condition.ne(sessions::Column::Id, keep);
+        }
+        Entity::update_many()
+            .col_expr(
+                sessions::Column::RevokedAt,
+                Expr::value(Some(DateTimeWithTimeZone::from(Local::now()))),
+            )
+            .filter(condition.build())
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Deletes the sessions whose tokens all expired, revoked or not. Returns
+    /// how many were deleted.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn remove_expired(db: &DatabaseConnection, expiration: u64) -> ModelResult<u64> {
+        let Some(cutoff) = expiry_cutoff(expiration) else {
+            return Ok(0);
+        };
+        let result = Entity::delete_many()
+            .filter(sessions::Column::LastSeenAt.lte(cutoff))
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+
+    #[must_use]
+    pub fn is_active(&self) -> bool {
+        self.revoked_at.is_none()
+    }
+
+    /// Bumps `last_seen_at`, at most once per [`LAST_SEEN_RESOLUTION`]
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn touch(self, db: &DatabaseConnection) -> ModelResult<Self> {
+        let now = Local::now();
+        if now.signed_duration_since(self.last_seen_at) < LAST_SEEN_RESOLUTION {
+            return Ok(self);
+        }
+        let mut session = self.into_active_model();
+        session.last_seen_at = ActiveValue::set(now.into());
+        Ok(session.update(db).await?)
+    }
+}
+
+impl ActiveModel {
+    /// Revokes the session, tokens issued for it are no longer accepted
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn revoke(mut self, db: &DatabaseConnection) -> ModelResult<Model> {
+        self.revoked_at = ActiveValue::set(Some(Local::now().into()));
+        Ok(self.update(db).await?)
+    }
+}
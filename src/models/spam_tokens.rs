@@ -0,0 +1,130 @@
+use std::collections::{BTreeSet, HashMap};
+
+use loco_rs::prelude::*;
+use sea_orm::{
+    sea_query::{Expr, OnConflict},
+    ConnectionTrait,
+};
+
+pub use super::_entities::spam_tokens::{self, ActiveModel, Entity, Model};
+pub type SpamTokens = Entity;
+
+/// Row holding the number of trained documents instead of a word. Tokens are
+/// alphanumeric, so it can't clash with one.
+const DOCUMENTS: &str = "*";
+
+/// Below this many spam and ham documents the classifier abstains
+const MIN_DOCUMENTS: i32 = 5;
+
+/// Words looked at per document, to bound the queries
+const MAX_TOKENS: usize = 200;
+
+/// The distinct lowercased words of the content
+#[must_use]
+pub fn tokens(content: &str) -> Vec<String> {
+    content
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| (2..=30).contains(&word.chars().count()))
+        .map(str::to_lowercase)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .take(MAX_TOKENS)
+        .collect()
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Model {
+    /// Counts the words of a comment a moderator decided on as spam or ham
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn train<C: ConnectionTrait>(db: &C, content: &str, spam: bool) -> ModelResult<()> {
+        let (spam, ham) = if spam { (1, 0) } else { (0, 1) };
+        let mut words = tokens(content);
+        words.push(DOCUMENTS.to_string());
+
+        let rows = words.into_iter().map(|token| ActiveModel {
+            token: ActiveValue::set(token),
+            spam: ActiveValue::set(spam),
+            ham: ActiveValue::set(ham),
+            ..Default::default()
+        });
+        Entity::insert_many(rows)
+            .on_conflict(
+                OnConflict::column(spam_tokens::Column::Token)
+                    .value(
+                        spam_tokens::Column::Spam,
+                        Expr::col((Entity, spam_tokens::Column::Spam)).add(spam),
+                    )
+                    .value(
+                        spam_tokens::Column::Ham,
+                        Expr::col((Entity, spam_tokens::Column::Ham)).add(ham),
+                    )
+                    .value(spam_tokens::Column::UpdatedAt, Expr::current_timestamp())
+                    .to_owned(),
+            )
+            .exec_without_returning(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Naive Bayes probability that the content is spam, from the words seen
+    /// in training. `None` until enough spam and ham was trained.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn spam_probability(
+        db: &DatabaseConnection,
+        content: &str,
+    ) -> ModelResult<Option<f64>> {
+        let mut words = tokens(content);
+        words.push(DOCUMENTS.to_string());
+        let counts: HashMap<String, (i32, i32)> = Entity::find()
+            .filter(spam_tokens::Column::Token.is_in(words))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|row| (row.token, (row.spam, row.ham)))
+            .collect();
+
+        let Some(&(spam_docs, ham_docs)) = counts.get(DOCUMENTS) else {
+            return Ok(None);
+        };
+        if spam_docs < MIN_DOCUMENTS || ham_docs < MIN_DOCUMENTS {
+            return Ok(None);
+        }
+
+        let (spam_docs, ham_docs) = (f64::from(spam_docs), f64::from(ham_docs));
+        let mut log_spam = (spam_docs / (spam_docs + ham_docs)).ln();
+        let mut log_ham = (ham_docs / (spam_docs + ham_docs)).ln();
+        // words never trained on say nothing either way and are skipped
+        for (token, (spam, ham)) in &counts {
+            if token == DOCUMENTS {
+                continue;
+            }
+            // Laplace smoothing so a word seen on one side only isn't final
+            log_spam += ((f64::from(*spam) + 1.0) / (spam_docs + 2.0)).ln();
+            log_ham += ((f64::from(*ham) + 1.0) / (ham_docs + 2.0)).ln();
+        }
+        Ok(Some(1.0 / (1.0 + (log_ham - log_spam).exp())))
+    }
+}
@@ -1,10 +1,119 @@
 use async_trait::async_trait;
-use chrono::offset::Local;
-use loco_rs::{auth::jwt, hash, prelude::*};
+use chrono::{offset::Local, Duration};
+use loco_rs::{auth::jwt, hash, prelude::*, storage::Storage};
+use sea_orm::{sea_query::Expr, Condition, QuerySelect};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use uuid::Uuid;
+use validator::ValidationError;
 
 pub use super::_entities::users::{self, ActiveModel, Entity, Model};
+use super::{
+    _entities::{
+        comments, follows, invites, media, mentions, notifications, posts, reports, sessions,
+    },
+    bookmarks, exports,
+    posts::remove_all,
+    reactions,
+};
+use crate::common::signing;
+
+/// Author of the posts and comments left by deleted accounts, see
+/// [`DeletionMode::Anonymize`]
+pub const DELETED_USER_PID: Uuid = Uuid::nil();
+
+/// What happens to the content of a deleted account
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeletionMode {
+    /// Posts and comments stay, their author becomes [`DELETED_USER_PID`]
+    #[default]
+    Anonymize,
+    /// The user's posts, the comments on them and the user's own comments are
+    /// deleted
+    Cascade,
+}
+
+/// Access level of an account. Roles are ordered, each one includes the
+/// permissions of the ones before it.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    #[default]
+    User,
+    /// Can review comments and reports
+    Moderator,
+    /// Can manage invites and everything a moderator can
+    Admin,
+}
+
+impl Role {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Moderator => "moderator",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+impl std::str::FromStr for Role {
+    type Err = ModelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "user" => Ok(Self::User),
+            "moderator" => Ok(Self::Moderator),
+            "admin" => Ok(Self::Admin),
+            _ => Err(ModelError::Any(format!("unknown role `{s}`").into())),
+        }
+    }
+}
+
+/// How a user hears about comments on their posts and replies to their
+/// comments
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationPreference {
+    /// An email for each comment
+    #[default]
+    Immediate,
+    /// One email a day listing the new comments
+    DailyDigest,
+    Off,
+}
+
+impl NotificationPreference {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Immediate => "immediate",
+            Self::DailyDigest => "daily_digest",
+            Self::Off => "off",
+        }
+    }
+}
+
+impl std::str::FromStr for NotificationPreference {
+    type Err = ModelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "immediate" => Ok(Self::Immediate),
+            "daily_digest" => Ok(Self::DailyDigest),
+            "off" => Ok(Self::Off),
+            _ => Err(ModelError::Any(
+                format!("unknown notification preference `{s}`").into(),
+            )),
+        }
+    }
+}
+
+/// Name of the custom JWT claim holding the token issue time in milliseconds
+pub const ISSUED_AT_CLAIM: &str = "issued_at_ms";
+/// Name of the custom JWT claim holding the id of the login session
+pub const SESSION_CLAIM: &str = "sid";
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct LoginParams {
@@ -17,6 +126,20 @@ pub struct RegisterParams {
     pub email: String,
     pub password: String,
     pub name: String,
+    /// Required when registration is invite only
+    #[serde(default)]
+    pub invite_code: Option<String>,
+}
+
+/// Editable public profile fields. Fields left out are kept as they are, an
+/// empty string clears an optional field.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ProfileParams {
+    pub name: Option<String>,
+    pub handle: Option<String>,
+    pub bio: Option<String>,
+    pub website: Option<String>,
+    pub avatar_url: Option<String>,
 }
 
 #[derive(Debug, Validate, Deserialize)]
@@ -25,6 +148,30 @@ pub struct Validator {
     pub name: String,
     #[validate(custom(function = "validation::is_valid_email"))]
     pub email: String,
+    #[validate(custom(function = "is_valid_handle"))]
+    pub handle: Option<String>,
+    #[validate(length(max = 500, message = "Bio must be at most 500 characters long."))]
+    pub bio: Option<String>,
+    #[validate(url(message = "Website must be a valid URL."))]
+    pub website: Option<String>,
+    #[validate(url(message = "Avatar URL must be a valid URL."))]
+    pub avatar_url: Option<String>,
+    #[validate(custom(function = "validation::is_valid_email"))]
+    pub pending_email: Option<String>,
+}
+
+/// Handles are 3 to 30 characters of lowercase letters, digits and
+/// underscores so they can be used as-is in URLs and `@mentions`.
+fn is_valid_handle(handle: &str) -> Result<(), ValidationError> {
+    let valid = (3..=30).contains(&handle.len())
+        && handle
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(ValidationError::new("invalid handle"))
+    }
 }
 
 impl Validatable for super::_entities::users::ActiveModel {
@@ -32,6 +179,11 @@ impl Validatable for super::_entities::users::ActiveModel {
         Box::new(Validator {
             name: self.name.as_ref().to_owned(),
             email: self.email.as_ref().to_owned(),
+            handle: self.handle.clone().take().flatten(),
+            bio: self.bio.clone().take().flatten(),
+            website: self.website.clone().take().flatten(),
+            avatar_url: self.avatar_url.clone().take().flatten(),
+            pending_email: self.pending_email.clone().take().flatten(),
         })
     }
 }
@@ -91,6 +243,23 @@ impl super::_entities::users::Model {
         user.ok_or_else(|| ModelError::EntityNotFound)
     }
 
+    /// finds a user by the provided public handle, case insensitive
+    ///
+    /// # Errors
+    ///
+    /// When could not find user by the given handle or DB query error
+    pub async fn find_by_handle(db: &DatabaseConnection, handle: &str) -> ModelResult<Self> {
+        let user = users::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(users::Column::Handle, handle.to_lowercase())
+                    .build(),
+            )
+            .one(db)
+            .await?;
+        user.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
     /// finds a user by the provided verification token
     ///
     /// # Errors
@@ -128,6 +297,29 @@ impl super::_entities::users::Model {
         user.ok_or_else(|| ModelError::EntityNotFound)
     }
 
+    /// finds a user by the provided email change token, unless it was sent
+    /// longer than `max_age` ago
+    ///
+    /// # Errors
+    ///
+    /// When could not find user by the given token or DB query error
+    pub async fn find_by_email_change_token(
+        db: &DatabaseConnection,
+        token: &str,
+        max_age: Duration,
+    ) -> ModelResult<Self> {
+        let user = users::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(users::Column::EmailChangeToken, token)
+                    .build(),
+            )
+            .filter(users::Column::EmailChangeSentAt.gt(Local::now() - max_age))
+            .one(db)
+            .await?;
+        user.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
     /// finds a user by the provided pid
     ///
     /// # Errors
@@ -174,11 +366,13 @@ impl super::_entities::users::Model {
     }
 
     /// Asynchronously creates a user with a password and saves it to the
-    /// database.
+    /// database. When the params carry an invite code, the invite is redeemed
+    /// along with the user creation.
     ///
     /// # Errors
     ///
-    /// When could not save the user into the DB
+    /// When could not save the user into the DB, or the invite is not usable
+    /// (`ModelError::EntityNotFound`)
     pub async fn create_with_password(
         db: &DatabaseConnection,
         params: &RegisterParams,
@@ -198,6 +392,10 @@ impl super::_entities::users::Model {
             return Err(ModelError::EntityAlreadyExists {});
         }
 
+        if let Some(code) = &params.invite_code {
+            invites::Model::redeem(&txn, code, &params.email).await?;
+        }
+
         let password_hash =
             hash::hash_password(&params.password).map_err(|e| ModelError::Any(e.into()))?;
         let user = users::ActiveModel {
@@ -214,13 +412,187 @@ impl super::_entities::users::Model {
         Ok(user)
     }
 
-    /// Creates a JWT
+    /// Deletes the user account. Posts, comments and uploads are anonymized or
+    /// deleted along with it depending on the given mode. The files of the
+    /// account are deleted once the rows are, so a failure can leave no row
+    /// pointing at a missing file.
+    ///
+    /// # Errors
+    ///
+    /// when has DB query error
+    pub async fn delete_account(
+        self,
+        db: &DatabaseConnection,
+        storage: &Storage,
+        mode: DeletionMode,
+    ) -> ModelResult<()> {
+        let txn = db.begin().await?;
+
+        let uploads = match mode {
+            DeletionMode::Anonymize => {
+                posts::Entity::update_many()
+                    .col_expr(posts::Column::UserId, Expr::value(DELETED_USER_PID))
+                    .filter(posts::Column::UserId.eq(self.pid))
+                    .exec(&txn)
+                    .await?;
+                comments::Entity::update_many()
+                    .col_expr(comments::Column::UserId, Expr::value(DELETED_USER_PID))
+                    .filter(comments::Column::UserId.eq(self.pid))
+                    .exec(&txn)
+                    .await?;
+                media::Entity::update_many()
+                    .col_expr(media::Column::UserId, Expr::value(DELETED_USER_PID))
+                    .filter(media::Column::UserId.eq(self.pid))
+                    .exec(&txn)
+                    .await?;
+                Vec::new()
+            }
+            DeletionMode::Cascade => {
+                let post_ids: Vec<i32> = posts::Entity::find()
+                    .select_only()
+                    .column(posts::Column::Id)
+                    .filter(posts::Column::UserId.eq(self.pid))
+                    .into_tuple()
+                    .all(&txn)
+                    .await?;
+                let comment_ids: Vec<i32> = comments::Entity::find()
+                    .select_only()
+                    .column(comments::Column::Id)
+                    .filter(comments::Column::UserId.eq(self.pid))
+                    .into_tuple()
+                    .all(&txn)
+                    .await?;
+                remove_all(&txn, &post_ids, &comment_ids).await?;
+                media::Model::remove_by(&txn, self.pid).await?
+            }
+        };
+        let archives = exports::Model::remove_by(&txn, self.pid).await?;
+
+        invites::Entity::update_many()
+            .col_expr(invites::Column::CreatedBy, Expr::value(DELETED_USER_PID))
+            .filter(invites::Column::CreatedBy.eq(self.pid))
+            .exec(&txn)
+            .await?;
+        notifications::Entity::update_many()
+            .col_expr(
+                notifications::Column::ActorId,
+                Expr::value(DELETED_USER_PID),
+            )
+            .filter(notifications::Column::ActorId.eq(self.pid))
+            .exec(&txn)
+            .await?;
+        sessions::Entity::delete_many()
+            .filter(sessions::Column::UserId.eq(self.pid))
+            .exec(&txn)
+            .await?;
+        notifications::Entity::delete_many()
+            .filter(notifications::Column::UserId.eq(self.pid))
+            .exec(&txn)
+            .await?;
+        mentions::Entity::delete_many()
+            .filter(mentions::Column::UserId.eq(self.pid))
+            .exec(&txn)
+            .await?;
+        reactions::Model::remove_by(&txn, self.pid).await?;
+        reports::Entity::delete_many()
+            .filter(reports::Column::ReporterId.eq(self.pid))
+            .exec(&txn)
+            .await?;
+        bookmarks::Model::remove_by(&txn, self.pid).await?;
+        follows::Entity::delete_many()
+            .filter(
+                Condition::any()
+                    .add(follows::Column::FollowerId.eq(self.pid))
+                    .add(follows::Column::FollowedId.eq(self.pid)),
+            )
+            .exec(&txn)
+            .await?;
+
+        self.delete(&txn).await?;
+        txn.commit().await?;
+
+        media::Model::delete_files(storage, uploads).await;
+        exports::Model::delete_files(storage, archives).await;
+        Ok(())
+    }
+
+    /// Creates a JWT for the given login session
+    ///
+    /// The token carries its issue time in milliseconds so it can be rejected
+    /// once the user's credentials change, see [`Self::accepts_token_issued_at`],
+    /// and its session so it can be revoked.
     ///
     /// # Errors
     ///
     /// when could not convert user claims to jwt token
-    pub fn generate_jwt(&self, secret: &str, expiration: &u64) -> ModelResult<String> {
-        Ok(jwt::JWT::new(secret).generate_token(expiration, self.pid.to_string(), None)?)
+    pub fn generate_jwt(
+        &self,
+        secret: &str,
+        expiration: &u64,
+        session_id: i32,
+    ) -> ModelResult<String> {
+        let claims = json!({
+            ISSUED_AT_CLAIM: Local::now().timestamp_millis(),
+            SESSION_CLAIM: session_id,
+        });
+        Ok(jwt::JWT::new(secret).generate_token(expiration, self.pid.to_string(), Some(claims))?)
+    }
+
+    /// The role of the user, unknown values fall back to [`Role::User`]
+    #[must_use]
+    pub fn role(&self) -> Role {
+        self.role.parse().unwrap_or_default()
+    }
+
+    /// Whether the user has the given role, or a higher one
+    #[must_use]
+    pub fn has_role(&self, role: Role) -> bool {
+        self.role() >= role
+    }
+
+    /// The notification preference of the user, unknown values fall back to
+    /// the default one
+    #[must_use]
+    pub fn notification_preference(&self) -> NotificationPreference {
+        self.notification_preference.parse().unwrap_or_default()
+    }
+
+    /// Token of the one-click unsubscribe link put in notification emails. It
+    /// is the user pid signed with the given secret, so it never expires.
+    #[must_use]
+    pub fn unsubscribe_token(&self, secret: &str) -> String {
+        let pid = self.pid.to_string();
+        format!(
+            "{pid}.{}",
+            signing::sign(secret, &unsubscribe_message(&pid))
+        )
+    }
+
+    /// finds the user of an unsubscribe token, checking its signature
+    ///
+    /// # Errors
+    ///
+    /// When the token is not valid or could not find the user
+    pub async fn find_by_unsubscribe_token(
+        db: &DatabaseConnection,
+        secret: &str,
+        token: &str,
+    ) -> ModelResult<Self> {
+        let Some((pid, signature)) = token.split_once('.') else {
+            return Err(ModelError::EntityNotFound);
+        };
+        if !signing::verify(secret, &unsubscribe_message(pid), signature) {
+            return Err(ModelError::EntityNotFound);
+        }
+        Self::find_by_pid(db, pid).await
+    }
+
+    /// Whether a token issued at the given unix time in milliseconds is still
+    /// accepted. Tokens issued before the last password change are not.
+    #[must_use]
+    pub fn accepts_token_issued_at(&self, issued_at_ms: i64) -> bool {
+        self.tokens_valid_after
+            .is_none_or(|after| issued_at_ms >= after.timestamp_millis())
     }
 }
 
@@ -295,4 +667,185 @@ impl super::_entities::users::ActiveModel {
         self.reset_sent_at = ActiveValue::Set(None);
         Ok(self.update(db).await?)
     }
+
+    /// Applies the given profile changes and updates the user in the
+    /// database.
+    ///
+    /// Handles are stored lowercase and must be unique across users.
+    ///
+    /// # Errors
+    ///
+    /// when the handle is taken, the profile is invalid or has DB query error
+    pub async fn update_profile(
+        mut self,
+        db: &DatabaseConnection,
+        params: &ProfileParams,
+    ) -> ModelResult<Model> {
+        fn non_empty(value: &str) -> Option<String> {
+            let value = value.trim();
+            (!value.is_empty()).then(|| value.to_string())
+        }
+
+        if let Some(name) = &params.name {
+            self.name = ActiveValue::set(name.trim().to_string());
+        }
+        if let Some(handle) = &params.handle {
+            let handle = non_empty(handle).map(|h| h.to_lowercase());
+            if let Some(handle) = &handle {
+                let taken = users::Entity::find()
+                    .filter(
+                        model::query::condition()
+                            .eq(users::Column::Handle, handle)
+                            .ne(users::Column::Pid, *self.pid.as_ref())
+                            .build(),
+                    )
+                    .one(db)
+                    .await?
+                    .is_some();
+                if taken {
+                    return Err(ModelError::EntityAlreadyExists {});
+                }
+            }
+            self.handle = ActiveValue::set(handle);
+        }
+        if let Some(bio) = &params.bio {
+            self.bio = ActiveValue::set(non_empty(bio));
+        }
+        if let Some(website) = &params.website {
+            self.website = ActiveValue::set(non_empty(website));
+        }
+        if let Some(avatar_url) = &params.avatar_url {
+            self.avatar_url = ActiveValue::set(non_empty(avatar_url));
+        }
+        Ok(self.update(db).await?)
+    }
+
+    /// Changes the password of a logged in user and invalidates every token
+    /// issued before now, logging out the user's other sessions.
+    ///
+    /// # Errors
+    ///
+    /// when has DB query error or could not hashed the given password
+    pub async fn change_password(
+        mut self,
+        db: &DatabaseConnection,
+        password: &str,
+    ) -> ModelResult<Model> {
+        self.password =
+            ActiveValue::set(hash::hash_password(password).map_err(|e| ModelError::Any(e.into()))?);
+        self.reset_token = ActiveValue::Set(None);
+        self.reset_sent_at = ActiveValue::Set(None);
+        self.tokens_valid_after = ActiveValue::Set(Some(Local::now().into()));
+        Ok(self.update(db).await?)
+    }
+
+    /// Records a pending email change and generates the token sent to the new
+    /// address. The current email stays in place until the change is
+    /// confirmed.
+    ///
+    /// # Errors
+    ///
+    /// when the email is used by another user or has DB query error
+    pub async fn set_email_change_sent(
+        mut self,
+        db: &DatabaseConnection,
+        email: &str,
+    ) -> ModelResult<Model> {
+        ensure_email_available(db, email).await?;
+        self.pending_email = ActiveValue::set(Some(email.to_string()));
+        self.email_change_token = ActiveValue::Set(Some(Uuid::new_v4().to_string()));
+        self.email_change_sent_at = ActiveValue::set(Some(Local::now().into()));
+        Ok(self.update(db).await?)
+    }
+
+    /// Swaps the user email for the confirmed pending one. The new address
+    /// is considered verified since the user followed the link sent to it.
+    ///
+    /// # Errors
+    ///
+    /// when there is no pending change, the email was taken in the meantime
+    /// or has DB query error
+    pub async fn confirm_email_change(mut self, db: &DatabaseConnection) -> ModelResult<Model> {
+        let Some(email) = self.pending_email.as_ref().clone() else {
+            return Err(ModelError::EntityNotFound);
+        };
+        ensure_email_available(db, &email).await?;
+        self.email = ActiveValue::set(email);
+        self.email_verified_at = ActiveValue::set(Some(Local::now().into()));
+        self.pending_email = ActiveValue::Set(None);
+        self.email_change_token = ActiveValue::Set(None);
+        self.email_change_sent_at = ActiveValue::Set(None);
+        Ok(self.update(db).await?)
+    }
+
+    /// Replaces the API key of the user, the previous one stops working
+    ///
+    /// # Errors
+    ///
+    /// when has DB query error
+    pub async fn rotate_api_key(mut self, db: &DatabaseConnection) -> ModelResult<Model> {
+        self.api_key = ActiveValue::set(format!("lo-{}", Uuid::new_v4()));
+        Ok(self.update(db).await?)
+    }
+
+    /// Changes how the user is notified of comments
+    ///
+    /// # Errors
+    ///
+    /// when has DB query error
+    pub async fn set_notification_preference(
+        mut self,
+        db: &DatabaseConnection,
+        preference: NotificationPreference,
+    ) -> ModelResult<Model> {
+        self.notification_preference = ActiveValue::set(preference.as_str().to_string());
+        Ok(self.update(db).await?)
+    }
+
+    /// Records that a comment digest covering everything up to `sent_at` was
+    /// sent
+    ///
+    /// # Errors
+    ///
+    /// when has DB query error
+    pub async fn set_digest_sent(
+        mut self,
+        db: &DatabaseConnection,
+        sent_at: DateTimeWithTimeZone,
+    ) -> ModelResult<Model> {
+        self.digest_sent_at = ActiveValue::set(Some(sent_at));
+        Ok(self.update(db).await?)
+    }
+
+    /// Changes the role of the user
+    ///
+    /// # Errors
+    ///
+    /// when has DB query error
+    pub async fn set_role(mut self, db: &DatabaseConnection, role: Role) -> ModelResult<Model> {
+        self.role = ActiveValue::set(role.as_str().to_string());
+        Ok(self.update(db).await?)
+    }
+}
+
+/// Signed part of an unsubscribe token, scoped so the signature can't be
+/// reused for anything else
+fn unsubscribe_message(pid: &str) -> String {
+    format!("unsubscribe:{pid}")
+}
+
+async fn ensure_email_available(db: &DatabaseConnection, email: &str) -> ModelResult<()> {
+    let taken = users::Entity::find()
+        .filter(
+            model::query::condition()
+                .eq(users::Column::Email, email)
+                .build(),
+        )
+        .one(db)
+        .await?
+        .is_some();
+    if taken {
+        return Err(ModelError::EntityAlreadyExists {});
+    }
+    Ok(())
 }
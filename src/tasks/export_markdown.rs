@@ -0,0 +1,505 @@
+//! Exports posts as Markdown files with front matter, in the directory layout
+//! of Hugo or Jekyll, as an offline backup or to move to a static site.
+//!
+//! # Example
+//!
+//! ```sh
+//! cargo loco task export_markdown dir:./site layout:jekyll author:jane@example.com tag:rust since:2024-01-01 until:2025-01-01
+//! ```
+//!
+//! Only `dir` is required. `layout` is `hugo` or `jekyll`, `hugo` when not
+//! given. The other arguments filter the posts: by the email of their author,
+//! by tag, and by date, from `since` up to `until` excluded. Drafts are dated
+//! by their creation.
+//!
+//! - Hugo: each post is a page bundle, `content/posts/<slug>/index.md`, with
+//!   its images and comments in `comments.json` next to it.
+//! - Jekyll: posts go to `_posts/<date>-<slug>.md` and drafts to
+//!   `_drafts/<slug>.md`. Images go to `assets/media/` and comments to
+//!   `_data/comments/<slug>.json`.
+//!
+//! Links to uploads in the content, and the cover, are rewritten to paths
+//! relative to the post file. Exported posts can be imported again, see
+//! [`super::import_markdown`].
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, FixedOffset};
+use loco_rs::prelude::*;
+use sea_orm::{
+    sea_query::{Expr, Func, Query},
+    QueryOrder,
+};
+use serde::Serialize;
+
+use crate::{
+    common::front_matter::{self, slugify, FrontMatter},
+    models::{
+        _entities::{comments, posts},
+        media::{self, ImageType},
+        media_variants, post_tags, users,
+    },
+};
+
+pub struct ExportMarkdown;
+#[async_trait]
+impl Task for ExportMarkdown {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "export_markdown".to_string(),
+            detail: "Export posts as Markdown files for Hugo or Jekyll".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, vars: &task::Vars) -> Result<()> {
+        let dir = PathBuf::from(vars.cli_arg("dir")?);
+        let layout = match vars.cli_arg("layout") {
+            Ok(layout) => layout.parse()?,
+            Err(_) => Layout::Hugo,
+        };
+        let author = match vars.cli_arg("author") {
+            Ok(email) => Some(
+                users::Model::find_by_email(&app_context.db, email)
+                    .await?
+                    .pid,
+            ),
+            Err(_) => None,
+        };
+        let date = |name: &str| -> Result<Option<DateTime<FixedOffset>>> {
+            vars.cli_arg(name).ok().map_or(Ok(None), |date| {
+                front_matter::parse_date(date)
+                    .map(Some)
+                    .ok_or_else(|| Error::Message(format!("invalid date: {date}")))
+            })
+        };
+        let filters = Filters {
+            author,
+            tag: vars.cli_arg("tag").ok().cloned(),
+            since: date("since")?,
+            until: date("until")?,
+        };
+
+        let summary = export(app_context, &dir, layout, &filters).await?;
+        println!("{summary} to {}", dir.display());
+        Ok(())
+    }
+}
+
+/// The directory layout of the export
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    Hugo,
+    Jekyll,
+}
+
+impl std::str::FromStr for Layout {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "hugo" => Ok(Self::Hugo),
+            "jekyll" => Ok(Self::Jekyll),
+            _ => Err(Error::Message(format!(
+                "unknown layout: {s}, use hugo or jekyll"
+            ))),
+        }
+    }
+}
+
+impl Layout {
+    /// The Markdown file of the post
+    fn post_file(self, slug: &str, date: DateTime<FixedOffset>, draft: bool) -> PathBuf {
+        match self {
+            Self::Hugo => PathBuf::from("content/posts").join(slug).join("index.md"),
+            Self::Jekyll if draft => PathBuf::from("_drafts").join(format!("{slug}.md")),
+            Self::Jekyll => {
+                PathBuf::from("_posts").join(format!("{}-{slug}.md", date.format("%Y-%m-%d")))
+            }
+        }
+    }
+
+    /// Where the images of the post go
+    fn media_dir(self, post_file: &Path) -> PathBuf {
+        match self {
+            Self::Hugo => post_file.parent().unwrap_or(post_file).to_path_buf(),
+            Self::Jekyll => PathBuf::from("assets/media"),
+        }
+    }
+
+    /// Where the comments of the post go
+    fn comments_file(self, slug: &str, post_file: &Path) -> PathBuf {
+        match self {
+            Self::Hugo => self.media_dir(post_file).join("comments.json"),
+            Self::Jekyll => PathBuf::from("_data/comments").join(format!("{slug}.json")),
+        }
+    }
+}
+
+/// The posts to export, all of them by default
+#[derive(Clone, Debug, Default)]
+pub struct Filters {
+    pub author: Option<Uuid>,
+    pub tag: Option<String>,
+    pub since: Option<DateTime<FixedOffset>>,
+    /// Excluded
+    pub until: Option<DateTime<FixedOffset>>,
+}
+
+/// What an export wrote
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub posts: usize,
+    pub media: usize,
+    pub comments: usize,
+    /// Links to uploads that could not be exported and were left as they are
+    pub missing_media: usize,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "exported {} posts, {} media files and {} comments",
+            self.posts, self.media, self.comments
+        )?;
+        if self.missing_media > 0 {
+            write!(f, " ({} missing media files)", self.missing_media)?;
+        }
+        Ok(())
+    }
+}
+
+/// A comment in the sidecar file of its post
+#[derive(Serialize)]
+struct ExportedComment<'a> {
+    id: i32,
+    parent_id: Option<i32>,
+    author: Option<&'a str>,
+    handle: Option<&'a str>,
+    content: Option<&'a str>,
+    status: &'a str,
+    created_at: DateTimeWithTimeZone,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hidden_at: Option<DateTimeWithTimeZone>,
+}
+
+/// A link to an upload in the content, see [`media::url`] and
+/// [`media_variants::url`]
+#[derive(Debug, PartialEq, Eq)]
+struct MediaRef {
+    /// Of the link in the content, with the origin of absolute URLs
+    range: Range<usize>,
+    pid: Uuid,
+    /// Name and format of the variant, the original if none
+    variant: Option<(String, ImageType)>,
+}
+
+/// The links to uploads in the content, in order
+fn media_refs(content: &str) -> Vec<MediaRef> {
+    const PREFIX: &str = "/api/media/";
+
+    let mut refs = Vec::new();
+    for (start, _) in content.match_indices(PREFIX) {
+        let pid_start = start + PREFIX.len();
+        let Some(pid) = content
+            .get(pid_start..pid_start + 36)
+            .and_then(|pid| Uuid::parse_str(pid).ok())
+        else {
+            continue;
+        };
+        let mut end = pid_start + 36;
+        let mut variant = None;
+        if let Some(file) = content[end..].strip_prefix('/') {
+            let len = file
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '_'))
+                .unwrap_or(file.len());
+            let found = file[..len].split_once('.').and_then(|(name, extension)| {
+                ImageType::from_extension(extension).map(|format| (name.to_string(), format))
+            });
+            if found.is_some() {
+                variant = found;
+                end += 1 + len;
+            }
+        }
+
+        // absolute URLs lose their origin too
+        let before = &content[..start];
+        let origin_start = before
+            .rfind(|c: char| c.is_whitespace() || matches!(c, '(' | '"' | '\'' | '<' | '[' | '='))
+            .map_or(0, |i| i + 1);
+        let origin = &before[origin_start..];
+        let host = origin
+            .strip_prefix("https://")
+            .or_else(|| origin.strip_prefix("http://"));
+        let start = if host.is_some_and(|host| !host.is_empty() && !host.contains('/')) {
+            origin_start
+        } else {
+            start
+        };
+
+        refs.push(MediaRef {
+            range: start..end,
+            pid,
+            variant,
+        });
+    }
+    refs
+}
+
+/// The path of `to` relative to the directory `from`, both in the export
+fn relative(from: &Path, to: &Path) -> String {
+    let from: Vec<_> = from.components().collect();
+    let to: Vec<_> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut parts = vec!["..".to_string(); from.len() - common];
+    parts.extend(
+        to[common..]
+            .iter()
+            .map(|part| part.as_os_str().to_string_lossy().to_string()),
+    );
+    parts.join("/")
+}
+
+fn write(path: &Path, content: &[u8]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+/// Writes the uploads the post links to and the posts themselves
+struct Exporter<'a> {
+    ctx: &'a AppContext,
+    dir: &'a Path,
+    layout: Layout,
+    /// Files already written, relative to the export
+    written: HashSet<PathBuf>,
+    summary: Summary,
+}
+
+impl Exporter<'_> {
+    /// Copies the upload to the media directory of the post, returning its
+    /// path relative to the post file. Uploads that are gone are left out.
+    async fn media(
+        &mut self,
+        post_file: &Path,
+        pid: Uuid,
+        variant: Option<&(String, ImageType)>,
+    ) -> Result<Option<String>> {
+        let Ok(item) = media::Model::find_by_pid(&self.ctx.db, pid).await else {
+            tracing::warn!(pid = pid.to_string(), "media not found, link kept");
+            self.summary.missing_media += 1;
+            return Ok(None);
+        };
+        let (source, name) = match variant {
+            Some((name, format)) => {
+                let Ok(file) =
+                    media_variants::Model::find_file(&self.ctx.db, item.id, name, *format).await
+                else {
+                    tracing::warn!(pid = pid.to_string(), name, "variant not found, link kept");
+                    self.summary.missing_media += 1;
+                    return Ok(None);
+                };
+                (file.path, format!("{pid}-{name}.{}", format.extension()))
+            }
+            None => {
+                let name = Path::new(&item.path).file_name().map_or_else(
+                    || pid.to_string(),
+                    |name| name.to_string_lossy().to_string(),
+                );
+                (item.path, name)
+            }
+        };
+
+        let target = self.layout.media_dir(post_file).join(name);
+        if !self.written.contains(&target) {
+            let content: Vec<u8> = match self.ctx.storage.download(&PathBuf::from(&source)).await {
+                Ok(content) => content,
+                Err(err) => {
+                    tracing::warn!(err = err.to_string(), path = source, "media file not found");
+                    self.summary.missing_media += 1;
+                    return Ok(None);
+                }
+            };
+            write(&self.dir.join(&target), &content)?;
+            self.written.insert(target.clone());
+            self.summary.media += 1;
+        }
+        Ok(Some(relative(
+            post_file.parent().unwrap_or(Path::new("")),
+            &target,
+        )))
+    }
+
+    /// The content with links to uploads rewritten to the exported files
+    async fn rewrite(&mut self, post_file: &Path, content: &str) -> Result<String> {
+        let mut out = String::with_capacity(content.len());
+        let mut pos = 0;
+        for media_ref in media_refs(content) {
+            out.push_str(&content[pos..media_ref.range.start]);
+            match self
+                .media(post_file, media_ref.pid, media_ref.variant.as_ref())
+                .await?
+            {
+                Some(path) => out.push_str(&path),
+                None => out.push_str(&content[media_ref.range.clone()]),
+            }
+            pos = media_ref.range.end;
+        }
+        out.push_str(&content[pos..]);
+        Ok(out)
+    }
+}
+
+/// The date of the post, as exported and filtered on
+fn date_of(post: &posts::Model) -> DateTime<FixedOffset> {
+    post.published_at.unwrap_or(post.created_at)
+}
+
+/// Exports the posts matching the filters to the directory. Files of an
+/// earlier export are overwritten.
+///
+/// # Errors
+///
+/// When the DB query fails or the files can't be written
+pub async fn export(
+    ctx: &AppContext,
+    dir: &Path,
+    layout: Layout,
+    filters: &Filters,
+) -> Result<Summary> {
+    let mut query = posts::Entity::find().order_by_asc(posts::Column::Id);
+    if let Some(author) = filters.author {
+        query = query.filter(posts::Column::UserId.eq(author));
+    }
+    // the date of each post, as in `date_of`
+    let date = Expr::expr(Func::coalesce([
+        Expr::col(posts::Column::PublishedAt).into(),
+        Expr::col(posts::Column::CreatedAt).into(),
+    ]));
+    if let Some(since) = filters.since {
+        query = query.filter(date.clone().gte(since));
+    }
+    if let Some(until) = filters.until {
+        query = query.filter(date.lt(until));
+    }
+    if let Some(tag) = &filters.tag {
+        let tag = post_tags::normalize(&[tag]).into_iter().next();
+        query = query.filter(
+            posts::Column::Id.in_subquery(
+                Query::select()
+                    .column(post_tags::post_tags::Column::PostId)
+                    .from(post_tags::Entity)
+                    .and_where(post_tags::post_tags::Column::Tag.eq(tag))
+                    .to_owned(),
+            ),
+        );
+    }
+    let posts = query.all(&ctx.db).await?;
+
+    let ids: Vec<i32> = posts.iter().map(|post| post.id).collect();
+    let mut tags = post_tags::Model::for_posts(&ctx.db, &ids).await?;
+    let mut comments_of: HashMap<i32, Vec<comments::Model>> = HashMap::new();
+    for comment in comments::Entity::find()
+        .filter(comments::Column::PostId.is_in(ids))
+        .order_by_asc(comments::Column::Id)
+        .all(&ctx.db)
+        .await?
+    {
+        if let Some(post_id) = comment.post_id {
+            comments_of.entry(post_id).or_default().push(comment);
+        }
+    }
+    let commenters: HashSet<Uuid> = comments_of
+        .values()
+        .flatten()
+        .map(|comment| comment.user_id)
+        .collect();
+    let commenters: HashMap<Uuid, users::Model> = users::Entity::find()
+        .filter(users::users::Column::Pid.is_in(commenters))
+        .all(&ctx.db)
+        .await?
+        .into_iter()
+        .map(|user| (user.pid, user))
+        .collect();
+
+    let mut exporter = Exporter {
+        ctx,
+        dir,
+        layout,
+        written: HashSet::new(),
+        summary: Summary::default(),
+    };
+    let mut slugs = HashSet::new();
+    for post in posts {
+        let draft = post.published != Some(true) || post.hidden_at.is_some();
+        let mut slug = slugify(post.slug.as_deref().unwrap_or_default());
+        if slug.is_empty() || !slugs.insert(slug.clone()) {
+            slug = format!("{slug}-{}", post.id)
+                .trim_start_matches('-')
+                .to_string();
+            slugs.insert(slug.clone());
+        }
+        let post_file = layout.post_file(&slug, date_of(&post), draft);
+
+        let content = exporter
+            .rewrite(&post_file, post.content.as_deref().unwrap_or_default())
+            .await?;
+        let cover = match post.cover_media_id {
+            Some(id) => match media::Entity::find_by_id(id).one(&ctx.db).await? {
+                Some(cover) => exporter.media(&post_file, cover.pid, None).await?,
+                None => None,
+            },
+            None => None,
+        };
+        let front_matter = FrontMatter {
+            title: post.title.clone(),
+            date: Some(date_of(&post).to_rfc3339()),
+            tags: tags.remove(&post.id).unwrap_or_default(),
+            slug: Some(post.slug.clone().unwrap_or_else(|| slug.clone())),
+            draft,
+            summary: post.summary.clone().filter(|_| !post.summary_generated),
+            cover,
+        };
+        let markdown = front_matter::render(&front_matter, &content).map_err(Box::from)?;
+        write(&dir.join(&post_file), markdown.as_bytes())?;
+        exporter.summary.posts += 1;
+
+        if let Some(comments) = comments_of.get(&post.id) {
+            let exported: Vec<_> = comments
+                .iter()
+                .map(|comment| {
+                    let author = commenters.get(&comment.user_id);
+                    ExportedComment {
+                        id: comment.id,
+                        parent_id: comment.parent_id,
+                        author: author.map(|user| user.name.as_str()),
+                        handle: author.and_then(|user| user.handle.as_deref()),
+                        content: comment.content.as_deref(),
+                        status: &comment.status,
+                        created_at: comment.created_at,
+                        hidden_at: comment.hidden_at,
+                    }
+                })
+                .collect();
+            write(
+                &dir.join(layout.comments_file(&slug, &post_file)),
+                &serde_json::to_vec_pretty(&exported)?,
+            )?;
+            exporter.summary.comments += comments.len();
+        }
+    }
+
+    tracing::info!(
+        posts = exporter.summary.posts,
+        media = exporter.summary.media,
+        "posts exported"
+    );
+    Ok(exporter.summary)
+}
@@ -0,0 +1,454 @@
+//! Imports Markdown files with YAML front matter, as Hugo and Jekyll write
+//! them, as posts of an author. See [`crate::common::front_matter`] for the
+//! fields read.
+//!
+//! # Example
+//!
+//! ```sh
+//! cargo loco task import_markdown dir:./posts author:jane@example.com
+//! ```
+//!
+//! Posts are matched by slug, taken from the front matter or else from the
+//! file name, without the date Jekyll puts in front. Importing again only
+//! updates the posts whose file changed. To see what would change without
+//! writing anything, add `dry_run:true`.
+//!
+//! Published posts dated in the future are scheduled, and show up once their
+//! date comes. The report tells which ones.
+//!
+//! Files that can't be imported are reported and skipped, and the task fails
+//! once the others are done. Mentions in imported posts don't notify anyone.
+
+use std::{
+    collections::HashSet,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, FixedOffset};
+use loco_rs::prelude::*;
+use sea_orm::{QueryOrder, TransactionTrait};
+
+use crate::{
+    common::front_matter::{self, slugify},
+    models::{_entities::posts, post_tags, users},
+};
+
+pub struct ImportMarkdown;
+#[async_trait]
+impl Task for ImportMarkdown {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "import_markdown".to_string(),
+            detail: "Import Markdown files with front matter as posts".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, vars: &task::Vars) -> Result<()> {
+        let dir = PathBuf::from(vars.cli_arg("dir")?);
+        let email = vars.cli_arg("author")?;
+        let dry_run = vars
+            .cli_arg("dry_run")
+            .is_ok_and(|dry_run| dry_run == "true");
+
+        let author = users::Model::find_by_email(&app_context.db, email).await?;
+        let report = import(&app_context.db, &dir, &author, dry_run).await?;
+        println!("{report}");
+
+        let failed = report.failed();
+        if failed > 0 {
+            return Err(Error::Message(format!(
+                "{failed} file(s) could not be imported"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// What the import did, or would do, with a file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Created,
+    /// With the fields that changed
+    Updated(Vec<&'static str>),
+    Unchanged,
+    Failed(String),
+}
+
+/// A file of the import
+#[derive(Clone, Debug)]
+pub struct Entry {
+    /// Relative to the imported directory
+    pub path: PathBuf,
+    pub slug: Option<String>,
+    pub outcome: Outcome,
+    /// The publication time of a post scheduled for later
+    pub scheduled: Option<DateTime<FixedOffset>>,
+}
+
+/// The change report of an import
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    pub dry_run: bool,
+    pub entries: Vec<Entry>,
+}
+
+impl Report {
+    fn count(&self, matches: impl Fn(&Outcome) -> bool) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| matches(&entry.outcome))
+            .count()
+    }
+
+    #[must_use]
+    pub fn created(&self) -> usize {
+        self.count(|outcome| *outcome == Outcome::Created)
+    }
+
+    #[must_use]
+    pub fn updated(&self) -> usize {
+        self.count(|outcome| matches!(outcome, Outcome::Updated(_)))
+    }
+
+    #[must_use]
+    pub fn unchanged(&self) -> usize {
+        self.count(|outcome| *outcome == Outcome::Unchanged)
+    }
+
+    #[must_use]
+    pub fn failed(&self) -> usize {
+        self.count(|outcome| matches!(outcome, Outcome::Failed(_)))
+    }
+
+    #[must_use]
+    pub fn scheduled(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.scheduled.is_some())
+            .count()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            let path = entry.path.display();
+            let slug = entry.slug.as_deref().unwrap_or_default();
+            match &entry.outcome {
+                Outcome::Created => write!(f, "created    {path} ({slug})")?,
+                Outcome::Updated(fields) => {
+                    write!(f, "updated    {path} ({slug}): {}", fields.join(", "))?;
+                }
+                Outcome::Unchanged => write!(f, "unchanged  {path} ({slug})")?,
+                Outcome::Failed(err) => write!(f, "failed     {path}: {err}")?,
+            }
+            match entry.scheduled {
+                Some(date) => writeln!(f, ", scheduled for {}", date.to_rfc3339())?,
+                None => writeln!(f)?,
+            }
+        }
+        write!(
+            f,
+            "{} created, {} updated, {} unchanged, {} failed, {} scheduled",
+            self.created(),
+            self.updated(),
+            self.unchanged(),
+            self.failed(),
+            self.scheduled()
+        )?;
+        if self.dry_run {
+            write!(f, " (dry run, nothing was written)")?;
+        }
+        Ok(())
+    }
+}
+
+/// A post as read from its file
+#[derive(Debug)]
+struct Post {
+    slug: String,
+    title: String,
+    content: String,
+    summary: Option<String>,
+    tags: Vec<String>,
+    draft: bool,
+    date: Option<DateTime<FixedOffset>>,
+}
+
+impl Post {
+    /// The date of a published post, when it is still to come
+    fn scheduled(&self) -> Option<DateTime<FixedOffset>> {
+        self.date
+            .filter(|date| !self.draft && *date > chrono::Utc::now())
+    }
+}
+
+/// Imports the Markdown files under the directory as posts of the author.
+/// With `dry_run` the report is made without writing anything.
+///
+/// # Errors
+///
+/// When the directory can't be read. Errors of single files are in the
+/// report.
+pub async fn import(
+    db: &DatabaseConnection,
+    dir: &Path,
+    author: &users::Model,
+    dry_run: bool,
+) -> Result<Report> {
+    let mut report = Report {
+        dry_run,
+        entries: Vec::new(),
+    };
+    let mut seen = HashSet::new();
+    for file in markdown_files(dir)? {
+        let path = file.strip_prefix(dir).unwrap_or(&file).to_path_buf();
+        let post = match read(&file) {
+            Ok(post) => post,
+            Err(err) => {
+                report.entries.push(Entry {
+                    path,
+                    slug: None,
+                    outcome: Outcome::Failed(err),
+                    scheduled: None,
+                });
+                continue;
+            }
+        };
+        let outcome = if seen.insert(post.slug.clone()) {
+            apply(db, author, &post, dry_run)
+                .await
+                .unwrap_or_else(Outcome::Failed)
+        } else {
+            Outcome::Failed(format!("slug {} is used by another file", post.slug))
+        };
+        let scheduled = match outcome {
+            Outcome::Failed(_) => None,
+            _ => post.scheduled(),
+        };
+        report.entries.push(Entry {
+            path,
+            slug: Some(post.slug),
+            outcome,
+            scheduled,
+        });
+    }
+    Ok(report)
+}
+
+/// The `.md` and `.markdown` files under the directory, sorted. Hidden files
+/// and directories are left out, and symlinked directories aren't followed,
+/// as they could loop.
+fn markdown_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+            {
+                continue;
+            }
+            if entry.file_type()?.is_dir() {
+                dirs.push(path);
+            } else if path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext == "md" || ext == "markdown")
+            {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn read(file: &Path) -> std::result::Result<Post, String> {
+    let text = std::fs::read_to_string(file).map_err(|err| err.to_string())?;
+    let document =
+        front_matter::parse(&text).map_err(|err| format!("invalid front matter: {err}"))?;
+    let front_matter = document.front_matter;
+
+    let title = front_matter
+        .title
+        .filter(|title| !title.trim().is_empty())
+        .ok_or("missing title")?;
+    let date = match front_matter.date.as_deref() {
+        Some(date) => {
+            Some(front_matter::parse_date(date).ok_or_else(|| format!("invalid date: {date}"))?)
+        }
+        None => None,
+    };
+    let slug = slugify(
+        front_matter
+            .slug
+            .as_deref()
+            .unwrap_or_else(|| file_slug(file)),
+    );
+    if slug.is_empty() {
+        return Err("missing slug".to_string());
+    }
+
+    Ok(Post {
+        slug,
+        title: title.trim().to_string(),
+        content: document.content.to_string(),
+        summary: front_matter
+            .summary
+            .filter(|summary| !summary.trim().is_empty()),
+        tags: post_tags::normalize(&front_matter.tags),
+        draft: front_matter.draft,
+        date,
+    })
+}
+
+/// The slug of a file without one in its front matter: its name without the
+/// `2024-05-01-` prefix of Jekyll, or the directory of Hugo page bundles
+fn file_slug(file: &Path) -> &str {
+    let stem = file
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default();
+    if stem == "index" || stem == "_index" {
+        return file
+            .parent()
+            .and_then(|dir| dir.file_name())
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+    }
+    let dated = stem.len() > 11
+        && stem.char_indices().take(11).all(|(i, c)| match i {
+            4 | 7 | 10 => c == '-',
+            _ => c.is_ascii_digit(),
+        });
+    if dated {
+        &stem[11..]
+    } else {
+        stem
+    }
+}
+
+/// Creates or updates the post of the author with the slug
+async fn apply(
+    db: &DatabaseConnection,
+    author: &users::Model,
+    post: &Post,
+    dry_run: bool,
+) -> std::result::Result<Outcome, String> {
+    let existing = posts::Entity::find()
+        .filter(posts::Column::Slug.eq(&post.slug))
+        .order_by_asc(posts::Column::Id)
+        .all(db)
+        .await
+        .map_err(|err| err.to_string())?;
+    if existing.iter().any(|item| item.user_id != Some(author.pid)) {
+        return Err(format!(
+            "slug {} is used by a post of another author",
+            post.slug
+        ));
+    }
+    match existing.into_iter().next() {
+        Some(item) => update(db, item, post, dry_run).await,
+        None => create(db, author, post, dry_run).await,
+    }
+    .map_err(|err| err.to_string())
+}
+
+async fn create(
+    db: &DatabaseConnection,
+    author: &users::Model,
+    post: &Post,
+    dry_run: bool,
+) -> ModelResult<Outcome> {
+    if dry_run {
+        return Ok(Outcome::Created);
+    }
+    let published_at = if post.draft {
+        None
+    } else {
+        Some(post.date.unwrap_or_else(|| chrono::Utc::now().into()))
+    };
+
+    let txn = db.begin().await?;
+    let item = posts::ActiveModel {
+        title: Set(Some(post.title.clone())),
+        content: Set(Some(post.content.clone())),
+        summary: Set(post.summary.clone()),
+        published: Set(Some(!post.draft)),
+        slug: Set(Some(post.slug.clone())),
+        user_id: Set(Some(author.pid)),
+        published_at: Set(published_at),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await?;
+    post_tags::Model::set_for(&txn, item.id, &post.tags).await?;
+    txn.commit().await?;
+    Ok(Outcome::Created)
+}
+
+async fn update(
+    db: &DatabaseConnection,
+    item: posts::Model,
+    post: &Post,
+    dry_run: bool,
+) -> ModelResult<Outcome> {
+    let published_at = if post.draft {
+        None
+    } else {
+        post.date
+            .or(item.published_at.filter(|_| item.published == Some(true)))
+            .or_else(|| Some(chrono::Utc::now().into()))
+    };
+    // without one in the front matter, the summary is generated
+    let summary_changed = match &post.summary {
+        Some(summary) => item.summary_generated || item.summary.as_ref() != Some(summary),
+        None => !item.summary_generated,
+    };
+    let tags = post_tags::Model::of(db, item.id).await?;
+
+    let mut changed = Vec::new();
+    let mut model = item.clone().into_active_model();
+    if item.title.as_ref() != Some(&post.title) {
+        changed.push("title");
+        model.title = Set(Some(post.title.clone()));
+    }
+    if item.content.as_ref() != Some(&post.content) {
+        changed.push("content");
+        model.content = Set(Some(post.content.clone()));
+    }
+    if summary_changed {
+        changed.push("summary");
+        model.summary = Set(post.summary.clone());
+    }
+    if item.published != Some(!post.draft) {
+        changed.push("draft");
+        model.published = Set(Some(!post.draft));
+    }
+    if item.published_at != published_at {
+        changed.push("date");
+        model.published_at = Set(published_at);
+    }
+    if tags != post.tags {
+        changed.push("tags");
+    }
+
+    if changed.is_empty() {
+        return Ok(Outcome::Unchanged);
+    }
+    if dry_run {
+        return Ok(Outcome::Updated(changed));
+    }
+    let txn = db.begin().await?;
+    model.update(&txn).await?;
+    if tags != post.tags {
+        post_tags::Model::set_for(&txn, item.id, &post.tags).await?;
+    }
+    txn.commit().await?;
+    Ok(Outcome::Updated(changed))
+}
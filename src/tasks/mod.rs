@@ -1 +1,7 @@
 pub mod seed;
+pub mod set_role;
+pub mod send_comment_digests;
+pub mod import_markdown;
+pub mod export_markdown;
+pub mod purge_exports;
+pub mod purge_sessions;
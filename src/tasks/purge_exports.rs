@@ -0,0 +1,37 @@
+//! Deletes the data export archives past the `export_ttl_hours` of the
+//! `accounts` settings. Meant to run every hour, see the `scheduler` section
+//! of the config.
+//!
+//! # Example
+//!
+//! ```sh
+//! cargo loco task purge_exports
+//! ```
+
+use chrono::Duration;
+use loco_rs::prelude::*;
+
+use crate::{common::settings::Settings, models::exports};
+
+pub struct PurgeExports;
+#[async_trait]
+impl Task for PurgeExports {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "purge_exports".to_string(),
+            detail: "Delete the expired data export archives".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, _vars: &task::Vars) -> Result<()> {
+        let settings = Settings::from_context(app_context)?;
+        let count = exports::Model::remove_expired(
+            &app_context.db,
+            &app_context.storage,
+            Duration::hours(settings.accounts.export_ttl_hours),
+        )
+        .await?;
+        tracing::info!(count, "expired exports deleted");
+        Ok(())
+    }
+}
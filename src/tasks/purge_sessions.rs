@@ -0,0 +1,31 @@
+//! Deletes the login sessions whose tokens all expired, past the `expiration`
+//! of the `auth.jwt` config. Meant to run every day, see the `scheduler`
+//! section of the config.
+//!
+//! # Example
+//!
+//! ```sh
+//! cargo loco task purge_sessions
+//! ```
+
+use loco_rs::prelude::*;
+
+use crate::models::sessions;
+
+pub struct PurgeSessions;
+#[async_trait]
+impl Task for PurgeSessions {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "purge_sessions".to_string(),
+            detail: "Delete the expired login sessions".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, _vars: &task::Vars) -> Result<()> {
+        let expiration = app_context.config.get_jwt_config()?.expiration;
+        let count = sessions::Model::remove_expired(&app_context.db, expiration).await?;
+        tracing::info!(count, "expired sessions deleted");
+        Ok(())
+    }
+}
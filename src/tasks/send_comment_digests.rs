@@ -0,0 +1,48 @@
+//! Queues the daily comment digest of every user who chose it. Meant to run
+//! once a day, see the `scheduler` section of the config.
+//!
+//! # Example
+//!
+//! ```sh
+//! cargo loco task send_comment_digests
+//! ```
+
+use loco_rs::prelude::*;
+
+use crate::{
+    models::{_entities::users, users::NotificationPreference},
+    workers::comment_digest::{CommentDigestWorker, CommentDigestWorkerArgs},
+};
+
+pub struct SendCommentDigests;
+#[async_trait]
+impl Task for SendCommentDigests {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "send_comment_digests".to_string(),
+            detail: "Queue the daily comment digest emails".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, _vars: &task::Vars) -> Result<()> {
+        let users = users::Entity::find()
+            .filter(
+                users::Column::NotificationPreference
+                    .eq(NotificationPreference::DailyDigest.as_str()),
+            )
+            .all(&app_context.db)
+            .await?;
+
+        for user in &users {
+            CommentDigestWorker::perform_later(
+                app_context,
+                CommentDigestWorkerArgs {
+                    user_guid: user.pid.to_string(),
+                },
+            )
+            .await?;
+        }
+        tracing::info!(count = users.len(), "comment digests queued");
+        Ok(())
+    }
+}
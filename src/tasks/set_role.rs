@@ -0,0 +1,42 @@
+//! Changes the role of a user, e.g. to make the first admin who can then
+//! issue invites.
+//!
+//! # Example
+//!
+//! ```sh
+//! cargo loco task set_role email:admin@example.com role:admin
+//! ```
+//!
+//! Roles are `user`, `moderator` and `admin`.
+
+use loco_rs::prelude::*;
+
+use crate::models::users::{self, Role};
+
+pub struct SetRole;
+#[async_trait]
+impl Task for SetRole {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "set_role".to_string(),
+            detail: "Change the role of a user".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, vars: &task::Vars) -> Result<()> {
+        let email = vars.cli_arg("email")?;
+        let role: Role = vars.cli_arg("role")?.parse()?;
+
+        let user = users::Model::find_by_email(&app_context.db, email).await?;
+        let user = user
+            .into_active_model()
+            .set_role(&app_context.db, role)
+            .await?;
+        tracing::info!(
+            pid = user.pid.to_string(),
+            role = role.as_str(),
+            "user role changed"
+        );
+        Ok(())
+    }
+}
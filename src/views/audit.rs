@@ -0,0 +1,31 @@
+use sea_orm::prelude::DateTimeWithTimeZone;
+use serde::{Deserialize, Serialize};
+
+use crate::models::audit_events;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AuditEventResponse {
+    pub id: i32,
+    pub user_id: Option<String>,
+    pub event: String,
+    pub outcome: String,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub detail: Option<String>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+impl From<audit_events::Model> for AuditEventResponse {
+    fn from(event: audit_events::Model) -> Self {
+        Self {
+            id: event.id,
+            user_id: event.user_id.map(|pid| pid.to_string()),
+            event: event.event,
+            outcome: event.outcome,
+            ip: event.ip,
+            user_agent: event.user_agent,
+            detail: event.detail,
+            created_at: event.created_at,
+        }
+    }
+}
@@ -1,6 +1,7 @@
+use sea_orm::prelude::DateTimeWithTimeZone;
 use serde::{Deserialize, Serialize};
 
-use crate::models::_entities::users;
+use crate::models::_entities::{sessions, users};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct LoginResponse {
@@ -27,6 +28,10 @@ pub struct CurrentResponse {
     pub pid: String,
     pub name: String,
     pub email: String,
+    pub handle: Option<String>,
+    pub bio: Option<String>,
+    pub website: Option<String>,
+    pub avatar_url: Option<String>,
 }
 
 impl CurrentResponse {
@@ -36,6 +41,49 @@ impl CurrentResponse {
             pid: user.pid.to_string(),
             name: user.name.clone(),
             email: user.email.clone(),
+            handle: user.handle.clone(),
+            bio: user.bio.clone(),
+            website: user.website.clone(),
+            avatar_url: user.avatar_url.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ApiKeyResponse {
+    pub api_key: String,
+}
+
+impl ApiKeyResponse {
+    #[must_use]
+    pub fn new(user: &users::Model) -> Self {
+        Self {
+            api_key: user.api_key.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SessionResponse {
+    pub id: i32,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: DateTimeWithTimeZone,
+    pub last_seen_at: DateTimeWithTimeZone,
+    /// Whether this is the session of the request
+    pub current: bool,
+}
+
+impl SessionResponse {
+    #[must_use]
+    pub fn new(session: &sessions::Model, current_session_id: i32) -> Self {
+        Self {
+            id: session.id,
+            user_agent: session.user_agent.clone(),
+            ip: session.ip.clone(),
+            created_at: session.created_at,
+            last_seen_at: session.last_seen_at,
+            current: session.id == current_session_id,
         }
     }
 }
@@ -0,0 +1,38 @@
+use sea_orm::prelude::DateTimeWithTimeZone;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{bookmark_collections, bookmarks};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BookmarkResponse {
+    pub post_id: i32,
+    pub collection_id: Option<i32>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+impl From<bookmarks::Model> for BookmarkResponse {
+    fn from(bookmark: bookmarks::Model) -> Self {
+        Self {
+            post_id: bookmark.post_id,
+            collection_id: bookmark.collection_id,
+            created_at: bookmark.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CollectionResponse {
+    pub id: i32,
+    pub name: String,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+impl From<bookmark_collections::Model> for CollectionResponse {
+    fn from(collection: bookmark_collections::Model) -> Self {
+        Self {
+            id: collection.id,
+            name: collection.name,
+            created_at: collection.created_at,
+        }
+    }
+}
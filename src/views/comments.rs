@@ -0,0 +1,52 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::models::{_entities::comments, mentions, reactions::ReactionSummary};
+
+/// A comment with its content rendered for display
+#[derive(Debug, Serialize)]
+pub struct CommentResponse {
+    #[serde(flatten)]
+    pub comment: comments::Model,
+    /// The content with mentions of known handles linked to their profile
+    pub rendered_content: Option<String>,
+    pub reactions: ReactionSummary,
+    /// How many replies the comment has, when listing threads
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply_count: Option<u64>,
+    /// The first replies, when listing threads
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replies: Option<Vec<CommentResponse>>,
+}
+
+impl CommentResponse {
+    #[must_use]
+    pub fn new(comment: comments::Model, known_handles: &HashSet<String>) -> Self {
+        let rendered_content = comment
+            .content
+            .as_deref()
+            .map(|content| mentions::link(content, known_handles));
+        Self {
+            comment,
+            rendered_content,
+            reactions: ReactionSummary::default(),
+            reply_count: None,
+            replies: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_reactions(mut self, reactions: ReactionSummary) -> Self {
+        self.reactions = reactions;
+        self
+    }
+
+    /// Adds the thread of the comment: its reply count and first replies
+    #[must_use]
+    pub fn with_replies(mut self, reply_count: u64, replies: Vec<Self>) -> Self {
+        self.reply_count = Some(reply_count);
+        self.replies = Some(replies);
+        self
+    }
+}
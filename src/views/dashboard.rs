@@ -0,0 +1,57 @@
+use sea_orm::prelude::DateTimeWithTimeZone;
+use serde::{Deserialize, Serialize};
+
+use crate::models::posts::PostStateCounts;
+
+/// A post of the author with how it is doing
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TopPost {
+    pub id: i32,
+    pub title: Option<String>,
+    pub views: u64,
+    /// Public comments on the post
+    pub comments: u64,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityKind {
+    /// Someone commented on one of the author's posts
+    Comment,
+    /// Someone reacted to one of the author's posts
+    Reaction,
+    /// Someone started following the author
+    Follow,
+}
+
+/// Who caused an activity
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Actor {
+    pub pid: String,
+    pub name: String,
+    pub handle: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Activity {
+    pub kind: ActivityKind,
+    pub at: DateTimeWithTimeZone,
+    /// `None` when the account was deleted since
+    pub actor: Option<Actor>,
+    pub post_id: Option<i32>,
+    pub post_title: Option<String>,
+    pub comment_id: Option<i32>,
+    pub emoji: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DashboardResponse {
+    pub posts: PostStateCounts,
+    /// Public comments by others on the author's posts
+    pub comments_received: u64,
+    /// Views of all the author's posts
+    pub views: u64,
+    pub top_posts: Vec<TopPost>,
+    /// Latest comments, reactions and follows, most recent first
+    pub activity: Vec<Activity>,
+}
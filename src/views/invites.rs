@@ -0,0 +1,34 @@
+use sea_orm::prelude::DateTimeWithTimeZone;
+use serde::{Deserialize, Serialize};
+
+use crate::models::invites;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct InviteResponse {
+    pub id: i32,
+    pub code: String,
+    pub email: Option<String>,
+    pub max_uses: i32,
+    pub uses: i32,
+    pub expires_at: DateTimeWithTimeZone,
+    pub revoked_at: Option<DateTimeWithTimeZone>,
+    pub created_by: String,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+impl InviteResponse {
+    #[must_use]
+    pub fn new(invite: &invites::Model) -> Self {
+        Self {
+            id: invite.id,
+            code: invite.code.clone(),
+            email: invite.email.clone(),
+            max_uses: invite.max_uses,
+            uses: invite.uses,
+            expires_at: invite.expires_at,
+            revoked_at: invite.revoked_at,
+            created_by: invite.created_by.to_string(),
+            created_at: invite.created_at,
+        }
+    }
+}
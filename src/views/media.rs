@@ -0,0 +1,77 @@
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{media, media_variants};
+
+/// A resized copy of an upload, for `srcset`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct VariantResponse {
+    /// `thumbnail`, `medium` or `large`
+    pub name: String,
+    pub format: String,
+    pub content_type: String,
+    pub url: String,
+    pub width: i32,
+    pub height: i32,
+    /// In bytes
+    pub size: i32,
+}
+
+impl VariantResponse {
+    #[must_use]
+    pub fn new(pid: Uuid, variant: media_variants::Model) -> Option<Self> {
+        let url = media_variants::url(pid, &variant.name, variant.format()?);
+        Some(Self {
+            name: variant.name,
+            format: variant.format,
+            content_type: variant.content_type,
+            url,
+            width: variant.width,
+            height: variant.height,
+            size: variant.size,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MediaResponse {
+    /// Set as `cover_media_id` of a post to use the image as its cover
+    pub id: i32,
+    pub pid: Uuid,
+    pub url: String,
+    pub content_type: String,
+    /// In bytes
+    pub size: i32,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub original_name: Option<String>,
+    pub created_at: DateTimeWithTimeZone,
+    /// Whether the variants were built, they are built in the background
+    /// after the upload
+    pub processed: bool,
+    /// Smallest first
+    pub variants: Vec<VariantResponse>,
+}
+
+impl MediaResponse {
+    #[must_use]
+    pub fn new(media: media::Model, variants: Vec<media_variants::Model>) -> Self {
+        let pid = media.pid;
+        Self {
+            id: media.id,
+            pid,
+            url: media::url(pid),
+            content_type: media.content_type,
+            size: media.size,
+            width: media.width,
+            height: media.height,
+            original_name: media.original_name,
+            created_at: media.created_at,
+            processed: media.processed_at.is_some(),
+            variants: variants
+                .into_iter()
+                .filter_map(|variant| VariantResponse::new(pid, variant))
+                .collect(),
+        }
+    }
+}
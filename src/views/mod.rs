@@ -1 +1,12 @@
+pub mod audit;
 pub mod auth;
+pub mod invites;
+pub mod users;
+pub mod notifications;
+pub mod posts;
+pub mod comments;
+pub mod moderation;
+pub mod reports;
+pub mod bookmarks;
+pub mod dashboard;
+pub mod media;
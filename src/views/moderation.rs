@@ -0,0 +1,7 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ModerationResponse {
+    /// The comments whose status changed
+    pub updated: Vec<i32>,
+}
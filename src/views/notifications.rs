@@ -0,0 +1,63 @@
+use sea_orm::prelude::DateTimeWithTimeZone;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    controllers::post::PaginatedResponse,
+    models::{
+        notifications,
+        users::{self, NotificationPreference},
+    },
+};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PreferencesResponse {
+    /// How the user hears about comments on their posts and replies
+    pub comment_emails: NotificationPreference,
+}
+
+impl PreferencesResponse {
+    #[must_use]
+    pub fn new(user: &users::Model) -> Self {
+        Self {
+            comment_emails: user.notification_preference(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NotificationResponse {
+    pub id: i32,
+    pub kind: String,
+    pub actor_id: Option<String>,
+    pub post_id: Option<i32>,
+    pub comment_id: Option<i32>,
+    pub detail: Option<String>,
+    pub read: bool,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+impl From<notifications::Model> for NotificationResponse {
+    fn from(notification: notifications::Model) -> Self {
+        Self {
+            id: notification.id,
+            kind: notification.kind,
+            actor_id: notification.actor_id.map(|pid| pid.to_string()),
+            post_id: notification.post_id,
+            comment_id: notification.comment_id,
+            detail: notification.detail,
+            read: notification.read_at.is_some(),
+            created_at: notification.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UnreadCountResponse {
+    pub unread_count: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InboxResponse {
+    pub unread_count: u64,
+    pub notifications: PaginatedResponse<NotificationResponse>,
+}
@@ -0,0 +1,62 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::models::{_entities::posts, mentions, reactions::ReactionSummary};
+
+/// A post with its content rendered for display
+#[derive(Debug, Serialize)]
+pub struct PostResponse {
+    #[serde(flatten)]
+    pub post: posts::Model,
+    /// The content with mentions of known handles linked to their profile
+    pub rendered_content: Option<String>,
+    pub reactions: ReactionSummary,
+    /// Whether the current user bookmarked the post, only for signed in users
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bookmarked: Option<bool>,
+    pub cover_url: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl PostResponse {
+    #[must_use]
+    pub fn new(post: posts::Model, known_handles: &HashSet<String>) -> Self {
+        let rendered_content = post
+            .content
+            .as_deref()
+            .map(|content| mentions::link(content, known_handles));
+        Self {
+            post,
+            rendered_content,
+            reactions: ReactionSummary::default(),
+            bookmarked: None,
+            cover_url: None,
+            tags: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_reactions(mut self, reactions: ReactionSummary) -> Self {
+        self.reactions = reactions;
+        self
+    }
+
+    #[must_use]
+    pub fn with_bookmarked(mut self, bookmarked: Option<bool>) -> Self {
+        self.bookmarked = bookmarked;
+        self
+    }
+
+    #[must_use]
+    pub fn with_cover_url(mut self, cover_url: Option<String>) -> Self {
+        self.cover_url = cover_url;
+        self
+    }
+
+    #[must_use]
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+}
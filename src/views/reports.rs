@@ -0,0 +1,41 @@
+use sea_orm::prelude::DateTimeWithTimeZone;
+use serde::{Deserialize, Serialize};
+
+use crate::models::reports;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReportResponse {
+    pub id: i32,
+    pub reporter_id: String,
+    pub target_type: String,
+    pub target_id: i32,
+    pub reason: String,
+    pub details: Option<String>,
+    pub status: String,
+    pub created_at: DateTimeWithTimeZone,
+    pub resolved_at: Option<DateTimeWithTimeZone>,
+    pub resolution_note: Option<String>,
+}
+
+impl From<reports::Model> for ReportResponse {
+    fn from(report: reports::Model) -> Self {
+        Self {
+            id: report.id,
+            reporter_id: report.reporter_id.to_string(),
+            target_type: report.target_type,
+            target_id: report.target_id,
+            reason: report.reason,
+            details: report.details,
+            status: report.status,
+            created_at: report.created_at,
+            resolved_at: report.resolved_at,
+            resolution_note: report.resolution_note,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ResolveResponse {
+    /// How many open reports on the content were settled
+    pub settled: u64,
+}
@@ -0,0 +1,66 @@
+use sea_orm::prelude::DateTimeWithTimeZone;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    controllers::post::{PaginatedResponse, PostListItem},
+    models::{_entities::users, follows::FollowCounts},
+};
+
+/// Public view of a user. Never carries the email, api key or any token.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProfileResponse {
+    pub pid: String,
+    pub name: String,
+    pub handle: Option<String>,
+    pub bio: Option<String>,
+    pub website: Option<String>,
+    pub avatar_url: Option<String>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+impl ProfileResponse {
+    #[must_use]
+    pub fn new(user: &users::Model) -> Self {
+        Self {
+            pid: user.pid.to_string(),
+            name: user.name.clone(),
+            handle: user.handle.clone(),
+            bio: user.bio.clone(),
+            website: user.website.clone(),
+            avatar_url: user.avatar_url.clone(),
+            created_at: user.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserPageResponse {
+    pub profile: ProfileResponse,
+    pub followers: u64,
+    pub following: u64,
+    /// Whether the current user follows the author, only for signed in users
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub followed: Option<bool>,
+    pub posts: PaginatedResponse<PostListItem>,
+}
+
+impl UserPageResponse {
+    #[must_use]
+    pub fn new(user: &users::Model, posts: PaginatedResponse<PostListItem>) -> Self {
+        Self {
+            profile: ProfileResponse::new(user),
+            followers: 0,
+            following: 0,
+            followed: None,
+            posts,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_follows(mut self, counts: FollowCounts, followed: Option<bool>) -> Self {
+        self.followers = counts.followers;
+        self.following = counts.following;
+        self.followed = followed;
+        self
+    }
+}
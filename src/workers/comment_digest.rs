@@ -0,0 +1,114 @@
+//! Daily digest of the comments a user was not emailed about one by one.
+use std::collections::{HashMap, HashSet};
+
+use chrono::{offset::Local, Duration};
+use loco_rs::prelude::*;
+use sea_orm::QuerySelect;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    mailers::notification::{excerpt, CommentNotice, DigestEntry, NotificationMailer},
+    models::{
+        _entities::{comments, posts},
+        users::{self, NotificationPreference},
+    },
+    workers::comment_notification::UNKNOWN_COMMENTER,
+};
+
+/// How far back the first digest of a user goes
+const FIRST_DIGEST_WINDOW: Duration = Duration::days(1);
+
+pub struct CommentDigestWorker {
+    pub ctx: AppContext,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+pub struct CommentDigestWorkerArgs {
+    pub user_guid: String,
+}
+
+#[async_trait]
+impl BackgroundWorker<CommentDigestWorkerArgs> for CommentDigestWorker {
+    fn build(ctx: &AppContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+    async fn perform(&self, args: CommentDigestWorkerArgs) -> Result<()> {
+        let db = &self.ctx.db;
+        let Ok(user) = users::Model::find_by_pid(db, &args.user_guid).await else {
+            return Ok(());
+        };
+        if user.notification_preference() != NotificationPreference::DailyDigest {
+            return Ok(());
+        }
+
+        let now = Local::now();
+        let since = user
+            .digest_sent_at
+            .unwrap_or_else(|| (now - FIRST_DIGEST_WINDOW).into());
+        let comments = comments::Model::find_for_user_since(db, user.pid, since).await?;
+
+        if !comments.is_empty() {
+            let post_ids: HashSet<i32> = comments.iter().filter_map(|c| c.post_id).collect();
+            let titles: HashMap<i32, Option<String>> = posts::Entity::find()
+                .select_only()
+                .column(posts::Column::Id)
+                .column(posts::Column::Title)
+                .filter(posts::Column::Id.is_in(post_ids))
+                .into_tuple::<(i32, Option<String>)>()
+                .all(db)
+                .await?
+                .into_iter()
+                .collect();
+            let parent_ids: HashSet<i32> = comments.iter().filter_map(|c| c.parent_id).collect();
+            let own_parents: HashSet<i32> = comments::Entity::find()
+                .select_only()
+                .column(comments::Column::Id)
+                .filter(comments::Column::Id.is_in(parent_ids))
+                .filter(comments::Column::UserId.eq(user.pid))
+                .into_tuple::<i32>()
+                .all(db)
+                .await?
+                .into_iter()
+                .collect();
+            let commenter_ids: HashSet<Uuid> = comments.iter().map(|c| c.user_id).collect();
+            let names: HashMap<Uuid, String> = users::Entity::find()
+                .filter(users::users::Column::Pid.is_in(commenter_ids))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|commenter| (commenter.pid, commenter.name))
+                .collect();
+
+            let entries: Vec<DigestEntry> = comments
+                .iter()
+                .map(|comment| DigestEntry {
+                    notice: if comment
+                        .parent_id
+                        .is_some_and(|parent_id| own_parents.contains(&parent_id))
+                    {
+                        CommentNotice::Reply
+                    } else {
+                        CommentNotice::NewComment
+                    },
+                    commenter_name: names
+                        .get(&comment.user_id)
+                        .cloned()
+                        .unwrap_or_else(|| UNKNOWN_COMMENTER.to_string()),
+                    post_id: comment.post_id,
+                    post_title: comment
+                        .post_id
+                        .and_then(|id| titles.get(&id).cloned().flatten())
+                        .unwrap_or_else(|| "Untitled".to_string()),
+                    comment_id: comment.id,
+                    excerpt: excerpt(comment),
+                })
+                .collect();
+            NotificationMailer::send_digest(&self.ctx, &user, &entries).await?;
+        }
+
+        user.into_active_model()
+            .set_digest_sent(db, now.into())
+            .await?;
+        Ok(())
+    }
+}
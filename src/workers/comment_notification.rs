@@ -0,0 +1,111 @@
+//! Tells the post author and the parent comment author about a new comment:
+//! always in their inbox, by email for those who want immediate notifications.
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    mailers::notification::{CommentNotice, NotificationMailer},
+    models::{
+        _entities::{comments, posts},
+        comments::CommentStatus,
+        notifications::{NewNotification, NotificationKind},
+        users::{self, NotificationPreference},
+    },
+};
+
+pub struct CommentNotificationWorker {
+    pub ctx: AppContext,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+pub struct CommentNotificationWorkerArgs {
+    pub comment_id: i32,
+}
+
+/// Shown for comment authors whose account is gone, also by the digest
+pub const UNKNOWN_COMMENTER: &str = "Someone";
+
+/// Display name of a comment author
+async fn commenter_name(db: &DatabaseConnection, comment: &comments::Model) -> String {
+    users::Model::find_by_pid(db, &comment.user_id.to_string())
+        .await
+        .map_or_else(|_| UNKNOWN_COMMENTER.to_string(), |user| user.name)
+}
+
+/// Users to tell about the comment: the author of the comment replied to, then
+/// the post author. The commenter is never told about their own comment and
+/// nobody is told twice.
+async fn recipients(
+    db: &DatabaseConnection,
+    comment: &comments::Model,
+    post: &posts::Model,
+) -> Result<Vec<(Uuid, CommentNotice)>> {
+    let mut recipients = Vec::new();
+    if let Some(parent_id) = comment.parent_id {
+        if let Some(parent) = comments::Entity::find_by_id(parent_id).one(db).await? {
+            recipients.push((parent.user_id, CommentNotice::Reply));
+        }
+    }
+    if let Some(author) = post.user_id {
+        recipients.push((author, CommentNotice::NewComment));
+    }
+
+    let mut seen = vec![comment.user_id];
+    recipients.retain(|(user_id, _)| {
+        if seen.contains(user_id) {
+            false
+        } else {
+            seen.push(*user_id);
+            true
+        }
+    });
+    Ok(recipients)
+}
+
+#[async_trait]
+impl BackgroundWorker<CommentNotificationWorkerArgs> for CommentNotificationWorker {
+    fn build(ctx: &AppContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+    async fn perform(&self, args: CommentNotificationWorkerArgs) -> Result<()> {
+        let db = &self.ctx.db;
+        let Some(comment) = comments::Entity::find_by_id(args.comment_id)
+            .one(db)
+            .await?
+        else {
+            tracing::info!(comment_id = args.comment_id, "comment gone, not notifying");
+            return Ok(());
+        };
+        if comment.status() != CommentStatus::Approved {
+            return Ok(());
+        }
+        let Some(post) = comment.post(db).await? else {
+            return Ok(());
+        };
+
+        let commenter = commenter_name(db, &comment).await;
+        for (user_id, notice) in recipients(db, &comment, &post).await? {
+            let kind = match notice {
+                CommentNotice::NewComment => NotificationKind::Comment,
+                CommentNotice::Reply => NotificationKind::Reply,
+            };
+            NewNotification::new(user_id, kind)
+                .actor(comment.user_id)
+                .post(comment.post_id)
+                .comment(comment.id)
+                .send(db)
+                .await?;
+
+            let Ok(user) = users::Model::find_by_pid(db, &user_id.to_string()).await else {
+                continue;
+            };
+            if user.notification_preference() != NotificationPreference::Immediate {
+                continue;
+            }
+            NotificationMailer::send_comment(&self.ctx, &user, notice, &comment, &post, &commenter)
+                .await?;
+        }
+
+        Ok(())
+    }
+}
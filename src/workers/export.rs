@@ -0,0 +1,163 @@
+//! Personal data export. Builds a ZIP archive with the user's profile, posts
+//! and comments as JSON and Markdown, stores it and emails the user a link to
+//! download it. Archives expire after the `export_ttl_hours` of the
+//! `accounts` settings.
+use std::{
+    io::{Cursor, Write},
+    path::PathBuf,
+};
+
+use loco_rs::prelude::*;
+use sea_orm::QueryOrder;
+use serde::{Deserialize, Serialize};
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+use crate::{
+    mailers::auth::AuthMailer,
+    models::{
+        _entities::{comments, posts},
+        exports, users,
+    },
+};
+
+pub struct ExportWorker {
+    pub ctx: AppContext,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+pub struct ExportWorkerArgs {
+    pub user_guid: String,
+}
+
+/// Storage location of an export archive
+#[must_use]
+pub fn archive_path(user_guid: &str, token: &str) -> PathBuf {
+    PathBuf::from("exports")
+        .join(user_guid)
+        .join(format!("{token}.zip"))
+}
+
+#[derive(Serialize)]
+struct ExportedProfile<'a> {
+    pid: String,
+    name: &'a str,
+    email: &'a str,
+    handle: Option<&'a str>,
+    bio: Option<&'a str>,
+    website: Option<&'a str>,
+    avatar_url: Option<&'a str>,
+    created_at: DateTimeWithTimeZone,
+    email_verified_at: Option<DateTimeWithTimeZone>,
+}
+
+impl<'a> From<&'a users::Model> for ExportedProfile<'a> {
+    fn from(user: &'a users::Model) -> Self {
+        Self {
+            pid: user.pid.to_string(),
+            name: &user.name,
+            email: &user.email,
+            handle: user.handle.as_deref(),
+            bio: user.bio.as_deref(),
+            website: user.website.as_deref(),
+            avatar_url: user.avatar_url.as_deref(),
+            created_at: user.created_at,
+            email_verified_at: user.email_verified_at,
+        }
+    }
+}
+
+fn post_markdown(post: &posts::Model) -> String {
+    let mut md = format!("# {}\n\n", post.title.as_deref().unwrap_or("Untitled"));
+    if let Some(published_at) = post.published_at {
+        md.push_str(&format!("_Published {}_\n\n", published_at.to_rfc3339()));
+    }
+    if let Some(summary) = post.summary.as_deref().filter(|s| !s.is_empty()) {
+        md.push_str(&format!("> {summary}\n\n"));
+    }
+    md.push_str(post.content.as_deref().unwrap_or_default());
+    md.push('\n');
+    md
+}
+
+fn comments_markdown(comments: &[comments::Model]) -> String {
+    let mut md = String::from("# Comments\n");
+    for comment in comments {
+        md.push_str(&format!(
+            "\n## On post {} at {}\n\n{}\n",
+            comment
+                .post_id
+                .map_or_else(|| "-".to_string(), |id| id.to_string()),
+            comment.created_at.to_rfc3339(),
+            comment.content.as_deref().unwrap_or_default()
+        ));
+    }
+    md
+}
+
+fn build_archive(
+    user: &users::Model,
+    posts: &[posts::Model],
+    comments: &[comments::Model],
+) -> Result<Vec<u8>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+
+    let mut add = |name: &str, content: &[u8]| -> Result<()> {
+        zip.start_file(name, options).map_err(Box::from)?;
+        zip.write_all(content)?;
+        Ok(())
+    };
+
+    add(
+        "profile.json",
+        &serde_json::to_vec_pretty(&ExportedProfile::from(user))?,
+    )?;
+    add("posts.json", &serde_json::to_vec_pretty(posts)?)?;
+    add("comments.json", &serde_json::to_vec_pretty(comments)?)?;
+    for post in posts {
+        let slug = post.slug.as_deref().unwrap_or("post");
+        add(
+            &format!("posts/{}-{slug}.md", post.id),
+            post_markdown(post).as_bytes(),
+        )?;
+    }
+    add("comments.md", comments_markdown(comments).as_bytes())?;
+
+    Ok(zip.finish().map_err(Box::from)?.into_inner())
+}
+
+#[async_trait]
+impl BackgroundWorker<ExportWorkerArgs> for ExportWorker {
+    fn build(ctx: &AppContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+    async fn perform(&self, args: ExportWorkerArgs) -> Result<()> {
+        let Ok(user) = users::Model::find_by_pid(&self.ctx.db, &args.user_guid).await else {
+            tracing::info!(pid = args.user_guid, "export skipped, user not found");
+            return Ok(());
+        };
+
+        let posts = posts::Entity::find()
+            .filter(posts::Column::UserId.eq(user.pid))
+            .order_by_asc(posts::Column::Id)
+            .all(&self.ctx.db)
+            .await?;
+        let comments = comments::Entity::find()
+            .filter(comments::Column::UserId.eq(user.pid))
+            .order_by_asc(comments::Column::Id)
+            .all(&self.ctx.db)
+            .await?;
+
+        let archive = build_archive(&user, &posts, &comments)?;
+
+        let token = Uuid::new_v4();
+        let path = archive_path(&args.user_guid, &token.to_string());
+        self.ctx.storage.upload(&path, &archive.into()).await?;
+        exports::Model::create(&self.ctx.db, user.pid, token, &path).await?;
+
+        AuthMailer::send_export_ready(&self.ctx, &user, &token.to_string()).await?;
+        tracing::info!(pid = args.user_guid, "user data exported");
+
+        Ok(())
+    }
+}
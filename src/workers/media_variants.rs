@@ -0,0 +1,100 @@
+//! Resized variants of uploaded images, see [`crate::common::images`]. Each
+//! size is built in WebP and in the format of the upload, without metadata.
+//! Images are never scaled up: sizes the upload already fits in are built at
+//! its own size, and only once.
+use std::path::PathBuf;
+
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::images::{self, VARIANT_SIZES},
+    models::{
+        media::{self, ImageType},
+        media_variants::{self, NewVariant},
+    },
+};
+
+pub struct MediaVariantsWorker {
+    pub ctx: AppContext,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+pub struct MediaVariantsWorkerArgs {
+    pub media_pid: String,
+}
+
+/// Storage location of a variant
+#[must_use]
+pub fn variant_path(pid: &Uuid, name: &str, format: ImageType) -> PathBuf {
+    PathBuf::from("media")
+        .join(pid.to_string())
+        .join(format!("{name}.{}", format.extension()))
+}
+
+#[async_trait]
+impl BackgroundWorker<MediaVariantsWorkerArgs> for MediaVariantsWorker {
+    fn build(ctx: &AppContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+    async fn perform(&self, args: MediaVariantsWorkerArgs) -> Result<()> {
+        let Ok(pid) = Uuid::parse_str(&args.media_pid) else {
+            return Ok(());
+        };
+        let Ok(media) = media::Model::find_by_pid(&self.ctx.db, pid).await else {
+            tracing::info!(pid = args.media_pid, "variants skipped, media not found");
+            return Ok(());
+        };
+
+        let content: Vec<u8> = self
+            .ctx
+            .storage
+            .download(&PathBuf::from(&media.path))
+            .await?;
+        let Some(original) = ImageType::sniff(&content) else {
+            tracing::warn!(pid = args.media_pid, "variants skipped, not an image");
+            return Ok(());
+        };
+        let image = images::decode(&content).map_err(Box::from)?;
+
+        let mut formats = vec![ImageType::Webp];
+        if original != ImageType::Webp {
+            formats.push(original);
+        }
+        let mut variants = Vec::new();
+        let mut built = Vec::new();
+        for size in VARIANT_SIZES {
+            let resized = images::fit(&image, size);
+            let dimensions = (resized.width(), resized.height());
+            if built.contains(&dimensions) {
+                continue;
+            }
+            built.push(dimensions);
+            for &format in &formats {
+                let encoded = images::encode(&resized, format).map_err(Box::from)?;
+                let encoded_size = encoded.len();
+                let path = variant_path(&media.pid, size.name, format);
+                self.ctx.storage.upload(&path, &encoded.into()).await?;
+                variants.push(NewVariant {
+                    name: size.name,
+                    format,
+                    width: dimensions.0,
+                    height: dimensions.1,
+                    size: encoded_size,
+                    path: path.to_string_lossy().to_string(),
+                });
+            }
+        }
+
+        let count = variants.len();
+        let stale = media_variants::Model::replace(&self.ctx.db, media.id, variants).await?;
+        for variant in stale {
+            if let Err(err) = self.ctx.storage.delete(&PathBuf::from(&variant.path)).await {
+                tracing::warn!(err = err.to_string(), "could not delete stale variant");
+            }
+        }
+        tracing::info!(pid = args.media_pid, count, "media variants built");
+
+        Ok(())
+    }
+}
@@ -1 +1,4 @@
-pub mod downloader;
+pub mod export;
+pub mod comment_digest;
+pub mod comment_notification;
+pub mod media_variants;
@@ -0,0 +1,135 @@
+use chrono::{offset::Local, Duration};
+use loco_rs::{model::ModelError, testing};
+use myapp::{
+    app::App,
+    models::{
+        invites::{self, CreateParams},
+        users::{self, RegisterParams},
+    },
+};
+use sea_orm::{ActiveModelTrait, ActiveValue, IntoActiveModel};
+use serial_test::serial;
+use uuid::Uuid;
+
+fn register_params(email: &str, invite_code: &str) -> RegisterParams {
+    RegisterParams {
+        email: email.to_string(),
+        password: "1234".to_string(),
+        name: "invited".to_string(),
+        invite_code: Some(invite_code.to_string()),
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn can_redeem_invite_up_to_its_limit() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+
+    let invite = invites::Model::create(
+        db,
+        Uuid::new_v4(),
+        &CreateParams {
+            max_uses: Some(2),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert!(invite.code.starts_with("inv-"));
+
+    for email in ["one@example.com", "two@example.com"] {
+        users::Model::create_with_password(db, &register_params(email, &invite.code))
+            .await
+            .unwrap();
+    }
+
+    let res =
+        users::Model::create_with_password(db, &register_params("three@example.com", &invite.code))
+            .await;
+    assert!(matches!(res, Err(ModelError::EntityNotFound)));
+    assert!(users::Model::find_by_email(db, "three@example.com")
+        .await
+        .is_err());
+
+    let invite = invites::Model::find_by_code(db, &invite.code)
+        .await
+        .unwrap();
+    assert_eq!(invite.uses, 2);
+}
+
+#[tokio::test]
+#[serial]
+async fn cannot_redeem_unusable_invites() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+    let admin = Uuid::new_v4();
+
+    let preassigned = invites::Model::create(
+        db,
+        admin,
+        &CreateParams {
+            email: Some("Invited@Example.com".to_string()),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert!(!preassigned.is_usable_by("other@example.com"));
+    assert!(preassigned.is_usable_by("invited@example.com"));
+    assert!(
+        invites::Model::redeem(db, &preassigned.code, "other@example.com")
+            .await
+            .is_err()
+    );
+
+    let expired = invites::Model::create(
+        db,
+        admin,
+        &CreateParams {
+            expires_at: Some((Local::now() - Duration::hours(1)).into()),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert!(!expired.is_usable_by("any@example.com"));
+    assert!(invites::Model::redeem(db, &expired.code, "any@example.com")
+        .await
+        .is_err());
+
+    let revoked = invites::Model::create(db, admin, &CreateParams::default())
+        .await
+        .unwrap()
+        .into_active_model()
+        .revoke(db)
+        .await
+        .unwrap();
+    assert!(!revoked.is_usable_by("any@example.com"));
+    assert!(invites::Model::redeem(db, &revoked.code, "any@example.com")
+        .await
+        .is_err());
+
+    assert!(
+        invites::Model::redeem(db, &preassigned.code, "INVITED@example.com")
+            .await
+            .is_ok()
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn cannot_create_invite_without_uses() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+
+    let res = invites::ActiveModel {
+        code: ActiveValue::set("inv-test".to_string()),
+        max_uses: ActiveValue::set(0),
+        expires_at: ActiveValue::set(Local::now().into()),
+        created_by: ActiveValue::set(Uuid::new_v4()),
+        ..Default::default()
+    }
+    .insert(&boot.app_context.db)
+    .await;
+    assert!(res.is_err());
+}
@@ -0,0 +1,58 @@
+use myapp::common::markdown;
+
+const POST: &str = "# Getting started
+
+Install the **CLI** with `cargo install` and read [the guide](https://example.com/guide).
+It only takes a _minute_.
+
+![A screenshot](/media/shot.png)
+
+```sh
+cargo install loco
+```
+
+> Quoted words count too
+
+- first item
+- second item
+";
+
+#[test]
+fn can_count_words() {
+    // heading 2, paragraph 15, quote 4, list 4, code 3
+    assert_eq!(markdown::word_count(POST), 28);
+    assert_eq!(markdown::word_count(""), 0);
+}
+
+#[test]
+fn can_estimate_reading_time() {
+    assert_eq!(markdown::reading_time(0), 0);
+    assert_eq!(markdown::reading_time(1), 1);
+    assert_eq!(markdown::reading_time(200), 1);
+    assert_eq!(markdown::reading_time(201), 2);
+}
+
+#[test]
+fn can_summarize_first_paragraphs() {
+    assert_eq!(
+        markdown::summary(POST, 200).as_deref(),
+        Some(
+            "Install the CLI with cargo install and read the guide. It only takes a minute. \
+             Quoted words count too first item second item"
+        )
+    );
+    assert_eq!(
+        markdown::summary("# Title only\n\n```\ncode\n```", 200),
+        None
+    );
+}
+
+#[test]
+fn can_cut_summary_at_word_boundary() {
+    let summary = markdown::summary("The quick brown fox jumps over the lazy dog", 20).unwrap();
+    assert_eq!(summary, "The quick brown fox…");
+    assert!(summary.chars().count() <= 20);
+
+    let summary = markdown::summary("Supercalifragilistic words, here", 28).unwrap();
+    assert_eq!(summary, "Supercalifragilistic words…");
+}
@@ -0,0 +1,76 @@
+use std::collections::HashSet;
+
+use loco_rs::testing;
+use myapp::{
+    app::App,
+    models::{
+        mentions::{self, Source},
+        notifications,
+    },
+};
+use sea_orm::PaginatorTrait;
+use serial_test::serial;
+use uuid::Uuid;
+
+const USER1_PID: &str = "11111111-1111-1111-1111-111111111111";
+const USER2_PID: &str = "22222222-2222-2222-2222-222222222222";
+
+#[test]
+fn can_parse_handles() {
+    assert_eq!(
+        mentions::handles("Thanks @User1 and @user2, cc @user1. Mail me at me@user1.com"),
+        vec!["user1".to_string(), "user2".to_string()]
+    );
+    // too short, too long and doubled up
+    assert!(mentions::handles(&format!("@ab @{} @@user1", "a".repeat(31))).is_empty());
+}
+
+#[test]
+fn can_link_known_handles_only() {
+    let known = HashSet::from(["user1".to_string()]);
+    assert_eq!(
+        mentions::link("Hi @User1 and @nobody!", &known),
+        "Hi [@User1](/users/user1) and @nobody!"
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn can_sync_mentions_without_renotifying() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+    let user1 = Uuid::parse_str(USER1_PID).unwrap();
+    let user2 = Uuid::parse_str(USER2_PID).unwrap();
+    let source = Source::Comment {
+        id: 1,
+        post_id: Some(1),
+    };
+    let author = Some(Uuid::new_v4());
+
+    let added = mentions::Model::sync(db, source, Some("Hey @user1 and @ghost"), author)
+        .await
+        .unwrap();
+    assert_eq!(added, vec![user1]);
+
+    // editing keeps user1 without telling them again, and adds user2
+    let added = mentions::Model::sync(db, source, Some("Hey @user1 and @user2"), author)
+        .await
+        .unwrap();
+    assert_eq!(added, vec![user2]);
+    for user_id in [user1, user2] {
+        let inbox = notifications::Model::inbox(user_id, true);
+        assert_eq!(inbox.count(db).await.unwrap(), 1);
+    }
+
+    // dropping a mention removes it, mentioning again does not notify again
+    mentions::Model::sync(db, source, Some("Hey @user2"), author)
+        .await
+        .unwrap();
+    let added = mentions::Model::sync(db, source, Some("Hey @user1 @user2"), author)
+        .await
+        .unwrap();
+    assert_eq!(added, vec![user1]);
+    let inbox = notifications::Model::inbox(user1, true);
+    assert_eq!(inbox.count(db).await.unwrap(), 1);
+}
@@ -1,4 +1,9 @@
 mod users;
 
 mod posts;
-mod comments;
\ No newline at end of file
+mod comments;
+mod invites;
+mod markdown;
+mod mentions;
+mod spam;
+mod reactions;
@@ -0,0 +1,79 @@
+use futures_util::future::join_all;
+use loco_rs::testing;
+use myapp::{
+    app::App,
+    common::settings::ReactionSettings,
+    models::{
+        _entities::comments,
+        reactions::{self, ReactionTarget},
+    },
+};
+use sea_orm::{ActiveModelTrait, ActiveValue, EntityTrait, PaginatorTrait};
+use serial_test::serial;
+use uuid::Uuid;
+
+#[tokio::test]
+#[serial]
+async fn can_keep_counts_under_concurrent_toggles() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+    let settings = ReactionSettings::default();
+    let comment = comments::ActiveModel {
+        content: ActiveValue::set(Some("Popular".to_string())),
+        user_id: ActiveValue::set(Uuid::new_v4()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    // ten users react at once, and three of them change their mind at once
+    let users: Vec<Uuid> = (0..10).map(|_| Uuid::new_v4()).collect();
+    let toggle = |user: Uuid| {
+        let settings = &settings;
+        async move {
+            reactions::Model::toggle(
+                db,
+                settings,
+                user,
+                ReactionTarget::Comment,
+                comment.id,
+                "👍",
+            )
+            .await
+            .unwrap()
+        }
+    };
+    let reacted = join_all(users.iter().map(|user| toggle(*user))).await;
+    assert!(reacted.iter().all(|reacted| *reacted));
+    let reacted = join_all(users[..3].iter().map(|user| toggle(*user))).await;
+    assert!(reacted.iter().all(|reacted| !*reacted));
+
+    let rows = reactions::Entity::find().count(db).await.unwrap();
+    let comment = comments::Entity::find_by_id(comment.id)
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(rows, 7);
+    assert_eq!(comment.reaction_count, 7);
+
+    let summary =
+        reactions::Model::summary(db, ReactionTarget::Comment, comment.id, Some(users[5]))
+            .await
+            .unwrap();
+    assert_eq!(summary.counts.get("👍"), Some(&7));
+    assert_eq!(summary.mine, vec!["👍".to_string()]);
+
+    assert!(reactions::Model::toggle(
+        db,
+        &settings,
+        users[0],
+        ReactionTarget::Comment,
+        comment.id,
+        "🦀",
+    )
+    .await
+    .is_err());
+}
@@ -0,0 +1,122 @@
+use loco_rs::testing;
+use myapp::{
+    app::App,
+    common::{
+        settings::SpamSettings,
+        spam::{Candidate, SpamFilter, Verdict},
+    },
+    models::{_entities::comments, spam_tokens},
+};
+use sea_orm::{ActiveModelTrait, ActiveValue, DatabaseConnection};
+use serial_test::serial;
+use uuid::Uuid;
+
+async fn verdict(
+    filter: &SpamFilter,
+    db: &DatabaseConnection,
+    content: &str,
+    user_id: Uuid,
+) -> Verdict {
+    let candidate = Candidate {
+        content,
+        user_id,
+        post_id: Some(1),
+    };
+    filter.check(db, &candidate).await.unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn can_score_with_heuristics() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+    let filter = SpamFilter::from_settings(&SpamSettings {
+        blocked_words: vec!["Casino".to_string()],
+        blocked_domains: vec!["spam.example".to_string()],
+        ..Default::default()
+    });
+    let user_id = Uuid::new_v4();
+
+    let links = "http://a.example http://b.example https://c.example";
+    assert_eq!(
+        verdict(&filter, db, "Great post, thanks", user_id).await,
+        Verdict::Accept
+    );
+    assert_eq!(
+        verdict(&filter, db, "Visit the best casino online", user_id).await,
+        Verdict::Reject
+    );
+    assert_eq!(
+        verdict(&filter, db, "See https://www.spam.example/offer", user_id).await,
+        Verdict::Reject
+    );
+    assert_eq!(
+        verdict(&filter, db, links, user_id).await,
+        Verdict::Moderate
+    );
+
+    // posting the same thing again
+    let content = "This is a long enough comment to be a copy";
+    comments::ActiveModel {
+        content: ActiveValue::set(Some(content.to_string())),
+        user_id: ActiveValue::set(user_id),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    assert_eq!(
+        verdict(&filter, db, content, user_id).await,
+        Verdict::Reject
+    );
+    assert_eq!(
+        verdict(&filter, db, content, Uuid::new_v4()).await,
+        Verdict::Moderate
+    );
+
+    // nothing is scored when disabled
+    let disabled = SpamFilter::from_settings(&SpamSettings {
+        enabled: false,
+        blocked_words: vec!["casino".to_string()],
+        ..Default::default()
+    });
+    assert_eq!(
+        verdict(&disabled, db, "casino", user_id).await,
+        Verdict::Accept
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn can_learn_spam_from_decisions() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+
+    assert_eq!(
+        spam_tokens::Model::spam_probability(db, "cheap pills")
+            .await
+            .unwrap(),
+        None
+    );
+    for i in 0..5 {
+        spam_tokens::Model::train(db, &format!("Buy cheap pills now, offer {i}"), true)
+            .await
+            .unwrap();
+        spam_tokens::Model::train(db, &format!("Nice write-up about rust {i}"), false)
+            .await
+            .unwrap();
+    }
+
+    let spam = spam_tokens::Model::spam_probability(db, "cheap pills here")
+        .await
+        .unwrap()
+        .unwrap();
+    let ham = spam_tokens::Model::spam_probability(db, "a rust write-up")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(spam > 0.9, "{spam}");
+    assert!(ham < 0.1, "{ham}");
+}
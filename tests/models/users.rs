@@ -2,7 +2,7 @@ use insta::assert_debug_snapshot;
 use loco_rs::{model::ModelError, testing};
 use myapp::{
     app::App,
-    models::users::{self, Model, RegisterParams},
+    models::users::{self, Model, ProfileParams, RegisterParams},
 };
 use sea_orm::{ActiveModelTrait, ActiveValue, IntoActiveModel};
 use serial_test::serial;
@@ -45,6 +45,7 @@ async fn can_create_with_password() {
         email: "test@framework.com".to_string(),
         password: "1234".to_string(),
         name: "framework".to_string(),
+        invite_code: None,
     };
     let res = Model::create_with_password(&boot.app_context.db, &params).await;
 
@@ -69,6 +70,7 @@ async fn handle_create_with_password_with_duplicate() {
             email: "user1@example.com".to_string(),
             password: "1234".to_string(),
             name: "framework".to_string(),
+            invite_code: None,
         },
     )
     .await;
@@ -221,3 +223,105 @@ async fn can_reset_password() {
             .verify_password("new-password")
     );
 }
+
+#[tokio::test]
+#[serial]
+async fn can_find_by_handle() {
+    configure_insta!();
+
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+
+    let existing_user = Model::find_by_handle(&boot.app_context.db, "User1").await;
+    let non_existing_user_results = Model::find_by_handle(&boot.app_context.db, "nobody").await;
+
+    assert_eq!(existing_user.unwrap().email, "user1@example.com");
+    assert!(matches!(
+        non_existing_user_results,
+        Err(ModelError::EntityNotFound)
+    ));
+}
+
+#[tokio::test]
+#[serial]
+async fn can_update_profile() {
+    configure_insta!();
+
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+
+    let user = Model::find_by_pid(&boot.app_context.db, "11111111-1111-1111-1111-111111111111")
+        .await
+        .unwrap();
+
+    let taken = user
+        .clone()
+        .into_active_model()
+        .update_profile(
+            &boot.app_context.db,
+            &ProfileParams {
+                handle: Some("user2".to_string()),
+                ..Default::default()
+            },
+        )
+        .await;
+    assert!(matches!(taken, Err(ModelError::EntityAlreadyExists)));
+
+    let invalid = user
+        .clone()
+        .into_active_model()
+        .update_profile(
+            &boot.app_context.db,
+            &ProfileParams {
+                handle: Some("no spaces".to_string()),
+                ..Default::default()
+            },
+        )
+        .await;
+    assert!(invalid.is_err());
+
+    let updated = user
+        .into_active_model()
+        .update_profile(
+            &boot.app_context.db,
+            &ProfileParams {
+                handle: Some("new_handle".to_string()),
+                bio: Some(String::new()),
+                avatar_url: Some("https://example.com/me.png".to_string()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(updated.handle.as_deref(), Some("new_handle"));
+    assert_eq!(updated.bio, None);
+    assert_eq!(
+        updated.avatar_url.as_deref(),
+        Some("https://example.com/me.png")
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn can_change_password() {
+    configure_insta!();
+
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+
+    let user = Model::find_by_pid(&boot.app_context.db, "11111111-1111-1111-1111-111111111111")
+        .await
+        .unwrap();
+    assert!(user.accepts_token_issued_at(0));
+
+    let user = user
+        .into_active_model()
+        .change_password(&boot.app_context.db, "new-password")
+        .await
+        .unwrap();
+
+    assert!(user.verify_password("new-password"));
+    assert!(!user.accepts_token_issued_at(0));
+    assert!(user.accepts_token_issued_at(chrono::Utc::now().timestamp_millis()));
+}
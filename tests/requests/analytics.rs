@@ -0,0 +1,127 @@
+use loco_rs::testing;
+use myapp::{app::App, common::analytics, models::_entities::posts};
+use sea_orm::{ActiveModelTrait, ActiveValue};
+use serial_test::serial;
+
+use super::prepare_data;
+
+async fn view(request: &loco_rs::TestServer, path: &str, user_agent: &str, referrer: Option<&str>) {
+    let mut req = request.get(path).add_header(
+        axum::http::header::USER_AGENT,
+        axum::http::HeaderValue::from_str(user_agent).unwrap(),
+    );
+    if let Some(referrer) = referrer {
+        req = req.add_header(
+            axum::http::header::REFERER,
+            axum::http::HeaderValue::from_str(referrer).unwrap(),
+        );
+    }
+    assert_eq!(req.await.status_code(), 200);
+}
+
+#[tokio::test]
+#[serial]
+async fn can_count_post_views() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let author = prepare_data::init_user_login(&request, &ctx).await;
+        let other =
+            prepare_data::init_named_user_login(&request, &ctx, "other", "other@loco.com").await;
+        let post = posts::ActiveModel {
+            title: ActiveValue::set(Some("Popular post".to_string())),
+            slug: ActiveValue::set(Some("popular-post".to_string())),
+            user_id: ActiveValue::set(Some(author.user.pid)),
+            published: ActiveValue::set(Some(true)),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+        let path = format!("/api/posts/{}", post.id);
+
+        // the same visitor counts once within the dedupe window
+        view(&request, &path, "Mozilla/5.0 (reader one)", None).await;
+        view(&request, &path, "Mozilla/5.0 (reader one)", None).await;
+        view(
+            &request,
+            &path,
+            "Mozilla/5.0 (reader two)",
+            Some("https://news.example.com/item?id=1"),
+        )
+        .await;
+        view(
+            &request,
+            "/api/posts/slug/popular-post",
+            "Mozilla/5.0 (reader three)",
+            Some("https://news.example.com/"),
+        )
+        .await;
+        // crawlers and the author don't count
+        view(
+            &request,
+            &path,
+            "Googlebot/2.1 (+http://www.google.com/bot.html)",
+            None,
+        )
+        .await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&author.token);
+        request
+            .get(&path)
+            .add_header(auth_key, auth_value)
+            .add_header(
+                axum::http::header::USER_AGENT,
+                axum::http::HeaderValue::from_static("Mozilla/5.0 (author)"),
+            )
+            .await;
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&author.token);
+        let res = request
+            .get(&format!("{path}/stats?days=7"))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(res.status_code(), 200);
+        let stats: serde_json::Value = serde_json::from_str(&res.text()).unwrap();
+        assert_eq!(stats["views"], 3);
+        assert_eq!(stats["unique_visitors"], 3);
+        let daily = stats["daily"].as_array().unwrap();
+        assert_eq!(daily.len(), 7);
+        assert_eq!(daily[6]["views"], 3);
+        assert_eq!(daily[0]["views"], 0);
+        assert_eq!(
+            stats["referrers"],
+            serde_json::json!([{ "referrer": "news.example.com", "views": 2 }])
+        );
+
+        // written views add up with the ones still buffered
+        analytics::flush(&ctx.db).await.unwrap();
+        view(
+            &request,
+            &path,
+            "Mozilla/5.0 (reader four)",
+            Some("https://news.example.com/"),
+        )
+        .await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&author.token);
+        let res = request
+            .get(&format!("{path}/stats?days=7"))
+            .add_header(auth_key, auth_value)
+            .await;
+        let stats: serde_json::Value = serde_json::from_str(&res.text()).unwrap();
+        assert_eq!(stats["views"], 4);
+        assert_eq!(stats["unique_visitors"], 4);
+        assert_eq!(
+            stats["referrers"],
+            serde_json::json!([{ "referrer": "news.example.com", "views": 3 }])
+        );
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&other.token);
+        let res = request
+            .get(&format!("{path}/stats"))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(res.status_code(), 403);
+        let res = request.get("/api/posts/slug/missing").await;
+        assert_eq!(res.status_code(), 404);
+    })
+    .await;
+}
@@ -0,0 +1,130 @@
+use loco_rs::testing;
+use myapp::{
+    app::App,
+    models::users::{self, Role},
+    views::{
+        audit::AuditEventResponse,
+        auth::{ApiKeyResponse, LoginResponse},
+    },
+};
+use sea_orm::IntoActiveModel;
+use serial_test::serial;
+
+use super::prepare_data;
+
+#[tokio::test]
+#[serial]
+async fn can_list_own_security_events() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+
+        let response = request
+            .post("/api/auth/login")
+            .json(&serde_json::json!({
+                "email": user.user.email,
+                "password": "wrong-password"
+            }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .post("/api/auth/refresh")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let refreshed: LoginResponse = serde_json::from_str(&response.text()).unwrap();
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&refreshed.token);
+        let response = request
+            .post("/api/auth/api-key")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let rotated: ApiKeyResponse = serde_json::from_str(&response.text()).unwrap();
+        assert_ne!(rotated.api_key, user.user.api_key);
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&refreshed.token);
+        let response = request
+            .get("/api/auth/audit")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let events: Vec<AuditEventResponse> = serde_json::from_str(&response.text()).unwrap();
+
+        // most recent first
+        let recorded: Vec<(&str, &str)> = events
+            .iter()
+            .map(|e| (e.event.as_str(), e.outcome.as_str()))
+            .collect();
+        assert_eq!(
+            recorded,
+            vec![
+                ("api_key_rotation", "success"),
+                ("token_refresh", "success"),
+                ("login", "failure"),
+                ("login", "success"),
+                ("verify", "success"),
+                ("register", "success"),
+            ]
+        );
+        assert!(events
+            .iter()
+            .all(|e| e.user_id == Some(user.user.pid.to_string())));
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn admins_can_search_audit_log() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .get("/api/admin/audit")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 403);
+
+        users::Model::find_by_pid(&ctx.db, &user.user.pid.to_string())
+            .await
+            .unwrap()
+            .into_active_model()
+            .set_role(&ctx.db, Role::Admin)
+            .await
+            .unwrap();
+
+        let response = request
+            .post("/api/auth/login")
+            .json(&serde_json::json!({
+                "email": "nobody@loco.com",
+                "password": "1234"
+            }))
+            .await;
+        assert_ne!(response.status_code(), 200);
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .get("/api/admin/audit?event=login&outcome=failure&page_size=5")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["total"], 1);
+        assert_eq!(body["page_size"], 5);
+        assert_eq!(body["items"][0]["user_id"], serde_json::Value::Null);
+        assert_eq!(body["items"][0]["detail"], "nobody@loco.com: unknown email");
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .get(&format!("/api/admin/audit?user_id={}", user.user.pid))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["total"], 3);
+    })
+    .await;
+}
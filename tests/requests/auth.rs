@@ -1,8 +1,24 @@
+use std::path::PathBuf;
+
+use axum::http::{HeaderName, HeaderValue};
+use chrono::Duration;
 use insta::{assert_debug_snapshot, with_settings};
-use loco_rs::testing;
-use myapp::{app::App, models::users};
+use loco_rs::{app::AppContext, boot::run_task, task, testing};
+use myapp::{
+    app::App,
+    models::{
+        _entities::{comments, exports, invites, media, notifications, posts, reports, sessions},
+        users,
+    },
+    views::auth::{LoginResponse, SessionResponse},
+};
 use rstest::rstest;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, EntityTrait, IntoActiveModel, PaginatorTrait,
+    QueryFilter, QueryOrder,
+};
 use serial_test::serial;
+use uuid::Uuid;
 
 use super::prepare_data;
 
@@ -216,3 +232,554 @@ async fn can_get_current_user() {
     })
     .await;
 }
+
+#[tokio::test]
+#[serial]
+async fn can_update_current_user_profile() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .patch("/api/auth/current")
+            .add_header(auth_key, auth_value)
+            .json(&serde_json::json!({
+                "handle": "Loco_Dev",
+                "bio": "Writes about trains",
+                "website": "https://loco.rs",
+            }))
+            .await;
+
+        assert_eq!(response.status_code(), 200);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["handle"], "loco_dev");
+        assert_eq!(body["bio"], "Writes about trains");
+        assert_eq!(body["website"], "https://loco.rs");
+        assert_eq!(body["avatar_url"], serde_json::Value::Null);
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .patch("/api/auth/current")
+            .add_header(auth_key, auth_value)
+            .json(&serde_json::json!({ "website": "not a url" }))
+            .await;
+        assert_eq!(response.status_code(), 400);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_change_password() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .post("/api/auth/change-password")
+            .add_header(auth_key, auth_value)
+            .json(&serde_json::json!({
+                "current_password": "wrong-password",
+                "new_password": "new-password",
+            }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .post("/api/auth/change-password")
+            .add_header(auth_key, auth_value)
+            .json(&serde_json::json!({
+                "current_password": "1234",
+                "new_password": "new-password",
+            }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let login_response: LoginResponse = serde_json::from_str(&response.text()).unwrap();
+
+        // the previous token is revoked, the one returned keeps working
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .get("/api/auth/current")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&login_response.token);
+        let response = request
+            .get("/api/auth/current")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let response = request
+            .post("/api/auth/login")
+            .json(&serde_json::json!({
+                "email": user.user.email,
+                "password": "new-password"
+            }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_change_email() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let new_email = "new@loco.com";
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .post("/api/auth/change-email")
+            .add_header(auth_key, auth_value)
+            .json(&serde_json::json!({
+                "password": "1234",
+                "email": new_email,
+            }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        // nothing changes until the new address confirms
+        let pending = users::Model::find_by_email(&ctx.db, &user.user.email)
+            .await
+            .unwrap();
+        assert_eq!(pending.pending_email.as_deref(), Some(new_email));
+        assert!(pending.email_change_token.is_some());
+
+        // welcome + confirmation + notice
+        let deliveries = ctx.mailer.as_ref().unwrap().deliveries().messages;
+        assert_eq!(deliveries.len(), 3);
+        assert!(deliveries
+            .iter()
+            .any(|m| m.contains(new_email) && m.contains("confirm-email#")));
+        assert!(deliveries
+            .iter()
+            .any(|m| m.contains(&user.user.email) && m.contains("Someone asked to change")));
+
+        // links older than the configured window don't work
+        let mut expired = pending.clone().into_active_model();
+        expired.email_change_sent_at =
+            ActiveValue::set(Some((chrono::Utc::now() - Duration::hours(25)).into()));
+        expired.update(&ctx.db).await.unwrap();
+        let response = request
+            .post("/api/auth/confirm-email")
+            .json(&serde_json::json!({ "token": pending.email_change_token }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert!(users::Model::find_by_email(&ctx.db, new_email)
+            .await
+            .is_err());
+
+        let mut fresh = pending.clone().into_active_model();
+        fresh.email_change_sent_at = ActiveValue::set(Some(chrono::Utc::now().into()));
+        fresh.update(&ctx.db).await.unwrap();
+        let response = request
+            .post("/api/auth/confirm-email")
+            .json(&serde_json::json!({ "token": pending.email_change_token }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let confirmed = users::Model::find_by_email(&ctx.db, new_email)
+            .await
+            .unwrap();
+        assert_eq!(confirmed.pid, user.user.pid);
+        assert!(confirmed.pending_email.is_none());
+        assert!(confirmed.email_change_token.is_none());
+        assert!(users::Model::find_by_email(&ctx.db, &user.user.email)
+            .await
+            .is_err());
+    })
+    .await;
+}
+
+async fn create_post_with_comment(
+    ctx: &AppContext,
+    user_id: Uuid,
+) -> (posts::Model, comments::Model) {
+    let post = prepare_data::create_post(&ctx.db, user_id, "post", false, None).await;
+    let comment = comments::ActiveModel {
+        content: ActiveValue::set(Some("comment".to_string())),
+        post_id: ActiveValue::set(Some(post.id)),
+        user_id: ActiveValue::set(user_id),
+        ..Default::default()
+    }
+    .insert(&ctx.db)
+    .await
+    .unwrap();
+    (post, comment)
+}
+
+#[rstest]
+#[case(None)]
+#[case(Some("cascade"))]
+#[tokio::test]
+#[serial]
+async fn can_delete_account(#[case] mode: Option<&str>) {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (post, comment) = create_post_with_comment(&ctx, user.user.pid).await;
+
+        // what others keep of the account: a reply to its comment on their
+        // post, a notification about it and reports of its content
+        let other =
+            prepare_data::init_named_user_login(&request, &ctx, "other", "other@loco.com").await;
+        let (theirs, on_theirs) = create_post_with_comment(&ctx, other.user.pid).await;
+        let on_theirs = comments::ActiveModel {
+            user_id: ActiveValue::set(user.user.pid),
+            ..on_theirs.into_active_model()
+        }
+        .update(&ctx.db)
+        .await
+        .unwrap();
+        let reply = comments::ActiveModel {
+            content: ActiveValue::set(Some("reply".to_string())),
+            post_id: ActiveValue::set(Some(theirs.id)),
+            user_id: ActiveValue::set(other.user.pid),
+            parent_id: ActiveValue::set(Some(on_theirs.id)),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+        notifications::ActiveModel {
+            user_id: ActiveValue::set(other.user.pid),
+            kind: ActiveValue::set("comment".to_string()),
+            actor_id: ActiveValue::set(Some(user.user.pid)),
+            post_id: ActiveValue::set(Some(theirs.id)),
+            comment_id: ActiveValue::set(Some(on_theirs.id)),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+        for (target_type, target_id) in [("post", post.id), ("comment", on_theirs.id)] {
+            reports::ActiveModel {
+                reporter_id: ActiveValue::set(other.user.pid),
+                target_type: ActiveValue::set(target_type.to_string()),
+                target_id: ActiveValue::set(target_id),
+                reason: ActiveValue::set("spam".to_string()),
+                status: ActiveValue::set("open".to_string()),
+                ..Default::default()
+            }
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+        }
+        let upload = media::ActiveModel {
+            pid: ActiveValue::set(Uuid::new_v4()),
+            user_id: ActiveValue::set(user.user.pid),
+            content_type: ActiveValue::set("image/png".to_string()),
+            size: ActiveValue::set(1),
+            path: ActiveValue::set("media/missing.png".to_string()),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+        let invite = invites::ActiveModel {
+            code: ActiveValue::set("welcome".to_string()),
+            max_uses: ActiveValue::set(1),
+            uses: ActiveValue::set(0),
+            expires_at: ActiveValue::set((chrono::Utc::now() + Duration::days(1)).into()),
+            created_by: ActiveValue::set(user.user.pid),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .delete("/api/auth/current")
+            .add_header(auth_key, auth_value)
+            .json(&serde_json::json!({ "password": "wrong-password", "mode": mode }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .delete("/api/auth/current")
+            .add_header(auth_key, auth_value)
+            .json(&serde_json::json!({ "password": "1234", "mode": mode }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        assert!(users::Model::find_by_email(&ctx.db, &user.user.email)
+            .await
+            .is_err());
+
+        let post = posts::Entity::find_by_id(post.id)
+            .one(&ctx.db)
+            .await
+            .unwrap();
+        let comment = comments::Entity::find_by_id(comment.id)
+            .one(&ctx.db)
+            .await
+            .unwrap();
+        let reply = comments::Entity::find_by_id(reply.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        let notifications = notifications::Entity::find()
+            .filter(notifications::Column::UserId.eq(other.user.pid))
+            .all(&ctx.db)
+            .await
+            .unwrap();
+        let reports = reports::Entity::find().count(&ctx.db).await.unwrap();
+        let upload = media::Entity::find_by_id(upload.id)
+            .one(&ctx.db)
+            .await
+            .unwrap();
+        let invite = invites::Entity::find_by_id(invite.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(invite.created_by, users::DELETED_USER_PID);
+        if mode == Some("cascade") {
+            assert!(post.is_none());
+            assert!(comment.is_none());
+            assert!(comments::Entity::find_by_id(on_theirs.id)
+                .one(&ctx.db)
+                .await
+                .unwrap()
+                .is_none());
+            // nothing is left pointing at the deleted content
+            assert_eq!(reply.parent_id, None);
+            assert!(notifications.is_empty());
+            assert_eq!(reports, 0);
+            assert!(upload.is_none());
+        } else {
+            // the configured default anonymizes
+            assert_eq!(post.unwrap().user_id, Some(users::DELETED_USER_PID));
+            assert_eq!(comment.unwrap().user_id, users::DELETED_USER_PID);
+            assert_eq!(reply.parent_id, Some(on_theirs.id));
+            assert_eq!(notifications.len(), 1);
+            assert_eq!(notifications[0].actor_id, Some(users::DELETED_USER_PID));
+            assert_eq!(reports, 2);
+            assert_eq!(upload.unwrap().user_id, users::DELETED_USER_PID);
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_export_data() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .post("/api/auth/export")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let deliveries = ctx.mailer.as_ref().unwrap().deliveries();
+        let message = deliveries.messages.last().unwrap();
+        let token_start = message.find("/export#").unwrap() + "/export#".len();
+        let token = &message[token_start..token_start + 36];
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .get(&format!("/api/auth/export/{token}"))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert_eq!(response.header("content-type"), "application/zip");
+        assert!(response.as_bytes().starts_with(b"PK"));
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .get(&format!("/api/auth/export/{}", Uuid::new_v4()))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 404);
+
+        // archives expire and get purged
+        let first = exports::Entity::find()
+            .filter(exports::Column::Token.eq(Uuid::parse_str(token).unwrap()))
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        request
+            .post("/api/auth/export")
+            .add_header(auth_key, auth_value)
+            .await;
+        let mut expired = first.clone().into_active_model();
+        expired.created_at = ActiveValue::set((chrono::Utc::now() - Duration::hours(49)).into());
+        expired.update(&ctx.db).await.unwrap();
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .get(&format!("/api/auth/export/{token}"))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 404);
+
+        run_task::<App>(
+            &ctx,
+            Some(&"purge_exports".to_string()),
+            &task::Vars::default(),
+        )
+        .await
+        .unwrap();
+        let archive: Result<Vec<u8>, _> = ctx.storage.download(&PathBuf::from(&first.path)).await;
+        assert!(archive.is_err());
+        assert_eq!(exports::Entity::find().count(&ctx.db).await.unwrap(), 1);
+
+        // and go with the account
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .delete("/api/auth/current")
+            .add_header(auth_key, auth_value)
+            .json(&serde_json::json!({ "password": "1234" }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert_eq!(exports::Entity::find().count(&ctx.db).await.unwrap(), 0);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_list_and_revoke_sessions() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+
+        let response = request
+            .post("/api/auth/login")
+            .add_header(
+                HeaderName::from_static("user-agent"),
+                HeaderValue::from_static("second-device"),
+            )
+            .json(&serde_json::json!({
+                "email": user.user.email,
+                "password": "1234"
+            }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let second: LoginResponse = serde_json::from_str(&response.text()).unwrap();
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .get("/api/auth/sessions")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let sessions: Vec<SessionResponse> = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions.iter().filter(|s| s.current).count(), 1);
+        let other = sessions.iter().find(|s| !s.current).unwrap();
+        assert_eq!(other.user_agent.as_deref(), Some("second-device"));
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .delete(&format!("/api/auth/sessions/{}", other.id))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        // the revoked device is logged out, the current one is not
+        let (auth_key, auth_value) = prepare_data::auth_header(&second.token);
+        let response = request
+            .get("/api/auth/current")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .get("/api/auth/current")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .delete("/api/auth/sessions/999999")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 404);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_expire_sessions() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let response = request
+            .post("/api/auth/login")
+            .json(&serde_json::json!({
+                "email": user.user.email,
+                "password": "1234"
+            }))
+            .await;
+        let second: LoginResponse = serde_json::from_str(&response.text()).unwrap();
+
+        // last seen before the 7 days a token lasts
+        let session = sessions::Entity::find()
+            .filter(sessions::Column::UserId.eq(user.user.pid))
+            .order_by_desc(sessions::Column::Id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        let mut stale = session.into_active_model();
+        stale.last_seen_at = ActiveValue::set((chrono::Utc::now() - Duration::days(8)).into());
+        stale.update(&ctx.db).await.unwrap();
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .get("/api/auth/sessions")
+            .add_header(auth_key, auth_value)
+            .await;
+        let listed: Vec<SessionResponse> = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert!(listed[0].current);
+
+        run_task::<App>(
+            &ctx,
+            Some(&"purge_sessions".to_string()),
+            &task::Vars::default(),
+        )
+        .await
+        .unwrap();
+        let left = sessions::Entity::find()
+            .filter(sessions::Column::UserId.eq(user.user.pid))
+            .count(&ctx.db)
+            .await
+            .unwrap();
+        assert_eq!(left, 1);
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&second.token);
+        let response = request
+            .get("/api/auth/current")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 401);
+    })
+    .await;
+}
@@ -0,0 +1,199 @@
+use loco_rs::testing;
+use myapp::app::App;
+use sea_orm::{ActiveModelTrait, ActiveValue, IntoActiveModel};
+use serial_test::serial;
+use uuid::Uuid;
+
+use super::prepare_data::{self, create_post};
+
+async fn send(
+    request: &loco_rs::TestServer,
+    token: &str,
+    path: &str,
+    body: serde_json::Value,
+) -> (u16, serde_json::Value) {
+    let (auth_key, auth_value) = prepare_data::auth_header(token);
+    let response = request
+        .post(path)
+        .add_header(auth_key, auth_value)
+        .json(&body)
+        .await;
+    let status = response.status_code().as_u16();
+    (
+        status,
+        serde_json::from_str(&response.text()).unwrap_or_default(),
+    )
+}
+
+async fn fetch(request: &loco_rs::TestServer, token: &str, path: &str) -> (u16, serde_json::Value) {
+    let (auth_key, auth_value) = prepare_data::auth_header(token);
+    let response = request.get(path).add_header(auth_key, auth_value).await;
+    let status = response.status_code().as_u16();
+    (
+        status,
+        serde_json::from_str(&response.text()).unwrap_or_default(),
+    )
+}
+
+fn titles(page: &serde_json::Value) -> Vec<&str> {
+    page["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["title"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+#[serial]
+async fn can_bookmark_posts_into_collections() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let other =
+            prepare_data::init_named_user_login(&request, &ctx, "other", "other@loco.com").await;
+        let author = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+        let first = create_post(&ctx.db, author, "First", true, None).await;
+        let second = create_post(&ctx.db, author, "Second", true, None).await;
+        // one of the reader's own, which they may delete
+        let third = create_post(&ctx.db, user.user.pid, "Third", true, None).await;
+        // posts readers can't see yet can't be bookmarked
+        let later = chrono::Utc::now() + chrono::Duration::days(1);
+        let scheduled = create_post(&ctx.db, author, "Scheduled", true, Some(later)).await;
+        let draft = create_post(&ctx.db, author, "Draft", false, None).await;
+
+        let (status, collection) = send(
+            &request,
+            &user.token,
+            "/api/bookmarks/collections",
+            serde_json::json!({ "name": " Rust " }),
+        )
+        .await;
+        assert_eq!(status, 200);
+        assert_eq!(collection["name"], "Rust");
+        let collection_id = collection["id"].as_i64().unwrap();
+        let (_, again) = send(
+            &request,
+            &user.token,
+            "/api/bookmarks/collections",
+            serde_json::json!({ "name": "Rust" }),
+        )
+        .await;
+        assert_eq!(again["id"], collection["id"]);
+
+        for (post, collection) in [
+            (&first, Some(collection_id)),
+            (&second, None),
+            (&third, Some(collection_id)),
+        ] {
+            let (status, _) = send(
+                &request,
+                &user.token,
+                "/api/bookmarks",
+                serde_json::json!({ "post_id": post.id, "collection_id": collection }),
+            )
+            .await;
+            assert_eq!(status, 200);
+        }
+        for post in [&scheduled, &draft] {
+            let (status, _) = send(
+                &request,
+                &user.token,
+                "/api/bookmarks",
+                serde_json::json!({ "post_id": post.id }),
+            )
+            .await;
+            assert_eq!(status, 404);
+        }
+
+        let (status, page) = fetch(&request, &user.token, "/api/bookmarks").await;
+        assert_eq!(status, 200);
+        assert_eq!(page["total"], 3);
+        assert_eq!(titles(&page), vec!["Third", "Second", "First"]);
+        assert_eq!(page["items"][0]["bookmarked"], true);
+        let (_, page) = fetch(
+            &request,
+            &user.token,
+            &format!("/api/bookmarks?collection_id={collection_id}"),
+        )
+        .await;
+        assert_eq!(titles(&page), vec!["Third", "First"]);
+
+        // the flag shows on posts for signed in users only
+        let (_, item) = fetch(&request, &user.token, &format!("/api/posts/{}", first.id)).await;
+        assert_eq!(item["bookmarked"], true);
+        let (_, item) = fetch(&request, &other.token, &format!("/api/posts/{}", first.id)).await;
+        assert_eq!(item["bookmarked"], false);
+        let res = request.get(&format!("/api/posts/{}", first.id)).await;
+        let item: serde_json::Value = serde_json::from_str(&res.text()).unwrap();
+        assert!(item.get("bookmarked").is_none());
+        let (_, page) = fetch(&request, &other.token, "/api/posts").await;
+        assert!(page["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|item| item["bookmarked"] == false));
+
+        // collections of other users can't be listed or filed into
+        let (status, _) = fetch(
+            &request,
+            &other.token,
+            &format!("/api/bookmarks?collection_id={collection_id}"),
+        )
+        .await;
+        assert_eq!(status, 404);
+        let (status, _) = send(
+            &request,
+            &other.token,
+            "/api/bookmarks",
+            serde_json::json!({ "post_id": first.id, "collection_id": collection_id }),
+        )
+        .await;
+        assert_eq!(status, 404);
+        let (status, _) = send(
+            &request,
+            &other.token,
+            "/api/bookmarks",
+            serde_json::json!({ "post_id": 9999 }),
+        )
+        .await;
+        assert_eq!(status, 404);
+
+        // unpublished and deleted posts drop out of the bookmarks quietly
+        let mut unpublished = second.into_active_model();
+        unpublished.published = ActiveValue::set(Some(false));
+        unpublished.update(&ctx.db).await.unwrap();
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let res = request
+            .delete(&format!("/api/posts/{}", third.id))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(res.status_code(), 200);
+        let (status, page) = fetch(&request, &user.token, "/api/bookmarks").await;
+        assert_eq!(status, 200);
+        assert_eq!(page["total"], 1);
+        assert_eq!(titles(&page), vec!["First"]);
+
+        // deleting the collection keeps its bookmarks
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let res = request
+            .delete(&format!("/api/bookmarks/collections/{collection_id}"))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(res.status_code(), 200);
+        let (_, collections) = fetch(&request, &user.token, "/api/bookmarks/collections").await;
+        assert_eq!(collections, serde_json::json!([]));
+        let (_, page) = fetch(&request, &user.token, "/api/bookmarks").await;
+        assert_eq!(page["items"][0]["id"], first.id);
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let res = request
+            .delete(&format!("/api/bookmarks/{}", first.id))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(res.status_code(), 200);
+        let (_, page) = fetch(&request, &user.token, "/api/bookmarks").await;
+        assert_eq!(page["total"], 0);
+    })
+    .await;
+}
@@ -1,6 +1,13 @@
 use myapp::app::App;
 use loco_rs::testing;
+use myapp::models::{
+    _entities::{comments, posts},
+    follows, notifications,
+};
 use serial_test::serial;
+use uuid::Uuid;
+
+use super::prepare_data::{self, create_post};
 
 #[tokio::test]
 #[serial]
@@ -14,3 +21,324 @@ async fn can_get_comments() {
     })
     .await;
 }
+
+#[tokio::test]
+#[serial]
+async fn can_mention_users_in_comments() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let post = create_policy_post(&ctx.db, "open", None).await;
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let res = request
+            .post("/api/comments")
+            .add_header(auth_key, auth_value)
+            .json(&serde_json::json!({
+                "content": "Hi @user1 and @nobody",
+                "post_id": post.id,
+            }))
+            .await;
+        assert_eq!(res.status_code(), 200);
+        let comment: serde_json::Value = serde_json::from_str(&res.text()).unwrap();
+        assert_eq!(comment["content"], "Hi @user1 and @nobody");
+        assert_eq!(
+            comment["rendered_content"],
+            "Hi [@user1](/users/user1) and @nobody"
+        );
+
+        let user1 = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+        let inbox = notifications::Model::inbox(user1, true)
+            .all(&ctx.db)
+            .await
+            .unwrap();
+        // user1 also hears of the comment as the author of the post
+        let mentions: Vec<_> = inbox.iter().filter(|item| item.kind == "mention").collect();
+        assert_eq!(mentions.len(), 1);
+        assert_eq!(mentions[0].actor_id, Some(user.user.pid));
+    })
+    .await;
+}
+
+/// A post of user1, published two days ago with the comment policy
+async fn create_policy_post(
+    db: &sea_orm::DatabaseConnection,
+    policy: &str,
+    close_after_days: Option<i32>,
+) -> posts::Model {
+    use sea_orm::{ActiveModelTrait, ActiveValue, IntoActiveModel};
+
+    let author = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    let published_at = chrono::Utc::now() - chrono::Duration::days(2);
+    let title = format!("{policy} post");
+    let mut post = create_post(db, author, &title, true, Some(published_at))
+        .await
+        .into_active_model();
+    post.comment_policy = ActiveValue::set(policy.to_string());
+    post.comments_close_after_days = ActiveValue::set(close_after_days);
+    post.update(db).await.unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn can_enforce_comment_policy() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        use sea_orm::{ActiveModelTrait, ActiveValue, IntoActiveModel};
+
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let comment_on = |post_id: i32| {
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+            request
+                .post("/api/comments")
+                .add_header(auth_key, auth_value)
+                .json(&serde_json::json!({ "content": "My two cents", "post_id": post_id }))
+        };
+
+        let closed = create_policy_post(&ctx.db, "closed", None).await;
+        let res = comment_on(closed.id).await;
+        assert_eq!(res.status_code(), 403);
+        let error: serde_json::Value = serde_json::from_str(&res.text()).unwrap();
+        assert_eq!(error["error"], "comments_not_allowed");
+        assert_eq!(error["description"], "comments are closed on this post");
+
+        let expired = create_policy_post(&ctx.db, "open", Some(1)).await;
+        assert_eq!(comment_on(expired.id).await.status_code(), 403);
+        let still_open = create_policy_post(&ctx.db, "open", Some(7)).await;
+        assert_eq!(comment_on(still_open.id).await.status_code(), 200);
+
+        let followers = create_policy_post(&ctx.db, "followers", None).await;
+        assert_eq!(comment_on(followers.id).await.status_code(), 403);
+        follows::ActiveModel {
+            follower_id: ActiveValue::set(user.user.pid),
+            followed_id: ActiveValue::set(followers.user_id.unwrap()),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+        assert_eq!(comment_on(followers.id).await.status_code(), 200);
+
+        assert_eq!(comment_on(9999).await.status_code(), 404);
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let res = request
+            .post("/api/comments")
+            .add_header(auth_key, auth_value)
+            .json(&serde_json::json!({ "content": "Nowhere" }))
+            .await;
+        assert_eq!(res.status_code(), 404);
+
+        // reported posts take no comments while hidden
+        let hidden = create_policy_post(&ctx.db, "open", None).await;
+        let mut model = hidden.clone().into_active_model();
+        model.hidden_at = ActiveValue::set(Some(chrono::Utc::now().into()));
+        model.update(&ctx.db).await.unwrap();
+        assert_eq!(comment_on(hidden.id).await.status_code(), 404);
+
+        let registered = create_policy_post(&ctx.db, "registered", None).await;
+        assert_eq!(comment_on(registered.id).await.status_code(), 200);
+        let mut unverified = user.user.clone().into_active_model();
+        unverified.email_verified_at = ActiveValue::set(None);
+        unverified.update(&ctx.db).await.unwrap();
+        assert_eq!(comment_on(registered.id).await.status_code(), 403);
+
+        // clients learn whether to show the comment box
+        let res = request.get("/api/posts?page_size=50").await;
+        let page: serde_json::Value = serde_json::from_str(&res.text()).unwrap();
+        let item = |id: i32| {
+            page["items"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|item| item["id"] == id)
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(item(closed.id)["comment_policy"], "closed");
+        assert_eq!(item(closed.id)["comments_open"], false);
+        assert_eq!(item(expired.id)["comments_open"], false);
+        assert_eq!(item(still_open.id)["comments_open"], true);
+        assert!(item(still_open.id)["comments_close_at"].is_string());
+        assert_eq!(item(followers.id)["comment_policy"], "followers");
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_reply_only_within_the_post() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        use sea_orm::{ActiveModelTrait, ActiveValue};
+
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let reply = |post_id: i32, parent_id: i32| {
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+            request
+                .post("/api/comments")
+                .add_header(auth_key, auth_value)
+                .json(&serde_json::json!({
+                    "content": "Agreed",
+                    "post_id": post_id,
+                    "parent_id": parent_id,
+                }))
+        };
+
+        let post = create_policy_post(&ctx.db, "open", None).await;
+        let other = create_policy_post(&ctx.db, "open", None).await;
+        let commenter = Uuid::parse_str("22222222-2222-2222-2222-222222222222").unwrap();
+        let comment = |status: &str| comments::ActiveModel {
+            content: ActiveValue::set(Some("First".to_string())),
+            post_id: ActiveValue::set(Some(post.id)),
+            user_id: ActiveValue::set(commenter),
+            status: ActiveValue::set(status.to_string()),
+            ..Default::default()
+        };
+        let parent = comment("approved").insert(&ctx.db).await.unwrap();
+        let spam = comment("spam").insert(&ctx.db).await.unwrap();
+
+        assert_eq!(reply(other.id, parent.id).await.status_code(), 404);
+        assert_eq!(reply(post.id, spam.id).await.status_code(), 404);
+        assert_eq!(reply(post.id, 9999).await.status_code(), 404);
+        let res = reply(post.id, parent.id).await;
+        assert_eq!(res.status_code(), 200);
+        let item: serde_json::Value = serde_json::from_str(&res.text()).unwrap();
+        assert_eq!(item["parent_id"], parent.id);
+    })
+    .await;
+}
+
+async fn list_comments(request: &loco_rs::TestServer, query: &str) -> serde_json::Value {
+    let res = request.get(&format!("/api/comments?{query}")).await;
+    assert_eq!(res.status_code(), 200);
+    serde_json::from_str(&res.text()).unwrap()
+}
+
+fn ids(page: &serde_json::Value) -> Vec<i64> {
+    page["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["id"].as_i64().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+#[serial]
+async fn can_page_through_comments() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        use sea_orm::{ActiveModelTrait, ActiveValue};
+
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let post = create_policy_post(&ctx.db, "open", None).await;
+        let commenter = Uuid::parse_str("22222222-2222-2222-2222-222222222222").unwrap();
+        let mut threads = Vec::new();
+        for (i, reactions) in [2, 0, 5, 1, 3].into_iter().enumerate() {
+            let comment = comments::ActiveModel {
+                content: ActiveValue::set(Some(format!("Comment {i}"))),
+                post_id: ActiveValue::set(Some(post.id)),
+                user_id: ActiveValue::set(commenter),
+                reaction_count: ActiveValue::set(reactions),
+                created_at: ActiveValue::set(
+                    (chrono::Utc::now()
+                        - chrono::Duration::minutes(10 - i64::try_from(i).unwrap()))
+                    .into(),
+                ),
+                ..Default::default()
+            }
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+            threads.push(i64::from(comment.id));
+        }
+        let mut replies = Vec::new();
+        for i in 0..4 {
+            let reply = comments::ActiveModel {
+                content: ActiveValue::set(Some(format!("Reply {i}"))),
+                post_id: ActiveValue::set(Some(post.id)),
+                user_id: ActiveValue::set(commenter),
+                parent_id: ActiveValue::set(Some(i32::try_from(threads[0]).unwrap())),
+                ..Default::default()
+            }
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+            replies.push(i64::from(reply.id));
+        }
+
+        // oldest first by default, following the cursor to the end
+        let mut seen = Vec::new();
+        let mut query = format!("post_id={}&page_size=4", post.id);
+        let mut pages = Vec::new();
+        loop {
+            let page = list_comments(&request, &query).await;
+            assert_eq!(page["total"], 9);
+            assert_eq!(page["total_pages"], 3);
+            pages.push(page["page"].as_u64().unwrap());
+            seen.extend(ids(&page));
+            let Some(cursor) = page["next_cursor"].as_str() else {
+                break;
+            };
+            query = format!("post_id={}&page_size=4&cursor={cursor}", post.id);
+        }
+        assert_eq!(pages, vec![1, 2, 3]);
+        assert_eq!(seen, [threads.clone(), replies.clone()].concat());
+
+        let page = list_comments(
+            &request,
+            &format!("post_id={}&top_level=true&sort=newest", post.id),
+        )
+        .await;
+        assert_eq!(page["total"], 5);
+        let newest: Vec<i64> = threads.iter().rev().copied().collect();
+        assert_eq!(ids(&page), newest);
+        // the first thread comes last, with a preview of its replies
+        let thread = &page["items"][4];
+        assert_eq!(thread["reply_count"], 4);
+        let preview: Vec<i64> = thread["replies"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|reply| reply["id"].as_i64().unwrap())
+            .collect();
+        assert_eq!(preview, replies[..3]);
+        assert_eq!(page["items"][0]["reply_count"], 0);
+
+        let first = list_comments(
+            &request,
+            &format!(
+                "post_id={}&top_level=true&sort=most_liked&page_size=3",
+                post.id
+            ),
+        )
+        .await;
+        assert_eq!(ids(&first), vec![threads[2], threads[4], threads[0]]);
+        let cursor = first["next_cursor"].as_str().unwrap();
+        let second = list_comments(
+            &request,
+            &format!(
+                "post_id={}&top_level=true&sort=most_liked&page_size=3&cursor={cursor}",
+                post.id
+            ),
+        )
+        .await;
+        assert_eq!(ids(&second), vec![threads[3], threads[1]]);
+        assert!(second["next_cursor"].is_null());
+
+        let page = list_comments(
+            &request,
+            &format!("post_id={}&parent_id={}&replies=1", post.id, threads[0]),
+        )
+        .await;
+        assert_eq!(ids(&page), replies);
+
+        let res = request
+            .get(&format!(
+                "/api/comments?post_id={}&cursor=nonsense",
+                post.id
+            ))
+            .await;
+        assert_eq!(res.status_code(), 400);
+    })
+    .await;
+}
@@ -0,0 +1,118 @@
+use loco_rs::testing;
+use myapp::{
+    app::App,
+    models::_entities::{comments, follows},
+};
+use sea_orm::{ActiveModelTrait, ActiveValue};
+use serial_test::serial;
+use uuid::Uuid;
+
+use super::prepare_data::{self, create_post};
+
+async fn dashboard(request: &loco_rs::TestServer, token: &str, query: &str) -> serde_json::Value {
+    let (auth_key, auth_value) = prepare_data::auth_header(token);
+    let res = request
+        .get(&format!("/api/dashboard{query}"))
+        .add_header(auth_key, auth_value)
+        .await;
+    assert_eq!(res.status_code(), 200);
+    serde_json::from_str(&res.text()).unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn can_get_dashboard() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let author = prepare_data::init_user_login(&request, &ctx).await;
+        let other =
+            prepare_data::init_named_user_login(&request, &ctx, "other", "other@loco.com").await;
+        let pid = author.user.pid;
+        let now = chrono::Utc::now();
+        create_post(&ctx.db, pid, "Draft", false, None).await;
+        create_post(
+            &ctx.db,
+            pid,
+            "Later",
+            true,
+            Some(now + chrono::Duration::days(1)),
+        )
+        .await;
+        let discussed = create_post(&ctx.db, pid, "Discussed", true, Some(now)).await;
+        let viewed = create_post(&ctx.db, pid, "Viewed", true, Some(now)).await;
+
+        let comment = |user_id: Uuid, status: &str| comments::ActiveModel {
+            content: ActiveValue::set(Some("Nice post".to_string())),
+            post_id: ActiveValue::set(Some(discussed.id)),
+            user_id: ActiveValue::set(user_id),
+            status: ActiveValue::set(status.to_string()),
+            ..Default::default()
+        };
+        comment(other.user.pid, "approved")
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+        // the author's own replies and comments waiting for moderation don't
+        // count
+        comment(pid, "approved").insert(&ctx.db).await.unwrap();
+        comment(other.user.pid, "pending")
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&other.token);
+        request
+            .post(&format!("/api/posts/{}/reactions", viewed.id))
+            .add_header(auth_key, auth_value)
+            .json(&serde_json::json!({ "emoji": "🎉" }))
+            .await;
+        follows::ActiveModel {
+            follower_id: ActiveValue::set(other.user.pid),
+            followed_id: ActiveValue::set(pid),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+        for user_agent in ["Mozilla/5.0 (dashboard one)", "Mozilla/5.0 (dashboard two)"] {
+            request
+                .get(&format!("/api/posts/{}", viewed.id))
+                .add_header(
+                    axum::http::header::USER_AGENT,
+                    axum::http::HeaderValue::from_static(user_agent),
+                )
+                .await;
+        }
+
+        let board = dashboard(&request, &author.token, "").await;
+        assert_eq!(
+            board["posts"],
+            serde_json::json!({ "draft": 1, "scheduled": 1, "published": 2 })
+        );
+        assert_eq!(board["comments_received"], 1);
+        assert_eq!(board["views"], 2);
+        assert_eq!(board["top_posts"][0]["title"], "Viewed");
+        assert_eq!(board["top_posts"][0]["views"], 2);
+        let kinds: Vec<&str> = board["activity"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|activity| activity["kind"].as_str().unwrap())
+            .collect();
+        assert_eq!(kinds, vec!["follow", "reaction", "comment"]);
+        assert_eq!(board["activity"][0]["actor"]["name"], "other");
+        assert_eq!(board["activity"][1]["post_title"], "Viewed");
+        assert_eq!(board["activity"][1]["emoji"], "🎉");
+
+        let board = dashboard(&request, &author.token, "?top=comments").await;
+        assert_eq!(board["top_posts"][0]["title"], "Discussed");
+        assert_eq!(board["top_posts"][0]["comments"], 2);
+
+        // other authors start from zero
+        let board = dashboard(&request, &other.token, "").await;
+        assert_eq!(board["posts"]["published"], 0);
+        assert_eq!(board["views"], 0);
+        assert_eq!(board["activity"], serde_json::json!([]));
+    })
+    .await;
+}
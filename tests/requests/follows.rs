@@ -0,0 +1,111 @@
+use loco_rs::testing;
+use myapp::{app::App, models::_entities::notifications};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serial_test::serial;
+use uuid::Uuid;
+
+use super::prepare_data::{self, create_post};
+
+const USER1: &str = "11111111-1111-1111-1111-111111111111";
+const USER2: &str = "22222222-2222-2222-2222-222222222222";
+
+async fn call(
+    request: &loco_rs::TestServer,
+    token: &str,
+    method: &str,
+    path: &str,
+) -> (u16, serde_json::Value) {
+    let (auth_key, auth_value) = prepare_data::auth_header(token);
+    let response = match method {
+        "POST" => request.post(path),
+        "DELETE" => request.delete(path),
+        _ => request.get(path),
+    }
+    .add_header(auth_key, auth_value)
+    .await;
+    let status = response.status_code().as_u16();
+    (
+        status,
+        serde_json::from_str(&response.text()).unwrap_or_default(),
+    )
+}
+
+fn titles(page: &serde_json::Value) -> Vec<&str> {
+    page["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["title"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+#[serial]
+async fn can_follow_authors_and_read_the_feed() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let user1 = Uuid::parse_str(USER1).unwrap();
+        let user2 = Uuid::parse_str(USER2).unwrap();
+        let ago = |minutes| Some(chrono::Utc::now() - chrono::Duration::minutes(minutes));
+        create_post(&ctx.db, user1, "Oldest", true, ago(30)).await;
+        create_post(&ctx.db, user2, "Middle", true, ago(20)).await;
+        create_post(&ctx.db, user1, "Newest", true, ago(10)).await;
+        create_post(&ctx.db, user1, "Draft", false, None).await;
+        create_post(&ctx.db, user1, "Scheduled", true, ago(-60)).await;
+        let (_, page) = call(&request, &user.token, "GET", "/api/feed").await;
+        assert_eq!(page["total"], 0);
+
+        let (status, counts) = call(&request, &user.token, "POST", "/api/users/user1/follow").await;
+        assert_eq!(status, 200);
+        assert_eq!(counts["followers"], 1);
+        // following again changes nothing and doesn't notify twice
+        call(&request, &user.token, "POST", "/api/users/user1/follow").await;
+        call(&request, &user.token, "POST", "/api/users/user2/follow").await;
+        let notified = notifications::Entity::find()
+            .filter(notifications::Column::UserId.eq(user1))
+            .filter(notifications::Column::Kind.eq("follow"))
+            .all(&ctx.db)
+            .await
+            .unwrap();
+        assert_eq!(notified.len(), 1);
+        assert_eq!(notified[0].actor_id, Some(user.user.pid));
+
+        let (status, page) = call(&request, &user.token, "GET", "/api/feed?page_size=2").await;
+        assert_eq!(status, 200);
+        assert_eq!(page["total"], 3);
+        assert_eq!(titles(&page), vec!["Newest", "Middle"]);
+        let cursor = page["next_cursor"].as_str().unwrap();
+        let (_, page) = call(
+            &request,
+            &user.token,
+            "GET",
+            &format!("/api/feed?page_size=2&cursor={cursor}"),
+        )
+        .await;
+        assert_eq!(page["page"], 2);
+        assert_eq!(titles(&page), vec!["Oldest"]);
+        assert!(page.get("next_cursor").is_none());
+
+        let (_, profile) = call(&request, &user.token, "GET", "/api/users/user1").await;
+        assert_eq!(profile["followers"], 1);
+        assert_eq!(profile["following"], 0);
+        assert_eq!(profile["followed"], true);
+        let res = request.get("/api/users/user1").await;
+        let profile: serde_json::Value = serde_json::from_str(&res.text()).unwrap();
+        assert!(profile.get("followed").is_none());
+
+        let (status, counts) =
+            call(&request, &user.token, "DELETE", "/api/users/user2/follow").await;
+        assert_eq!(status, 200);
+        assert_eq!(counts["followers"], 0);
+        let (_, page) = call(&request, &user.token, "GET", "/api/feed").await;
+        assert_eq!(titles(&page), vec!["Newest", "Oldest"]);
+
+        let (status, _) = call(&request, &user.token, "POST", "/api/users/missing/follow").await;
+        assert_eq!(status, 404);
+        let (status, _) = call(&request, &user.token, "GET", "/api/feed?cursor=zz").await;
+        assert_eq!(status, 400);
+    })
+    .await;
+}
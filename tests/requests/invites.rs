@@ -0,0 +1,124 @@
+use loco_rs::testing;
+use myapp::{
+    app::App,
+    models::users::{self, Role},
+    views::invites::InviteResponse,
+};
+use sea_orm::IntoActiveModel;
+use serial_test::serial;
+
+use super::prepare_data;
+
+#[tokio::test]
+#[serial]
+async fn only_admins_can_manage_invites() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .get("/api/admin/invites")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 403);
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .post("/api/admin/invites")
+            .add_header(auth_key, auth_value)
+            .json(&serde_json::json!({}))
+            .await;
+        assert_eq!(response.status_code(), 403);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_issue_list_and_revoke_invites() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let admin = prepare_data::init_user_login(&request, &ctx).await;
+        admin
+            .user
+            .clone()
+            .into_active_model()
+            .set_role(&ctx.db, Role::Admin)
+            .await
+            .unwrap();
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&admin.token);
+        let response = request
+            .post("/api/admin/invites")
+            .add_header(auth_key, auth_value)
+            .json(&serde_json::json!({
+                "email": "invited@loco.com",
+                "max_uses": 1,
+            }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let invite: InviteResponse = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(invite.uses, 0);
+        assert_eq!(invite.email.as_deref(), Some("invited@loco.com"));
+
+        // welcome mail of the admin + the invitation
+        let deliveries = ctx.mailer.as_ref().unwrap().deliveries().messages;
+        assert_eq!(deliveries.len(), 2);
+        assert!(deliveries
+            .iter()
+            .any(|m| m.contains("invited@loco.com")
+                && m.contains(&format!("register#{}", invite.code))));
+
+        // the invite is bound to its email
+        let response = request
+            .post("/api/auth/register")
+            .json(&serde_json::json!({
+                "name": "other",
+                "email": "other@loco.com",
+                "password": "1234",
+                "invite_code": invite.code,
+            }))
+            .await;
+        assert_eq!(response.status_code(), 400);
+
+        let response = request
+            .post("/api/auth/register")
+            .json(&serde_json::json!({
+                "name": "invited",
+                "email": "invited@loco.com",
+                "password": "1234",
+                "invite_code": invite.code,
+            }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert!(users::Model::find_by_email(&ctx.db, "invited@loco.com")
+            .await
+            .is_ok());
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&admin.token);
+        let response = request
+            .get("/api/admin/invites")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let invites: Vec<InviteResponse> = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(invites.len(), 1);
+        assert_eq!(invites[0].uses, 1);
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&admin.token);
+        let response = request
+            .delete(&format!("/api/admin/invites/{}", invite.id))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let revoked: InviteResponse = serde_json::from_str(&response.text()).unwrap();
+        assert!(revoked.revoked_at.is_some());
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&admin.token);
+        let response = request
+            .delete("/api/admin/invites/999999")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 404);
+    })
+    .await;
+}
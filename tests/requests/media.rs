@@ -0,0 +1,386 @@
+use std::io::Cursor;
+
+use axum::http::header;
+use axum_test::multipart::{MultipartForm, Part};
+use image::{DynamicImage, ImageFormat, RgbImage};
+use loco_rs::testing;
+use myapp::app::App;
+use serial_test::serial;
+
+use super::prepare_data;
+
+fn image(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+    let image = RgbImage::from_fn(width, height, |x, y| {
+        image::Rgb([(x % 256) as u8, (y % 256) as u8, 128])
+    });
+    let mut out = Cursor::new(Vec::new());
+    DynamicImage::ImageRgb8(image)
+        .write_to(&mut out, format)
+        .unwrap();
+    out.into_inner()
+}
+
+/// A JPEG whose EXIF says it is to be turned a quarter clockwise, and where
+/// it was taken
+fn rotated_jpeg_with_gps(width: u32, height: u32) -> Vec<u8> {
+    let jpeg = image(width, height, ImageFormat::Jpeg);
+    let mut exif = b"Exif\0\0MM\0\x2a\0\0\0\x08".to_vec();
+    // one entry: orientation (0x0112), SHORT, 1 value, 6 = rotate 90 CW
+    exif.extend_from_slice(b"\0\x01\x01\x12\0\x03\0\0\0\x01\0\x06\0\0\0\0\0\0");
+    exif.extend_from_slice(b"GPS 48.8584 N 2.2945 E");
+    let len = u16::try_from(exif.len() + 2).unwrap();
+    let mut out = jpeg[..2].to_vec();
+    out.extend_from_slice(&[0xFF, 0xE1]);
+    out.extend_from_slice(&len.to_be_bytes());
+    out.extend_from_slice(&exif);
+    out.extend_from_slice(&jpeg[2..]);
+    out
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+async fn upload(
+    request: &loco_rs::TestServer,
+    token: &str,
+    content: Vec<u8>,
+    mime_type: &str,
+) -> (u16, serde_json::Value) {
+    let (auth_key, auth_value) = prepare_data::auth_header(token);
+    let form = MultipartForm::new().add_part(
+        "file",
+        Part::bytes(content)
+            .file_name("cover.png")
+            .mime_type(mime_type),
+    );
+    let response = request
+        .post("/api/media")
+        .add_header(auth_key, auth_value)
+        .multipart(form)
+        .await;
+    let status = response.status_code().as_u16();
+    (
+        status,
+        serde_json::from_str(&response.text()).unwrap_or_default(),
+    )
+}
+
+async fn send_post(
+    request: &loco_rs::TestServer,
+    token: &str,
+    body: serde_json::Value,
+) -> (u16, serde_json::Value) {
+    let (auth_key, auth_value) = prepare_data::auth_header(token);
+    let response = request
+        .post("/api/posts")
+        .add_header(auth_key, auth_value)
+        .json(&body)
+        .await;
+    let status = response.status_code().as_u16();
+    (
+        status,
+        serde_json::from_str(&response.text()).unwrap_or_default(),
+    )
+}
+
+#[tokio::test]
+#[serial]
+async fn can_upload_and_serve_images() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+
+        let png = image(400, 200, ImageFormat::Png);
+        let (status, media) = upload(&request, &user.token, png.clone(), "image/png").await;
+        assert_eq!(status, 200);
+        assert_eq!(media["content_type"], "image/png");
+        assert_eq!(media["size"], png.len());
+        assert_eq!(
+            (&media["width"], &media["height"]),
+            (&400.into(), &200.into())
+        );
+        assert_eq!(media["original_name"], "cover.png");
+        let url = media["url"].as_str().unwrap().to_string();
+
+        // served to anyone, cached for good
+        let res = request.get(&url).await;
+        assert_eq!(res.status_code(), 200);
+        assert_eq!(res.as_bytes().as_ref(), png.as_slice());
+        assert_eq!(res.header(header::CONTENT_TYPE), "image/png");
+        assert_eq!(
+            res.header(header::CACHE_CONTROL),
+            "public, max-age=31536000, immutable"
+        );
+        let etag = res.header(header::ETAG);
+        let res = request
+            .get(&url)
+            .add_header(header::IF_NONE_MATCH, etag)
+            .await;
+        assert_eq!(res.status_code(), 304);
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let res = request
+            .get("/api/media")
+            .add_header(auth_key, auth_value)
+            .await;
+        let list: serde_json::Value = serde_json::from_str(&res.text()).unwrap();
+        assert_eq!(list.as_array().unwrap().len(), 1);
+        assert_eq!(list[0]["url"], url.as_str());
+        assert_eq!(list[0]["variants"], media["variants"]);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_build_variants() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+
+        // workers run in the foreground in tests
+        let png = image(400, 200, ImageFormat::Png);
+        let (_, media) = upload(&request, &user.token, png, "image/png").await;
+        assert_eq!(media["processed"], true);
+        let variants: Vec<(&str, &str, i64, i64)> = media["variants"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|variant| {
+                (
+                    variant["name"].as_str().unwrap(),
+                    variant["format"].as_str().unwrap(),
+                    variant["width"].as_i64().unwrap(),
+                    variant["height"].as_i64().unwrap(),
+                )
+            })
+            .collect();
+        // never scaled up, so medium and large would be the same
+        assert_eq!(
+            variants,
+            vec![
+                ("thumbnail", "png", 320, 160),
+                ("thumbnail", "webp", 320, 160),
+                ("medium", "png", 400, 200),
+                ("medium", "webp", 400, 200),
+            ]
+        );
+
+        for variant in media["variants"].as_array().unwrap() {
+            let res = request.get(variant["url"].as_str().unwrap()).await;
+            assert_eq!(res.status_code(), 200);
+            assert_eq!(
+                res.header(header::CONTENT_TYPE),
+                variant["content_type"].as_str().unwrap()
+            );
+            assert_eq!(
+                res.header(header::CACHE_CONTROL),
+                "public, max-age=31536000, immutable"
+            );
+            let decoded = image::load_from_memory(res.as_bytes()).unwrap();
+            assert_eq!(
+                (i64::from(decoded.width()), i64::from(decoded.height())),
+                (
+                    variant["width"].as_i64().unwrap(),
+                    variant["height"].as_i64().unwrap()
+                )
+            );
+        }
+        let pid = media["pid"].as_str().unwrap();
+        let res = request.get(&format!("/api/media/{pid}/large.webp")).await;
+        assert_eq!(res.status_code(), 404);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_strip_metadata_and_turn_photos_upright() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+
+        let jpeg = rotated_jpeg_with_gps(400, 200);
+        assert!(contains(&jpeg, b"GPS"));
+        let (status, media) = upload(&request, &user.token, jpeg, "image/jpeg").await;
+        assert_eq!(status, 200);
+        assert_eq!(
+            (&media["width"], &media["height"]),
+            (&200.into(), &400.into())
+        );
+
+        let res = request.get(media["url"].as_str().unwrap()).await;
+        assert!(!contains(res.as_bytes(), b"Exif"));
+        assert!(!contains(res.as_bytes(), b"GPS"));
+        let original = image::load_from_memory(res.as_bytes()).unwrap();
+        assert_eq!((original.width(), original.height()), (200, 400));
+
+        for variant in media["variants"].as_array().unwrap() {
+            let res = request.get(variant["url"].as_str().unwrap()).await;
+            assert!(!contains(res.as_bytes(), b"Exif"));
+            assert!(!contains(res.as_bytes(), b"GPS"));
+        }
+        let thumbnail = &media["variants"].as_array().unwrap()[0];
+        assert_eq!(
+            (&thumbnail["width"], &thumbnail["height"]),
+            (&160.into(), &320.into())
+        );
+
+        // metadata is cut out without encoding the image again when it is
+        // upright already
+        let mut png = image(10, 10, ImageFormat::Png);
+        let text = b"tEXtComment\0taken at home";
+        let mut chunk = u32::try_from(text.len() - 4)
+            .unwrap()
+            .to_be_bytes()
+            .to_vec();
+        chunk.extend_from_slice(text);
+        chunk.extend_from_slice(&[0, 0, 0, 0]);
+        png.splice(33..33, chunk);
+        let (status, media) = upload(&request, &user.token, png.clone(), "image/png").await;
+        assert_eq!(status, 200);
+        let res = request.get(media["url"].as_str().unwrap()).await;
+        assert!(!contains(res.as_bytes(), b"taken at home"));
+        assert_eq!(res.as_bytes().len(), png.len() - text.len() - 8);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_reject_invalid_uploads() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+
+        // the declared type doesn't matter, the content does
+        let (status, _) = upload(
+            &request,
+            &user.token,
+            b"<svg onload=alert(1)>".to_vec(),
+            "image/png",
+        )
+        .await;
+        assert_eq!(status, 400);
+
+        // the test config allows 1 MiB
+        let mut large = image(10, 10, ImageFormat::Png);
+        large.resize(1024 * 1024 + 1, 0);
+        let (status, _) = upload(&request, &user.token, large, "image/png").await;
+        assert_eq!(status, 413);
+
+        let res = request
+            .post("/api/media")
+            .multipart(
+                MultipartForm::new().add_part("file", Part::bytes(image(10, 10, ImageFormat::Png))),
+            )
+            .await;
+        assert_eq!(res.status_code(), 401);
+
+        let res = request
+            .get("/api/media/00000000-0000-0000-0000-000000000000")
+            .await;
+        assert_eq!(res.status_code(), 404);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_use_own_uploads_as_covers() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let other =
+            prepare_data::init_named_user_login(&request, &ctx, "other", "other@loco.com").await;
+        let png = image(10, 10, ImageFormat::Png);
+        let (_, media) = upload(&request, &user.token, png, "image/png").await;
+        let url = media["url"].as_str().unwrap().to_string();
+
+        let (status, _) = send_post(
+            &request,
+            &other.token,
+            serde_json::json!({ "title": "Stolen", "cover_media_id": media["id"] }),
+        )
+        .await;
+        assert_eq!(status, 400);
+
+        let (status, post) = send_post(
+            &request,
+            &user.token,
+            serde_json::json!({
+                "title": "With cover",
+                "published": true,
+                "cover_media_id": media["id"],
+            }),
+        )
+        .await;
+        assert_eq!(status, 200);
+        assert_eq!(post["cover_url"], url.as_str());
+
+        let res = request.get("/api/posts").await;
+        let page: serde_json::Value = serde_json::from_str(&res.text()).unwrap();
+        assert_eq!(page["items"][0]["cover_url"], url.as_str());
+
+        // edits without the cover keep it, and only own uploads can be set
+        let path = format!("/api/posts/{}", post["id"]);
+        let edit = |body: serde_json::Value| {
+            let (request, path) = (&request, &path);
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+            async move {
+                let res = request
+                    .put(path)
+                    .add_header(auth_key, auth_value)
+                    .json(&body)
+                    .await;
+                let status = res.status_code().as_u16();
+                (
+                    status,
+                    serde_json::from_str::<serde_json::Value>(&res.text()).unwrap_or_default(),
+                )
+            }
+        };
+        let (status, edited) =
+            edit(serde_json::json!({ "title": "Renamed", "published": true })).await;
+        assert_eq!(status, 200);
+        assert_eq!(edited["cover_url"], url.as_str());
+        let png = image(10, 10, ImageFormat::Png);
+        let (_, foreign) = upload(&request, &other.token, png, "image/png").await;
+        let (status, _) = edit(serde_json::json!({
+            "title": "Renamed",
+            "published": true,
+            "user_id": other.user.pid,
+            "cover_media_id": foreign["id"],
+        }))
+        .await;
+        assert_eq!(status, 400);
+        let (status, edited) = edit(serde_json::json!({
+            "title": "Renamed",
+            "published": true,
+            "cover_media_id": null,
+        }))
+        .await;
+        assert_eq!(status, 200);
+        assert!(edited["cover_url"].is_null());
+        let (status, _) = edit(serde_json::json!({
+            "title": "With cover",
+            "published": true,
+            "cover_media_id": media["id"],
+        }))
+        .await;
+        assert_eq!(status, 200);
+
+        // only the owner may delete an upload, which leaves posts without cover
+        let (auth_key, auth_value) = prepare_data::auth_header(&other.token);
+        let res = request.delete(&url).add_header(auth_key, auth_value).await;
+        assert_eq!(res.status_code(), 404);
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let res = request.delete(&url).add_header(auth_key, auth_value).await;
+        assert_eq!(res.status_code(), 200);
+        assert_eq!(request.get(&url).await.status_code(), 404);
+
+        let res = request.get(&format!("/api/posts/{}", post["id"])).await;
+        let post: serde_json::Value = serde_json::from_str(&res.text()).unwrap();
+        assert_eq!(post["cover_media_id"], serde_json::Value::Null);
+        assert_eq!(post["cover_url"], serde_json::Value::Null);
+    })
+    .await;
+}
@@ -2,4 +2,16 @@ mod auth;
 mod prepare_data;
 
 pub mod post;
-pub mod comments;
\ No newline at end of file
+pub mod comments;
+pub mod users;
+pub mod invites;
+pub mod audit;
+pub mod notifications;
+pub mod moderation;
+pub mod reports;
+pub mod reactions;
+pub mod bookmarks;
+pub mod follows;
+pub mod analytics;
+pub mod dashboard;
+pub mod media;
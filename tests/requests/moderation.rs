@@ -0,0 +1,221 @@
+use loco_rs::testing;
+use myapp::{
+    app::App,
+    models::{
+        _entities::{comments, posts},
+        notifications,
+        users::Role,
+    },
+    views::moderation::ModerationResponse,
+};
+use sea_orm::{ActiveModelTrait, ActiveValue, DatabaseConnection, IntoActiveModel};
+use serial_test::serial;
+use uuid::Uuid;
+
+use super::prepare_data::{self, create_post};
+
+const AUTHOR_PID: &str = "11111111-1111-1111-1111-111111111111";
+
+async fn create_moderated_post(db: &DatabaseConnection) -> posts::Model {
+    let author = Uuid::parse_str(AUTHOR_PID).unwrap();
+    let mut post = create_post(db, author, "Moderated post", false, None)
+        .await
+        .into_active_model();
+    post.comment_moderation = ActiveValue::set(true);
+    post.update(db).await.unwrap()
+}
+
+async fn comment_ids(request: &loco_rs::TestServer, post_id: i32, token: Option<&str>) -> Vec<i64> {
+    let mut req = request.get(&format!("/api/comments?post_id={post_id}"));
+    if let Some(token) = token {
+        let (auth_key, auth_value) = prepare_data::auth_header(token);
+        req = req.add_header(auth_key, auth_value);
+    }
+    let comments: serde_json::Value = serde_json::from_str(&req.await.text()).unwrap();
+    comments["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|comment| comment["id"].as_i64().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+#[serial]
+async fn can_moderate_pending_comments() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let commenter = prepare_data::init_user_login(&request, &ctx).await;
+        let moderator =
+            prepare_data::init_named_user_login(&request, &ctx, "mod", "mod@loco.com").await;
+        moderator
+            .user
+            .clone()
+            .into_active_model()
+            .set_role(&ctx.db, Role::Moderator)
+            .await
+            .unwrap();
+        let post = create_moderated_post(&ctx.db).await;
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&commenter.token);
+        let response = request
+            .post("/api/comments")
+            .add_header(auth_key, auth_value)
+            .json(&serde_json::json!({ "content": "First!", "post_id": post.id }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let comment: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(comment["status"], "pending");
+        let id = comment["id"].as_i64().unwrap();
+
+        // only the author sees it while it waits
+        assert!(comment_ids(&request, post.id, None).await.is_empty());
+        assert_eq!(
+            comment_ids(&request, post.id, Some(&commenter.token)).await,
+            vec![id]
+        );
+
+        // users can't moderate
+        let (auth_key, auth_value) = prepare_data::auth_header(&commenter.token);
+        let response = request
+            .get("/api/moderation/comments")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 403);
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&moderator.token);
+        let response = request
+            .get("/api/moderation/comments")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let queue: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(queue["total"], 1);
+        assert_eq!(queue["items"][0]["id"], id);
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&moderator.token);
+        let response = request
+            .post("/api/moderation/comments")
+            .add_header(auth_key, auth_value)
+            .json(&serde_json::json!({ "ids": [id], "action": "approve" }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let moderation: ModerationResponse = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(moderation.updated.len(), 1);
+
+        assert_eq!(comment_ids(&request, post.id, None).await, vec![id]);
+
+        // the commenter hears about the decision, the post author about the
+        // comment once it is approved
+        let inbox = notifications::Model::inbox(commenter.user.pid, true)
+            .all(&ctx.db)
+            .await
+            .unwrap();
+        assert_eq!(inbox.len(), 1);
+        assert_eq!(inbox[0].kind, "moderation");
+        assert_eq!(inbox[0].detail.as_deref(), Some("approved"));
+        let author = Uuid::parse_str(AUTHOR_PID).unwrap();
+        let inbox = notifications::Model::inbox(author, true)
+            .all(&ctx.db)
+            .await
+            .unwrap();
+        assert_eq!(inbox.len(), 1);
+        assert_eq!(inbox[0].kind, "comment");
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_auto_approve_trusted_users() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let post = create_moderated_post(&ctx.db).await;
+        for _ in 0..3 {
+            comments::ActiveModel {
+                content: ActiveValue::set(Some("Approved before".to_string())),
+                post_id: ActiveValue::set(Some(post.id)),
+                user_id: ActiveValue::set(user.user.pid),
+                status: ActiveValue::set("approved".to_string()),
+                ..Default::default()
+            }
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+        }
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .post("/api/comments")
+            .add_header(auth_key, auth_value)
+            .json(&serde_json::json!({ "content": "Trusted", "post_id": post.id }))
+            .await;
+        let comment: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(comment["status"], "approved");
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_hold_suspected_spam() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let author = Uuid::parse_str(AUTHOR_PID).unwrap();
+        let post = create_post(&ctx.db, author, "Open post", false, None).await;
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .post("/api/comments")
+            .add_header(auth_key, auth_value)
+            .json(&serde_json::json!({
+                "content": "http://a.example http://b.example http://c.example",
+                "post_id": post.id,
+            }))
+            .await;
+        let comment: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(comment["status"], "pending");
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_screen_edited_comments() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let author = Uuid::parse_str(AUTHOR_PID).unwrap();
+        let post = create_post(&ctx.db, author, "Open post", false, None).await;
+        let other = create_moderated_post(&ctx.db).await;
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .post("/api/comments")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&serde_json::json!({ "content": "Nice read", "post_id": post.id }))
+            .await;
+        let comment: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(comment["status"], "approved");
+        let id = comment["id"].as_i64().unwrap();
+
+        // the post and thread stay, the new content is screened
+        let response = request
+            .put(&format!("/api/comments/{id}"))
+            .add_header(auth_key, auth_value)
+            .json(&serde_json::json!({
+                "content": "http://a.example http://b.example http://c.example",
+                "post_id": other.id,
+                "parent_id": 1,
+            }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let comment: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(comment["status"], "pending");
+        assert_eq!(comment["post_id"], post.id);
+        assert!(comment["parent_id"].is_null());
+    })
+    .await;
+}
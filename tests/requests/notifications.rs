@@ -0,0 +1,215 @@
+use loco_rs::testing;
+use myapp::{
+    app::App,
+    models::{
+        notifications::{NewNotification, NotificationKind},
+        sessions,
+        users::{self, NotificationPreference},
+    },
+    views::notifications::{NotificationResponse, PreferencesResponse, UnreadCountResponse},
+};
+use sea_orm::DatabaseConnection;
+use serial_test::serial;
+use uuid::Uuid;
+
+use super::prepare_data;
+
+async fn notify(db: &DatabaseConnection, user_id: Uuid, kind: NotificationKind) -> i32 {
+    NewNotification::new(user_id, kind)
+        .actor(Uuid::new_v4())
+        .send(db)
+        .await
+        .unwrap()
+        .unwrap()
+        .id
+}
+
+#[tokio::test]
+#[serial]
+async fn can_read_notifications() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let first = notify(&ctx.db, user.user.pid, NotificationKind::Comment).await;
+        notify(&ctx.db, user.user.pid, NotificationKind::Reply).await;
+        // users are not notified of their own actions
+        let own = NewNotification::new(user.user.pid, NotificationKind::Comment)
+            .actor(user.user.pid)
+            .send(&ctx.db)
+            .await
+            .unwrap();
+        assert!(own.is_none());
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .get("/api/notifications")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let inbox: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(inbox["unread_count"], 2);
+        let items = inbox["notifications"]["items"].as_array().unwrap();
+        assert_eq!(items.len(), 2);
+        // newest first
+        assert_eq!(items[0]["kind"], "reply");
+        assert_eq!(items[1]["kind"], "comment");
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .post(&format!("/api/notifications/{first}/read"))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let notification: NotificationResponse = serde_json::from_str(&response.text()).unwrap();
+        assert!(notification.read);
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .get("/api/notifications/unread-count")
+            .add_header(auth_key, auth_value)
+            .await;
+        let unread: UnreadCountResponse = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(unread.unread_count, 1);
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .post("/api/notifications/read-all")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .get("/api/notifications?unread_only=true")
+            .add_header(auth_key, auth_value)
+            .await;
+        let inbox: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(inbox["unread_count"], 0);
+        assert!(inbox["notifications"]["items"]
+            .as_array()
+            .unwrap()
+            .is_empty());
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn cannot_read_notifications_of_others() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let other = notify(&ctx.db, Uuid::new_v4(), NotificationKind::Follow).await;
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .post(&format!("/api/notifications/{other}/read"))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 404);
+
+        // the stream checks the token it is given
+        let response = request.get("/api/notifications/stream?token=invalid").await;
+        assert_eq!(response.status_code(), 401);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_update_notification_preferences() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .get("/api/notifications/preferences")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let preferences: PreferencesResponse = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(
+            preferences.comment_emails,
+            NotificationPreference::Immediate
+        );
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .put("/api/notifications/preferences")
+            .add_header(auth_key, auth_value)
+            .json(&serde_json::json!({ "comment_emails": "daily_digest" }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let user = users::Model::find_by_pid(&ctx.db, &user.user.pid.to_string())
+            .await
+            .unwrap();
+        assert_eq!(
+            user.notification_preference(),
+            NotificationPreference::DailyDigest
+        );
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_unsubscribe_with_signed_link() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let secret = ctx.config.get_jwt_config().unwrap().secret.clone();
+        let token = user.user.unsubscribe_token(&secret);
+
+        // a token signed for someone else is refused
+        let (pid, signature) = token.split_once('.').unwrap();
+        let forged = format!("{}.{signature}", uuid::Uuid::new_v4());
+        assert_ne!(pid, forged.split_once('.').unwrap().0);
+        let response = request
+            .get(&format!("/api/notifications/unsubscribe?token={forged}"))
+            .await;
+        assert_eq!(response.status_code(), 404);
+
+        // opening the link only asks
+        let response = request
+            .get(&format!("/api/notifications/unsubscribe?token={token}"))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert!(response.text().contains("method=\"post\""));
+        let user = users::Model::find_by_pid(&ctx.db, pid).await.unwrap();
+        assert_ne!(user.notification_preference(), NotificationPreference::Off);
+
+        let response = request
+            .post(&format!("/api/notifications/unsubscribe?token={token}"))
+            .form(&[("List-Unsubscribe", "One-Click")])
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let user = users::Model::find_by_pid(&ctx.db, pid).await.unwrap();
+        assert_eq!(user.notification_preference(), NotificationPreference::Off);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn stream_ends_once_the_session_is_revoked() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let path = format!("/api/notifications/stream?token={}", user.token);
+
+        let revoke = async {
+            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+            sessions::Model::revoke_all_for_user(&ctx.db, user.user.pid, None)
+                .await
+                .unwrap();
+            notify(&ctx.db, user.user.pid, NotificationKind::Follow).await;
+        };
+        let (response, ()) = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            tokio::join!(request.get(&path), revoke)
+        })
+        .await
+        .expect("the stream should end");
+        assert_eq!(response.status_code(), 200);
+        // only the count sent on connect
+        assert_eq!(response.text().matches("event: unread").count(), 1);
+    })
+    .await;
+}
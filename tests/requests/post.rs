@@ -1,6 +1,14 @@
-use myapp::app::App;
 use loco_rs::testing;
+use myapp::app::App;
+use myapp::models::{
+    _entities::{comments, notifications, posts, reactions, reports},
+    users::Role,
+};
+use sea_orm::{ActiveModelTrait, ActiveValue, EntityTrait, IntoActiveModel, PaginatorTrait};
 use serial_test::serial;
+use uuid::Uuid;
+
+use super::prepare_data;
 
 #[tokio::test]
 #[serial]
@@ -14,3 +22,434 @@ async fn can_get_posts() {
     })
     .await;
 }
+
+#[tokio::test]
+#[serial]
+async fn can_sort_posts_by_discussion() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let author = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+        let commenter = Uuid::parse_str("22222222-2222-2222-2222-222222222222").unwrap();
+        let mut posts = Vec::new();
+        for (i, title) in ["Quiet", "Busy", "Lively"].into_iter().enumerate() {
+            let post = posts::ActiveModel {
+                title: ActiveValue::set(Some(title.to_string())),
+                user_id: ActiveValue::set(Some(author)),
+                published: ActiveValue::set(Some(true)),
+                published_at: ActiveValue::set(Some(
+                    (chrono::Utc::now() - chrono::Duration::hours(3 - i64::try_from(i).unwrap()))
+                        .into(),
+                )),
+                ..Default::default()
+            }
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+            posts.push(post);
+        }
+        let (busy, lively) = (posts[1].id, posts[2].id);
+
+        // only public comments count
+        for (post_id, status, hidden) in [
+            (busy, "approved", false),
+            (busy, "approved", false),
+            (busy, "approved", false),
+            (busy, "pending", false),
+            (lively, "approved", false),
+            (lively, "approved", true),
+        ] {
+            comments::ActiveModel {
+                content: ActiveValue::set(Some("Nice".to_string())),
+                post_id: ActiveValue::set(Some(post_id)),
+                user_id: ActiveValue::set(commenter),
+                status: ActiveValue::set(status.to_string()),
+                hidden_at: ActiveValue::set(hidden.then(|| chrono::Utc::now().into())),
+                ..Default::default()
+            }
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+        }
+
+        let list = |sort: &'static str| {
+            let request = &request;
+            async move {
+                let res = request.get(&format!("/api/posts?sort={sort}")).await;
+                assert_eq!(res.status_code(), 200);
+                let page: serde_json::Value = serde_json::from_str(&res.text()).unwrap();
+                page["items"].as_array().unwrap().clone()
+            }
+        };
+
+        let newest = list("newest").await;
+        let titles: Vec<&str> = newest
+            .iter()
+            .map(|item| item["title"].as_str().unwrap())
+            .collect();
+        assert_eq!(titles, vec!["Lively", "Busy", "Quiet"]);
+        assert_eq!(newest[0]["comment_count"], 1);
+        assert_eq!(newest[1]["comment_count"], 3);
+        assert!(newest[1]["last_comment_at"].is_string());
+        assert_eq!(newest[2]["comment_count"], 0);
+        assert!(newest[2]["last_comment_at"].is_null());
+
+        let discussed = list("most_discussed").await;
+        let titles: Vec<&str> = discussed
+            .iter()
+            .map(|item| item["title"].as_str().unwrap())
+            .collect();
+        assert_eq!(titles, vec!["Busy", "Lively", "Quiet"]);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_generate_reading_stats_and_summary() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let words = "word ".repeat(250);
+        let save = |path: String, body: serde_json::Value| {
+            let request = &request;
+            let (auth_key, auth_value) = (auth_key.clone(), auth_value.clone());
+            async move {
+                let res = if path == "/api/posts" {
+                    request
+                        .post(&path)
+                        .add_header(auth_key, auth_value)
+                        .json(&body)
+                        .await
+                } else {
+                    request
+                        .put(&path)
+                        .add_header(auth_key, auth_value)
+                        .json(&body)
+                        .await
+                };
+                assert_eq!(res.status_code(), 200);
+                serde_json::from_str::<serde_json::Value>(&res.text()).unwrap()
+            }
+        };
+
+        let post = save(
+            "/api/posts".to_string(),
+            serde_json::json!({
+                "title": "Long read",
+                "content": format!("# Intro\n\nA **long** post.\n\n{words}"),
+                "published": true,
+            }),
+        )
+        .await;
+        assert_eq!(post["word_count"], 254);
+        assert_eq!(post["reading_time"], 2);
+        let summary = post["summary"].as_str().unwrap();
+        assert!(summary.starts_with("A long post. word word"));
+        assert!(summary.ends_with("word…"));
+        assert!(summary.chars().count() <= 200);
+
+        // the generated summary sent back follows the new content
+        let path = format!("/api/posts/{}", post["id"]);
+        let post = save(
+            path.clone(),
+            serde_json::json!({
+                "title": "Long read",
+                "content": "Now a short post.",
+                "summary": summary,
+                "published": true,
+            }),
+        )
+        .await;
+        assert_eq!(post["summary"], "Now a short post.");
+        assert_eq!(post["word_count"], 4);
+        assert_eq!(post["reading_time"], 1);
+
+        // a written summary is kept
+        for content in ["Now a short post.", "Edited again."] {
+            let post = save(
+                path.clone(),
+                serde_json::json!({
+                    "title": "Long read",
+                    "content": content,
+                    "summary": "My own words",
+                    "published": true,
+                }),
+            )
+            .await;
+            assert_eq!(post["summary"], "My own words");
+        }
+
+        let res = request.get("/api/posts").await;
+        let page: serde_json::Value = serde_json::from_str(&res.text()).unwrap();
+        let item = &page["items"][0];
+        assert_eq!(item["summary"], "My own words");
+        assert_eq!(item["word_count"], 2);
+        assert_eq!(item["reading_time"], 1);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn only_authors_and_moderators_can_change_posts() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let author = prepare_data::init_user_login(&request, &ctx).await;
+        let other =
+            prepare_data::init_named_user_login(&request, &ctx, "other", "other@loco.com").await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&author.token);
+        let res = request
+            .post("/api/posts")
+            .add_header(auth_key, auth_value)
+            .json(&serde_json::json!({ "title": "Mine", "published": true }))
+            .await;
+        let post: serde_json::Value = serde_json::from_str(&res.text()).unwrap();
+        let path = format!("/api/posts/{}", post["id"]);
+        let edit = serde_json::json!({
+            "title": "Taken over",
+            "published": true,
+            "user_id": other.user.pid,
+            "comment_policy": "closed",
+        });
+
+        let res = request.put(&path).json(&edit).await;
+        assert_eq!(res.status_code(), 401);
+        let res = request
+            .patch(&format!("{path}/publish"))
+            .json(&serde_json::json!({ "published": false }))
+            .await;
+        assert_eq!(res.status_code(), 401);
+        assert_eq!(request.delete(&path).await.status_code(), 401);
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&other.token);
+        let res = request
+            .put(&path)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&edit)
+            .await;
+        assert_eq!(res.status_code(), 403);
+        let res = request
+            .delete(&path)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(res.status_code(), 403);
+
+        // moderators may edit, but the author stays the same
+        other
+            .user
+            .clone()
+            .into_active_model()
+            .set_role(&ctx.db, Role::Moderator)
+            .await
+            .unwrap();
+        let res = request
+            .put(&path)
+            .add_header(auth_key, auth_value)
+            .json(&edit)
+            .await;
+        assert_eq!(res.status_code(), 200);
+        let post: serde_json::Value = serde_json::from_str(&res.text()).unwrap();
+        assert_eq!(post["title"], "Taken over");
+        assert_eq!(post["user_id"], author.user.pid.to_string());
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_keep_or_clear_comment_closing() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let author = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&author.token);
+        let res = request
+            .post("/api/posts")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&serde_json::json!({
+                "title": "Closing",
+                "published": true,
+                "comments_close_after_days": 7,
+            }))
+            .await;
+        let post: serde_json::Value = serde_json::from_str(&res.text()).unwrap();
+        assert_eq!(post["comments_close_after_days"], 7);
+        let path = format!("/api/posts/{}", post["id"]);
+
+        let save = |body: serde_json::Value| {
+            let (request, path) = (&request, &path);
+            let (auth_key, auth_value) = (auth_key.clone(), auth_value.clone());
+            async move {
+                let res = request
+                    .put(path)
+                    .add_header(auth_key, auth_value)
+                    .json(&body)
+                    .await;
+                assert_eq!(res.status_code(), 200);
+                serde_json::from_str::<serde_json::Value>(&res.text()).unwrap()
+            }
+        };
+
+        let post = save(serde_json::json!({ "title": "Edited", "published": true })).await;
+        assert_eq!(post["comments_close_after_days"], 7);
+
+        let post = save(serde_json::json!({
+            "title": "Edited",
+            "published": true,
+            "comments_close_after_days": null,
+        }))
+        .await;
+        assert!(post["comments_close_after_days"].is_null());
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn edits_keep_closed_comments_closed() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let author = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&author.token);
+        let published_at = chrono::Utc::now() - chrono::Duration::days(10);
+        let mut post =
+            prepare_data::create_post(&ctx.db, author.user.pid, "Closed", true, Some(published_at))
+                .await
+                .into_active_model();
+        post.comments_close_after_days = ActiveValue::set(Some(7));
+        let post = post.update(&ctx.db).await.unwrap();
+        let path = format!("/api/posts/{}", post.id);
+
+        let res = request
+            .put(&path)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&serde_json::json!({ "title": "Edited", "published": true }))
+            .await;
+        assert_eq!(res.status_code(), 200);
+        let res = request
+            .patch(&format!("{path}/publish"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&serde_json::json!({ "published": true }))
+            .await;
+        assert_eq!(res.status_code(), 200);
+
+        let post = posts::Entity::find_by_id(post.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(post.title.as_deref(), Some("Edited"));
+        assert_eq!(
+            post.published_at.map(|at| at.timestamp()),
+            Some(published_at.timestamp())
+        );
+        let res = request
+            .post("/api/comments")
+            .add_header(auth_key, auth_value)
+            .json(&serde_json::json!({ "post_id": post.id, "content": "Late" }))
+            .await;
+        assert_eq!(res.status_code(), 403);
+        let error: serde_json::Value = serde_json::from_str(&res.text()).unwrap();
+        assert_eq!(
+            error["description"],
+            "comments closed automatically on this post"
+        );
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn removing_a_post_removes_its_discussion() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let author = prepare_data::init_user_login(&request, &ctx).await;
+        let reader =
+            prepare_data::init_named_user_login(&request, &ctx, "reader", "reader@loco.com").await;
+        let post = prepare_data::create_post(&ctx.db, author.user.pid, "Removed", true, None).await;
+        let comment = comments::ActiveModel {
+            content: ActiveValue::set(Some("Nice".to_string())),
+            post_id: ActiveValue::set(Some(post.id)),
+            user_id: ActiveValue::set(reader.user.pid),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+        reactions::ActiveModel {
+            user_id: ActiveValue::set(author.user.pid),
+            target_type: ActiveValue::set("comment".to_string()),
+            target_id: ActiveValue::set(comment.id),
+            emoji: ActiveValue::set("👍".to_string()),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+        notifications::ActiveModel {
+            user_id: ActiveValue::set(author.user.pid),
+            kind: ActiveValue::set("comment".to_string()),
+            actor_id: ActiveValue::set(Some(reader.user.pid)),
+            post_id: ActiveValue::set(Some(post.id)),
+            comment_id: ActiveValue::set(Some(comment.id)),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+        for (target_type, target_id) in [("post", post.id), ("comment", comment.id)] {
+            reports::ActiveModel {
+                reporter_id: ActiveValue::set(reader.user.pid),
+                target_type: ActiveValue::set(target_type.to_string()),
+                target_id: ActiveValue::set(target_id),
+                reason: ActiveValue::set("spam".to_string()),
+                status: ActiveValue::set("open".to_string()),
+                ..Default::default()
+            }
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+        }
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&author.token);
+        let res = request
+            .delete(&format!("/api/posts/{}", post.id))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(res.status_code(), 200);
+
+        assert!(posts::Entity::find_by_id(post.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(comments::Entity::find().count(&ctx.db).await.unwrap(), 0);
+        assert_eq!(reactions::Entity::find().count(&ctx.db).await.unwrap(), 0);
+        assert_eq!(
+            notifications::Entity::find().count(&ctx.db).await.unwrap(),
+            0
+        );
+        assert_eq!(reports::Entity::find().count(&ctx.db).await.unwrap(), 0);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn lists_only_released_posts() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let author = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+        let hour_ago = chrono::Utc::now() - chrono::Duration::hours(1);
+        let in_an_hour = chrono::Utc::now() + chrono::Duration::hours(1);
+        prepare_data::create_post(&ctx.db, author, "Out", true, Some(hour_ago)).await;
+        prepare_data::create_post(&ctx.db, author, "Draft", false, None).await;
+        prepare_data::create_post(&ctx.db, author, "Scheduled", true, Some(in_an_hour)).await;
+
+        let res = request.get("/api/posts").await;
+        assert_eq!(res.status_code(), 200);
+        let page: serde_json::Value = serde_json::from_str(&res.text()).unwrap();
+        let titles: Vec<&str> = page["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["title"].as_str().unwrap())
+            .collect();
+        assert_eq!(titles, vec!["Out"]);
+    })
+    .await;
+}
@@ -1,6 +1,12 @@
 use axum::http::{HeaderName, HeaderValue};
+use chrono::{DateTime, Utc};
 use loco_rs::{app::AppContext, TestServer};
-use myapp::{models::users, views::auth::LoginResponse};
+use myapp::{
+    models::{_entities::posts, users},
+    views::auth::LoginResponse,
+};
+use sea_orm::{ActiveModelTrait, ActiveValue, DatabaseConnection};
+use uuid::Uuid;
 
 const USER_EMAIL: &str = "test@loco.com";
 const USER_PASSWORD: &str = "1234";
@@ -11,9 +17,19 @@ pub struct LoggedInUser {
 }
 
 pub async fn init_user_login(request: &TestServer, ctx: &AppContext) -> LoggedInUser {
+    init_named_user_login(request, ctx, "loco", USER_EMAIL).await
+}
+
+/// Registers, verifies and logs in another user than the default one
+pub async fn init_named_user_login(
+    request: &TestServer,
+    ctx: &AppContext,
+    name: &str,
+    email: &str,
+) -> LoggedInUser {
     let register_payload = serde_json::json!({
-        "name": "loco",
-        "email": USER_EMAIL,
+        "name": name,
+        "email": email,
         "password": USER_PASSWORD
     });
 
@@ -22,9 +38,7 @@ pub async fn init_user_login(request: &TestServer, ctx: &AppContext) -> LoggedIn
         .post("/api/auth/register")
         .json(&register_payload)
         .await;
-    let user = users::Model::find_by_email(&ctx.db, USER_EMAIL)
-        .await
-        .unwrap();
+    let user = users::Model::find_by_email(&ctx.db, email).await.unwrap();
 
     let verify_payload = serde_json::json!({
         "token": user.email_verification_token,
@@ -35,7 +49,7 @@ pub async fn init_user_login(request: &TestServer, ctx: &AppContext) -> LoggedIn
     let response = request
         .post("/api/auth/login")
         .json(&serde_json::json!({
-            "email": USER_EMAIL,
+            "email": email,
             "password": USER_PASSWORD
         }))
         .await;
@@ -43,9 +57,7 @@ pub async fn init_user_login(request: &TestServer, ctx: &AppContext) -> LoggedIn
     let login_response: LoginResponse = serde_json::from_str(&response.text()).unwrap();
 
     LoggedInUser {
-        user: users::Model::find_by_email(&ctx.db, USER_EMAIL)
-            .await
-            .unwrap(),
+        user: users::Model::find_by_email(&ctx.db, email).await.unwrap(),
         token: login_response.token,
     }
 }
@@ -55,3 +67,23 @@ pub fn auth_header(token: &str) -> (HeaderName, HeaderValue) {
 
     (HeaderName::from_static("authorization"), auth_header_value)
 }
+
+/// Creates a post of the author, scheduled when published in the future
+pub async fn create_post(
+    db: &DatabaseConnection,
+    author: Uuid,
+    title: &str,
+    published: bool,
+    published_at: Option<DateTime<Utc>>,
+) -> posts::Model {
+    posts::ActiveModel {
+        title: ActiveValue::set(Some(title.to_string())),
+        user_id: ActiveValue::set(Some(author)),
+        published: ActiveValue::set(Some(published)),
+        published_at: ActiveValue::set(published_at.map(Into::into)),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
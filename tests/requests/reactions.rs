@@ -0,0 +1,112 @@
+use loco_rs::testing;
+use myapp::{app::App, models::_entities::posts};
+use sea_orm::{ActiveModelTrait, ActiveValue};
+use serial_test::serial;
+use uuid::Uuid;
+
+use super::prepare_data;
+
+async fn react(
+    request: &loco_rs::TestServer,
+    token: &str,
+    path: &str,
+    emoji: &str,
+) -> (u16, serde_json::Value) {
+    let (auth_key, auth_value) = prepare_data::auth_header(token);
+    let response = request
+        .post(path)
+        .add_header(auth_key, auth_value)
+        .json(&serde_json::json!({ "emoji": emoji }))
+        .await;
+    let status = response.status_code().as_u16();
+    (
+        status,
+        serde_json::from_str(&response.text()).unwrap_or_default(),
+    )
+}
+
+#[tokio::test]
+#[serial]
+async fn can_toggle_reactions() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let other =
+            prepare_data::init_named_user_login(&request, &ctx, "other", "other@loco.com").await;
+        let post = posts::ActiveModel {
+            title: ActiveValue::set(Some("Liked post".to_string())),
+            user_id: ActiveValue::set(Some(
+                Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap(),
+            )),
+            published: ActiveValue::set(Some(true)),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+        let path = format!("/api/posts/{}/reactions", post.id);
+
+        let (status, summary) = react(&request, &user.token, &path, "👍").await;
+        assert_eq!(status, 200);
+        assert_eq!(summary["counts"]["👍"], 1);
+        assert_eq!(summary["mine"], serde_json::json!(["👍"]));
+        react(&request, &user.token, &path, "🎉").await;
+        let (_, summary) = react(&request, &other.token, &path, "👍").await;
+        assert_eq!(summary["counts"]["👍"], 2);
+        assert_eq!(summary["mine"], serde_json::json!(["👍"]));
+
+        // toggling again takes the reaction back
+        let (_, summary) = react(&request, &other.token, &path, "👍").await;
+        assert_eq!(summary["counts"]["👍"], 1);
+        assert_eq!(summary["mine"], serde_json::json!([]));
+
+        let (status, _) = react(&request, &user.token, &path, "🦀").await;
+        assert_eq!(status, 400);
+        let (status, _) = react(&request, &user.token, "/api/posts/9999/reactions", "👍").await;
+        assert_eq!(status, 404);
+
+        // the list shows the counts to everyone, and their own reactions to
+        // the signed in user
+        let res = request.get("/api/posts").await;
+        let page: serde_json::Value = serde_json::from_str(&res.text()).unwrap();
+        let item = &page["items"][0];
+        assert_eq!(item["reactions"]["counts"]["👍"], 1);
+        assert_eq!(item["reactions"]["counts"]["🎉"], 1);
+        assert_eq!(item["reactions"]["mine"], serde_json::json!([]));
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let res = request
+            .get("/api/posts")
+            .add_header(auth_key, auth_value)
+            .await;
+        let page: serde_json::Value = serde_json::from_str(&res.text()).unwrap();
+        assert_eq!(
+            page["items"][0]["reactions"]["mine"],
+            serde_json::json!(["🎉", "👍"])
+        );
+
+        // comments carry their reactions too
+        let (auth_key, auth_value) = prepare_data::auth_header(&other.token);
+        let res = request
+            .post("/api/comments")
+            .add_header(auth_key, auth_value)
+            .json(&serde_json::json!({ "content": "Agreed", "post_id": post.id }))
+            .await;
+        let comment: serde_json::Value = serde_json::from_str(&res.text()).unwrap();
+        let comment_path = format!("/api/comments/{}/reactions", comment["id"]);
+        let (status, _) = react(&request, &user.token, &comment_path, "❤️").await;
+        assert_eq!(status, 200);
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let res = request
+            .get(&format!("/api/comments?post_id={}", post.id))
+            .add_header(auth_key, auth_value)
+            .await;
+        let page: serde_json::Value = serde_json::from_str(&res.text()).unwrap();
+        assert_eq!(page["items"][0]["reactions"]["counts"]["❤️"], 1);
+        assert_eq!(
+            page["items"][0]["reactions"]["mine"],
+            serde_json::json!(["❤️"])
+        );
+        assert_eq!(page["items"][0]["reaction_count"], 1);
+    })
+    .await;
+}
@@ -0,0 +1,189 @@
+use loco_rs::testing;
+use myapp::{
+    app::App,
+    models::{_entities::comments, users::Role},
+    views::reports::{ReportResponse, ResolveResponse},
+};
+use sea_orm::{ActiveModelTrait, ActiveValue, IntoActiveModel};
+use serial_test::serial;
+use uuid::Uuid;
+
+use super::prepare_data::{self, create_post};
+
+const AUTHOR_PID: &str = "11111111-1111-1111-1111-111111111111";
+const COMMENTER_PID: &str = "22222222-2222-2222-2222-222222222222";
+
+async fn report(
+    request: &loco_rs::TestServer,
+    token: &str,
+    body: serde_json::Value,
+) -> (u16, String) {
+    let (auth_key, auth_value) = prepare_data::auth_header(token);
+    let response = request
+        .post("/api/reports")
+        .add_header(auth_key, auth_value)
+        .json(&body)
+        .await;
+    (response.status_code().as_u16(), response.text())
+}
+
+async fn comment_ids(request: &loco_rs::TestServer, post_id: i32) -> Vec<i64> {
+    let response = request
+        .get(&format!("/api/comments?post_id={post_id}"))
+        .await;
+    let comments: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+    comments["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|comment| comment["id"].as_i64().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+#[serial]
+async fn can_report_content_once() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let author = Uuid::parse_str(AUTHOR_PID).unwrap();
+        let post = create_post(&ctx.db, author, "Reported post", false, None).await;
+
+        let body = serde_json::json!({
+            "target_type": "post",
+            "target_id": post.id,
+            "reason": "harassment",
+            "details": "  Targets another user  ",
+        });
+        let (status, body_text) = report(&request, &user.token, body.clone()).await;
+        assert_eq!(status, 200);
+        let first: ReportResponse = serde_json::from_str(&body_text).unwrap();
+        assert_eq!(first.status, "open");
+        assert_eq!(first.reason, "harassment");
+        assert_eq!(first.details.as_deref(), Some("Targets another user"));
+
+        // reporting again changes nothing
+        let (status, body_text) = report(&request, &user.token, body).await;
+        assert_eq!(status, 200);
+        let second: ReportResponse = serde_json::from_str(&body_text).unwrap();
+        assert_eq!(second.id, first.id);
+
+        let (status, _) = report(
+            &request,
+            &user.token,
+            serde_json::json!({ "target_type": "comment", "target_id": 9999, "reason": "spam" }),
+        )
+        .await;
+        assert_eq!(status, 404);
+
+        // users can't see the reports
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .get("/api/moderation/reports")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 403);
+
+        // a single report doesn't hide the post
+        let response = request.get(&format!("/api/posts/{}", post.id)).await;
+        assert_eq!(response.status_code(), 200);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_hide_reported_content_until_dismissed() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let author = Uuid::parse_str(AUTHOR_PID).unwrap();
+        let post = create_post(&ctx.db, author, "Reported post", false, None).await;
+        let comment = comments::ActiveModel {
+            content: ActiveValue::set(Some("Buy followers now".to_string())),
+            post_id: ActiveValue::set(Some(post.id)),
+            user_id: ActiveValue::set(Uuid::parse_str(COMMENTER_PID).unwrap()),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+
+        let mut reporters = vec![prepare_data::init_user_login(&request, &ctx).await];
+        for name in ["second", "third"] {
+            reporters.push(
+                prepare_data::init_named_user_login(
+                    &request,
+                    &ctx,
+                    name,
+                    &format!("{name}@loco.com"),
+                )
+                .await,
+            );
+        }
+        let body = serde_json::json!({
+            "target_type": "comment",
+            "target_id": comment.id,
+            "reason": "spam",
+        });
+        for (i, reporter) in reporters.iter().enumerate() {
+            let (status, _) = report(&request, &reporter.token, body.clone()).await;
+            assert_eq!(status, 200);
+            let expected = if i < 2 {
+                vec![i64::from(comment.id)]
+            } else {
+                vec![]
+            };
+            assert_eq!(comment_ids(&request, post.id).await, expected);
+        }
+
+        let moderator = &reporters[0];
+        moderator
+            .user
+            .clone()
+            .into_active_model()
+            .set_role(&ctx.db, Role::Moderator)
+            .await
+            .unwrap();
+        let (auth_key, auth_value) = prepare_data::auth_header(&moderator.token);
+        let response = request
+            .get("/api/moderation/reports")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let queue: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(queue["total"], 3);
+        let id = queue["items"][0]["id"].as_i64().unwrap();
+
+        // reports stay open until resolved or dismissed
+        let (auth_key, auth_value) = prepare_data::auth_header(&moderator.token);
+        let response = request
+            .post(&format!("/api/moderation/reports/{id}/resolve"))
+            .add_header(auth_key, auth_value)
+            .json(&serde_json::json!({ "status": "open" }))
+            .await;
+        assert_eq!(response.status_code(), 400);
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&moderator.token);
+        let response = request
+            .post(&format!("/api/moderation/reports/{id}/resolve"))
+            .add_header(auth_key, auth_value)
+            .json(&serde_json::json!({ "status": "dismissed", "note": "Not spam" }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let resolved: ResolveResponse = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(resolved.settled, 3);
+
+        assert_eq!(
+            comment_ids(&request, post.id).await,
+            vec![i64::from(comment.id)]
+        );
+        let (auth_key, auth_value) = prepare_data::auth_header(&moderator.token);
+        let response = request
+            .get("/api/moderation/reports")
+            .add_header(auth_key, auth_value)
+            .await;
+        let queue: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(queue["total"], 0);
+    })
+    .await;
+}
@@ -0,0 +1,44 @@
+use loco_rs::testing;
+use myapp::app::App;
+use serial_test::serial;
+use uuid::Uuid;
+
+use super::prepare_data::create_post;
+
+#[tokio::test]
+#[serial]
+async fn can_get_user_page() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let user_pid = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+        let now = chrono::Utc::now();
+        create_post(&ctx.db, user_pid, "published post", true, Some(now)).await;
+        create_post(&ctx.db, user_pid, "draft post", false, None).await;
+        // scheduled posts show up once their time comes
+        let later = now + chrono::Duration::days(1);
+        create_post(&ctx.db, user_pid, "scheduled post", true, Some(later)).await;
+
+        let res = request.get("/api/users/USER1").await;
+        assert_eq!(res.status_code(), 200);
+
+        let body: serde_json::Value = res.json();
+        assert_eq!(body["profile"]["handle"], "user1");
+        assert_eq!(body["profile"]["name"], "user1");
+        assert!(body["profile"].get("email").is_none());
+        assert!(body["profile"].get("api_key").is_none());
+        assert_eq!(body["posts"]["total"], 1);
+        assert_eq!(body["posts"]["items"][0]["title"], "published post");
+        assert!(!res.text().contains("user1@example.com"));
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn user_page_not_found() {
+    testing::request::<App, _, _>(|request, _ctx| async move {
+        let res = request.get("/api/users/nobody").await;
+        assert_eq!(res.status_code(), 404);
+    })
+    .await;
+}
@@ -0,0 +1,263 @@
+use std::{io::Cursor, path::Path};
+
+use image::{DynamicImage, ImageFormat, RgbImage};
+use loco_rs::{boot::run_task, prelude::*, task, testing};
+use myapp::{
+    app::App,
+    common::front_matter,
+    models::{
+        _entities::{comments, posts},
+        media, post_tags,
+    },
+    tasks::export_markdown::{self, Filters, Layout},
+    workers::media_variants::{MediaVariantsWorker, MediaVariantsWorkerArgs},
+};
+use serial_test::serial;
+
+const USER1: &str = "11111111-1111-1111-1111-111111111111";
+const USER2: &str = "22222222-2222-2222-2222-222222222222";
+
+fn png() -> Vec<u8> {
+    let image = RgbImage::from_fn(400, 300, |x, y| {
+        image::Rgb([(x % 256) as u8, (y % 256) as u8, 128])
+    });
+    let mut out = Cursor::new(Vec::new());
+    DynamicImage::ImageRgb8(image)
+        .write_to(&mut out, ImageFormat::Png)
+        .unwrap();
+    out.into_inner()
+}
+
+/// A published post of user1 with an image and a comment, a draft of user1
+/// and a post of user2
+async fn prepare(ctx: &AppContext) -> Uuid {
+    let db = &ctx.db;
+    let user1 = Uuid::parse_str(USER1).unwrap();
+    let user2 = Uuid::parse_str(USER2).unwrap();
+
+    let image = media::Model::create(db, &ctx.storage, user1, png().into(), None)
+        .await
+        .unwrap();
+    MediaVariantsWorker::perform_later(
+        ctx,
+        MediaVariantsWorkerArgs {
+            media_pid: image.pid.to_string(),
+        },
+    )
+    .await
+    .unwrap();
+
+    let pid = image.pid;
+    let first = posts::ActiveModel {
+        title: Set(Some("First".to_string())),
+        content: Set(Some(format!(
+            "![photo](/api/media/{pid})\n\n\
+             [thumbnail](https://blog.example.com/api/media/{pid}/thumbnail.webp)\n\n\
+             ![gone](/api/media/{})",
+            Uuid::new_v4()
+        ))),
+        summary: Set(Some("Hand written".to_string())),
+        slug: Set(Some("first".to_string())),
+        published: Set(Some(true)),
+        published_at: Set(Some(
+            chrono::DateTime::parse_from_rfc3339("2024-05-01T10:00:00+00:00").unwrap(),
+        )),
+        user_id: Set(Some(user1)),
+        cover_media_id: Set(Some(image.id)),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    post_tags::Model::set_for(db, first.id, &["rust", "web"])
+        .await
+        .unwrap();
+    comments::ActiveModel {
+        content: Set(Some("Nice".to_string())),
+        post_id: Set(Some(first.id)),
+        user_id: Set(user2),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    posts::ActiveModel {
+        title: Set(Some("Draft".to_string())),
+        content: Set(Some("Not yet.".to_string())),
+        slug: Set(Some("draft".to_string())),
+        published: Set(Some(false)),
+        user_id: Set(Some(user1)),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    let other = posts::ActiveModel {
+        title: Set(Some("Other".to_string())),
+        content: Set(Some("By someone else.".to_string())),
+        slug: Set(Some("other".to_string())),
+        published: Set(Some(true)),
+        published_at: Set(Some(
+            chrono::DateTime::parse_from_rfc3339("2023-01-01T00:00:00+00:00").unwrap(),
+        )),
+        user_id: Set(Some(user2)),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    post_tags::Model::set_for(db, other.id, &["rust"])
+        .await
+        .unwrap();
+
+    pid
+}
+
+fn read(path: &Path) -> String {
+    std::fs::read_to_string(path).unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn can_export_to_hugo() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let pid = prepare(&boot.app_context).await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let summary = export_markdown::export(
+        &boot.app_context,
+        dir.path(),
+        Layout::Hugo,
+        &Filters::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(summary.posts, 3);
+    assert_eq!(summary.media, 2);
+    assert_eq!(summary.comments, 1);
+    assert_eq!(summary.missing_media, 1);
+
+    let bundle = dir.path().join("content/posts/first");
+    let text = read(&bundle.join("index.md"));
+    let document = front_matter::parse(&text).unwrap();
+    assert_eq!(document.front_matter.title.as_deref(), Some("First"));
+    assert_eq!(
+        document.front_matter.date.as_deref(),
+        Some("2024-05-01T10:00:00+00:00")
+    );
+    assert_eq!(document.front_matter.tags, vec!["rust", "web"]);
+    assert_eq!(document.front_matter.slug.as_deref(), Some("first"));
+    assert!(!document.front_matter.draft);
+    assert_eq!(
+        document.front_matter.summary.as_deref(),
+        Some("Hand written")
+    );
+    assert_eq!(document.front_matter.cover, Some(format!("{pid}.png")));
+    assert!(document.content.starts_with(&format!(
+        "![photo]({pid}.png)\n\n[thumbnail]({pid}-thumbnail.webp)"
+    )));
+    assert!(document.content.contains("![gone](/api/media/"));
+    assert!(bundle.join(format!("{pid}.png")).is_file());
+    assert!(bundle.join(format!("{pid}-thumbnail.webp")).is_file());
+
+    let comments: serde_json::Value =
+        serde_json::from_str(&read(&bundle.join("comments.json"))).unwrap();
+    assert_eq!(comments[0]["content"], "Nice");
+    assert_eq!(comments[0]["author"], "user2");
+    assert_eq!(comments[0]["status"], "approved");
+
+    let draft = front_matter::parse(&read(&dir.path().join("content/posts/draft/index.md")))
+        .unwrap()
+        .front_matter;
+    assert!(draft.draft);
+    assert_eq!(draft.summary, None);
+    assert!(!dir
+        .path()
+        .join("content/posts/draft/comments.json")
+        .exists());
+
+    // exporting again overwrites the same files
+    export_markdown::export(
+        &boot.app_context,
+        dir.path(),
+        Layout::Hugo,
+        &Filters::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(read(&bundle.join("index.md")), text);
+}
+
+#[tokio::test]
+#[serial]
+async fn can_export_filtered_posts_to_jekyll() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let pid = prepare(&boot.app_context).await;
+
+    let dir = tempfile::tempdir().unwrap();
+    run_task::<App>(
+        &boot.app_context,
+        Some(&"export_markdown".to_string()),
+        &task::Vars::from_cli_args(vec![
+            ("dir".to_string(), dir.path().to_string_lossy().to_string()),
+            ("layout".to_string(), "jekyll".to_string()),
+            ("author".to_string(), "user1@example.com".to_string()),
+            ("tag".to_string(), "Rust".to_string()),
+        ]),
+    )
+    .await
+    .unwrap();
+
+    let text = read(&dir.path().join("_posts/2024-05-01-first.md"));
+    let document = front_matter::parse(&text).unwrap();
+    assert_eq!(
+        document.front_matter.cover,
+        Some(format!("../assets/media/{pid}.png"))
+    );
+    assert!(document.content.contains(&format!(
+        "[thumbnail](../assets/media/{pid}-thumbnail.webp)"
+    )));
+    assert!(dir
+        .path()
+        .join(format!("assets/media/{pid}-thumbnail.webp"))
+        .is_file());
+    assert!(dir.path().join("_data/comments/first.json").is_file());
+    // the draft has no tag, the other post is of another author
+    assert!(!dir.path().join("_drafts").exists());
+    assert_eq!(
+        std::fs::read_dir(dir.path().join("_posts"))
+            .unwrap()
+            .count(),
+        1
+    );
+
+    let summary = export_markdown::export(
+        &boot.app_context,
+        dir.path(),
+        Layout::Jekyll,
+        &Filters {
+            since: front_matter::parse_date("2023-01-01"),
+            until: front_matter::parse_date("2024-01-01"),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(summary.posts, 1);
+    assert!(dir.path().join("_posts/2023-01-01-other.md").is_file());
+
+    let result = run_task::<App>(
+        &boot.app_context,
+        Some(&"export_markdown".to_string()),
+        &task::Vars::from_cli_args(vec![
+            ("dir".to_string(), dir.path().to_string_lossy().to_string()),
+            ("layout".to_string(), "gatsby".to_string()),
+        ]),
+    )
+    .await;
+    assert!(result.is_err());
+}
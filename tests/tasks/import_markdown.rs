@@ -0,0 +1,297 @@
+use std::path::Path;
+
+use loco_rs::{boot::run_task, prelude::*, task, testing};
+use myapp::{
+    app::App,
+    models::{_entities::posts, post_tags, users},
+    tasks::import_markdown::{self, Outcome},
+};
+use sea_orm::PaginatorTrait;
+use serial_test::serial;
+
+const FIRST: &str = "---
+title: First post
+date: 2024-05-01 10:00:00 +0200
+tags: rust web
+---
+
+Hello **world**.
+";
+
+const SECOND: &str = "---
+title: Second post
+tags: [Release Notes]
+draft: true
+---
+
+Not yet.
+";
+
+fn write(dir: &Path, name: &str, text: &str) {
+    let path = dir.join(name);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, text).unwrap();
+}
+
+async fn post(db: &DatabaseConnection, slug: &str) -> Option<posts::Model> {
+    posts::Entity::find()
+        .filter(posts::Column::Slug.eq(slug))
+        .one(db)
+        .await
+        .unwrap()
+}
+
+fn vars(dir: &Path, extra: &[(&str, &str)]) -> task::Vars {
+    let mut args = vec![
+        ("dir".to_string(), dir.to_string_lossy().to_string()),
+        ("author".to_string(), "user1@example.com".to_string()),
+    ];
+    args.extend(
+        extra
+            .iter()
+            .map(|(k, v)| ((*k).to_string(), (*v).to_string())),
+    );
+    task::Vars::from_cli_args(args)
+}
+
+#[tokio::test]
+#[serial]
+async fn can_import_posts_idempotently() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "_posts/2024-05-01-first-post.md", FIRST);
+    write(dir.path(), "second/index.md", SECOND);
+    write(dir.path(), "notes.txt", "not markdown");
+
+    run_task::<App>(
+        &boot.app_context,
+        Some(&"import_markdown".to_string()),
+        &vars(dir.path(), &[]),
+    )
+    .await
+    .unwrap();
+
+    let first = post(db, "first-post").await.expect("first post imported");
+    assert_eq!(first.title.as_deref(), Some("First post"));
+    assert_eq!(first.content.as_deref(), Some("Hello **world**."));
+    assert_eq!(first.published, Some(true));
+    assert_eq!(
+        first.published_at.unwrap().to_rfc3339(),
+        "2024-05-01T08:00:00+00:00"
+    );
+    assert_eq!(
+        first.user_id,
+        Some(Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap())
+    );
+    assert_eq!(
+        post_tags::Model::of(db, first.id).await.unwrap(),
+        vec!["rust", "web"]
+    );
+
+    let second = post(db, "second").await.expect("second post imported");
+    assert_eq!(second.published, Some(false));
+    assert_eq!(second.published_at, None);
+    assert_eq!(
+        post_tags::Model::of(db, second.id).await.unwrap(),
+        vec!["release-notes"]
+    );
+
+    let author = users::Model::find_by_email(db, "user1@example.com")
+        .await
+        .unwrap();
+    let report = import_markdown::import(db, dir.path(), &author, false)
+        .await
+        .unwrap();
+    assert_eq!(report.unchanged(), 2);
+    assert_eq!(report.entries.len(), 2);
+    assert_eq!(posts::Entity::find().count(db).await.unwrap(), 2);
+}
+
+#[tokio::test]
+#[serial]
+async fn can_dry_run_an_import() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+    let author = users::Model::find_by_email(db, "user1@example.com")
+        .await
+        .unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "first-post.md", FIRST);
+
+    run_task::<App>(
+        &boot.app_context,
+        Some(&"import_markdown".to_string()),
+        &vars(dir.path(), &[("dry_run", "true")]),
+    )
+    .await
+    .unwrap();
+    assert!(post(db, "first-post").await.is_none());
+
+    let report = import_markdown::import(db, dir.path(), &author, true)
+        .await
+        .unwrap();
+    assert_eq!(report.created(), 1);
+    assert!(report.to_string().contains("dry run"));
+    assert!(post(db, "first-post").await.is_none());
+
+    import_markdown::import(db, dir.path(), &author, false)
+        .await
+        .unwrap();
+    write(
+        dir.path(),
+        "first-post.md",
+        &FIRST
+            .replace("First post", "First post, edited")
+            .replace("rust web", "rust"),
+    );
+
+    let report = import_markdown::import(db, dir.path(), &author, true)
+        .await
+        .unwrap();
+    assert_eq!(
+        report.entries[0].outcome,
+        Outcome::Updated(vec!["title", "tags"])
+    );
+    let first = post(db, "first-post").await.unwrap();
+    assert_eq!(first.title.as_deref(), Some("First post"));
+
+    let report = import_markdown::import(db, dir.path(), &author, false)
+        .await
+        .unwrap();
+    assert_eq!(report.updated(), 1);
+    let first = post(db, "first-post").await.unwrap();
+    assert_eq!(first.title.as_deref(), Some("First post, edited"));
+    assert_eq!(
+        post_tags::Model::of(db, first.id).await.unwrap(),
+        vec!["rust"]
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn reports_failed_files_without_aborting() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+
+    posts::ActiveModel {
+        title: Set(Some("Taken".to_string())),
+        slug: Set(Some("taken".to_string())),
+        user_id: Set(Some(
+            Uuid::parse_str("22222222-2222-2222-2222-222222222222").unwrap(),
+        )),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    write(
+        dir.path(),
+        "a-broken.md",
+        "---\ntitle: [unclosed\n---\n\nText.\n",
+    );
+    write(
+        dir.path(),
+        "b-untitled.md",
+        "---\ndraft: true\n---\n\nText.\n",
+    );
+    write(
+        dir.path(),
+        "c-bad-date.md",
+        "---\ntitle: Bad\ndate: someday\n---\n",
+    );
+    write(
+        dir.path(),
+        "d-taken.md",
+        "---\ntitle: Taken\nslug: taken\n---\n",
+    );
+    write(dir.path(), "e-first-post.md", FIRST);
+    write(
+        dir.path(),
+        "f-copy.md",
+        "---\ntitle: Copy\nslug: e-first-post\n---\n",
+    );
+
+    let result = run_task::<App>(
+        &boot.app_context,
+        Some(&"import_markdown".to_string()),
+        &vars(dir.path(), &[]),
+    )
+    .await;
+    assert!(result.is_err());
+    assert!(post(db, "e-first-post").await.is_some());
+
+    let author = users::Model::find_by_email(db, "user1@example.com")
+        .await
+        .unwrap();
+    let report = import_markdown::import(db, dir.path(), &author, true)
+        .await
+        .unwrap();
+    let outcomes: Vec<_> = report
+        .entries
+        .iter()
+        .map(|entry| match &entry.outcome {
+            Outcome::Failed(err) => err.split(':').next().unwrap().to_string(),
+            outcome => format!("{outcome:?}"),
+        })
+        .collect();
+    assert_eq!(
+        outcomes,
+        vec![
+            "invalid front matter",
+            "missing title",
+            "invalid date",
+            "slug taken is used by a post of another author",
+            "Unchanged",
+            "slug e-first-post is used by another file",
+        ]
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn can_schedule_posts_dated_in_the_future() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+    let author = users::Model::find_by_email(db, "user1@example.com")
+        .await
+        .unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    write(
+        dir.path(),
+        "later.md",
+        "---\ntitle: Later\ndate: 2999-01-01 10:00:00 +0000\n---\n\nSoon.\n",
+    );
+    write(dir.path(), "first-post.md", FIRST);
+    // symlinked directories are skipped rather than followed around
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(dir.path(), dir.path().join("loop")).unwrap();
+
+    let report = import_markdown::import(db, dir.path(), &author, true)
+        .await
+        .unwrap();
+    assert_eq!(report.created(), 2);
+    assert_eq!(report.scheduled(), 1);
+    assert!(report
+        .to_string()
+        .contains("later.md (later), scheduled for 2999-01-01T10:00:00+00:00"));
+
+    import_markdown::import(db, dir.path(), &author, false)
+        .await
+        .unwrap();
+    let later = post(db, "later").await.unwrap();
+    assert_eq!(later.published, Some(true));
+    assert_eq!(
+        later.published_at.unwrap().to_rfc3339(),
+        "2999-01-01T10:00:00+00:00"
+    );
+}
@@ -1 +1,3 @@
 pub mod seed;
+pub mod import_markdown;
+pub mod export_markdown;
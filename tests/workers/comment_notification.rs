@@ -0,0 +1,198 @@
+use loco_rs::{bgworker::BackgroundWorker, testing};
+use myapp::{
+    app::App,
+    models::{
+        _entities::{comments, posts},
+        notifications,
+        users::{self, NotificationPreference},
+    },
+    workers::{
+        comment_digest::{CommentDigestWorker, CommentDigestWorkerArgs},
+        comment_notification::{CommentNotificationWorker, CommentNotificationWorkerArgs},
+    },
+};
+use sea_orm::{ActiveModelTrait, ActiveValue, DatabaseConnection, IntoActiveModel};
+use serial_test::serial;
+use uuid::Uuid;
+
+const AUTHOR_PID: &str = "11111111-1111-1111-1111-111111111111";
+const READER_PID: &str = "22222222-2222-2222-2222-222222222222";
+
+async fn create_post(db: &DatabaseConnection) -> posts::Model {
+    posts::ActiveModel {
+        title: ActiveValue::set(Some("Notified post".to_string())),
+        user_id: ActiveValue::set(Some(Uuid::parse_str(AUTHOR_PID).unwrap())),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+async fn create_comment(
+    db: &DatabaseConnection,
+    post: &posts::Model,
+    user_pid: &str,
+    parent_id: Option<i32>,
+    content: &str,
+) -> comments::Model {
+    comments::ActiveModel {
+        content: ActiveValue::set(Some(content.to_string())),
+        post_id: ActiveValue::set(Some(post.id)),
+        user_id: ActiveValue::set(Uuid::parse_str(user_pid).unwrap()),
+        parent_id: ActiveValue::set(parent_id),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+async fn set_preference(db: &DatabaseConnection, pid: &str, preference: NotificationPreference) {
+    users::Model::find_by_pid(db, pid)
+        .await
+        .unwrap()
+        .into_active_model()
+        .set_notification_preference(db, preference)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn can_notify_post_author_and_replied_user() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+    let worker = CommentNotificationWorker::build(ctx);
+
+    let post = create_post(&ctx.db).await;
+    let comment = create_comment(&ctx.db, &post, READER_PID, None, "Nice <b>post</b>").await;
+    worker
+        .perform(CommentNotificationWorkerArgs {
+            comment_id: comment.id,
+        })
+        .await
+        .unwrap();
+
+    // the author replying to a comment on their own post only tells the reader
+    let reply = create_comment(&ctx.db, &post, AUTHOR_PID, Some(comment.id), "Thanks!").await;
+    worker
+        .perform(CommentNotificationWorkerArgs {
+            comment_id: reply.id,
+        })
+        .await
+        .unwrap();
+
+    let messages = ctx.mailer.as_ref().unwrap().deliveries().messages;
+    assert_eq!(messages.len(), 2);
+    assert!(messages[0].contains("user1@example.com"));
+    assert!(messages[0].contains("commented on your post"));
+    // user content is escaped in the html part
+    assert!(messages[0].contains("Nice &lt;b&gt;post"));
+    assert!(messages[0].contains("/api/notifications/unsubscribe?"));
+    assert!(messages[1].contains("user2@example.com"));
+    assert!(messages[1].contains("replied to your comment"));
+}
+
+#[tokio::test]
+#[serial]
+async fn does_not_email_users_who_opted_out() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+
+    set_preference(&ctx.db, AUTHOR_PID, NotificationPreference::Off).await;
+    let post = create_post(&ctx.db).await;
+    let comment = create_comment(&ctx.db, &post, READER_PID, None, "Hello").await;
+    CommentNotificationWorker::build(ctx)
+        .perform(CommentNotificationWorkerArgs {
+            comment_id: comment.id,
+        })
+        .await
+        .unwrap();
+
+    assert!(ctx
+        .mailer
+        .as_ref()
+        .unwrap()
+        .deliveries()
+        .messages
+        .is_empty());
+
+    // the inbox gets it regardless
+    let author = Uuid::parse_str(AUTHOR_PID).unwrap();
+    let inbox = notifications::Model::inbox(author, true)
+        .all(&ctx.db)
+        .await
+        .unwrap();
+    assert_eq!(inbox.len(), 1);
+    assert_eq!(inbox[0].kind, "comment");
+    assert_eq!(
+        inbox[0].actor_id,
+        Some(Uuid::parse_str(READER_PID).unwrap())
+    );
+    assert_eq!(inbox[0].comment_id, Some(comment.id));
+}
+
+#[tokio::test]
+#[serial]
+async fn can_send_daily_digest() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+    let worker = CommentDigestWorker::build(ctx);
+    let args = || CommentDigestWorkerArgs {
+        user_guid: AUTHOR_PID.to_string(),
+    };
+
+    set_preference(&ctx.db, AUTHOR_PID, NotificationPreference::DailyDigest).await;
+    let post = create_post(&ctx.db).await;
+    create_comment(&ctx.db, &post, READER_PID, None, "First comment").await;
+    create_comment(&ctx.db, &post, READER_PID, None, "Second comment").await;
+    create_comment(&ctx.db, &post, AUTHOR_PID, None, "Own comment").await;
+
+    worker.perform(args()).await.unwrap();
+    let messages = ctx.mailer.as_ref().unwrap().deliveries().messages;
+    assert_eq!(messages.len(), 1);
+    assert!(messages[0].contains("First comment"));
+    assert!(messages[0].contains("Second comment"));
+    assert!(!messages[0].contains("Own comment"));
+
+    // nothing new since the last digest
+    worker.perform(args()).await.unwrap();
+    assert_eq!(ctx.mailer.as_ref().unwrap().deliveries().count, 1);
+}
+
+#[tokio::test]
+#[serial]
+async fn can_send_held_comments_in_digest_once_approved() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+    let worker = CommentDigestWorker::build(ctx);
+    let args = || CommentDigestWorkerArgs {
+        user_guid: AUTHOR_PID.to_string(),
+    };
+
+    set_preference(&ctx.db, AUTHOR_PID, NotificationPreference::DailyDigest).await;
+    let post = create_post(&ctx.db).await;
+    let own = create_comment(&ctx.db, &post, AUTHOR_PID, None, "Own comment").await;
+    let two_days_ago = chrono::Utc::now() - chrono::Duration::days(2);
+    for (content, moderated) in [("Held comment", true), ("Old comment", false)] {
+        let comment = create_comment(&ctx.db, &post, READER_PID, Some(own.id), content).await;
+        let mut comment = comment.into_active_model();
+        comment.created_at = ActiveValue::set(two_days_ago.into());
+        if moderated {
+            comment.moderated_at = ActiveValue::set(Some(chrono::Utc::now().into()));
+        }
+        comment.update(&ctx.db).await.unwrap();
+    }
+
+    worker.perform(args()).await.unwrap();
+    let messages = ctx.mailer.as_ref().unwrap().deliveries().messages;
+    assert_eq!(messages.len(), 1);
+    assert!(messages[0].contains("Held comment"));
+    assert!(messages[0].contains("replied to your comment"));
+    assert!(!messages[0].contains("Old comment"));
+}
@@ -0,0 +1,93 @@
+use std::io::{Cursor, Read};
+
+use loco_rs::{bgworker::BackgroundWorker, testing};
+use myapp::{
+    app::App,
+    models::_entities::{comments, posts},
+    workers::export::{archive_path, ExportWorker, ExportWorkerArgs},
+};
+use sea_orm::{ActiveModelTrait, ActiveValue};
+use serial_test::serial;
+use uuid::Uuid;
+
+const USER_PID: &str = "11111111-1111-1111-1111-111111111111";
+
+#[tokio::test]
+#[serial]
+async fn can_export_user_data() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+    let user_pid = Uuid::parse_str(USER_PID).unwrap();
+
+    let post = posts::ActiveModel {
+        title: ActiveValue::set(Some("Exported post".to_string())),
+        content: ActiveValue::set(Some("Some **markdown** content".to_string())),
+        slug: ActiveValue::set(Some("exported-post".to_string())),
+        user_id: ActiveValue::set(Some(user_pid)),
+        ..Default::default()
+    }
+    .insert(&ctx.db)
+    .await
+    .unwrap();
+    comments::ActiveModel {
+        content: ActiveValue::set(Some("First!".to_string())),
+        post_id: ActiveValue::set(Some(post.id)),
+        user_id: ActiveValue::set(user_pid),
+        ..Default::default()
+    }
+    .insert(&ctx.db)
+    .await
+    .unwrap();
+
+    assert!(ExportWorker::build(ctx)
+        .perform(ExportWorkerArgs {
+            user_guid: USER_PID.to_string(),
+        })
+        .await
+        .is_ok());
+
+    let deliveries = ctx.mailer.as_ref().unwrap().deliveries();
+    assert_eq!(deliveries.count, 1);
+    let message = &deliveries.messages[0];
+    let token_start = message.find("/export#").unwrap() + "/export#".len();
+    let token = &message[token_start..token_start + 36];
+
+    let archive: Vec<u8> = ctx
+        .storage
+        .download(&archive_path(USER_PID, token))
+        .await
+        .unwrap();
+    let mut zip = zip::ZipArchive::new(Cursor::new(archive)).unwrap();
+
+    let mut names: Vec<&str> = zip.file_names().collect();
+    names.sort_unstable();
+    let post_file = format!("posts/{}-exported-post.md", post.id);
+    assert_eq!(
+        names,
+        vec![
+            "comments.json",
+            "comments.md",
+            "posts.json",
+            post_file.as_str(),
+            "profile.json"
+        ]
+    );
+
+    let mut profile = String::new();
+    zip.by_name("profile.json")
+        .unwrap()
+        .read_to_string(&mut profile)
+        .unwrap();
+    assert!(profile.contains("user1@example.com"));
+    assert!(!profile.contains("api_key"));
+    assert!(!profile.contains("password"));
+
+    let mut markdown = String::new();
+    zip.by_name(&post_file)
+        .unwrap()
+        .read_to_string(&mut markdown)
+        .unwrap();
+    assert!(markdown.starts_with("# Exported post"));
+    assert!(markdown.contains("Some **markdown** content"));
+}
@@ -1 +1,2 @@
-
+mod export;
+mod comment_notification;